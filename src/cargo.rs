@@ -0,0 +1,166 @@
+use crate::result::Result;
+use regex::Regex;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+// Flags of `cargo build` and `cargo test` that take a value, so that the
+// value isn't mistaken for a test name.
+const VALUE_FLAGS: &[&str] = &[
+    "-p",
+    "--package",
+    "--exclude",
+    "--bin",
+    "--test",
+    "--example",
+    "--bench",
+    "-F",
+    "--features",
+    "--target",
+    "--target-dir",
+    "--manifest-path",
+    "--profile",
+    "-j",
+    "--jobs",
+    "--color",
+    "--config",
+    "-Z",
+];
+
+/// What `rust-debugger cargo run ...` or `cargo test ...` debugs.
+#[derive(Debug)]
+pub struct Target {
+    /// The binary cargo built and the arguments it runs with.
+    pub cmd: Vec<String>,
+    /// For a test, the function of the test to break on, as it demangles.
+    pub test: Option<String>,
+}
+
+/// Whether `cmd` asks for cargo to build what's debugged rather than
+/// naming a binary.
+pub fn is_cargo(cmd: &[String]) -> bool {
+    cmd.len() >= 2 && cmd[0] == "cargo" && (cmd[1] == "run" || cmd[1] == "test")
+}
+
+/// Runs `cargo build` for `cargo run` and `cargo test --no-run` for
+/// `cargo test` with the rest of `cmd`, and finds the binary it built from
+/// the JSON messages cargo prints. For a test, it's the test binary
+/// defining a function the test name names, when cargo built several.
+pub fn build(cmd: &[String]) -> Result<Target> {
+    let (subcommand, args) = (&cmd[1], &cmd[2..]);
+    let split = args.iter().position(|arg| arg == "--");
+    let (cargo_args, program_args) = match split {
+        Some(split) => (&args[..split], &args[split + 1..]),
+        None => (args, &[][..]),
+    };
+    let testing = subcommand == "test";
+
+    let mut cargo = Command::new("cargo");
+    if testing {
+        cargo.args(["test", "--no-run"]);
+    } else {
+        cargo.arg("build");
+    }
+    // Rendered diagnostics still go to stderr for the user to read.
+    let output = cargo
+        .arg("--message-format=json-render-diagnostics")
+        .args(cargo_args)
+        .stdin(Stdio::null())
+        .stderr(Stdio::inherit())
+        .output()
+        .map_err(|e| format!("couldn't run cargo: {}", e))?;
+    if !output.status.success() {
+        return Err(format!("cargo {} failed", subcommand).into());
+    }
+    let executables = executables(&String::from_utf8_lossy(&output.stdout), testing);
+
+    let name = test_name(cargo_args).filter(|_| testing);
+    let (binary, test) = match (name, executables.as_slice()) {
+        (_, []) => return Err(format!("cargo {} built nothing to run", subcommand).into()),
+        (Some(name), executables) => {
+            let found = executables
+                .iter()
+                .find_map(|binary| Some((binary.clone(), test_function(binary, name)?)));
+            match found {
+                Some((binary, function)) => (binary, Some(function)),
+                None => return Err(format!("no test function named `{}`", name).into()),
+            }
+        }
+        (None, [binary]) => (binary.clone(), None),
+        (None, executables) => {
+            return Err(format!(
+                "cargo {} built {} binaries, pick one with --bin, --lib or --test",
+                subcommand,
+                executables.len()
+            )
+            .into())
+        }
+    };
+
+    let mut cmd = vec![binary.to_string_lossy().into_owned()];
+    cmd.extend(name.cloned());
+    cmd.extend(program_args.iter().cloned());
+    Ok(Target { cmd, test })
+}
+
+// The test name in `cargo test` arguments, the one that isn't a flag or a
+// flag's value.
+fn test_name(args: &[String]) -> Option<&String> {
+    let mut takes_value = false;
+    for arg in args {
+        if takes_value {
+            takes_value = false;
+        } else if VALUE_FLAGS.contains(&arg.as_str()) {
+            takes_value = true;
+        } else if !arg.starts_with('-') {
+            return Some(arg);
+        }
+    }
+    None
+}
+
+// The executables in cargo's `compiler-artifact` messages, the test ones
+// or the others.
+fn executables(messages: &str, tests: bool) -> Vec<PathBuf> {
+    let executable = Regex::new(r#""executable":"((?:[^"\\]|\\.)*)""#).unwrap();
+    let test_profile = Regex::new(r#""profile":\{[^}]*"test":true"#).unwrap();
+    messages
+        .lines()
+        .filter(|message| message.contains(r#""reason":"compiler-artifact""#))
+        .filter(|message| test_profile.is_match(message) == tests)
+        .filter_map(|message| executable.captures(message))
+        .map(|captures| PathBuf::from(unescape(&captures[1])))
+        .collect()
+}
+
+// A JSON string's contents without its escapes, as far as paths need.
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('n') => unescaped.push('\n'),
+                Some('t') => unescaped.push('\t'),
+                Some(escaped) => unescaped.push(escaped),
+                None => {}
+            },
+            c => unescaped.push(c),
+        }
+    }
+    unescaped
+}
+
+// The function in `binary` that the test `name` names, either all of its
+// path or the end of it, like `tests::adds` for `demo::tests::adds`.
+fn test_function(binary: &Path, name: &str) -> Option<String> {
+    let elf = elf::File::open_path(binary).ok()?;
+    let symbols = elf.get_symbols(elf.get_section(".symtab")?).ok()?;
+    let suffix = format!("::{}", name);
+    symbols
+        .iter()
+        .filter(|symbol| symbol.symtype == elf::types::STT_FUNC && symbol.value != 0)
+        .filter_map(|symbol| rustc_demangle::try_demangle(&symbol.name).ok())
+        // The alternate form leaves off the hash.
+        .map(|demangled| format!("{:#}", demangled))
+        .find(|demangled| demangled == name || demangled.ends_with(&suffix))
+}
@@ -1,151 +1,2865 @@
-use crate::debugger::{Disassembler, Subordinate};
+use crate::debugger::coredump;
+use crate::debugger::processes;
+use crate::debugger::registers;
+use crate::debugger::syscalls::Arg;
+use crate::debugger::{
+    backtrace, build_id, chrome_trace, local, locals, objdump, rodata_strings, syscalls, Access,
+    Annotations, Disassembler, Expr, FollowFork, Machine, Place, RegionKind, Registers, StepMode,
+    StopDiff, StopRecorder, Subordinate, Tracepoint, Xrefs, MAX_INSTRUCTION_LEN,
+};
+use crate::error::Error;
 use crate::result::Result;
+use crate::sys::{
+    capture_stdout, inotify_names, inotify_watch, signal_name, signal_number, signals,
+};
+use regex::Regex;
 use rustyline::error::ReadlineError;
-use rustyline::Editor;
+use rustyline::{Cmd, Editor, KeyPress, Movement};
+use std::collections::VecDeque;
+use std::convert::TryInto;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::fs::FileTypeExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread;
+use termion::color;
+
+// How many frames `whereami` shows.
+const OVERVIEW_FRAMES: usize = 5;
+// Call cycles `bt -fold-recursion` looks for, up to this many functions
+// long and repeating at least `MIN_RECURSION` times.
+const MAX_RECURSION_CYCLE: usize = 4;
+const MIN_RECURSION: usize = 3;
+// How much of a string `x/s` shows before cutting it off.
+const MAX_EXAMINE_STRING: usize = 200;
+// How many stepped instructions `history` keeps.
+const HISTORY_LEN: usize = 1000;
+// The shortest run of printable characters `info strings` counts, as with
+// strings(1).
+const MIN_STRING_LEN: usize = 4;
+// How many lines `list` shows either side of the current one.
+const SOURCE_CONTEXT: usize = 5;
 
 pub struct Cli {
     subordinate: Subordinate,
+    settings: Settings,
+    // How many trace events have been printed so far.
+    reported_events: usize,
+    control: Option<PathBuf>,
+    watch_binary: bool,
+    record_stops: Option<StopRecorder>,
+    diff_stops: Option<StopDiff>,
+}
+
+enum Input {
+    // A line typed at the prompt, and where to say whether to keep going.
+    Line(String, mpsc::Sender<bool>),
+    Control(String, UnixStream),
+    // The binary was written anew.
+    Rebuilt,
+    Eof,
+    Failed(String),
+}
+
+/// Reads the prompt on its own thread, waiting for each line to be handled
+/// before showing the prompt again.
+fn spawn_prompt(inputs: mpsc::Sender<Input>) {
+    thread::spawn(move || {
+        let mut rl = Editor::<()>::new();
+        // Alt-E fills in `edit`, Alt-W `whereami` and Alt-R a switch between
+        // the compact and full register views, enter then runs them.
+        for (key, command) in &[('E', "edit"), ('W', "whereami"), ('R', "regs toggle")] {
+            rl.bind_sequence(
+                KeyPress::Meta(*key),
+                Cmd::Replace(Movement::WholeLine, Some(command.to_string())),
+            );
+        }
+        loop {
+            let input = match rl.readline("> ") {
+                Ok(line) => {
+                    let (done, handled) = mpsc::channel();
+                    if inputs.send(Input::Line(line, done)).is_err() {
+                        return;
+                    }
+                    match handled.recv() {
+                        Ok(true) => continue,
+                        _ => return,
+                    }
+                }
+                Err(ReadlineError::Interrupted) => continue,
+                Err(ReadlineError::Eof) => Input::Eof,
+                Err(err) => Input::Failed(err.to_string()),
+            };
+            let _ = inputs.send(input);
+            return;
+        }
+    });
+}
+
+/// Sends `Input::Rebuilt` each time the binary at `path` is written. Its
+/// directory is what's watched, since linkers replace the file rather than
+/// write over it.
+fn spawn_watcher(path: &Path, inputs: mpsc::Sender<Input>) -> Result<()> {
+    let dir = path
+        .parent()
+        .ok_or("the binary has no directory to watch")?;
+    let name = path.file_name().map(|name| name.to_owned());
+    let mut watch = inotify_watch(dir)?;
+    thread::spawn(move || loop {
+        let names = match inotify_names(&mut watch) {
+            Ok(names) => names,
+            Err(_) => return,
+        };
+        if names.iter().any(|changed| Some(changed) == name.as_ref())
+            && inputs.send(Input::Rebuilt).is_err()
+        {
+            return;
+        }
+    });
+    Ok(())
+}
+
+/// The `--control` socket, removed again when dropped.
+struct ControlSocket {
+    path: PathBuf,
+}
+
+impl ControlSocket {
+    fn listen(path: &Path, inputs: mpsc::Sender<Input>) -> Result<Self> {
+        // Only clear out a stale socket, never some other file.
+        if let Ok(metadata) = std::fs::symlink_metadata(path) {
+            if metadata.file_type().is_socket() {
+                std::fs::remove_file(path)?;
+            }
+        }
+        let listener = UnixListener::bind(path)?;
+
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let stream = match stream {
+                    Ok(stream) => stream,
+                    Err(e) => {
+                        warn!("control socket: {}", e);
+                        continue;
+                    }
+                };
+                let mut line = String::new();
+                let read = stream
+                    .try_clone()
+                    .and_then(|s| BufReader::new(s).read_line(&mut line));
+                if let Err(e) = read {
+                    warn!("control socket: {}", e);
+                    continue;
+                }
+                if inputs.send(Input::Control(line, stream)).is_err() {
+                    return;
+                }
+            }
+        });
+
+        Ok(Self {
+            path: path.to_owned(),
+        })
+    }
+}
+
+impl Drop for ControlSocket {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Radix {
+    Hex,
+    Decimal,
+}
+
+#[derive(Debug, Clone)]
+pub struct Settings {
+    pub radix: Radix,
+    // Where `trace export` writes, the file is rewritten when the session
+    // ends so a trace that runs to exit is complete.
+    pub trace_export: Option<String>,
+    // Whether `cont` sums up what changed between stops.
+    pub show_changes: bool,
+    // Whether the source around where the process stopped is listed.
+    pub show_source: bool,
+    pub displays: Vec<Display>,
+    pub examine: Examine,
+    // Whether `regs` lists every register rather than the common ones.
+    pub full_registers: bool,
+    // The instructions `si` ran, oldest first.
+    pub history: VecDeque<Executed>,
+    // Built the first time `xref` is used, as it disassembles everything.
+    pub xrefs: Option<Xrefs>,
+    // What immediates and printed values are recognised as.
+    pub annotations: Annotations,
+}
+
+/// An instruction `si` ran and the registers it changed, for `history`.
+#[derive(Debug, Clone)]
+pub struct Executed {
+    addr: u64,
+    // Enough for the longest instruction, decoded when shown.
+    bytes: Vec<u8>,
+    changes: Vec<(&'static str, u64, u64)>,
+}
+
+/// An expression shown after every command that runs the subordinate.
+#[derive(Debug, Clone)]
+pub struct Display {
+    expr: String,
+    // What it showed last time, to point out changes.
+    last: Option<String>,
+}
+
+/// The format `x` last used and where it stopped, so a bare `x` carries on
+/// from there.
+#[derive(Debug, Clone, Copy)]
+pub struct Examine {
+    format: char,
+    unit: usize,
+    next: Option<u64>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            radix: Radix::Hex,
+            trace_export: None,
+            show_changes: false,
+            show_source: false,
+            displays: Vec::new(),
+            examine: Examine {
+                format: 'x',
+                unit: 4,
+                next: None,
+            },
+            full_registers: false,
+            history: VecDeque::new(),
+            xrefs: None,
+            annotations: Annotations::default(),
+        }
+    }
+}
+
+/// Registers and watched memory at a stop, to diff against the next one.
+struct Snapshot {
+    registers: Registers,
+    memory: Vec<(usize, Vec<u8>)>,
+}
+
+impl Snapshot {
+    fn take(subordinate: &Subordinate) -> Self {
+        let memory = subordinate
+            .hardware_breakpoints()
+            .filter(|hw| hw.access != Access::Execute)
+            .filter_map(|hw| Some((hw.addr, subordinate.read_bytes(hw.addr, hw.len).ok()?)))
+            .collect();
+        Self {
+            registers: subordinate.registers().clone(),
+            memory,
+        }
+    }
+
+    /// e.g. `rax 0x1→0x40, [0x7ffc3a20] 0x0→0xdead`. rip is left out, it
+    /// always changes and the stop already says where it is.
+    fn changes(&self, now: &Snapshot) -> Vec<String> {
+        let mut changes: Vec<String> = self
+            .registers
+            .diff(&now.registers)
+            .into_iter()
+            .filter(|(name, _, _)| *name != "rip")
+            .map(|(name, old, new)| format!("{} 0x{:x}→0x{:x}", name, old, new))
+            .collect();
+        for (addr, old) in &self.memory {
+            let new = now.memory.iter().find(|(a, _)| a == addr);
+            if let Some((_, new)) = new.filter(|(_, new)| new != old) {
+                changes.push(format!(
+                    "[0x{:x}] 0x{:x}→0x{:x}",
+                    addr,
+                    word(old),
+                    word(new)
+                ));
+            }
+        }
+        changes
+    }
+}
+
+fn word(bytes: &[u8]) -> u64 {
+    let mut buf = [0u8; 8];
+    buf[..bytes.len()].copy_from_slice(bytes);
+    u64::from_le_bytes(buf)
+}
+
+impl Settings {
+    /// Formats a register-sized value in the current radix. Values small
+    /// enough to be counts or offsets are also shown in the other radix.
+    pub fn format(&self, value: u64) -> String {
+        let both = (10..=0xffff).contains(&value);
+        match (self.radix, both) {
+            (Radix::Hex, false) => format!("0x{:x}", value),
+            (Radix::Hex, true) => format!("0x{:x} ({})", value, value),
+            (Radix::Decimal, false) => format!("{}", value),
+            (Radix::Decimal, true) => format!("{} (0x{:x})", value, value),
+        }
+    }
 }
 
 impl Cli {
     pub fn new(subordinate: Subordinate) -> Self {
-        Self { subordinate }
+        Self {
+            subordinate,
+            settings: Settings::default(),
+            reported_events: 0,
+            control: None,
+            watch_binary: false,
+            record_stops: None,
+            diff_stops: None,
+        }
+    }
+
+    /// Also takes commands from a Unix socket at `path`, one per connection,
+    /// answering with the command's output.
+    pub fn control_socket(&mut self, path: PathBuf) {
+        self.control = Some(path);
+    }
+
+    /// Breaks on the function `name` before anything runs, like the test
+    /// `cargo test` was asked for.
+    pub fn break_on(&mut self, name: &str) -> Result<()> {
+        let addr = match self.subordinate.symbol(name) {
+            Some(symbol) => symbol.value,
+            None => return Err(format!("couldn't find symbol with name \"{}\"", name).into()),
+        };
+        self.subordinate.breakpoint(addr as usize)?;
+        println!("breakpoint at 0x{:x} <{}>", addr, name);
+        Ok(())
+    }
+
+    /// Reads the binary's symbols again whenever it's rebuilt.
+    pub fn watch_binary(&mut self) {
+        self.watch_binary = true;
+    }
+
+    /// Writes where the process stops and its registers there to `path`.
+    pub fn record_stops(&mut self, path: &Path) -> Result<()> {
+        self.record_stops = Some(StopRecorder::create(path)?);
+        Ok(())
+    }
+
+    /// Compares each stop against the ones recorded at `path` and says
+    /// where the two runs first differ.
+    pub fn diff_stops(&mut self, path: &Path) -> Result<()> {
+        self.diff_stops = Some(StopDiff::load(path)?);
+        Ok(())
     }
 
     pub fn start(&mut self) -> Result<()> {
-        let mut rl = Editor::<()>::new();
-        loop {
-            let readline = rl.readline("> ");
-            match readline {
-                Ok(line) => {
-                    execute_command(&mut self.subordinate, line.split_whitespace().collect())?;
-                    if let Some(exit_status) = self.subordinate.exit_status() {
-                        println!("debugged process exited with status: {}", exit_status);
+        for warning in self.subordinate.capabilities().warnings() {
+            println!("warning: {}", warning);
+        }
+        warn_build_id(&self.subordinate);
+        warn_launch(&self.subordinate);
+        if self.subordinate.is_core() {
+            let signal = self.subordinate.stop_signal().unwrap_or(0);
+            let rip = self.subordinate.registers().rip;
+            println!(
+                "process {} dumped core on {} at {}",
+                self.subordinate.pid(),
+                signal_name(signal).unwrap_or("unknown signal"),
+                self.subordinate.describe(rip)
+            );
+        }
+        if self.subordinate.is_running() {
+            // The first reading is what later stops are compared against.
+            self.subordinate.map_changes()?;
+            self.subordinate.update_libraries()?;
+        } else {
+            println!(
+                "inspecting {}, `run` starts it",
+                self.subordinate.path().display()
+            );
+        }
+
+        // ptrace requests have to come from this thread, so the prompt and
+        // the control socket read on their own threads and send input here,
+        // as does the watcher for rebuilds.
+        let (inputs, received) = mpsc::channel();
+        spawn_prompt(inputs.clone());
+        if self.watch_binary {
+            spawn_watcher(self.subordinate.path(), inputs.clone())?;
+        }
+        let _listener = match &self.control {
+            Some(path) => Some(ControlSocket::listen(path, inputs)?),
+            None => None,
+        };
+
+        for input in received {
+            match input {
+                Input::Line(line, done) => {
+                    let go_on = self.execute_line(&line)?;
+                    let _ = done.send(go_on);
+                    if !go_on {
+                        break;
+                    }
+                }
+                Input::Control(line, mut stream) => {
+                    let cmd: Vec<&str> = line.split_whitespace().collect();
+                    // A child forked now would inherit the capture pipe as
+                    // its stdout.
+                    if matches!(cmd.as_slice(), ["run"] | ["restart"]) {
+                        let _ = writeln!(stream, "error: run is only available at the prompt");
+                        continue;
+                    }
+                    let (go_on, output) = capture_stdout(|| self.execute_line(&line))?;
+                    let _ = stream.write_all(output.as_bytes());
+                    if !go_on? {
                         break;
                     }
                 }
-                Err(ReadlineError::Interrupted) => break,
-                Err(ReadlineError::Eof) => break,
-                Err(err) => return Err(err.into()),
+                Input::Rebuilt if self.subordinate.is_running() => println!(
+                    "{} was rebuilt, `run` restarts it with the new one",
+                    self.subordinate.path().display()
+                ),
+                Input::Rebuilt => {
+                    self.execute_line("reload-symbols")?;
+                }
+                Input::Eof => break,
+                Input::Failed(e) => return Err(e.into()),
+            }
+        }
+
+        // Don't leave the process stopped under a tracer that's gone.
+        if self.subordinate.is_running() {
+            self.subordinate.kill()?;
+            self.finish_run()?;
+        }
+        Ok(())
+    }
+
+    /// Runs one command line, returning false when the session should end.
+    fn execute_line(&mut self, line: &str) -> Result<bool> {
+        let cmd: Vec<&str> = line.split_whitespace().collect();
+        if let ["source", file] = cmd.as_slice() {
+            return self.source(file);
+        }
+        let restarting = matches!(cmd.as_slice(), ["run"] | ["restart"]);
+        let resuming = resumes(&cmd);
+        let was_running = self.subordinate.is_running();
+        if !was_running && needs_process(&cmd) && calculation(line).is_none() {
+            println!("error: the program is not running, `run` starts it again");
+            return Ok(true);
+        }
+        if resuming && !restarting {
+            if let Err(e) = self.subordinate.ensure_live() {
+                println!("error: {}", e);
+                return Ok(true);
+            }
+        }
+
+        match execute_command(&mut self.subordinate, &mut self.settings, cmd) {
+            Ok(()) if restarting => {
+                self.reported_events = 0;
+                // The binary may have been rebuilt.
+                self.settings.xrefs = None;
+                warn_build_id(&self.subordinate);
+                warn_launch(&self.subordinate);
+                self.subordinate.map_changes()?;
+            }
+            Ok(()) => {}
+            Err(Error::TargetGone(pid)) => {
+                println!("debugged process {} is gone", pid);
+                // How it ended is reported below like any other end of a
+                // run, and the session carries on without it.
+                self.subordinate.reap()?;
+            }
+            Err(Error::Errno(errno)) => {
+                println!("error: {}", std::io::Error::from_raw_os_error(errno));
+            }
+            Err(e) => println!("error: {}", e),
+        }
+        let events = self.subordinate.trace_events();
+        for event in &events[self.reported_events..] {
+            println!("[trace] {}", event.description);
+        }
+        self.reported_events = events.len();
+        if self.subordinate.is_detached() {
+            println!("detached from process {}", self.subordinate.pid());
+            return Ok(false);
+        }
+        if was_running && !self.subordinate.is_running() {
+            self.finish_run()?;
+        } else if resuming && self.subordinate.is_running() {
+            // SIGINT is reported as an interruption, SIGTRAP is ours.
+            let signal = self.subordinate.stop_signal().unwrap_or(0);
+            if let Some(name) =
+                signal_name(signal).filter(|_| signal != libc::SIGINT && signal != libc::SIGTRAP)
+            {
+                let rip = self.subordinate.registers().rip;
+                println!("stopped by {} at {}", name, self.subordinate.describe(rip));
+            }
+            if let Some(call) = self.subordinate.caught() {
+                println!("caught {}", call);
+            }
+            if self.subordinate.at_panic() {
+                println!("caught panic");
+                print_backtrace(&self.subordinate, &[])?;
+            }
+            for change in self.subordinate.map_changes()? {
+                println!("memory {}", change);
+            }
+            self.subordinate.update_libraries()?;
+            print_displays(&self.subordinate, &mut self.settings);
+            if self.settings.show_source {
+                let rip = self.subordinate.registers().rip;
+                // Stops outside the program's own code have no source.
+                let _ = print_source(&mut self.subordinate, rip);
+            }
+            if let Some(recorder) = &mut self.record_stops {
+                recorder.record(&self.subordinate)?;
+            }
+            let subordinate = &self.subordinate;
+            if let Some(divergence) = self
+                .diff_stops
+                .as_mut()
+                .and_then(|diff| diff.check(subordinate))
+            {
+                println!("{}", divergence);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Runs the commands in a script, one per line, skipping blank lines
+    /// and `#` comments.
+    fn source(&mut self, file: &str) -> Result<bool> {
+        let script = match std::fs::read_to_string(file) {
+            Ok(script) => script,
+            Err(e) => {
+                println!("error: couldn't read {}: {}", file, e);
+                return Ok(true);
+            }
+        };
+        for line in script.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if !self.execute_line(line)? {
+                return Ok(false);
             }
         }
+        Ok(true)
+    }
 
+    /// Reports how the process ended and writes out what was traced.
+    fn finish_run(&self) -> Result<()> {
+        if let Some(exit_status) = self.subordinate.exit_status() {
+            println!("debugged process exited with status: {}", exit_status);
+        }
+        if let Some(signal) = self.subordinate.term_signal() {
+            let name = signal_name(signal).unwrap_or("unknown signal");
+            println!("debugged process was killed by {}", name);
+        }
+        self.print_files();
+        if let Some(divergence) = self.diff_stops.as_ref().and_then(StopDiff::finish) {
+            println!("{}", divergence);
+        }
+        if let Some(file) = &self.settings.trace_export {
+            export_trace(&self.subordinate, file)?;
+        }
         Ok(())
     }
+
+    /// Lists the paths traced syscalls looked up, once each in first-seen
+    /// order, with the errno if no lookup of it succeeded.
+    fn print_files(&self) {
+        let mut files: Vec<(&str, Option<i32>)> = Vec::new();
+        for event in self.subordinate.trace_events() {
+            let path = match &event.path {
+                Some(path) => path.as_str(),
+                None => continue,
+            };
+            match files.iter_mut().find(|(seen, _)| *seen == path) {
+                Some((_, last)) => *last = last.and(event.errno),
+                None => files.push((path, event.errno)),
+            }
+        }
+
+        if files.is_empty() {
+            return;
+        }
+        println!("files touched:");
+        for (path, errno) in files {
+            match errno.map(|errno| syscalls::errno_name(errno).unwrap_or("error")) {
+                Some(name) => println!("  {} ({})", path, name),
+                None => println!("  {}", path),
+            }
+        }
+    }
 }
 
-fn execute_command(subordinate: &mut Subordinate, cmd: Vec<&str>) -> Result<()> {
+/// Whether a command lets the subordinate run, so there's a new stop to
+/// show displays at.
+fn resumes(cmd: &[&str]) -> bool {
+    matches!(
+        cmd,
+        ["c"]
+            | ["cont"]
+            | ["si"]
+            | ["stepi"]
+            | ["syscall"]
+            | ["stepo"]
+            | ["si", _]
+            | ["stepi", _]
+            | ["stepo", _]
+            | ["until", _]
+            | ["advance", _]
+            | ["skip-insn"]
+            | ["reverse-step"]
+            | ["rsi"]
+            | ["reverse-step", _]
+            | ["rsi", _]
+            | ["reverse-continue"]
+            | ["rc"]
+            | ["run"]
+            | ["restart"]
+    )
+}
+
+/// Whether a command acts on the live process, as opposed to the session's
+/// own state, which stays usable after the process is gone.
+fn needs_process(cmd: &[&str]) -> bool {
+    !matches!(
+        cmd,
+        [] | ["run"]
+            | ["restart"]
+            | ["restart", _]
+            | ["info", "checkpoints"]
+            | ["events", ..]
+            | ["trace", "export", _]
+            | ["set", "radix", _]
+            | ["set", "show-changes", _]
+            | ["set", "show-source", _]
+            | ["set", "annotate", _, _]
+            | ["info", "annotations"]
+            | ["set", "stack-limit", _]
+            | ["set", "print", "mangled", _]
+            | ["set", "follow-fork-mode", _]
+            | ["set", "rlimit", ..]
+            | ["handle", ..]
+            | ["info", "signals"]
+            | ["info", "rlimits"]
+            | ["formatter", ..]
+            | ["formatters"]
+            | ["ptype", ..]
+            | ["sizeof", ..]
+            | ["offsetof", ..]
+            | ["layout", "type", ..]
+            | ["objdump", _]
+            | ["syms"]
+            | ["symbols"]
+            | ["sym", _]
+            | ["symbol", _]
+            | ["info", "strings", ..]
+            | ["info", "elf"]
+            | ["reload-symbols"]
+            | ["l", _]
+            | ["list", _]
+    )
+}
+
+fn execute_command(
+    subordinate: &mut Subordinate,
+    settings: &mut Settings,
+    cmd: Vec<&str>,
+) -> Result<()> {
     match cmd.as_slice() {
-        ["regs"] | ["registers"] => print_registers(subordinate)?,
-        ["r", name] | ["reg", name] | ["register", name] => print_register(subordinate, name)?,
-        ["si"] | ["stepi"] => subordinate.step()?,
-        ["c"] | ["cont"] => subordinate.cont()?,
-        ["d"] | ["disas"] => {
+        ["regs"] | ["registers"] => print_registers(subordinate, settings)?,
+        ["regs", view] | ["registers", view] => {
+            settings.full_registers = match *view {
+                "full" => true,
+                "compact" => false,
+                "toggle" => !settings.full_registers,
+                other => return Err(format!("unknown register view `{}`", other).into()),
+            };
+            print_registers(subordinate, settings)?
+        }
+        ["r", name] | ["reg", name] | ["register", name] => {
+            print_register(subordinate, settings, name)?
+        }
+        ["detach"] => subordinate.detach()?,
+        ["run"] | ["restart"] => subordinate.restart()?,
+        ["restart", n] => {
+            let n = n
+                .parse()
+                .map_err(|_| format!("`{}` isn't a checkpoint number", n))?;
+            subordinate.restore_checkpoint(n)?;
+            println!(
+                "restored checkpoint {} at {}",
+                n,
+                subordinate.describe(subordinate.registers().rip)
+            );
+        }
+        ["checkpoint"] => {
+            let n = subordinate.checkpoint()?;
+            println!(
+                "checkpoint {} at {}",
+                n,
+                subordinate.describe(subordinate.registers().rip)
+            );
+        }
+        ["info", "checkpoints"] => print_checkpoints(subordinate),
+        ["info", "sharedlibrary"] => print_libraries(subordinate)?,
+        ["info", "elf"] => print_elf(subordinate)?,
+        ["reload-symbols"] => {
+            let notes = subordinate.reload_symbols()?;
+            // The code they were found in may have changed.
+            settings.xrefs = None;
+            println!("reloaded symbols from {}", subordinate.path().display());
+            for note in notes {
+                println!("{}", note);
+            }
+        }
+        ["kill"] => subordinate.kill()?,
+        ["trace", "getenv"] => {
+            let addr = resolve_address(subordinate, "getenv")?;
+            subordinate.tracepoint(addr, Tracepoint::new("getenv", &[Arg::Str]))?
+        }
+        ["trace", "files"] => {
+            let numbers: Vec<u64> = syscalls::FILE_SYSCALLS
+                .iter()
+                .filter_map(|name| syscalls::by_name(name))
+                .map(|syscall| syscall.number)
+                .collect();
+            subordinate.trace_syscalls(&numbers, None);
+        }
+        ["trace", "export", file] => {
+            export_trace(subordinate, file)?;
+            settings.trace_export = Some(file.to_string());
+        }
+        ["trace", "syscalls", rest @ ..] => trace_syscalls(subordinate, rest)?,
+        ["catch", name @ ("mmap" | "mprotect" | "munmap")] => {
+            let syscall = syscalls::by_name(name).ok_or("unknown syscall")?;
+            subordinate.catch_syscall(syscall.number);
+        }
+        ["catch", "panic"] => catch_panic(subordinate)?,
+        ["catch", "load", name] => {
+            if !subordinate.catch_load(name)? {
+                return Err("the program is statically linked, it loads no libraries".into());
+            }
+        }
+        ["catch", "syscall", names @ ..] if !names.is_empty() => {
+            for name in names {
+                let number = match syscalls::by_name(name) {
+                    Some(syscall) => syscall.number,
+                    None => name
+                        .parse()
+                        .map_err(|_| format!("unknown syscall `{}`", name))?,
+                };
+                subordinate.catch_syscall_entry(number);
+            }
+        }
+        ["events"] => print_events(subordinate, Some(20)),
+        ["events", "all"] => print_events(subordinate, None),
+        ["events", "export", file] => {
+            let events = subordinate.events();
+            let mut text: String = events.lines().collect::<Vec<String>>().join("\n");
+            text.push('\n');
+            std::fs::write(file, text)?;
+            println!("wrote {} events to {}", events.len(), file);
+        }
+        ["edit"] => edit_source(subordinate)?,
+        ["whereami"] => print_overview(subordinate),
+        ["bt", args @ ..] | ["backtrace", args @ ..] => print_backtrace(subordinate, args)?,
+        ["frame"] => select_frame(subordinate, subordinate.selected_frame())?,
+        ["frame", n] | ["f", n] => {
+            let n = n
+                .parse()
+                .map_err(|_| format!("not a frame number: {}", n))?;
+            select_frame(subordinate, n)?
+        }
+        ["up"] => select_frame(subordinate, subordinate.selected_frame() + 1)?,
+        ["down"] => match subordinate.selected_frame() {
+            0 => println!("already at the innermost frame"),
+            n => select_frame(subordinate, n - 1)?,
+        },
+        ["info", "locals"] => print_locals(subordinate)?,
+        ["display"] => print_displays(subordinate, settings),
+        ["display", rest @ ..] => {
+            let expr = rest.join(" ");
+            Expr::parse(&expr)?;
+            settings.displays.push(Display { expr, last: None });
+            print_displays(subordinate, settings);
+        }
+        ["undisplay", n] => {
+            let n: usize = n
+                .parse()
+                .map_err(|_| format!("not a display number: {}", n))?;
+            if n == 0 || n > settings.displays.len() {
+                return Err(format!("no display {}", n).into());
+            }
+            settings.displays.remove(n - 1);
+        }
+        [x, rest @ ..] if *x == "x" || x.starts_with("x/") => {
+            examine(subordinate, settings, x.strip_prefix("x/"), rest)?
+        }
+        ["p", rest @ ..] | ["print", rest @ ..] if !rest.is_empty() => {
+            print_expression(subordinate, settings, &rest.join(" "))?
+        }
+        ["ptype", rest @ ..] if !rest.is_empty() => print_type(subordinate, &rest.join(" "))?,
+        ["sizeof", rest @ ..] if !rest.is_empty() => print_sizeof(subordinate, &rest.join(" "))?,
+        ["layout", "type", rest @ ..] if !rest.is_empty() => {
+            print_layout(subordinate, &rest.join(" "))?
+        }
+        ["offsetof", rest @ ..] if !rest.is_empty() => {
+            print_offsetof(subordinate, &rest.join(" "))?
+        }
+        ["info", "proctree"] => print_process_tree(subordinate)?,
+        ["formatters"] => {
+            for (i, (pattern, template)) in subordinate.formatters().list().enumerate() {
+                println!("{}: {} {}", i + 1, pattern, template);
+            }
+        }
+        ["formatter", "delete", n] => {
+            let n: usize = n
+                .parse()
+                .map_err(|_| format!("not a formatter number: {}", n))?;
+            let index = n
+                .checked_sub(1)
+                .ok_or_else(|| format!("no formatter {}", n))?;
+            subordinate.formatters_mut().remove(index)?
+        }
+        ["formatter", rest @ ..] if !rest.is_empty() => {
+            add_formatter(subordinate, &rest.join(" "))?
+        }
+        ["info", "threads"] => print_threads(subordinate)?,
+        ["info", "strings"] => print_strings(subordinate, settings, None)?,
+        ["info", "strings", pattern] => print_strings(subordinate, settings, Some(pattern))?,
+        ["thread"] => print_thread(subordinate)?,
+        ["thread", n] => {
+            let n = n
+                .parse()
+                .map_err(|_| format!("not a thread number: {}", n))?;
+            subordinate.select_thread(n)?;
+            print_thread(subordinate)?
+        }
+        ["info", "float"] => print_float(subordinate)?,
+        ["info", "vector"] => print_vector(subordinate)?,
+        ["info", "maps"] => print_maps(subordinate, None)?,
+        ["info", "maps", addr @ ..] => {
+            let addr = examine_address(subordinate, &addr.join(" "))?;
+            print_maps(subordinate, Some(addr))?
+        }
+        ["stack"] => print_stack(subordinate, settings)?,
+        ["set", "var", rest @ ..] | ["set", "variable", rest @ ..] => {
+            set_variable(subordinate, &rest.join(" "))?
+        }
+        ["set", register, ..] if register.starts_with('$') => {
+            set_register(subordinate, settings, &cmd[1..].join(" "))?
+        }
+        ["set", unit, rest @ ..] if store_size(unit).is_some() => {
+            set_memory(subordinate, unit, &rest.join(" "))?
+        }
+        ["set", "rlimit", resource, limit] => {
+            let limit = match *limit {
+                "unlimited" => None,
+                limit => Some(parse_size(limit)?),
+            };
+            subordinate.launch_mut().set_rlimit(resource, limit)?;
+            println!("{} limit set, it applies from the next run", resource);
+        }
+        ["info", "rlimits"] => print_rlimits(subordinate),
+        ["handle"] | ["info", "signals"] => print_signals(subordinate, None),
+        ["handle", signal, actions @ ..] => handle_signal(subordinate, signal, actions)?,
+        ["set", "print", "mangled", "on"] => subordinate.set_print_mangled(true),
+        ["set", "print", "mangled", "off"] => subordinate.set_print_mangled(false),
+        ["set", "radix", radix] => set_radix(settings, radix)?,
+        ["set", "stack-limit", "off"] => subordinate.set_stack_limit(None),
+        ["set", "stack-limit", size] => subordinate.set_stack_limit(Some(parse_size(size)?)),
+        ["set", "show-changes", "on"] => settings.show_changes = true,
+        ["set", "show-changes", "off"] => settings.show_changes = false,
+        ["set", "show-source", "on"] => settings.show_source = true,
+        ["set", "show-source", "off"] => settings.show_source = false,
+        ["set", "annotate", table, "on"] => settings.annotations.set(table, true)?,
+        ["set", "annotate", table, "off"] => settings.annotations.set(table, false)?,
+        ["info", "annotations"] => {
+            for (table, on) in settings.annotations.list() {
+                println!("{} {}", table, if on { "on" } else { "off" });
+            }
+        }
+        ["set", "step-mode", "hardware"] => subordinate.set_step_mode(StepMode::Hardware),
+        ["set", "step-mode", "software"] => subordinate.set_step_mode(StepMode::Software),
+        ["set", "follow-fork-mode", "parent"] => subordinate.set_follow_fork(FollowFork::Parent),
+        ["set", "follow-fork-mode", "child"] => subordinate.set_follow_fork(FollowFork::Child),
+        ["si"] | ["stepi"] => step_instructions(subordinate, settings, 1)?,
+        ["si", n] | ["stepi", n] => step_instructions(subordinate, settings, step_count(n)?)?,
+        ["history"] => print_history(settings, Some(20))?,
+        ["history", "all"] => print_history(settings, None)?,
+        ["history", n] => {
+            let n = n
+                .parse()
+                .map_err(|_| format!("not a number of instructions: {}", n))?;
+            print_history(settings, Some(n))?
+        }
+        ["syscall"] => {
+            if let Some(call) = subordinate.syscall_step()? {
+                println!("{}", call);
+            }
+        }
+        ["stepo"] => step_over(subordinate)?,
+        ["stepo", n] => repeat_steps(subordinate, step_count(n)?, step_over)?,
+        ["until", addr] | ["advance", addr] => {
+            let addr = resolve_address(subordinate, addr)?;
+            subordinate.run_to(addr)?
+        }
+        ["skip-insn"] => skip_instruction(subordinate)?,
+        ["record"] => {
+            subordinate.set_recording(true);
+            println!("recording steps");
+        }
+        ["record", "stop"] => {
+            subordinate.set_recording(false);
+            println!("stopped recording");
+        }
+        ["info", "record"] => {
+            if subordinate.is_recording() {
+                println!(
+                    "recording, {} steps to go back over",
+                    subordinate.recorded_steps()
+                );
+            } else {
+                println!("not recording");
+            }
+        }
+        ["reverse-step"] | ["rsi"] => subordinate.reverse_step()?,
+        ["reverse-step", n] | ["rsi", n] => {
+            for _ in 0..step_count(n)? {
+                subordinate.reverse_step()?;
+            }
+        }
+        ["reverse-continue"] | ["rc"] => {
+            let (undone, at_breakpoint) = subordinate.reverse_cont()?;
             let rip = subordinate.registers().rip;
+            if at_breakpoint {
+                println!(
+                    "back at breakpoint {} after {} steps",
+                    subordinate.describe(rip),
+                    undone
+                );
+            } else {
+                println!(
+                    "back at the start of the recording {} after {} steps",
+                    subordinate.describe(rip),
+                    undone
+                );
+            }
+        }
+        ["undo"] => match subordinate.undo()? {
+            Some(restored) => println!("restored {}", restored),
+            None => println!("nothing to undo"),
+        },
+        ["c"] | ["cont"] => {
+            let before = if settings.show_changes {
+                Some(Snapshot::take(subordinate))
+            } else {
+                None
+            };
+            subordinate.cont()?;
+            if let Some(before) = before.filter(|_| subordinate.is_running()) {
+                let changes = before.changes(&Snapshot::take(subordinate));
+                if changes.is_empty() {
+                    println!("changed: nothing");
+                } else {
+                    println!("changed: {}", changes.join(", "));
+                }
+            }
+            if subordinate.stop_signal() == Some(libc::SIGINT) {
+                println!("interrupted at 0x{:x}", subordinate.registers().rip);
+            }
+            if let Some(depth) = subordinate.stack_exceeded() {
+                println!(
+                    "stopped at {} with the stack {} bytes deep, past the limit of {}",
+                    subordinate.describe(subordinate.registers().rip),
+                    depth,
+                    subordinate.stack_limit().unwrap_or(0)
+                );
+            }
+            print_watch_hit(subordinate, settings);
+            for addr in subordinate.left_scope() {
+                println!("watchpoint 0x{:x} deleted, its frame returned", addr);
+            }
+        }
+        ["d"] | ["disas"] => {
+            let rip = subordinate.frame_registers().rip;
             let bytes = subordinate.read_bytes(rip as usize, 64)?;
-            let disassembly = Disassembler::new().disassemble(rip, &bytes)?;
-            println!("{}", disassembly);
-        }
-        ["d", sym] | ["disas", sym] => {
-            match subordinate.symbol(sym) {
-                Some(symbol) => {
-                    let rip = symbol.value;
-                    let bytes = subordinate.instructions(symbol)?;
-                    let disassembly = Disassembler::new().disassemble(rip, &bytes)?;
-                    println!("{}", disassembly);
+            let disassembler = Disassembler::annotated(&settings.annotations);
+            // The preview reads the live registers, which only describe the
+            // innermost frame.
+            if subordinate.selected_frame() == 0 {
+                println!("{}", disassembler.preview(subordinate)?);
+                if let Some(branch) = disassembler.branch(subordinate)? {
+                    println!("{}", branch);
                 }
+            }
+            println!(
+                "{}",
+                disassembler.disassemble_symbolized(subordinate, rip, &bytes)?
+            );
+        }
+        ["d", addr] | ["disas", addr] => {
+            // A whole function when given one, otherwise as much as `disas`
+            // shows at rip.
+            let (rip, bytes) = match subordinate.symbol(addr) {
+                Some(symbol) => (symbol.value, subordinate.instructions(symbol)?),
                 None => {
-                    println!("unknwon symbol {}", sym);
+                    let addr = resolve_address(subordinate, addr)?;
+                    (addr as u64, subordinate.read_bytes(addr, 64)?)
                 }
             };
+            let disassembler = Disassembler::annotated(&settings.annotations);
+            println!(
+                "{}",
+                disassembler.disassemble_symbolized(subordinate, rip, &bytes)?
+            );
         }
-        ["l", sym] | ["list", sym] => {
-            let debug_info = subordinate.debug_info();
-            let lines = subordinate
-                .symbol(sym)
-                .and_then(|symbol| debug_info.line_info(symbol.value as usize))
-                .and_then(|line_info| debug_info.lines(&line_info.path));
-
-            if let Some(lines) = lines {
-                lines.iter().for_each(|line| println!("{}", line));
-            } else {
-                println!("couldn't find source code for symbol {}", sym);
-            }
+        ["objdump", name] => {
+            let disassembler = Disassembler::annotated(&settings.annotations);
+            println!("{}", objdump(subordinate.path(), name, &disassembler)?);
+        }
+        ["compare", "disas", left, right] => compare_disassembly(subordinate, left, right)?,
+        ["xref", addr] => print_xrefs(subordinate, settings, addr)?,
+        ["gcore"] => save_core(subordinate, &format!("core.{}", subordinate.pid()))?,
+        ["gcore", path] => save_core(subordinate, path)?,
+        ["l"] | ["list"] => {
+            let rip = subordinate.registers().rip;
+            print_source(subordinate, rip)?
+        }
+        ["l", addr] | ["list", addr] => {
+            let addr = resolve_address(subordinate, addr)?;
+            print_source(subordinate, addr as u64)?
         }
         ["syms"] | ["symbols"] => print_symbols(subordinate)?,
         ["sym", name] | ["symbol", name] => print_symbol(subordinate, name)?,
         ["b", addr] | ["break", addr] => set_breakpoint(subordinate, addr)?,
-        other => println!("unknown command `{:?}`", other),
+        ["rbreak", pattern] => break_matching(subordinate, pattern)?,
+        ["break-module", path] => break_module(subordinate, path)?,
+        ["break-callers", function] => break_callers(subordinate, function)?,
+        ["hb", addr] | ["hbreak", addr] => {
+            let addr = resolve_address(subordinate, addr)?;
+            subordinate.hardware_breakpoint(addr)?
+        }
+        ["watch", "-l", name] => watch_local(subordinate, name)?,
+        ["watch", addr] => {
+            let addr = resolve_address(subordinate, addr)?;
+            subordinate.watchpoint(addr, Access::Write)?
+        }
+        ["rwatch", addr] => {
+            let addr = resolve_address(subordinate, addr)?;
+            subordinate.watchpoint(addr, Access::ReadWrite)?
+        }
+        ["tb", addr] | ["tbreak", addr] => {
+            let addr = resolve_address(subordinate, addr)?;
+            subordinate.temporary_breakpoint(addr)?
+        }
+        ["b", addr, "thread", n] | ["break", addr, "thread", n] => {
+            let addr = resolve_address(subordinate, addr)?;
+            let number = n
+                .parse()
+                .map_err(|_| format!("not a thread number: {}", n))?;
+            subordinate.thread_breakpoint(addr, number)?
+        }
+        ["b", addr, "if", condition @ ..] | ["break", addr, "if", condition @ ..] => {
+            set_conditional_breakpoint(subordinate, addr, &condition.join(" "))?
+        }
+        other => match calculation(&other.join(" ")) {
+            Some(expr) => print_calculation(subordinate, &expr)?,
+            None => println!("unknown command `{:?}`", other),
+        },
     };
 
     Ok(())
 }
 
+/// Breaks at `addr`, or once a library defines it when it's a symbol no
+/// loaded code has yet.
 fn set_breakpoint(subordinate: &mut Subordinate, addr: &str) -> Result<()> {
-    if let Some(hex) = addr.strip_prefix("0x") {
-        if let Ok(addr) = usize::from_str_radix(hex, 16) {
-            return subordinate.breakpoint(addr);
+    let error = match resolve_address(subordinate, addr) {
+        Ok(addr) => return subordinate.breakpoint(addr),
+        Err(e) => e,
+    };
+    match Expr::parse(addr) {
+        // Only the program's own lines are known, none can come later.
+        Ok(Expr::Symbol(name))
+            if file_line(addr).is_none() && subordinate.break_pending(&name)? =>
+        {
+            println!("breakpoint on {} pending until a library defines it", name);
+            Ok(())
         }
+        _ => Err(error),
     }
-
-    let name = addr;
-    if let Some(symbol) = subordinate.symbol(name).map(|s| s.to_owned()) {
-        return subordinate.breakpoint(symbol.value as usize);
-    }
-
-    Err(format!(
-        "couldn't set breakpoint on `{}`, not a known address or symbol",
-        addr
-    )
-    .into())
 }
 
-fn print_registers(subordinate: &mut Subordinate) -> Result<()> {
-    let regs = subordinate.registers();
+/// Breaks on every function whose name matches `pattern`.
+fn break_matching(subordinate: &mut Subordinate, pattern: &str) -> Result<()> {
+    let regex = Regex::new(pattern).map_err(|e| e.to_string())?;
+    let functions: Vec<(u64, String)> = subordinate
+        .symbols()
+        .iter()
+        .filter(|symbol| symbol.symtype == elf::types::STT_FUNC && symbol.value != 0)
+        .filter(|symbol| regex.is_match(&symbol.name))
+        .map(|symbol| (symbol.value, subordinate.symbol_name(symbol)))
+        .collect();
+    set_breakpoints(subordinate, functions)
+}
 
-    println!("rip: 0x{:x}", regs.rip);
-    println!("rsp: 0x{:x}", regs.rsp);
-    println!("rbp: 0x{:x}", regs.rbp);
-    println!("rax: 0x{:x}", regs.rax);
-    println!("rbx: 0x{:x}", regs.rbx);
-    println!("rcx: 0x{:x}", regs.rcx);
-    println!("rdx: 0x{:x}", regs.rdx);
-    println!("rdi: 0x{:x}", regs.rdi);
-    println!("rsi: 0x{:x}", regs.rsi);
+/// Functions every Rust panic goes through, the first one found is used.
+/// `rust_panic` is there for debuggers to break on, `__rust_start_panic` is
+/// a fallback for binaries where it was inlined away.
+const PANIC_FUNCTIONS: &[&str] = &["rust_panic", "__rust_start_panic"];
 
+/// `catch panic`, breaking where a panic starts unwinding so the
+/// backtrace shows where it came from.
+fn catch_panic(subordinate: &mut Subordinate) -> Result<()> {
+    // `rust_panic` is mangled as `std::panicking::rust_panic` in some
+    // versions of std, so names are compared without their path.
+    let found = PANIC_FUNCTIONS.iter().find_map(|name| {
+        subordinate
+            .symbols()
+            .iter()
+            .filter(|symbol| symbol.symtype == elf::types::STT_FUNC && symbol.value != 0)
+            .find(|symbol| {
+                let demangled = subordinate.demangler().demangle(&symbol.name);
+                demangled.rsplit("::").next() == Some(*name)
+            })
+            .map(|symbol| (symbol.value, subordinate.symbol_name(symbol)))
+    });
+    let (addr, name) = found.ok_or("no panic machinery in this binary, is it a Rust program?")?;
+    subordinate.panic_breakpoint(addr as usize)?;
+    println!("catching panics at {}", name);
     Ok(())
 }
 
-fn print_register(subordinate: &mut Subordinate, name: &str) -> Result<()> {
-    match subordinate.registers().get(name) {
-        Some(value) => {
-            println!("{} 0x{:x}", name, value);
+/// Breaks on every function compiled from a source file whose path ends
+/// with `path`, e.g. `parser.c` or `src/net/`.
+fn break_module(subordinate: &mut Subordinate, path: &str) -> Result<()> {
+    let bias = subordinate.load_bias();
+    let suffix = Path::new(path.trim_end_matches('/'));
+    let functions: Vec<(u64, String)> = subordinate
+        .debug_info()
+        .functions()
+        .iter()
+        .filter(|function| {
+            function.file.ends_with(suffix)
+                || function
+                    .file
+                    .parent()
+                    .is_some_and(|dir| dir.ends_with(suffix))
+        })
+        .map(|function| (function.low_pc + bias, function.name.clone()))
+        .collect();
+    set_breakpoints(subordinate, functions)
+}
+
+/// Breaks on every direct call to `function`, found by disassembling all
+/// the functions in the symbol table.
+fn break_callers(subordinate: &mut Subordinate, function: &str) -> Result<()> {
+    let target = resolve_address(subordinate, function)? as u64;
+    let disassembler = Disassembler::new();
+    let mut sites = Vec::new();
+    for symbol in subordinate.symbols() {
+        if symbol.symtype != elf::types::STT_FUNC || symbol.value == 0 || symbol.size == 0 {
+            continue;
         }
-        None => {
-            println!("couldn't find register with name \"{}\"", name);
+        // Functions in sections that aren't loaded can't be read.
+        let bytes = match subordinate.instructions(symbol) {
+            Ok(bytes) => bytes,
+            Err(_) => continue,
+        };
+        let name = subordinate.symbol_name(symbol);
+        for site in disassembler.call_sites(symbol.value, &bytes, target) {
+            sites.push((site, format!("{}+0x{:x}", name, site - symbol.value)));
         }
     }
-    Ok(())
+    set_breakpoints(subordinate, sites)
 }
 
-fn print_symbols(subordinate: &mut Subordinate) -> Result<()> {
-    for symbol in subordinate.symbols().into_iter() {
-        if symbol.symtype != elf::types::STT_FUNC {
-            continue;
-        }
-        println!("0x{:x} {}", symbol.value, symbol.name);
+fn set_breakpoints(subordinate: &mut Subordinate, mut functions: Vec<(u64, String)>) -> Result<()> {
+    functions.sort();
+    functions.dedup_by_key(|(addr, _)| *addr);
+    // Setting one again would drop its condition.
+    let existing: Vec<usize> = subordinate
+        .breakpoints()
+        .iter()
+        .map(|(addr, _)| *addr)
+        .collect();
+    functions.retain(|(addr, _)| !existing.contains(&(*addr as usize)));
+
+    for (addr, name) in &functions {
+        subordinate.breakpoint(*addr as usize)?;
+        println!("breakpoint at 0x{:x} <{}>", addr, name);
     }
+    println!("set {} breakpoints", functions.len());
     Ok(())
 }
 
-fn print_symbol(subordinate: &mut Subordinate, name: &str) -> Result<()> {
-    for symbol in subordinate.symbols() {
-        if symbol.name != name {
-            continue;
-        }
-        println!("0x{:x} {}", symbol.value, symbol.name);
-        return Ok(());
+fn set_conditional_breakpoint(
+    subordinate: &mut Subordinate,
+    addr: &str,
+    condition: &str,
+) -> Result<()> {
+    let condition = Expr::parse(condition)?;
+    let addr = resolve_address(subordinate, addr)?;
+    subordinate.conditional_breakpoint(addr, condition)
+}
+
+/// `trace syscalls [name...] [if <expr>]`, tracing every known syscall when
+/// no names are given.
+fn trace_syscalls(subordinate: &mut Subordinate, args: &[&str]) -> Result<()> {
+    let (names, condition) = match args.iter().position(|arg| *arg == "if") {
+        Some(i) => (&args[..i], Some(Expr::parse(&args[i + 1..].join(" "))?)),
+        None => (args, None),
+    };
+
+    let numbers = if names.is_empty() {
+        syscalls::SYSCALLS
+            .iter()
+            .map(|syscall| syscall.number)
+            .collect()
+    } else {
+        names
+            .iter()
+            .map(|name| {
+                syscalls::by_name(name)
+                    .map(|syscall| syscall.number)
+                    .ok_or_else(|| format!("unknown syscall `{}`", name).into())
+            })
+            .collect::<Result<Vec<u64>>>()?
+    };
+
+    subordinate.trace_syscalls(&numbers, condition);
+    Ok(())
+}
+
+/// Reads an address the same way for every command that takes one: `0x`
+/// hex, decimal, a symbol, or an expression like `main+0x1a` in which
+/// symbols stand for their addresses.
+fn resolve_address(subordinate: &Subordinate, input: &str) -> Result<usize> {
+    // Symbol names can hold characters expressions don't, like `@`.
+    if let Some(symbol) = subordinate.symbol(input) {
+        return Ok(symbol.value as usize);
+    }
+    if let Some((file, line)) = file_line(input) {
+        let addr = subordinate
+            .debug_info()
+            .addr_for_line(Path::new(file), line)
+            .ok_or_else(|| format!("no code at {}:{}", file, line))?;
+        return Ok(addr + subordinate.load_bias() as usize);
+    }
+
+    let expr = Expr::parse(input).map_err(|e| format!("`{}` isn't an address, {}", input, e))?;
+    match expr.evaluate(subordinate) {
+        Ok(addr) => Ok(addr as usize),
+        Err(e) if matches!(expr, Expr::Symbol(_)) => Err(e),
+        Err(e) => Err(format!("{} in `{}`", e, input).into()),
+    }
+}
+
+/// `file:line`, where `file` can be just the file's name.
+fn file_line(input: &str) -> Option<(&str, u64)> {
+    let (file, line) = input.rsplit_once(':')?;
+    let line = line.parse().ok()?;
+    (!file.is_empty()).then_some((file, line))
+}
+
+/// A line that isn't a command but reads as arithmetic, like
+/// `0x7ffe1234 - 0x7ffe1200` or `1 << 12`, for the prompt to work out. A
+/// lone word is more likely a mistyped command than a symbol.
+fn calculation(line: &str) -> Option<Expr> {
+    match Expr::parse(line).ok()? {
+        Expr::Symbol(_) => None,
+        expr => Some(expr),
+    }
+}
+
+/// Shows a result in hex, decimal and binary, e.g. `0x34 = 52 = 0b110100`,
+/// negative in decimal when the top bit is set.
+fn print_calculation(subordinate: &Subordinate, expr: &Expr) -> Result<()> {
+    let value = expr.evaluate(subordinate)?;
+    let decimal = if (value as i64) < 0 {
+        (value as i64).to_string()
+    } else {
+        value.to_string()
+    };
+    println!("0x{:x} = {} = 0b{:b}", value, decimal, value);
+    Ok(())
+}
+
+fn step_count(n: &str) -> Result<usize> {
+    n.parse()
+        .map_err(|_| format!("not a number of steps: {}", n).into())
+}
+
+/// Runs `step` up to `count` times, stopping early when the process stops
+/// for anything else, like Ctrl-C or a breakpoint.
+fn repeat_steps<F>(subordinate: &mut Subordinate, count: usize, mut step: F) -> Result<()>
+where
+    F: FnMut(&mut Subordinate) -> Result<()>,
+{
+    for done in 1..=count {
+        step(subordinate)?;
+        if !subordinate.is_running() || done == count {
+            break;
+        }
+        let rip = subordinate.registers().rip as usize;
+        let at_breakpoint = subordinate
+            .breakpoints()
+            .iter()
+            .any(|(addr, b)| *addr == rip && b.tracepoint.is_none() && !b.watch_scope);
+        let signalled = subordinate.stop_signal() != Some(libc::SIGTRAP);
+        if at_breakpoint || signalled {
+            println!("stopped after {} of {} steps", done, count);
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// `si [n]`, noting the flags if they changed once all the steps are done.
+fn step_instructions(
+    subordinate: &mut Subordinate,
+    settings: &mut Settings,
+    count: usize,
+) -> Result<()> {
+    let before = subordinate.registers().eflags;
+    repeat_steps(subordinate, count, |subordinate| {
+        step_and_record(subordinate, &mut settings.history)
+    })?;
+    let after = subordinate.registers().eflags;
+    if subordinate.is_running() && after != before {
+        println!(
+            "flags {} (was {})",
+            registers::decode_flags(after),
+            registers::decode_flags(before)
+        );
+    }
+    Ok(())
+}
+
+/// Steps one instruction, adding it to `history`.
+fn step_and_record(subordinate: &mut Subordinate, history: &mut VecDeque<Executed>) -> Result<()> {
+    let registers = subordinate.registers().clone();
+    let bytes = subordinate
+        .read_bytes(registers.rip as usize, MAX_INSTRUCTION_LEN)
+        .ok();
+    step_instruction(subordinate)?;
+    if let Some(bytes) = bytes.filter(|_| subordinate.is_running()) {
+        // rip always changes, the new one is the next entry.
+        let mut changes = registers.diff(subordinate.registers());
+        changes.retain(|(name, _, _)| *name != "rip");
+        if history.len() == HISTORY_LEN {
+            history.pop_front();
+        }
+        history.push_back(Executed {
+            addr: registers.rip,
+            bytes,
+            changes,
+        });
+    }
+    Ok(())
+}
+
+/// `stepo`, running to the end of a rep instruction or tight loop, or
+/// stepping one instruction if rip isn't at one.
+fn step_over(subordinate: &mut Subordinate) -> Result<()> {
+    match Disassembler::new().fast_forward_target(subordinate)? {
+        Some(addr) => subordinate.run_to(addr as usize),
+        None => subordinate.step(),
+    }
+}
+
+/// Steps one instruction, showing the call and its result when that
+/// instruction was a syscall.
+fn step_instruction(subordinate: &mut Subordinate) -> Result<()> {
+    if !Disassembler::new().is_syscall(subordinate)? {
+        return subordinate.step();
+    }
+
+    let regs = subordinate.registers();
+    let number = regs.rax;
+    let call = syscalls::format_call(subordinate, number, &syscalls::arguments(regs));
+    subordinate.step()?;
+    if subordinate.exit_status().is_none() {
+        let result = syscalls::format_return(number, subordinate.registers().rax);
+        println!("{} = {}", call, result);
+    }
+    Ok(())
+}
+
+fn export_trace(subordinate: &Subordinate, file: &str) -> Result<()> {
+    let events = subordinate.trace_events();
+    std::fs::write(file, chrome_trace(events, subordinate.pid()))?;
+    println!("wrote {} trace events to {}", events.len(), file);
+    Ok(())
+}
+
+/// `watch -l <local>`, watching the variable's stack slot only for as long
+/// as the selected frame lives.
+fn watch_local(subordinate: &mut Subordinate, name: &str) -> Result<()> {
+    let local = local(subordinate, name).ok_or_else(|| format!("no local called `{}`", name))?;
+    let addr = match local.place {
+        Some(Place::Address(addr)) => addr as usize,
+        _ => return Err(format!("{} isn't in memory here", name).into()),
+    };
+    let size = local.bytes.map_or(8, |bytes| bytes.len());
+    subordinate.scoped_watchpoint(addr, size, Access::Write)?;
+    println!("watching {} at 0x{:x} until its frame returns", name, addr);
+    Ok(())
+}
+
+fn print_watch_hit(subordinate: &Subordinate, settings: &Settings) {
+    let hit = match subordinate.watch_hit() {
+        Some(hit) => hit,
+        None => return,
+    };
+    println!(
+        "watchpoint 0x{:x} hit at {}",
+        hit.watchpoint.addr,
+        subordinate.describe(subordinate.registers().rip)
+    );
+    if hit.old == hit.new {
+        println!("value: {}", settings.format(hit.new));
+    } else {
+        println!("old: {}", settings.format(hit.old));
+        println!("new: {}", settings.format(hit.new));
+    }
+}
+
+/// Moves rip past the current instruction without running it.
+fn skip_instruction(subordinate: &mut Subordinate) -> Result<()> {
+    let (next, warning) = Disassembler::new().skip(subordinate)?;
+    let mut registers = subordinate.registers().clone();
+    registers.rip = next;
+    subordinate.set_registers(registers)?;
+    if let Some(warning) = warning {
+        println!("warning: {}", warning);
+    }
+    println!("rip is now {}", subordinate.describe(next));
+    Ok(())
+}
+
+/// `handle <signal> [stop|nostop] [pass|nopass]`, showing the signal's
+/// policy afterwards.
+fn handle_signal(subordinate: &mut Subordinate, signal: &str, actions: &[&str]) -> Result<()> {
+    let number = signal_number(signal).ok_or_else(|| format!("unknown signal `{}`", signal))?;
+    if number == libc::SIGTRAP || number == libc::SIGKILL {
+        return Err(format!(
+            "{} can't be handled differently",
+            signal_name(number).unwrap()
+        )
+        .into());
+    }
+    let mut policy = subordinate.signals().policy(number);
+    for action in actions {
+        match *action {
+            "stop" => policy.stop = true,
+            "nostop" => policy.stop = false,
+            "pass" => policy.pass = true,
+            "nopass" => policy.pass = false,
+            other => {
+                return Err(format!(
+                    "unknown action `{}`, it's stop, nostop, pass or nopass",
+                    other
+                )
+                .into())
+            }
+        }
+    }
+    subordinate.signals_mut().set(number, policy);
+    print_signals(subordinate, Some(number));
+    Ok(())
+}
+
+/// The policy of one signal or, with None, of every one.
+fn print_signals(subordinate: &Subordinate, only: Option<i32>) {
+    println!("{:<10} {:<5} pass", "signal", "stop");
+    let yes_no = |b: bool| if b { "yes" } else { "no" };
+    for (number, name) in signals() {
+        if only.is_some_and(|only| only != number)
+            || number == libc::SIGTRAP
+            || number == libc::SIGKILL
+        {
+            continue;
+        }
+        let policy = subordinate.signals().policy(number);
+        println!(
+            "{:<10} {:<5} {}",
+            name,
+            yes_no(policy.stop),
+            yes_no(policy.pass)
+        );
+    }
+}
+
+fn print_rlimits(subordinate: &Subordinate) {
+    let rlimits = subordinate.launch().rlimits();
+    if rlimits.is_empty() {
+        println!("no limits set, the process inherits the debugger's");
+    }
+    for (name, limit) in rlimits {
+        match limit {
+            Some(limit) => println!("{:<8} {}", name, format_size(*limit)),
+            None => println!("{:<8} unlimited", name),
+        }
+    }
+}
+
+/// A byte count in the largest unit that divides it, the way `parse_size`
+/// reads them, e.g. `4K` or `132K`.
+fn format_size(bytes: u64) -> String {
+    let units = [(30, "G"), (20, "M"), (10, "K")];
+    for (shift, unit) in units.iter() {
+        if bytes >= 1 << shift && bytes.is_multiple_of(1 << shift) {
+            return format!("{}{}", bytes >> shift, unit);
+        }
+    }
+    format!("{}", bytes)
+}
+
+/// A byte count like `65536`, `512K` or `8M`.
+pub fn parse_size(size: &str) -> Result<u64> {
+    let (digits, unit) = match size.find(|c: char| !c.is_ascii_digit()) {
+        Some(i) => size.split_at(i),
+        None => (size, ""),
+    };
+    let shift = match unit {
+        "" => 0,
+        "K" | "k" => 10,
+        "M" | "m" => 20,
+        "G" | "g" => 30,
+        _ => return Err(format!("not a size: {}", size).into()),
+    };
+    let count: u64 = digits
+        .parse()
+        .map_err(|_| format!("not a size: {}", size))?;
+    count
+        .checked_mul(1 << shift)
+        .ok_or_else(|| "size too large".into())
+}
+
+fn set_radix(settings: &mut Settings, radix: &str) -> Result<()> {
+    settings.radix = match radix {
+        "16" | "hex" => Radix::Hex,
+        "10" | "dec" | "decimal" => Radix::Decimal,
+        other => return Err(format!("unsupported radix `{}`, expected 10 or 16", other).into()),
+    };
+    Ok(())
+}
+
+fn print_registers(subordinate: &mut Subordinate, settings: &Settings) -> Result<()> {
+    let regs = subordinate.frame_registers();
+
+    if settings.full_registers {
+        for name in registers::DISPLAY_NAMES {
+            let value = regs.get(name).unwrap_or(0);
+            if *name == "eflags" {
+                println!("eflags: 0x{:x} {}", value, registers::decode_flags(value));
+            } else {
+                println!("{:<7}{}", format!("{}:", name), settings.format(value));
+            }
+        }
+        return Ok(());
+    }
+
+    println!("rip: {}", settings.format(regs.rip));
+    println!("rsp: {}", settings.format(regs.rsp));
+    println!("rbp: {}", settings.format(regs.rbp));
+    println!("rax: {}", settings.format(regs.rax));
+    println!("rbx: {}", settings.format(regs.rbx));
+    println!("rcx: {}", settings.format(regs.rcx));
+    println!("rdx: {}", settings.format(regs.rdx));
+    println!("rdi: {}", settings.format(regs.rdi));
+    println!("rsi: {}", settings.format(regs.rsi));
+    println!(
+        "eflags: 0x{:x} {}",
+        regs.eflags,
+        registers::decode_flags(regs.eflags)
+    );
+
+    Ok(())
+}
+
+fn print_float(subordinate: &mut Subordinate) -> Result<()> {
+    let regs = subordinate.float_registers()?;
+    println!(
+        "fcw: 0x{:04x} fsw: 0x{:04x} ftw: 0x{:02x} mxcsr: 0x{:08x}",
+        regs.fcw, regs.fsw, regs.ftw, regs.mxcsr
+    );
+    for n in 0..8 {
+        if regs.st_in_use(n) {
+            println!("st{}: {:?}", n, regs.st_value(n));
+        } else {
+            println!("st{}: empty", n);
+        }
+    }
+    for (n, xmm) in regs.xmm.iter().enumerate() {
+        println!("{:<6} {}", format!("xmm{}:", n), vector_lanes(xmm));
+    }
+    Ok(())
+}
+
+fn print_vector(subordinate: &mut Subordinate) -> Result<()> {
+    let regs = subordinate.float_registers()?;
+    let ymm_hi = match regs.ymm_hi {
+        Some(ymm_hi) => ymm_hi,
+        None => {
+            println!("no AVX state, the xmm registers are in `info float`");
+            return Ok(());
+        }
+    };
+    for (n, (lo, hi)) in regs.xmm.iter().zip(&ymm_hi).enumerate() {
+        let bytes: Vec<u8> = lo.iter().chain(hi).copied().collect();
+        println!("{:<6} {}", format!("ymm{}:", n), vector_lanes(&bytes));
+    }
+    Ok(())
+}
+
+/// A vector register as hex, then as floats and as doubles, lowest lane
+/// first.
+fn vector_lanes(bytes: &[u8]) -> String {
+    let hex: String = bytes.iter().rev().map(|b| format!("{:02x}", b)).collect();
+    let floats: Vec<String> = bytes
+        .chunks(4)
+        .map(|lane| format!("{:?}", f32::from_le_bytes(lane.try_into().unwrap())))
+        .collect();
+    let doubles: Vec<String> = bytes
+        .chunks(8)
+        .map(|lane| format!("{:?}", f64::from_le_bytes(lane.try_into().unwrap())))
+        .collect();
+    format!(
+        "0x{} f = [{}] d = [{}]",
+        hex,
+        floats.join(", "),
+        doubles.join(", ")
+    )
+}
+
+/// The xref index, built the first time it's needed.
+fn xrefs<'a>(subordinate: &Subordinate, settings: &'a mut Settings) -> Result<&'a Xrefs> {
+    let xrefs = match settings.xrefs.take() {
+        Some(xrefs) => xrefs,
+        None => Xrefs::build(subordinate)?,
+    };
+    Ok(settings.xrefs.insert(xrefs))
+}
+
+/// Lists the strings in `.rodata`, or those matching `pattern`, each with
+/// the functions that use it.
+fn print_strings(
+    subordinate: &Subordinate,
+    settings: &mut Settings,
+    pattern: Option<&str>,
+) -> Result<()> {
+    let regex = match pattern {
+        Some(pattern) => Some(Regex::new(pattern).map_err(|e| e.to_string())?),
+        None => None,
+    };
+    let xrefs = xrefs(subordinate, settings)?;
+
+    for (addr, text) in rodata_strings(subordinate, MIN_STRING_LEN)? {
+        if regex.as_ref().is_some_and(|regex| !regex.is_match(&text)) {
+            continue;
+        }
+        let mut users: Vec<String> = xrefs
+            .within(addr..addr + text.len() as u64)
+            .filter_map(|(from, _)| subordinate.symbol_at(*from))
+            .map(|(symbol, _)| subordinate.symbol_name(symbol))
+            .collect();
+        users.sort();
+        users.dedup();
+        if users.is_empty() {
+            println!("0x{:x} {:?}", addr, text);
+        } else {
+            println!("0x{:x} {:?} used by {}", addr, text, users.join(", "));
+        }
+    }
+    Ok(())
+}
+
+fn save_core(subordinate: &Subordinate, path: &str) -> Result<()> {
+    let dumped = coredump::write(subordinate, Path::new(path))?;
+    println!(
+        "saved core file {} with {} of memory",
+        path,
+        format_size(dumped as u64)
+    );
+    Ok(())
+}
+
+/// Lists the code that calls, jumps to or loads from `addr`.
+fn print_xrefs(subordinate: &Subordinate, settings: &mut Settings, addr: &str) -> Result<()> {
+    let addr = resolve_address(subordinate, addr)? as u64;
+    let references = xrefs(subordinate, settings)?.to(addr);
+    if references.is_empty() {
+        println!("nothing refers to {}", subordinate.describe(addr));
+    }
+    for (from, kind) in references {
+        println!("{} {}", subordinate.describe(*from), kind);
+    }
+    Ok(())
+}
+
+/// Shows two functions side by side, lined up on the instructions they
+/// have in common. `|` marks rows that differ, `<` and `>` instructions
+/// only one side has.
+fn compare_disassembly(subordinate: &mut Subordinate, left: &str, right: &str) -> Result<()> {
+    let disassembler = Disassembler::new();
+    let mut sides = Vec::new();
+    for name in &[left, right] {
+        let symbol = subordinate
+            .symbol(name)
+            .ok_or_else(|| format!("unknown symbol `{}`", name))?;
+        let rip = symbol.value;
+        let bytes = subordinate.instructions(symbol)?;
+        sides.push(disassembler.instructions(rip, &bytes));
+    }
+    let (left_code, right_code) = (&sides[0], &sides[1]);
+
+    let show = |instruction: Option<&(u64, String)>| match instruction {
+        Some((addr, text)) => format!("0x{:x} {}", addr, text),
+        None => String::new(),
+    };
+    let rows = align(left_code, right_code);
+    let width = rows
+        .iter()
+        .map(|(l, _)| show(l.map(|l| &left_code[l])).chars().count())
+        .chain(std::iter::once(left.chars().count()))
+        .max()
+        .unwrap_or(0);
+
+    println!("{:<width$}   {}", left, right, width = width);
+    for (l, r) in rows {
+        let (l, r) = (l.map(|l| &left_code[l]), r.map(|r| &right_code[r]));
+        let marker = match (l, r) {
+            (Some(l), Some(r)) if l.1 == r.1 => ' ',
+            (Some(_), Some(_)) => '|',
+            (Some(_), None) => '<',
+            _ => '>',
+        };
+        let row = format!("{:<width$} {} {}", show(l), marker, show(r), width = width);
+        println!("{}", row.trim_end());
+    }
+    Ok(())
+}
+
+/// Pairs up instructions by the longest common subsequence of their text,
+/// then pairs off what's left between matches so a changed instruction
+/// sits next to its counterpart.
+fn align(left: &[(u64, String)], right: &[(u64, String)]) -> Vec<(Option<usize>, Option<usize>)> {
+    let (n, m) = (left.len(), right.len());
+    let mut common = vec![vec![0usize; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            common[i][j] = if left[i].1 == right[j].1 {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let mut rows = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    let mut pending: (Vec<usize>, Vec<usize>) = (Vec::new(), Vec::new());
+    let flush = |rows: &mut Vec<_>, pending: &mut (Vec<usize>, Vec<usize>)| {
+        let (only_left, only_right) = (&pending.0, &pending.1);
+        for k in 0..only_left.len().max(only_right.len()) {
+            rows.push((only_left.get(k).copied(), only_right.get(k).copied()));
+        }
+        pending.0.clear();
+        pending.1.clear();
+    };
+    while i < n || j < m {
+        if i < n && j < m && left[i].1 == right[j].1 {
+            flush(&mut rows, &mut pending);
+            rows.push((Some(i), Some(j)));
+            i += 1;
+            j += 1;
+        } else if j == m || (i < n && common[i + 1][j] >= common[i][j + 1]) {
+            pending.0.push(i);
+            i += 1;
+        } else {
+            pending.1.push(j);
+            j += 1;
+        }
+    }
+    flush(&mut rows, &mut pending);
+    rows
+}
+
+fn print_register(subordinate: &mut Subordinate, settings: &Settings, name: &str) -> Result<()> {
+    match subordinate.frame_registers().get(name) {
+        Some(value) if name == "eflags" || name == "rflags" => {
+            println!("{} 0x{:x} {}", name, value, registers::decode_flags(value));
+        }
+        Some(value) => {
+            println!("{} {}", name, settings.format(value));
+        }
+        None => {
+            println!("couldn't find register with name \"{}\"", name);
+        }
+    }
+    Ok(())
+}
+
+fn print_stack(subordinate: &mut Subordinate, settings: &Settings) -> Result<()> {
+    let regions = subordinate.maps()?;
+    let colored = termion::is_tty(&std::io::stdout());
+    let paint = |kind: RegionKind, text: &str| -> String {
+        if !colored {
+            return text.to_owned();
+        }
+        match kind {
+            RegionKind::Text => format!(
+                "{}{}{}",
+                color::Fg(color::Blue),
+                text,
+                color::Fg(color::Reset)
+            ),
+            RegionKind::Heap => format!(
+                "{}{}{}",
+                color::Fg(color::Green),
+                text,
+                color::Fg(color::Reset)
+            ),
+            RegionKind::Stack => format!(
+                "{}{}{}",
+                color::Fg(color::Yellow),
+                text,
+                color::Fg(color::Reset)
+            ),
+            RegionKind::Library => format!(
+                "{}{}{}",
+                color::Fg(color::Magenta),
+                text,
+                color::Fg(color::Reset)
+            ),
+            RegionKind::Other => text.to_owned(),
+        }
+    };
+
+    println!(
+        "stack [{} {} {} {}]",
+        paint(RegionKind::Text, "text"),
+        paint(RegionKind::Heap, "heap"),
+        paint(RegionKind::Stack, "stack"),
+        paint(RegionKind::Library, "libs")
+    );
+
+    let rsp = subordinate.registers().rsp as usize;
+    let wordlen = std::mem::size_of::<usize>();
+    for (i, word) in subordinate.stack().iter().enumerate() {
+        let kind = regions
+            .iter()
+            .find(|region| region.contains(*word))
+            .map(|region| region.kind(subordinate.path()))
+            .unwrap_or(RegionKind::Other);
+        println!(
+            "0x{:x}: {}",
+            rsp + wordlen * i,
+            paint(kind, &settings.format(*word as u64))
+        );
+    }
+
+    Ok(())
+}
+
+/// `info maps`, every mapped region with its size and what it is, marking
+/// the ones rip and rsp point into. Given an address, only the region
+/// holding it.
+fn print_maps(subordinate: &Subordinate, addr: Option<u64>) -> Result<()> {
+    let regions = subordinate.maps()?;
+    let regs = subordinate.registers();
+    let mut shown = 0;
+    for region in &regions {
+        if addr.is_some_and(|addr| !region.contains(addr as usize)) {
+            continue;
+        }
+        let kind = match region.kind(subordinate.path()) {
+            RegionKind::Text => "text",
+            RegionKind::Heap => "heap",
+            RegionKind::Stack => "stack",
+            RegionKind::Library => "lib",
+            RegionKind::Other => "",
+        };
+        let mut marks = Vec::new();
+        if region.contains(regs.rip as usize) {
+            marks.push("rip");
+        }
+        if region.contains(regs.rsp as usize) {
+            marks.push("rsp");
+        }
+        let name = match (&region.path, &region.name) {
+            (Some(path), _) => path.display().to_string(),
+            (None, Some(name)) => name.clone(),
+            (None, None) => String::new(),
+        };
+        let marks = if marks.is_empty() {
+            String::new()
+        } else {
+            format!(" <- {}", marks.join(", "))
+        };
+        let line = format!(
+            "0x{:012x}-0x{:012x} {} {:>6} {:<5} {}{}",
+            region.start,
+            region.end,
+            region.perms,
+            format_size((region.end - region.start) as u64),
+            kind,
+            name,
+            marks
+        );
+        println!("{}", line.trim_end());
+        shown += 1;
+    }
+    if let (Some(addr), 0) = (addr, shown) {
+        println!("0x{:x} isn't mapped", addr);
+    }
+    Ok(())
+}
+
+/// Says which launch settings couldn't be applied as asked, like a
+/// sandbox memory limit without a cgroup to put it in.
+fn warn_launch(subordinate: &Subordinate) {
+    for warning in subordinate.launch_warnings() {
+        println!("warning: {}", warning);
+    }
+}
+
+/// Shouts if the running binary isn't the file symbols were read from, and
+/// where debuginfod would have the right one.
+fn warn_build_id(subordinate: &Subordinate) {
+    let (on_disk, running) = match subordinate.build_id_mismatch() {
+        Some(ids) => ids,
+        None => return,
+    };
+    let text = format!(
+        "warning: {} doesn't match the running program, symbols and backtraces may be wrong",
+        subordinate.path().display()
+    );
+    if termion::is_tty(&std::io::stdout()) {
+        println!(
+            "{}{}{}",
+            color::Fg(color::Red),
+            text,
+            color::Fg(color::Reset)
+        );
+    } else {
+        println!("{}", text);
+    }
+    println!("  build-id on disk: {}", build_id::hex(&on_disk));
+    println!("  build-id running: {}", build_id::hex(&running));
+    if let Ok(urls) = std::env::var("DEBUGINFOD_URLS") {
+        for url in urls.split_whitespace() {
+            let url = url.trim_end_matches('/');
+            println!(
+                "  the right binary may be at {}/buildid/{}/executable",
+                url,
+                build_id::hex(&running)
+            );
+        }
+    }
+}
+
+/// Opens the current line in $VISUAL or $EDITOR, then re-reads the file so
+/// `list` shows what was saved.
+fn edit_source(subordinate: &mut Subordinate) -> Result<()> {
+    let rip = subordinate.registers().rip as usize;
+    let linked = rip.wrapping_sub(subordinate.load_bias() as usize);
+    let line_info = match subordinate.debug_info().line_for_addr(linked) {
+        Some(line_info) => line_info.clone(),
+        None => return Err("no source for this address".into()),
+    };
+
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_owned());
+    let mut words = editor.split_whitespace();
+    let program = words.next().ok_or("$EDITOR is empty")?;
+
+    let modified = |path: &Path| path.metadata().and_then(|m| m.modified()).ok();
+    let before = modified(&line_info.path);
+    let status = std::process::Command::new(program)
+        .args(words)
+        .arg(format!("+{}", line_info.line))
+        .arg(&line_info.path)
+        .status()?;
+    if !status.success() {
+        println!("warning: {} exited with {}", program, status);
+    }
+
+    let after = modified(&line_info.path);
+    if after == before {
+        return Ok(());
+    }
+    subordinate
+        .debug_info_mut()
+        .reload_source(&line_info.path)?;
+    // The line table comes from the binary, so until it's rebuilt the
+    // lines it points at may have moved.
+    if after > modified(subordinate.path()) {
+        println!(
+            "warning: {} is newer than the program, line numbers may be off until it's rebuilt",
+            line_info.path.display()
+        );
+    }
+    Ok(())
+}
+
+/// The lines around the one `addr` is on, with that one marked, reading the
+/// file from disk the first time.
+fn print_source(subordinate: &mut Subordinate, addr: u64) -> Result<()> {
+    let linked = addr.wrapping_sub(subordinate.load_bias()) as usize;
+    let line_info = subordinate
+        .debug_info()
+        .line_for_addr(linked)
+        .cloned()
+        .ok_or_else(|| format!("no line info for {}", subordinate.describe(addr)))?;
+    let lines = subordinate
+        .debug_info_mut()
+        .source(&line_info.path)
+        .ok_or_else(|| format!("couldn't read {}", line_info.path.display()))?;
+
+    let current = line_info.line as usize;
+    let first = current.saturating_sub(SOURCE_CONTEXT).max(1);
+    let last = (current + SOURCE_CONTEXT).min(lines.len());
+    let colored = termion::is_tty(&std::io::stdout());
+    for number in first..=last {
+        let text = &lines[number - 1];
+        if number != current {
+            println!("   {:>4}  {}", number, text);
+        } else if colored {
+            println!(
+                "{}=> {:>4}  {}{}",
+                color::Fg(color::Green),
+                number,
+                text,
+                color::Fg(color::Reset)
+            );
+        } else {
+            println!("=> {:>4}  {}", number, text);
+        }
+    }
+    Ok(())
+}
+
+/// `bt [n|-n] [-fold-recursion] [--json|--symbolic]`: the innermost n
+/// frames, or with -n the outermost n, optionally with repeated call cycles
+/// folded into one line. `--json` exports them as a Sentry-style stack trace
+/// and `--symbolic` as module offsets and build-ids, for symbolicating
+/// elsewhere.
+fn print_backtrace(subordinate: &Subordinate, args: &[&str]) -> Result<()> {
+    let mut limit: Option<i64> = None;
+    let mut fold = false;
+    let mut json = false;
+    let mut symbolic = false;
+    for arg in args {
+        match *arg {
+            "-fold-recursion" => fold = true,
+            "--json" => json = true,
+            "--symbolic" => symbolic = true,
+            n => match n.parse() {
+                Ok(n) => limit = Some(n),
+                Err(_) => return Err(format!("unknown backtrace option `{}`", n).into()),
+            },
+        }
+    }
+
+    let frames = backtrace(subordinate);
+    let shown = match limit {
+        Some(n) if n < 0 => frames.len().saturating_sub(n.unsigned_abs() as usize)..frames.len(),
+        Some(n) => 0..frames.len().min(n as usize),
+        None => 0..frames.len(),
+    };
+    if json {
+        println!("{}", backtrace::json(subordinate, &frames, shown)?);
+        return Ok(());
+    }
+    if symbolic {
+        for line in backtrace::symbolic(subordinate, &frames, shown)? {
+            println!("{}", line);
+        }
+        return Ok(());
+    }
+    let names: Vec<String> = frames
+        .iter()
+        .map(|frame| match subordinate.symbol_at(frame.pc) {
+            Some((symbol, _)) => subordinate.symbol_name(symbol),
+            None => format!("0x{:x}", frame.pc),
+        })
+        .collect();
+
+    let selected = subordinate.selected_frame();
+    let print_frame = |i: usize| {
+        let marker = if i == selected { "*" } else { " " };
+        println!("{}#{:<2} {}", marker, i, subordinate.describe(frames[i].pc));
+    };
+    let mut i = shown.start;
+    while i < shown.end {
+        let cycle = if fold {
+            recursion_at(&names[i..shown.end])
+        } else {
+            None
+        };
+        match cycle {
+            Some((len, repeats)) => {
+                // Show one round of the cycle, fold the rest.
+                (i..i + len).for_each(print_frame);
+                let first = i + len;
+                let last = i + len * repeats - 1;
+                let marker = if (first..=last).contains(&selected) {
+                    "*"
+                } else {
+                    " "
+                };
+                println!(
+                    "{}[frames {}–{}: recursion of {} ×{}]",
+                    marker,
+                    first,
+                    last,
+                    names[i..i + len].join("↔"),
+                    repeats - 1
+                );
+                i = last + 1;
+            }
+            None => {
+                print_frame(i);
+                i += 1;
+            }
+        }
+    }
+
+    let hidden = frames.len() - shown.len();
+    if hidden > 0 {
+        println!("({} more frames, `bt` without a count shows them)", hidden);
+    }
+    Ok(())
+}
+
+/// The shortest call cycle `names` starts with that repeats at least
+/// `MIN_RECURSION` times in a row, as its length and repeat count.
+fn recursion_at(names: &[String]) -> Option<(usize, usize)> {
+    (1..=MAX_RECURSION_CYCLE)
+        .filter(|len| *len <= names.len())
+        .map(|len| {
+            let periodic = (len..names.len())
+                .take_while(|j| names[*j] == names[j - len])
+                .count();
+            (len, (len + periodic) / len)
+        })
+        .find(|(_, repeats)| *repeats >= MIN_RECURSION)
+}
+
+/// Everything needed to pick a session back up: where the process is, how
+/// it got there, and what will stop it next.
+fn print_overview(subordinate: &Subordinate) {
+    let rip = subordinate.registers().rip;
+    let debug_info = subordinate.debug_info();
+    match debug_info.line_for_addr(rip.wrapping_sub(subordinate.load_bias()) as usize) {
+        Some(line_info) => {
+            let file = line_info.path.file_name().unwrap_or_default();
+            println!(
+                "at {} ({}:{})",
+                subordinate.describe(rip),
+                file.to_string_lossy(),
+                line_info.line
+            );
+        }
+        None => println!("at {}", subordinate.describe(rip)),
+    }
+    if let Some(event) = subordinate.events().last() {
+        println!("last event: {}", event);
+    }
+
+    let frames = backtrace(subordinate);
+    println!("frames:");
+    for (i, frame) in frames.iter().take(OVERVIEW_FRAMES).enumerate() {
+        println!("  #{:<2} {}", i, subordinate.describe(frame.pc));
+    }
+    if frames.len() > OVERVIEW_FRAMES {
+        println!("  ... {} more", frames.len() - OVERVIEW_FRAMES);
+    }
+
+    println!("breakpoints:");
+    let mut any = false;
+    for (addr, breakpoint) in subordinate.breakpoints() {
+        let mut notes = Vec::new();
+        if let Some(tracepoint) = &breakpoint.tracepoint {
+            notes.push(format!("traces {}", tracepoint.name));
+        }
+        if breakpoint.temporary {
+            notes.push("temporary".to_owned());
+        }
+        if breakpoint.condition.is_some() {
+            notes.push("conditional".to_owned());
+        }
+        if let Some(number) = breakpoint.thread {
+            notes.push(format!("thread {}", number));
+        }
+        print_overview_breakpoint(subordinate, addr as u64, &notes);
+        any = true;
+    }
+    for name in subordinate.pending_breakpoints() {
+        println!("  {} (pending)", name);
+        any = true;
+    }
+    for hw in subordinate.hardware_breakpoints() {
+        let note = match hw.access {
+            Access::Execute => "hardware".to_owned(),
+            Access::Write => format!("watch, {} bytes", hw.len),
+            Access::ReadWrite => format!("rwatch, {} bytes", hw.len),
+        };
+        print_overview_breakpoint(subordinate, hw.addr as u64, &[note]);
+        any = true;
+    }
+    if !any {
+        println!("  none");
+    }
+}
+
+fn print_overview_breakpoint(subordinate: &Subordinate, addr: u64, notes: &[String]) {
+    if notes.is_empty() {
+        println!("  {}", subordinate.describe(addr));
+    } else {
+        println!("  {} ({})", subordinate.describe(addr), notes.join(", "));
+    }
+}
+
+fn print_locals(subordinate: &Subordinate) -> Result<()> {
+    let locals = locals(subordinate)?;
+    if locals.is_empty() {
+        println!("no locals");
+    }
+    for local in locals {
+        let value = local.value(subordinate);
+        let value = value.as_deref().unwrap_or("<optimized out>");
+        let kind = if local.parameter { "arg " } else { "" };
+        println!("{}{} {} = {}", kind, local.type_name, local.name, value);
+    }
+    Ok(())
+}
+
+/// `print <expr>`. A lone variable is shown decoded by its type, anything
+/// else is evaluated as a word with variables standing for their values.
+fn print_expression(subordinate: &Subordinate, settings: &Settings, input: &str) -> Result<()> {
+    let value = expression_value(subordinate, settings, input)?;
+    if local(subordinate, input.trim()).is_some() {
+        println!("{} = {}", input.trim(), value);
+    } else {
+        println!("{}", value);
+    }
+    Ok(())
+}
+
+fn expression_value(subordinate: &Subordinate, settings: &Settings, input: &str) -> Result<String> {
+    if let Some(local) = local(subordinate, input.trim()) {
+        let value = local.value(subordinate);
+        return Ok(value.unwrap_or_else(|| "<optimized out>".to_owned()));
+    }
+
+    let value = evaluate(subordinate, input)?;
+    // Words are unsigned, but a small negative is far more likely meant
+    // than a huge positive.
+    let text = if (value as i64) < 0 && (value as i64) > -0x10000 {
+        format!("{} (0x{:x})", value as i64, value)
+    } else {
+        settings.format(value)
+    };
+    let notes = settings.annotations.describe(value, 8);
+    if notes.is_empty() {
+        Ok(text)
+    } else {
+        Ok(format!("{} ; {}", text, notes.join(", ")))
+    }
+}
+
+/// Re-evaluates every `display` expression, noting the ones that changed
+/// since they were last shown.
+fn print_displays(subordinate: &Subordinate, settings: &mut Settings) {
+    let mut displays = std::mem::take(&mut settings.displays);
+    for (i, display) in displays.iter_mut().enumerate() {
+        let value = match expression_value(subordinate, settings, &display.expr) {
+            Ok(value) => value,
+            Err(e) => format!("<{}>", e),
+        };
+        match &display.last {
+            Some(last) if *last != value => {
+                println!("{}: {} = {} (was {})", i + 1, display.expr, value, last)
+            }
+            _ => println!("{}: {} = {}", i + 1, display.expr, value),
+        }
+        display.last = Some(value);
+    }
+    settings.displays = displays;
+}
+
+/// Evaluates an expression in which variables stand for their values.
+fn evaluate(subordinate: &Subordinate, input: &str) -> Result<u64> {
+    Expr::parse(input)?.evaluate_with(subordinate, &|name| match local(subordinate, name) {
+        Some(local) => local.integer(subordinate),
+        None => global_value(subordinate, name),
+    })
+}
+
+/// `set var <name> = <expr>`, writing the value at the variable's size.
+fn set_variable(subordinate: &mut Subordinate, assignment: &str) -> Result<()> {
+    let (name, value) = match assignment.split_once('=') {
+        Some((name, value)) => (name.trim(), value.trim()),
+        None => return Err("expected `set var <name> = <value>`".into()),
+    };
+
+    let (place, size, float) = match local(subordinate, name) {
+        Some(local) => {
+            let types = subordinate.debug_info().types();
+            let float = types.float_size(local.ty);
+            let scalar = local
+                .bytes
+                .as_ref()
+                .is_some_and(|bytes| types.integer(local.ty, bytes).is_some());
+            if !scalar && float.is_none() {
+                return Err(
+                    format!("can only set numbers and pointers, not {}", local.type_name).into(),
+                );
+            }
+            let place = local
+                .place
+                .ok_or_else(|| format!("{} has no location here", name))?;
+            (place, local.bytes.map_or(0, |bytes| bytes.len()), float)
+        }
+        None => match subordinate.symbol(name) {
+            Some(symbol) if symbol.symtype == elf::types::STT_OBJECT => (
+                Place::Address(symbol.value),
+                symbol.size.min(8) as usize,
+                None,
+            ),
+            _ => return Err(format!("no variable called `{}`", name).into()),
+        },
+    };
+
+    if size > 8 {
+        return Err("can only set values up to 8 bytes".into());
+    }
+    let bytes = match float {
+        Some(4) => (parse_float(value)? as f32).to_le_bytes().to_vec(),
+        Some(_) => parse_float(value)?.to_le_bytes().to_vec(),
+        None => evaluate(subordinate, value)?.to_le_bytes()[..size].to_vec(),
+    };
+
+    match place {
+        Place::Address(addr) => subordinate.write_bytes(addr as usize, &bytes),
+        Place::Register(number) => {
+            let name = registers::DWARF_NAMES
+                .get(number as usize)
+                .ok_or("can't write to that register")?;
+            let mut regs = subordinate.registers().clone();
+            let mut word = regs.get(name).unwrap_or(0).to_le_bytes();
+            word[..bytes.len()].copy_from_slice(&bytes);
+            regs.set(name, u64::from_le_bytes(word));
+            subordinate.set_registers(regs)
+        }
+        Place::Value(_) => Err(format!("{} is a constant here", name).into()),
+    }
+}
+
+/// `set $<register> = <expr>`, where functions stand for their address so
+/// `set $rip = <function>` jumps there.
+fn set_register(
+    subordinate: &mut Subordinate,
+    settings: &Settings,
+    assignment: &str,
+) -> Result<()> {
+    let (name, value) = match assignment.split_once('=') {
+        Some((name, value)) => (name.trim().trim_start_matches('$'), value.trim()),
+        None => return Err("expected `set $<register> = <value>`".into()),
+    };
+    if subordinate.selected_frame() != 0 {
+        return Err("registers can only be set in frame 0, `frame 0` selects it".into());
+    }
+    let mut registers = subordinate.registers().clone();
+    if registers.get(name).is_none() {
+        return Err(format!("no register called `{}`", name).into());
+    }
+    let value = examine_address(subordinate, value)?;
+    registers.set(name, value);
+    subordinate.set_registers(registers)?;
+    if name == "rip" {
+        println!("rip is now {}", subordinate.describe(value));
+    } else {
+        println!("{} = {}", name, settings.format(value));
+    }
+    Ok(())
+}
+
+/// The size of a `set <unit>` store, the braces of gdb's `{int}` style
+/// being optional.
+fn store_size(unit: &str) -> Option<usize> {
+    match unit.trim_start_matches('{').trim_end_matches('}') {
+        "byte" => Some(1),
+        "word" => Some(2),
+        "dword" => Some(4),
+        "qword" => Some(8),
+        _ => None,
+    }
+}
+
+/// `formatter <regex> <template>`, the regex in double quotes if it has
+/// spaces, e.g. `formatter "^struct entry$" {key} x{count}`.
+fn add_formatter(subordinate: &mut Subordinate, args: &str) -> Result<()> {
+    let (pattern, template) = match args.strip_prefix('"') {
+        Some(quoted) => quoted
+            .split_once('"')
+            .ok_or("unterminated `\"` around the pattern")?,
+        None => args
+            .split_once(' ')
+            .ok_or("expected `formatter <regex> <template>`")?,
+    };
+    let template = template.trim();
+    if template.is_empty() {
+        return Err("expected `formatter <regex> <template>`".into());
+    }
+    subordinate.formatters_mut().add(pattern, template)
+}
+
+/// `set {byte|word|dword|qword} <addr> = <value>`. Values are truncated to
+/// the unit only when that loses nothing, signed or unsigned.
+fn set_memory(subordinate: &mut Subordinate, unit: &str, assignment: &str) -> Result<()> {
+    let size = store_size(unit).unwrap_or(8);
+    let (addr, value) = match assignment.split_once('=') {
+        Some((addr, value)) => (addr.trim(), value.trim()),
+        None => return Err(format!("expected `set {} <addr> = <value>`", unit).into()),
+    };
+    let addr = examine_address(subordinate, addr)?;
+    let value = evaluate(subordinate, value)?;
+
+    let bits = size * 8;
+    let fits = bits == 64 || value >> bits == 0 || (value as i64) >> (bits - 1) == -1;
+    if !fits {
+        return Err(format!(
+            "0x{:x} doesn't fit in a {}",
+            value,
+            unit.trim_matches(&['{', '}'][..])
+        )
+        .into());
+    }
+    subordinate.write_bytes(addr as usize, &value.to_le_bytes()[..size])
+}
+
+fn parse_float(value: &str) -> Result<f64> {
+    value
+        .parse()
+        .map_err(|_| format!("invalid number `{}`", value).into())
+}
+
+/// `x/NFU <addr>`: N units of size U (b, h, w or g) shown in format F,
+/// one of x, d, u, s (strings) or i (instructions). Both default to what
+/// was used last.
+fn examine(
+    subordinate: &Subordinate,
+    settings: &mut Settings,
+    spec: Option<&str>,
+    addr: &[&str],
+) -> Result<()> {
+    let mut examine = settings.examine;
+    let mut count = 1;
+    if let Some(spec) = spec {
+        let digits = spec.len() - spec.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        if digits > 0 {
+            count = spec[..digits].parse().map_err(|_| "count too large")?;
+        }
+        for letter in spec[digits..].chars() {
+            match letter {
+                'x' | 'd' | 'u' | 's' | 'i' => examine.format = letter,
+                'b' => examine.unit = 1,
+                'h' => examine.unit = 2,
+                'w' => examine.unit = 4,
+                'g' => examine.unit = 8,
+                other => return Err(format!("unknown format letter `{}`", other).into()),
+            }
+        }
+    }
+
+    let addr = if addr.is_empty() {
+        examine
+            .next
+            .ok_or("no address to examine, `x <addr>` takes one")?
+    } else {
+        examine_address(subordinate, &addr.join(" "))?
+    };
+    let next = match examine.format {
+        's' => examine_strings(subordinate, addr, count)?,
+        'i' => {
+            let (text, next) = Disassembler::new().disassemble_at(subordinate, addr, count)?;
+            print!("{}", text);
+            next
+        }
+        format => examine_units(subordinate, addr, count, examine.unit, format)?,
+    };
+    examine.next = Some(next);
+    settings.examine = examine;
+    Ok(())
+}
+
+/// Like `evaluate`, but arrays and functions stand for their address as
+/// they would in C.
+fn examine_address(subordinate: &Subordinate, input: &str) -> Result<u64> {
+    Expr::parse(input)?.evaluate_with(subordinate, &|name| match local(subordinate, name) {
+        Some(local) => local.integer(subordinate).or(match local.place {
+            Some(Place::Address(addr)) => Some(addr),
+            _ => None,
+        }),
+        None => global_value(subordinate, name)
+            .or_else(|| subordinate.symbol(name).map(|symbol| symbol.value)),
+    })
+}
+
+fn examine_units(
+    subordinate: &Subordinate,
+    addr: u64,
+    count: usize,
+    unit: usize,
+    format: char,
+) -> Result<u64> {
+    let size = count.checked_mul(unit).ok_or("count too large")?;
+    let mapped = mapped_from(subordinate, addr)?;
+    if size > mapped {
+        return Err(format!("cannot access memory at 0x{:x}", addr + mapped as u64).into());
+    }
+    let bytes = subordinate.read_bytes(addr as usize, size)?;
+    let per_line = match unit {
+        8 => 2,
+        4 => 4,
+        _ => 8,
+    };
+    for (i, line) in bytes.chunks(unit * per_line).enumerate() {
+        let values: Vec<String> = line
+            .chunks(unit)
+            .map(|bytes| {
+                let value = word(bytes);
+                let shift = 64 - unit * 8;
+                match format {
+                    'd' => format!("{}", ((value << shift) as i64) >> shift),
+                    'u' => format!("{}", value),
+                    _ => format!("0x{:0width$x}", value, width = unit * 2),
+                }
+            })
+            .collect();
+        let line_addr = addr + (i * unit * per_line) as u64;
+        println!(
+            "{}:  {}",
+            subordinate.describe(line_addr),
+            values.join("  ")
+        );
+    }
+    Ok(addr + bytes.len() as u64)
+}
+
+/// How many bytes from `addr` on are mapped, through any regions that
+/// follow on from the one it is in.
+fn mapped_from(subordinate: &Subordinate, addr: u64) -> Result<usize> {
+    let regions = subordinate.maps()?;
+    let mut end = addr as usize;
+    while let Some(region) = regions.iter().find(|region| region.contains(end)) {
+        end = region.end;
+    }
+    Ok(end - addr as usize)
+}
+
+/// Prints `count` NUL-terminated strings one after the other, returning
+/// where the next would start.
+fn examine_strings(subordinate: &Subordinate, mut addr: u64, count: usize) -> Result<u64> {
+    for _ in 0..count {
+        let mut bytes = Vec::new();
+        let mut terminated = false;
+        while !terminated && bytes.len() < MAX_EXAMINE_STRING {
+            let word = subordinate
+                .peek((addr as usize) + bytes.len())?
+                .to_ne_bytes();
+            let end = word.iter().position(|byte| *byte == 0);
+            terminated = end.is_some();
+            bytes.extend_from_slice(&word[..end.unwrap_or(word.len())]);
+        }
+        bytes.truncate(MAX_EXAMINE_STRING);
+        let text = String::from_utf8_lossy(&bytes);
+        let more = if terminated { "" } else { "..." };
+        println!("{}:  {:?}{}", subordinate.describe(addr), text, more);
+        addr += bytes.len() as u64 + if terminated { 1 } else { 0 };
+    }
+    Ok(addr)
+}
+
+/// The value of a global data symbol, read at its symbol's size.
+fn global_value(subordinate: &Subordinate, name: &str) -> Option<u64> {
+    let symbol = subordinate.symbol(name)?;
+    if symbol.symtype != elf::types::STT_OBJECT || !matches!(symbol.size, 1 | 2 | 4 | 8) {
+        return None;
+    }
+    let bytes = subordinate
+        .read_bytes(symbol.value as usize, symbol.size as usize)
+        .ok()?;
+    let mut buf = [0u8; 8];
+    buf[..bytes.len()].copy_from_slice(&bytes);
+    Some(u64::from_le_bytes(buf))
+}
+
+fn select_frame(subordinate: &mut Subordinate, n: usize) -> Result<()> {
+    let pc = subordinate.select_frame(n)?;
+    println!("#{:<2} {}", n, subordinate.describe(pc));
+    Ok(())
+}
+
+/// Prints the instructions `si` ran, only the most recent `last` if given,
+/// each with the registers it changed.
+fn print_history(settings: &Settings, last: Option<usize>) -> Result<()> {
+    if settings.history.is_empty() {
+        println!("no instructions stepped yet");
+    }
+    let skip = last.map_or(0, |last| settings.history.len().saturating_sub(last));
+    let disassembler = Disassembler::new();
+    for executed in settings.history.iter().skip(skip) {
+        let text = disassembler.disassemble_one(executed.addr, &executed.bytes)?;
+        let changes: Vec<String> = executed
+            .changes
+            .iter()
+            .map(|(name, old, new)| format!("{} 0x{:x} -> 0x{:x}", name, old, new))
+            .collect();
+        if changes.is_empty() {
+            println!("{}", text);
+        } else {
+            println!("{}  ; {}", text, changes.join(", "));
+        }
+    }
+    Ok(())
+}
+
+/// Every checkpoint with its process and where it was taken.
+fn print_checkpoints(subordinate: &Subordinate) {
+    for checkpoint in subordinate.checkpoints() {
+        println!(
+            "{} process {} at {}",
+            checkpoint.number,
+            checkpoint.pid,
+            subordinate.describe(checkpoint.registers.rip)
+        );
+    }
+}
+
+fn find_type(subordinate: &Subordinate, name: &str) -> Result<usize> {
+    subordinate
+        .debug_info()
+        .types()
+        .find(name)
+        .ok_or_else(|| format!("no type named `{}`", name).into())
+}
+
+fn print_type(subordinate: &Subordinate, name: &str) -> Result<()> {
+    let id = find_type(subordinate, name)?;
+    println!("type = {}", subordinate.debug_info().types().definition(id));
+    Ok(())
+}
+
+fn print_sizeof(subordinate: &Subordinate, name: &str) -> Result<()> {
+    let id = find_type(subordinate, name)?;
+    match subordinate.debug_info().types().size(Some(id)) {
+        Some(size) => println!("{}", size),
+        None => println!("`{}` has no known size", name),
+    }
+    Ok(())
+}
+
+/// `offsetof <type>.<field>`, where the field can be a path into nested
+/// structs like `where.y`.
+fn print_offsetof(subordinate: &Subordinate, query: &str) -> Result<()> {
+    let (name, path) = query
+        .split_once('.')
+        .ok_or("usage: offsetof <type>.<field>")?;
+    let types = subordinate.debug_info().types();
+    let mut id = Some(find_type(subordinate, name)?);
+    let mut offset = 0;
+    for field in path.split('.') {
+        let member = types
+            .member(id, field.trim())
+            .ok_or_else(|| format!("`{}` has no field `{}`", types.name(id), field.trim()))?;
+        offset += member.offset;
+        id = member.ty;
+    }
+    println!("{}", offset);
+    Ok(())
+}
+
+/// `layout type <type>`, a type's bytes field by field with the padding
+/// between them, in red on a terminal, and the values fields can't hold.
+fn print_layout(subordinate: &Subordinate, name: &str) -> Result<()> {
+    let types = subordinate.debug_info().types();
+    let id = find_type(subordinate, name)?;
+    let colored = termion::is_tty(&std::io::stdout());
+    let size = types.size(Some(id)).unwrap_or(0);
+    println!("{}, {} bytes", types.name(Some(id)), size);
+    println!("offset  size");
+    let mut padding = 0;
+    for span in types.layout(id) {
+        let field = match &span.field {
+            Some(field) => field,
+            None => {
+                padding += span.size;
+                let text = format!("{:>6}  {:>4}  padding", span.offset, span.size);
+                if colored {
+                    println!(
+                        "{}{}{}",
+                        color::Fg(color::Red),
+                        text,
+                        color::Fg(color::Reset)
+                    );
+                } else {
+                    println!("{}", text);
+                }
+                continue;
+            }
+        };
+        let mut line = format!(
+            "{:>6}  {:>4}  {}",
+            span.offset,
+            span.size,
+            types.declaration(span.ty, field).trim_end()
+        );
+        if let Some(niche) = types.niche(span.ty) {
+            line += &format!("  (niche: {})", niche);
+        }
+        println!("{}", line);
+    }
+    println!("{} of {} bytes are padding", padding, size);
+    Ok(())
+}
+
+fn print_libraries(subordinate: &mut Subordinate) -> Result<()> {
+    subordinate.update_libraries()?;
+    for library in subordinate.libraries() {
+        println!(
+            "0x{:x} {} ({} symbols)",
+            library.base,
+            library.path.display(),
+            library.symbols.len()
+        );
+    }
+    Ok(())
+}
+
+/// What kind of file the binary is and its sections, from the file on
+/// disk.
+fn print_elf(subordinate: &Subordinate) -> Result<()> {
+    let elf = elf::File::open_path(subordinate.path())?;
+    let kind = match elf.ehdr.elftype {
+        elf::types::ET_EXEC => "executable",
+        elf::types::ET_DYN => "position-independent executable",
+        elf::types::ET_REL => "object file",
+        _ => "ELF file",
+    };
+    println!(
+        "{}: {} for {}, entry 0x{:x}",
+        subordinate.path().display(),
+        kind,
+        Machine::from_elf(elf.ehdr.machine.0),
+        elf.ehdr.entry
+    );
+    for section in elf.sections.iter().filter(|s| !s.shdr.name.is_empty()) {
+        println!(
+            "0x{:08x} {:>8} {}",
+            section.shdr.addr, section.shdr.size, section.shdr.name
+        );
+    }
+    Ok(())
+}
+
+/// Every thread, the current one marked with `*`, e.g.
+/// `* 2 thread 4242 "worker" 0x401136 <work+0x4>`.
+fn print_threads(subordinate: &Subordinate) -> Result<()> {
+    for thread in subordinate.threads() {
+        let marker = if thread.tid == subordinate.current_thread() {
+            "*"
+        } else {
+            " "
+        };
+        let rip = subordinate.thread_rip(thread.tid)?;
+        let name = subordinate.thread_name(thread.tid).unwrap_or_default();
+        println!(
+            "{} {} thread {} {:?} {}",
+            marker,
+            thread.number,
+            thread.tid,
+            name,
+            subordinate.describe(rip)
+        );
+    }
+    Ok(())
+}
+
+fn print_thread(subordinate: &Subordinate) -> Result<()> {
+    let tid = subordinate.current_thread();
+    let thread = subordinate
+        .threads()
+        .iter()
+        .find(|thread| thread.tid == tid)
+        .ok_or("the current thread is gone")?;
+    println!(
+        "thread {} ({}) at {}",
+        thread.number,
+        tid,
+        subordinate.describe(subordinate.registers().rip)
+    );
+    Ok(())
+}
+
+/// Prints the event log, only the most recent `last` entries if given.
+fn print_events(subordinate: &Subordinate, last: Option<usize>) {
+    let events = subordinate.events();
+    let skip = last.map_or(0, |last| events.len().saturating_sub(last));
+    if skip > 0 {
+        println!("({} earlier events, `events all` shows them)", skip);
+    }
+    for line in events.lines().skip(skip) {
+        println!("{}", line);
+    }
+}
+
+fn print_process_tree(subordinate: &Subordinate) -> Result<()> {
+    for (depth, process) in processes::tree(subordinate.pid())? {
+        println!(
+            "{}{} {} ({})",
+            "  ".repeat(depth),
+            process.pid,
+            process.name,
+            process.state_name()
+        );
+    }
+    Ok(())
+}
+
+fn print_symbols(subordinate: &mut Subordinate) -> Result<()> {
+    for symbol in subordinate.symbols().into_iter() {
+        if symbol.symtype != elf::types::STT_FUNC {
+            continue;
+        }
+        println!("0x{:x} {}", symbol.value, subordinate.symbol_name(symbol));
+    }
+    Ok(())
+}
+
+fn print_symbol(subordinate: &mut Subordinate, name: &str) -> Result<()> {
+    match subordinate.symbol(name) {
+        Some(symbol) => println!("0x{:x} {}", symbol.value, subordinate.symbol_name(symbol)),
+        None => println!("couldn't find symbol with name \"{}\"", name),
     }
-    println!("couldn't find symbol with name \"{}\"", name);
     Ok(())
 }
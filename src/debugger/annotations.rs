@@ -0,0 +1,100 @@
+use crate::debugger::syscalls::{errno_name, format_open_flags, is_open_flags};
+use crate::result::Result;
+
+/// Kinds of well-known constants a value can be recognised as.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Table {
+    // A negated errno, as syscalls and the kernel return them.
+    Errno,
+    OpenFlags,
+    // Characters packed into an integer, as compilers do to compare or
+    // store short strings.
+    Ascii,
+}
+
+const TABLES: &[(Table, &str)] = &[
+    (Table::Errno, "errno"),
+    (Table::OpenFlags, "open-flags"),
+    (Table::Ascii, "ascii"),
+];
+
+/// Which tables immediates in disassembly and printed values are looked up
+/// in, all of them to begin with.
+#[derive(Debug, Clone)]
+pub struct Annotations {
+    enabled: Vec<Table>,
+}
+
+impl Default for Annotations {
+    fn default() -> Self {
+        Self {
+            enabled: TABLES.iter().map(|(table, _)| *table).collect(),
+        }
+    }
+}
+
+impl Annotations {
+    pub fn set(&mut self, name: &str, on: bool) -> Result<()> {
+        let table = TABLES
+            .iter()
+            .find(|(_, table_name)| *table_name == name)
+            .map(|(table, _)| *table)
+            .ok_or_else(|| format!("no annotation table `{}`", name))?;
+        self.enabled.retain(|enabled| *enabled != table);
+        if on {
+            self.enabled.push(table);
+        }
+        Ok(())
+    }
+
+    /// Every table's name and whether it's used.
+    pub fn list(&self) -> impl Iterator<Item = (&'static str, bool)> + '_ {
+        TABLES
+            .iter()
+            .map(move |(table, name)| (*name, self.enabled.contains(table)))
+    }
+
+    /// What `value` may stand for, given it's `width` bytes wide, e.g.
+    /// `-ENOENT` or `"GET "`.
+    pub fn describe(&self, value: u64, width: usize) -> Vec<String> {
+        let bits = width * 8;
+        let value = if bits < 64 {
+            value & ((1 << bits) - 1)
+        } else {
+            value
+        };
+        let signed = if bits < 64 {
+            ((value << (64 - bits)) as i64) >> (64 - bits)
+        } else {
+            value as i64
+        };
+
+        let mut notes = Vec::new();
+        for table in &self.enabled {
+            let note = match table {
+                // -1 is more often just -1.
+                Table::Errno if signed < -1 => errno_name((-signed).min(i32::MAX as i64) as i32)
+                    .map(|name| format!("-{}", name)),
+                Table::OpenFlags if is_open_flags(value) => Some(format_open_flags(value)),
+                Table::Ascii => ascii(value, width),
+                _ => None,
+            };
+            notes.extend(note);
+        }
+        notes
+    }
+}
+
+// The bytes of `value` as they'd be in memory, when there are at least two
+// and they're all printable. At least half have to be letters, or addresses
+// like 0x404040 would read as "@@@".
+fn ascii(value: u64, width: usize) -> Option<String> {
+    let bytes = value.to_le_bytes();
+    let len = bytes[..width].iter().rposition(|b| *b != 0)? + 1;
+    let text = &bytes[..len];
+    let letters = text.iter().filter(|b| b.is_ascii_alphabetic()).count();
+    if len < 2 || !text.iter().all(|b| (0x20..0x7f).contains(b)) || letters * 2 < len {
+        return None;
+    }
+    Some(format!("{:?}", String::from_utf8_lossy(text)))
+}
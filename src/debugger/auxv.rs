@@ -1,5 +1,6 @@
+use crate::debugger::build_id::u64_at;
 use crate::debugger::Subordinate;
-use crate::Result;
+use crate::result::Result;
 
 const AT_NULL: usize = 0;
 const AT_PHDR: usize = 3;
@@ -115,6 +116,39 @@ impl Entry {
     }
 }
 
+/// How many bytes AT_RANDOM points at.
+pub const RANDOM_LEN: usize = 16;
+// An entry of this type is skipped by whoever reads the vector.
+const AT_IGNORE: usize = 1;
+
+/// For a deterministic launch, hides the vDSO so the clock is read with
+/// syscalls the tracer sees, and replaces the AT_RANDOM bytes.
+pub fn fix(subordinate: &Subordinate, random: &[u8]) -> Result<()> {
+    let regs = subordinate.registers();
+    let mut addr = (regs.rsp + 8) as usize;
+    addr = advance_to_next_null_entry(subordinate, addr)?;
+    addr = advance_to_next_null_entry(subordinate, addr)?;
+
+    loop {
+        let aux_type = read_u64(subordinate, addr)? as usize;
+        match aux_type {
+            AT_NULL => break,
+            AT_SYSINFO_EHDR => subordinate.poke(addr, AT_IGNORE)?,
+            AT_RANDOM => {
+                let at = read_u64(subordinate, addr + 8)? as usize;
+                for (i, chunk) in random.chunks(8).enumerate() {
+                    let mut word = [0; 8];
+                    word[..chunk.len()].copy_from_slice(chunk);
+                    subordinate.poke(at + i * 8, usize::from_le_bytes(word))?;
+                }
+            }
+            _ => {}
+        }
+        addr += 16;
+    }
+    Ok(())
+}
+
 pub fn read(subordinate: &Subordinate) -> Result<Vec<Entry>> {
     let mut auxv: Vec<Entry> = Vec::new();
     let regs = subordinate.registers();
@@ -141,6 +175,16 @@ pub fn read(subordinate: &Subordinate) -> Result<Vec<Entry>> {
     Ok(auxv)
 }
 
+/// Parses an auxv as the kernel lays it out, e.g. in a core file's
+/// NT_AUXV note: type and value pairs of u64s up to AT_NULL.
+pub fn parse(data: &[u8]) -> Vec<Entry> {
+    data.chunks_exact(16)
+        .map(|pair| (u64_at(pair, 0), u64_at(pair, 8)))
+        .take_while(|(aux_type, _)| *aux_type as usize != AT_NULL)
+        .map(|(aux_type, aux_val)| Entry::new(aux_type as usize, aux_val as usize))
+        .collect()
+}
+
 fn read_u64(subordinate: &Subordinate, addr: usize) -> Result<u64> {
     let mut buf = [0 as u8; 8];
     let bytes = subordinate.read_bytes(addr, 8)?;
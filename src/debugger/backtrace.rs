@@ -0,0 +1,222 @@
+use crate::debugger::dwarf::{FrameRegisters, Unwind};
+use crate::debugger::{build_id, trace::json_string, Subordinate};
+use crate::result::Result;
+use std::fs;
+use std::ops::Range;
+use std::path::PathBuf;
+
+const MAX_FRAMES: usize = 65536;
+
+pub type Frame = FrameRegisters;
+
+/// Walks the stack from the current registers, using CFI where the binary
+/// has it and following the rbp chain where it doesn't. Frame 0 is the
+/// innermost.
+pub fn backtrace(subordinate: &Subordinate) -> Vec<Frame> {
+    let regs = subordinate.registers();
+    let call_frames = subordinate.debug_info().call_frames();
+    let bias = subordinate.load_bias();
+    let read = |addr: u64| subordinate.peek(addr as usize).ok().map(|word| word as u64);
+
+    let mut frames = Vec::new();
+    let mut frame = Frame {
+        pc: regs.rip,
+        sp: regs.rsp,
+        fp: regs.rbp,
+    };
+
+    while frames.len() < MAX_FRAMES {
+        frames.push(frame);
+
+        // A return address is just past the call, which may be the last
+        // instruction of the function, so look up the byte before it.
+        let lookup = if frames.len() == 1 {
+            frame.pc
+        } else {
+            frame.pc - 1
+        };
+        let caller = match call_frames.unwind(&frame, lookup.wrapping_sub(bias), &read) {
+            Unwind::Caller(caller) => caller,
+            Unwind::Outermost => break,
+            Unwind::NoInfo => match frame_pointer_caller(&frame, &read) {
+                Some(caller) => caller,
+                None => break,
+            },
+        };
+
+        // The stack grows down, a caller's frame that isn't above ours means
+        // we've wandered off into garbage.
+        if caller.pc == 0 || caller.sp <= frame.sp {
+            break;
+        }
+        frame = caller;
+    }
+
+    frames
+}
+
+// With frame pointers, [rbp] is the caller's rbp and [rbp+8] the return
+// address.
+fn frame_pointer_caller(frame: &Frame, read: &dyn Fn(u64) -> Option<u64>) -> Option<Frame> {
+    if frame.fp == 0 {
+        return None;
+    }
+    Some(Frame {
+        pc: read(frame.fp + 8)?,
+        sp: frame.fp + 16,
+        fp: read(frame.fp)?,
+    })
+}
+
+/// A file mapped into the process, identified the way crash tooling does
+/// it: by build-id, with code given as an offset from where it's loaded.
+pub struct Module {
+    pub path: PathBuf,
+    pub base: u64,
+    pub end: u64,
+    pub build_id: Option<String>,
+}
+
+/// The modules the frames are in, and the index of each frame's module.
+fn modules(
+    subordinate: &Subordinate,
+    frames: &[Frame],
+) -> Result<(Vec<Module>, Vec<Option<usize>>)> {
+    let regions = subordinate.maps()?;
+    let mut modules: Vec<Module> = Vec::new();
+    let mut found = Vec::new();
+    for frame in frames {
+        let path = regions
+            .iter()
+            .find(|region| region.contains(frame.pc as usize))
+            .and_then(|region| region.path.clone());
+        let path = match path {
+            Some(path) => path,
+            None => {
+                found.push(None);
+                continue;
+            }
+        };
+        if let Some(i) = modules.iter().position(|module| module.path == path) {
+            found.push(Some(i));
+            continue;
+        }
+        // A file is mapped in several pieces, it's loaded where the first
+        // one starts.
+        let pieces = regions
+            .iter()
+            .filter(|region| region.path.as_ref() == Some(&path));
+        let base = pieces.clone().map(|region| region.start).min().unwrap_or(0) as u64;
+        let end = pieces.map(|region| region.end).max().unwrap_or(0) as u64;
+        let build_id = fs::read(&path)
+            .ok()
+            .and_then(|data| build_id::of_file(&data))
+            .map(|id| build_id::hex(&id));
+        modules.push(Module {
+            path,
+            base,
+            end,
+            build_id,
+        });
+        found.push(Some(modules.len() - 1));
+    }
+    Ok((modules, found))
+}
+
+/// One line per frame in `shown`, innermost first, of its pc, then its
+/// module and the offset into it, then the module's build-id or `-`, e.g.
+/// `#0  0x401136 /usr/bin/crash+0x1136 3f2a...`.
+pub fn symbolic(
+    subordinate: &Subordinate,
+    frames: &[Frame],
+    shown: Range<usize>,
+) -> Result<Vec<String>> {
+    let (modules, found) = modules(subordinate, &frames[shown.clone()])?;
+    Ok(shown
+        .clone()
+        .zip(&frames[shown])
+        .zip(found)
+        .map(|((i, frame), module)| match module.map(|m| &modules[m]) {
+            Some(module) => format!(
+                "#{:<2} 0x{:x} {}+0x{:x} {}",
+                i,
+                frame.pc,
+                module.path.display(),
+                frame.pc - module.base,
+                module.build_id.as_deref().unwrap_or("-")
+            ),
+            None => format!("#{:<2} 0x{:x} ? -", i, frame.pc),
+        })
+        .collect())
+}
+
+/// The frames in `shown` as a Sentry-style stack trace, outermost first as
+/// Sentry has them, with the modules they're in as `images` for
+/// symbolicating.
+pub fn json(subordinate: &Subordinate, frames: &[Frame], shown: Range<usize>) -> Result<String> {
+    let (modules, found) = modules(subordinate, &frames[shown.clone()])?;
+    let bias = subordinate.load_bias();
+    let mut entries = Vec::new();
+    for ((i, frame), module) in shown.clone().zip(&frames[shown]).zip(found).rev() {
+        let mut fields = vec![format!("\"instruction_addr\":\"0x{:x}\"", frame.pc)];
+        if let Some((symbol, _)) = subordinate.symbol_at(frame.pc) {
+            fields.push(format!(
+                "\"function\":{}",
+                json_string(&subordinate.symbol_name(symbol))
+            ));
+            fields.push(format!("\"symbol_addr\":\"0x{:x}\"", symbol.value));
+        }
+        let module = module.map(|m| &modules[m]);
+        if let Some(module) = module {
+            fields.push(format!(
+                "\"package\":{}",
+                json_string(&module.path.to_string_lossy())
+            ));
+            fields.push(format!(
+                "\"module_offset\":\"0x{:x}\"",
+                frame.pc - module.base
+            ));
+        }
+        // Only the program's own debug info is loaded. Callers' pcs are
+        // return addresses, the call being the byte before.
+        let lookup = if i == 0 { frame.pc } else { frame.pc - 1 };
+        let line = module
+            .filter(|module| module.path == subordinate.path())
+            .and_then(|_| {
+                subordinate
+                    .debug_info()
+                    .line_for_addr(lookup.wrapping_sub(bias) as usize)
+            });
+        if let Some(line) = line {
+            fields.push(format!(
+                "\"filename\":{}",
+                json_string(&line.path.to_string_lossy())
+            ));
+            fields.push(format!("\"lineno\":{}", line.line));
+        }
+        entries.push(format!("{{{}}}", fields.join(",")));
+    }
+
+    let images: Vec<String> = modules
+        .iter()
+        .map(|module| {
+            let code_id = match &module.build_id {
+                Some(id) => json_string(id),
+                None => "null".to_owned(),
+            };
+            format!(
+                "{{\"type\":\"elf\",\"code_file\":{},\"code_id\":{},\"image_addr\":\"0x{:x}\",\"image_size\":{}}}",
+                json_string(&module.path.to_string_lossy()),
+                code_id,
+                module.base,
+                module.end - module.base
+            )
+        })
+        .collect();
+
+    Ok(format!(
+        "{{\"frames\":[\n{}\n],\"images\":[\n{}\n]}}",
+        entries.join(",\n"),
+        images.join(",\n")
+    ))
+}
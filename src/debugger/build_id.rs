@@ -0,0 +1,101 @@
+use crate::debugger::auxv::Entry::{ProgramHeaderAddr, ProgramHeaderCount};
+use crate::debugger::Subordinate;
+
+pub(crate) const PT_NOTE: u32 = 4;
+const NT_GNU_BUILD_ID: u32 = 3;
+pub(crate) const PHDR_SIZE: usize = 56;
+
+/// The GNU build-id note of an ELF file.
+pub fn of_file(data: &[u8]) -> Option<Vec<u8>> {
+    let offset = u64_at(data.get(..64)?, 32) as usize;
+    let count = u16::from_le_bytes([data[56], data[57]]) as usize;
+    let headers = data.get(offset..offset + count * PHDR_SIZE)?;
+    find_in_segments(headers, |header| {
+        let offset = u64_at(header, 8) as usize;
+        let size = u64_at(header, 32) as usize;
+        data.get(offset..offset + size).map(|notes| notes.to_vec())
+    })
+}
+
+/// The build-id of the executable as it's mapped into the process, found
+/// through the program headers the kernel points at in the auxv.
+pub fn of_process(subordinate: &Subordinate) -> Option<Vec<u8>> {
+    let mut phdr = None;
+    let mut count = None;
+    for entry in subordinate.auxv() {
+        match entry {
+            ProgramHeaderAddr(addr) => phdr = Some(*addr),
+            ProgramHeaderCount(n) => count = Some(*n),
+            _ => {}
+        }
+    }
+    let headers = subordinate.read_bytes(phdr?, count? * PHDR_SIZE).ok()?;
+
+    find_in_segments(&headers, |header| {
+        let vaddr = u64_at(header, 16) + subordinate.load_bias();
+        let size = u64_at(header, 32) as usize;
+        subordinate.read_bytes(vaddr as usize, size).ok()
+    })
+}
+
+pub fn hex(id: &[u8]) -> String {
+    id.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn find_in_segments<F>(headers: &[u8], read: F) -> Option<Vec<u8>>
+where
+    F: Fn(&[u8]) -> Option<Vec<u8>>,
+{
+    headers
+        .chunks(PHDR_SIZE)
+        .filter(|header| u32_at(header, 0) == PT_NOTE)
+        .filter_map(|header| find_note(&read(header)?))
+        .next()
+}
+
+fn find_note(notes: &[u8]) -> Option<Vec<u8>> {
+    notes_in(notes)
+        .into_iter()
+        .find(|(kind, name, _)| *kind == NT_GNU_BUILD_ID && *name == b"GNU\0")
+        .map(|(_, _, desc)| desc.to_vec())
+}
+
+/// Each note in a PT_NOTE segment as its type, name and descriptor. Notes
+/// are a header of three u32s (name size, descriptor size, type), then the
+/// name and descriptor each padded to 4 bytes.
+pub(crate) fn notes_in(mut notes: &[u8]) -> Vec<(u32, &[u8], &[u8])> {
+    let align = |n: usize| (n + 3) & !3;
+    let mut found = Vec::new();
+    while notes.len() >= 12 {
+        let name_size = u32_at(notes, 0) as usize;
+        let desc_size = u32_at(notes, 4) as usize;
+        let kind = u32_at(notes, 8);
+        let desc = 12 + align(name_size);
+        let end = desc + align(desc_size);
+        if notes.len() < desc + desc_size {
+            break;
+        }
+        found.push((
+            kind,
+            &notes[12..12 + name_size],
+            &notes[desc..desc + desc_size],
+        ));
+        notes = match notes.get(end..) {
+            Some(rest) => rest,
+            None => break,
+        };
+    }
+    found
+}
+
+pub(crate) fn u32_at(bytes: &[u8], at: usize) -> u32 {
+    let mut buf = [0u8; 4];
+    buf.copy_from_slice(&bytes[at..at + 4]);
+    u32::from_le_bytes(buf)
+}
+
+pub(crate) fn u64_at(bytes: &[u8], at: usize) -> u64 {
+    let mut buf = [0u8; 8];
+    buf.copy_from_slice(&bytes[at..at + 8]);
+    u64::from_le_bytes(buf)
+}
@@ -0,0 +1,106 @@
+use std::fs;
+
+const CAP_SYS_PTRACE: u64 = 19;
+
+const PTRACE_SCOPE_PATH: &str = "/proc/sys/kernel/yama/ptrace_scope";
+const PERF_EVENT_PARANOID_PATH: &str = "/proc/sys/kernel/perf_event_paranoid";
+const OSRELEASE_PATH: &str = "/proc/sys/kernel/osrelease";
+
+/// What the kernel will let us do, checked once before spawning so that a
+/// missing feature shows up as a warning at startup rather than as an errno
+/// halfway through a session.
+#[derive(Debug, Clone, Default)]
+pub struct Capabilities {
+    pub sys_ptrace: bool,
+    pub ptrace_scope: Option<u32>,
+    pub perf_event_paranoid: Option<i32>,
+    pub wsl1: bool,
+    pub process_vm_readv: bool,
+}
+
+impl Capabilities {
+    pub fn detect() -> Self {
+        let wsl1 = is_wsl1();
+
+        Capabilities {
+            sys_ptrace: has_capability(CAP_SYS_PTRACE),
+            ptrace_scope: read_number(PTRACE_SCOPE_PATH),
+            perf_event_paranoid: read_number(PERF_EVENT_PARANOID_PATH),
+            wsl1,
+            // WSL1 doesn't implement process_vm_readv, other environments
+            // (seccomp'd containers) are caught the first time a read fails.
+            process_vm_readv: !wsl1,
+        }
+    }
+
+    /// Yama's ptrace_scope 3 forbids PTRACE_TRACEME as well as attaching.
+    pub fn ptrace_disabled(&self) -> bool {
+        self.ptrace_scope == Some(3)
+    }
+
+    pub fn warnings(&self) -> Vec<String> {
+        let mut warnings = Vec::new();
+
+        if self.ptrace_disabled() {
+            warnings
+                .push("ptrace is disabled system-wide (kernel.yama.ptrace_scope = 3)".to_owned());
+        } else if let Some(scope) = self.ptrace_scope {
+            if scope >= 2 && !self.sys_ptrace {
+                warnings.push(format!(
+                    "CAP_SYS_PTRACE is not available and kernel.yama.ptrace_scope = {}, only spawned processes can be debugged",
+                    scope
+                ));
+            }
+        }
+
+        if self.wsl1 {
+            warnings.push(
+                "running under WSL1, ptrace support is partial and memory reads will be slower"
+                    .to_owned(),
+            );
+        }
+
+        match self.perf_event_paranoid {
+            None => warnings.push(
+                "kernel has no perf event support, hardware breakpoints and watchpoints will not work"
+                    .to_owned(),
+            ),
+            Some(level) if level > 2 => warnings.push(format!(
+                "perf events are restricted (kernel.perf_event_paranoid = {})",
+                level
+            )),
+            _ => {}
+        }
+
+        warnings
+    }
+}
+
+fn has_capability(cap: u64) -> bool {
+    let status = match fs::read_to_string("/proc/self/status") {
+        Ok(status) => status,
+        Err(_) => return false,
+    };
+
+    for line in status.lines() {
+        if let Some(hex) = line.strip_prefix("CapEff:") {
+            if let Ok(caps) = u64::from_str_radix(hex.trim(), 16) {
+                return caps & (1 << cap) != 0;
+            }
+        }
+    }
+
+    false
+}
+
+// WSL2 reports "microsoft-standard", WSL1 reports "Microsoft".
+fn is_wsl1() -> bool {
+    match fs::read_to_string(OSRELEASE_PATH) {
+        Ok(release) => release.contains("Microsoft"),
+        Err(_) => false,
+    }
+}
+
+fn read_number<T: std::str::FromStr>(path: &str) -> Option<T> {
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
@@ -0,0 +1,469 @@
+use crate::debugger::build_id::{notes_in, u32_at, u64_at, PHDR_SIZE, PT_NOTE};
+use crate::debugger::registers::NAMES;
+use crate::debugger::{auxv, Machine, Region, Registers, Subordinate};
+use crate::result::Result;
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+mod minidump;
+
+const ZSTD_MAGIC: &[u8] = b"\x28\xb5\x2f\xfd";
+const ET_CORE: u16 = 4;
+const EM_X86_64: u16 = 62;
+const EHDR_SIZE: usize = 64;
+const PAGE_SIZE: u64 = 4096;
+const PT_LOAD: u32 = 1;
+const PF_X: u32 = 1;
+const PF_W: u32 = 2;
+const PF_R: u32 = 4;
+const NT_PRSTATUS: u32 = 1;
+const NT_PRPSINFO: u32 = 3;
+const NT_AUXV: u32 = 6;
+const NT_FILE: u32 = 0x4649_4c45;
+// Where the fields used are in x86-64's `elf_prstatus`.
+const PRSTATUS_CURSIG: usize = 12;
+const PRSTATUS_PID: usize = 32;
+const PRSTATUS_REGS: usize = 112;
+const PRSTATUS_SIZE: usize = 336;
+// And in `elf_prpsinfo`.
+const PRPSINFO_PID: usize = 24;
+const PRPSINFO_FNAME: usize = 40;
+const PRPSINFO_PSARGS: usize = 56;
+const PRPSINFO_SIZE: usize = 136;
+
+/// A process as it was when it dumped core, for looking at after the fact.
+pub struct Core {
+    segments: Vec<Segment>,
+    files: Vec<MappedFile>,
+    // Each thread's id and registers, the one that got the signal first.
+    threads: Vec<(i32, Registers)>,
+    signal: i32,
+    auxv: Vec<auxv::Entry>,
+    pid: Option<i32>,
+    // The memory map when the dump has one, minidumps only holding some of
+    // the memory.
+    maps: Option<Vec<Region>>,
+}
+
+// A PT_LOAD segment, whose data may stop short of its size when the kernel
+// left out memory that's in a file anyway, like code.
+struct Segment {
+    vaddr: u64,
+    memsz: u64,
+    flags: u32,
+    data: Vec<u8>,
+}
+
+// A file mapping from the NT_FILE note.
+struct MappedFile {
+    start: u64,
+    end: u64,
+    offset: u64,
+    path: PathBuf,
+}
+
+impl Core {
+    /// Opens an ELF core file or a Breakpad/Crashpad minidump, either of
+    /// which may be zstd-compressed.
+    pub fn open(path: &Path) -> Result<Self> {
+        let mut data = fs::read(path)
+            .map_err(|e| format!("couldn't read core file {}: {}", path.display(), e))?;
+        if data.starts_with(ZSTD_MAGIC) {
+            data = decompress(path)?;
+        }
+        if data.starts_with(minidump::SIGNATURE) {
+            return minidump::parse(&data);
+        }
+
+        let header = data
+            .get(..64)
+            .filter(|header| header.starts_with(b"\x7fELF"));
+        let header = header
+            .ok_or_else(|| format!("{} isn't an ELF core file or a minidump", path.display()))?;
+        if u16::from_le_bytes([header[16], header[17]]) != ET_CORE {
+            return Err(format!("{} isn't a core file", path.display()).into());
+        }
+        // The register layout in NT_PRSTATUS is x86-64's.
+        Machine::from_elf(u16::from_le_bytes([header[18], header[19]])).check("core file")?;
+
+        let offset = u64_at(header, 32) as usize;
+        let count = u16::from_le_bytes([header[56], header[57]]) as usize;
+        let headers = offset
+            .checked_add(count * PHDR_SIZE)
+            .and_then(|end| data.get(offset..end))
+            .ok_or("truncated core file")?;
+
+        let mut core = Core {
+            segments: Vec::new(),
+            files: Vec::new(),
+            threads: Vec::new(),
+            signal: 0,
+            auxv: Vec::new(),
+            pid: None,
+            maps: None,
+        };
+        for header in headers.chunks(PHDR_SIZE) {
+            let offset = u64_at(header, 8) as usize;
+            let size = u64_at(header, 32) as usize;
+            let contents = offset
+                .checked_add(size)
+                .and_then(|end| data.get(offset..end))
+                .ok_or("truncated core file")?;
+            match u32_at(header, 0) {
+                PT_LOAD => core.segments.push(Segment {
+                    vaddr: u64_at(header, 16),
+                    memsz: u64_at(header, 40),
+                    flags: u32_at(header, 4),
+                    data: contents.to_vec(),
+                }),
+                PT_NOTE => core.read_notes(contents),
+                _ => {}
+            }
+        }
+
+        if core.threads.is_empty() {
+            return Err("the core file has no threads in it".into());
+        }
+        Ok(core)
+    }
+
+    fn read_notes(&mut self, notes: &[u8]) {
+        for (kind, name, desc) in notes_in(notes) {
+            if name != b"CORE\0" {
+                continue;
+            }
+            match kind {
+                NT_PRSTATUS if desc.len() >= PRSTATUS_REGS + NAMES.len() * 8 => {
+                    let mut registers = Registers::default();
+                    for (i, name) in NAMES.iter().enumerate() {
+                        registers.set(name, u64_at(desc, PRSTATUS_REGS + i * 8));
+                    }
+                    if self.threads.is_empty() {
+                        let cursig = &desc[PRSTATUS_CURSIG..PRSTATUS_CURSIG + 2];
+                        self.signal = u16::from_le_bytes([cursig[0], cursig[1]]) as i32;
+                    }
+                    let tid = u32_at(desc, PRSTATUS_PID) as i32;
+                    self.threads.push((tid, registers));
+                }
+                NT_AUXV => self.auxv = auxv::parse(desc),
+                NT_FILE => self.files = mapped_files(desc),
+                _ => {}
+            }
+        }
+    }
+
+    /// The id of the process that dumped, when not given its first
+    /// thread's.
+    pub fn pid(&self) -> i32 {
+        self.pid.unwrap_or(self.threads[0].0)
+    }
+
+    /// The signal that made the process dump.
+    pub fn signal(&self) -> i32 {
+        self.signal
+    }
+
+    pub fn threads(&self) -> impl Iterator<Item = i32> + '_ {
+        self.threads.iter().map(|(tid, _)| *tid)
+    }
+
+    pub fn registers(&self, tid: i32) -> Result<Registers> {
+        self.threads
+            .iter()
+            .find(|(id, _)| *id == tid)
+            .map(|(_, registers)| registers.clone())
+            .ok_or_else(|| format!("no thread {} in the core file", tid).into())
+    }
+
+    pub fn take_auxv(&mut self) -> Vec<auxv::Entry> {
+        std::mem::take(&mut self.auxv)
+    }
+
+    /// Reads memory from the core file, or for what it left out, from the
+    /// file that was mapped there.
+    pub fn read(&self, from: usize, size: usize) -> Result<Vec<u8>> {
+        let mut bytes = Vec::with_capacity(size);
+        while bytes.len() < size {
+            let addr = from
+                .checked_add(bytes.len())
+                .ok_or("read past the end of the address space")? as u64;
+            let chunk = self
+                .read_chunk(addr, size - bytes.len())
+                .ok_or_else(|| format!("0x{:x} isn't in the core file", addr))?;
+            bytes.extend_from_slice(&chunk);
+        }
+        Ok(bytes)
+    }
+
+    // As much as can be read at `addr` from one place, up to `max` bytes.
+    fn read_chunk(&self, addr: u64, max: usize) -> Option<Vec<u8>> {
+        let dumped = self.segments.iter().find(|segment| {
+            addr >= segment.vaddr && addr - segment.vaddr < segment.data.len() as u64
+        });
+        if let Some(segment) = dumped {
+            let at = (addr - segment.vaddr) as usize;
+            let end = segment.data.len().min(at.saturating_add(max));
+            return Some(segment.data[at..end].to_vec());
+        }
+
+        let file = self
+            .files
+            .iter()
+            .find(|file| addr >= file.start && addr < file.end)?;
+        let mut handle = File::open(&file.path).ok()?;
+        handle
+            .seek(SeekFrom::Start(file.offset.checked_add(addr - file.start)?))
+            .ok()?;
+        let mut buf = vec![0; max.min((file.end - addr) as usize)];
+        let read = handle.read(&mut buf).ok()?;
+        if read == 0 {
+            return None;
+        }
+        buf.truncate(read);
+        Some(buf)
+    }
+
+    /// The memory map as it was, like /proc/<pid>/maps would have shown it.
+    pub fn regions(&self) -> Vec<Region> {
+        if let Some(maps) = &self.maps {
+            return maps.clone();
+        }
+        self.segments
+            .iter()
+            .map(|segment| {
+                let file = self.files.iter().find(|file| file.start == segment.vaddr);
+                let flag = |bit, c| if segment.flags & bit != 0 { c } else { '-' };
+                Region {
+                    start: segment.vaddr as usize,
+                    end: (segment.vaddr + segment.memsz) as usize,
+                    perms: format!("{}{}{}p", flag(PF_R, 'r'), flag(PF_W, 'w'), flag(PF_X, 'x')),
+                    offset: file.map_or(0, |file| file.offset as usize),
+                    path: file.map(|file| file.path.clone()),
+                    name: None,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Decompresses with zstd(1), there being no zstd support built in.
+fn decompress(path: &Path) -> Result<Vec<u8>> {
+    let output = Command::new("zstd")
+        .arg("-dc")
+        .arg(path)
+        .output()
+        .map_err(|e| format!("couldn't run zstd to decompress {}: {}", path.display(), e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "zstd couldn't decompress {}: {}",
+            path.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+        .into());
+    }
+    Ok(output.stdout)
+}
+
+// NT_FILE is a count and page size, then the start, end and offset in pages
+// of each mapping, then their paths NUL-terminated.
+fn mapped_files(desc: &[u8]) -> Vec<MappedFile> {
+    if desc.len() < 16 {
+        return Vec::new();
+    }
+    let count = u64_at(desc, 0) as usize;
+    let page_size = u64_at(desc, 8);
+    let paths_at = match count.checked_mul(24).and_then(|size| size.checked_add(16)) {
+        Some(paths_at) => paths_at,
+        None => return Vec::new(),
+    };
+    let paths = match desc.get(paths_at..) {
+        Some(paths) => paths.split(|b| *b == 0),
+        None => return Vec::new(),
+    };
+    desc[16..paths_at]
+        .chunks(24)
+        .zip(paths)
+        .filter_map(|(entry, path)| {
+            Some(MappedFile {
+                start: u64_at(entry, 0),
+                end: u64_at(entry, 8),
+                offset: u64_at(entry, 16).checked_mul(page_size)?,
+                path: PathBuf::from(String::from_utf8_lossy(path).into_owned()),
+            })
+        })
+        .collect()
+}
+
+/// Writes the process's memory and each thread's registers to `path` as an
+/// ELF core file, the current thread first as the one that "crashed".
+/// Returns how many bytes of memory went in.
+pub fn write(subordinate: &Subordinate, path: &Path) -> Result<usize> {
+    subordinate.ensure_live()?;
+    let pid = subordinate.pid();
+
+    let mut notes = Vec::new();
+    let current = subordinate.current_thread();
+    let mut tids: Vec<i32> = subordinate.threads().iter().map(|t| t.tid).collect();
+    tids.sort_by_key(|tid| *tid != current);
+    for tid in tids {
+        let registers = subordinate.thread_registers(tid)?;
+        let mut prstatus = vec![0u8; PRSTATUS_SIZE];
+        let signal = subordinate.stop_signal().unwrap_or(0);
+        prstatus[0..4].copy_from_slice(&signal.to_le_bytes());
+        prstatus[PRSTATUS_CURSIG..PRSTATUS_CURSIG + 2]
+            .copy_from_slice(&(signal as u16).to_le_bytes());
+        prstatus[PRSTATUS_PID..PRSTATUS_PID + 4].copy_from_slice(&tid.to_le_bytes());
+        for (i, name) in NAMES.iter().enumerate() {
+            let at = PRSTATUS_REGS + i * 8;
+            let value = registers.get(name).unwrap_or(0);
+            prstatus[at..at + 8].copy_from_slice(&value.to_le_bytes());
+        }
+        push_note(&mut notes, NT_PRSTATUS, &prstatus);
+    }
+
+    let mut prpsinfo = vec![0u8; PRPSINFO_SIZE];
+    prpsinfo[PRPSINFO_PID..PRPSINFO_PID + 4].copy_from_slice(&pid.to_le_bytes());
+    let cmdline = fs::read(format!("/proc/{}/cmdline", pid)).unwrap_or_default();
+    let name = subordinate
+        .path()
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    copy_truncated(
+        &mut prpsinfo[PRPSINFO_FNAME..PRPSINFO_PSARGS],
+        name.as_bytes(),
+    );
+    let args: Vec<u8> = cmdline
+        .iter()
+        .map(|b| if *b == 0 { b' ' } else { *b })
+        .collect();
+    copy_truncated(&mut prpsinfo[PRPSINFO_PSARGS..], args.trim_ascii_end());
+    push_note(&mut notes, NT_PRPSINFO, &prpsinfo);
+
+    let auxv = fs::read(format!("/proc/{}/auxv", pid))?;
+    push_note(&mut notes, NT_AUXV, &auxv);
+
+    // Memory the process can't read, like [vvar], goes in with no data.
+    let regions: Vec<(Region, Option<Vec<u8>>)> = subordinate
+        .maps()?
+        .into_iter()
+        .map(|region| {
+            let data = if region.perms.starts_with('r') {
+                subordinate
+                    .read_bytes(region.start, region.end - region.start)
+                    .ok()
+            } else {
+                None
+            };
+            (region, data)
+        })
+        .collect();
+    push_note(&mut notes, NT_FILE, &file_note(&regions));
+
+    let headers = 1 + regions.len();
+    let notes_at = EHDR_SIZE + headers * PHDR_SIZE;
+    let mut offset = align_up((notes_at + notes.len()) as u64, PAGE_SIZE);
+
+    let mut out = Vec::new();
+    out.extend_from_slice(b"\x7fELF\x02\x01\x01");
+    out.resize(16, 0);
+    out.extend_from_slice(&ET_CORE.to_le_bytes());
+    out.extend_from_slice(&EM_X86_64.to_le_bytes());
+    out.extend_from_slice(&1u32.to_le_bytes());
+    out.extend_from_slice(&0u64.to_le_bytes());
+    out.extend_from_slice(&(EHDR_SIZE as u64).to_le_bytes());
+    out.extend_from_slice(&0u64.to_le_bytes());
+    out.extend_from_slice(&0u32.to_le_bytes());
+    for size in &[EHDR_SIZE, PHDR_SIZE, headers, 0, 0, 0] {
+        out.extend_from_slice(&(*size as u16).to_le_bytes());
+    }
+
+    let notes_len = notes.len() as u64;
+    push_phdr(
+        &mut out,
+        PT_NOTE,
+        0,
+        [notes_at as u64, 0, notes_len, notes_len, 0],
+    );
+    for (region, data) in &regions {
+        let size = (region.end - region.start) as u64;
+        let stored = data.as_ref().map_or(0, |data| data.len() as u64);
+        let perms = region.perms.as_bytes();
+        let flag = |at: usize, c: u8, bit: u32| if perms.get(at) == Some(&c) { bit } else { 0 };
+        let flags = flag(0, b'r', PF_R) | flag(1, b'w', PF_W) | flag(2, b'x', PF_X);
+        let start = region.start as u64;
+        push_phdr(
+            &mut out,
+            PT_LOAD,
+            flags,
+            [offset, start, stored, size, PAGE_SIZE],
+        );
+        offset += stored;
+    }
+
+    out.extend_from_slice(&notes);
+    out.resize(align_up(out.len() as u64, PAGE_SIZE) as usize, 0);
+    let mut dumped = 0;
+    for data in regions.iter().filter_map(|(_, data)| data.as_ref()) {
+        out.extend_from_slice(data);
+        dumped += data.len();
+    }
+
+    fs::write(path, out).map_err(|e| format!("couldn't write {}: {}", path.display(), e))?;
+    Ok(dumped)
+}
+
+fn push_note(notes: &mut Vec<u8>, kind: u32, desc: &[u8]) {
+    let name = b"CORE\0";
+    notes.extend_from_slice(&(name.len() as u32).to_le_bytes());
+    notes.extend_from_slice(&(desc.len() as u32).to_le_bytes());
+    notes.extend_from_slice(&kind.to_le_bytes());
+    notes.extend_from_slice(name);
+    notes.resize(align_up(notes.len() as u64, 4) as usize, 0);
+    notes.extend_from_slice(desc);
+    notes.resize(align_up(notes.len() as u64, 4) as usize, 0);
+}
+
+// `fields` are the offset, address, file size, memory size and alignment,
+// the address doubling as the physical one.
+fn push_phdr(out: &mut Vec<u8>, kind: u32, flags: u32, fields: [u64; 5]) {
+    let [offset, vaddr, filesz, memsz, align] = fields;
+    out.extend_from_slice(&kind.to_le_bytes());
+    out.extend_from_slice(&flags.to_le_bytes());
+    for field in &[offset, vaddr, vaddr, filesz, memsz, align] {
+        out.extend_from_slice(&field.to_le_bytes());
+    }
+}
+
+// The inverse of `mapped_files`, for the regions that map a file.
+fn file_note(regions: &[(Region, Option<Vec<u8>>)]) -> Vec<u8> {
+    let files: Vec<(&Region, &PathBuf)> = regions
+        .iter()
+        .filter_map(|(region, _)| Some((region, region.path.as_ref()?)))
+        .collect();
+    let mut desc = Vec::new();
+    desc.extend_from_slice(&(files.len() as u64).to_le_bytes());
+    desc.extend_from_slice(&PAGE_SIZE.to_le_bytes());
+    for (region, _) in &files {
+        desc.extend_from_slice(&(region.start as u64).to_le_bytes());
+        desc.extend_from_slice(&(region.end as u64).to_le_bytes());
+        desc.extend_from_slice(&(region.offset as u64 / PAGE_SIZE).to_le_bytes());
+    }
+    for (_, path) in &files {
+        desc.extend_from_slice(path.to_string_lossy().as_bytes());
+        desc.push(0);
+    }
+    desc
+}
+
+// Copies as much of `from` as fits while leaving a NUL at the end.
+fn copy_truncated(to: &mut [u8], from: &[u8]) {
+    let len = from.len().min(to.len() - 1);
+    to[..len].copy_from_slice(&from[..len]);
+}
+
+fn align_up(n: u64, align: u64) -> u64 {
+    n.div_ceil(align) * align
+}
@@ -0,0 +1,246 @@
+use super::{Core, MappedFile, Segment, PF_R, PF_W};
+use crate::debugger::build_id::{u32_at, u64_at};
+use crate::debugger::{auxv, maps, Region, Registers};
+use crate::result::Result;
+
+pub const SIGNATURE: &[u8] = b"MDMP";
+
+// Stream types, the Linux ones being Breakpad's.
+const THREAD_LIST: u32 = 3;
+const MODULE_LIST: u32 = 4;
+const MEMORY_LIST: u32 = 5;
+const EXCEPTION: u32 = 6;
+const MEMORY64_LIST: u32 = 9;
+const MISC_INFO: u32 = 15;
+const LINUX_AUXV: u32 = 0x4767_0008;
+const LINUX_MAPS: u32 = 0x4767_0009;
+
+const DIRECTORY_ENTRY_SIZE: usize = 12;
+const THREAD_SIZE: usize = 48;
+const MODULE_SIZE: usize = 108;
+const MEMORY_DESCRIPTOR_SIZE: usize = 16;
+// Where the context's location is in MINIDUMP_EXCEPTION_STREAM, after the
+// thread id and the exception record.
+const EXCEPTION_CONTEXT: usize = 160;
+const MISC_INFO_HAS_PID: u32 = 1;
+
+// Where the general purpose registers are in the x86-64 CONTEXT, which has
+// no fs_base or gs_base.
+const CONTEXT_EFLAGS: usize = 0x44;
+const CONTEXT_SEGMENTS: &[(&str, usize)] = &[
+    ("cs", 0x38),
+    ("ds", 0x3a),
+    ("es", 0x3c),
+    ("fs", 0x3e),
+    ("gs", 0x40),
+    ("ss", 0x42),
+];
+const CONTEXT_REGISTERS: &[&str] = &[
+    "rax", "rcx", "rdx", "rbx", "rsp", "rbp", "rsi", "rdi", "r8", "r9", "r10", "r11", "r12", "r13",
+    "r14", "r15", "rip",
+];
+const CONTEXT_RAX: usize = 0x78;
+const CONTEXT_SIZE: usize = 0x100;
+
+/// Reads a minidump into a `Core`. Minidumps hold the stacks and little
+/// else, the rest of memory comes from the mapped files, by the Linux
+/// memory map when the dump has it and the module list when not.
+pub fn parse(data: &[u8]) -> Result<Core> {
+    let header = data.get(..32).ok_or("truncated minidump")?;
+    let count = u32_at(header, 8) as usize;
+    let directory = u32_at(header, 12) as usize;
+    let entries = slice(data, directory, count * DIRECTORY_ENTRY_SIZE)?;
+
+    let mut core = Core {
+        segments: Vec::new(),
+        files: Vec::new(),
+        threads: Vec::new(),
+        signal: 0,
+        auxv: Vec::new(),
+        pid: None,
+        maps: None,
+    };
+    let mut modules = Vec::new();
+    let mut crashed = None;
+    for entry in entries.chunks(DIRECTORY_ENTRY_SIZE) {
+        let size = u32_at(entry, 4) as usize;
+        let rva = u32_at(entry, 8) as usize;
+        let stream = slice(data, rva, size)?;
+        match u32_at(entry, 0) {
+            THREAD_LIST => {
+                for thread in records(stream, THREAD_SIZE) {
+                    let tid = u32_at(thread, 0) as i32;
+                    let registers = context(data, &thread[40..])?;
+                    core.threads.push((tid, registers));
+                }
+            }
+            MEMORY_LIST => {
+                for descriptor in records(stream, MEMORY_DESCRIPTOR_SIZE) {
+                    let start = u64_at(descriptor, 0);
+                    let contents = location(data, &descriptor[8..])?;
+                    core.segments.push(segment(start, contents));
+                }
+            }
+            MEMORY64_LIST if stream.len() >= 16 => {
+                let count = u64_at(stream, 0) as usize;
+                let mut at = u64_at(stream, 8) as usize;
+                for descriptor in stream[16..].chunks(16).take(count) {
+                    let size = u64_at(descriptor, 8) as usize;
+                    let contents = slice(data, at, size)?;
+                    core.segments.push(segment(u64_at(descriptor, 0), contents));
+                    at += size;
+                }
+            }
+            EXCEPTION if stream.len() >= EXCEPTION_CONTEXT + 8 => {
+                // On Linux the exception code is the signal number.
+                core.signal = u32_at(stream, 8) as i32;
+                let registers = context(data, &stream[EXCEPTION_CONTEXT..])?;
+                crashed = Some((u32_at(stream, 0) as i32, registers));
+            }
+            MODULE_LIST => {
+                for module in records(stream, MODULE_SIZE) {
+                    let start = u64_at(module, 0);
+                    let end = start
+                        .checked_add(u32_at(module, 8) as u64)
+                        .ok_or("truncated minidump")?;
+                    modules.push((start, end, string(data, u32_at(module, 20) as usize)?));
+                }
+            }
+            MISC_INFO if stream.len() >= 12 && u32_at(stream, 4) & MISC_INFO_HAS_PID != 0 => {
+                core.pid = Some(u32_at(stream, 8) as i32);
+            }
+            LINUX_AUXV => core.auxv = auxv::parse(stream),
+            LINUX_MAPS => core.maps = Some(maps::parse(&String::from_utf8_lossy(stream))?),
+            _ => {}
+        }
+    }
+
+    // The thread that crashed goes first, with its registers as they were
+    // at the signal rather than in the handler that wrote the dump.
+    if let Some((tid, registers)) = crashed {
+        core.threads.retain(|(id, _)| *id != tid);
+        core.threads.insert(0, (tid, registers));
+    }
+    if core.threads.is_empty() {
+        return Err("the minidump has no threads in it".into());
+    }
+
+    core.files = match &core.maps {
+        Some(maps) => maps
+            .iter()
+            .filter_map(|region| {
+                Some(MappedFile {
+                    start: region.start as u64,
+                    end: region.end as u64,
+                    offset: region.offset as u64,
+                    path: region.path.clone()?,
+                })
+            })
+            .collect(),
+        // Without the map, a module is taken to be its file mapped as is.
+        None => modules
+            .iter()
+            .map(|(start, end, path)| MappedFile {
+                start: *start,
+                end: *end,
+                offset: 0,
+                path: path.into(),
+            })
+            .collect(),
+    };
+    if core.maps.is_none() {
+        core.maps = Some(regions(&core, &modules));
+    }
+    Ok(core)
+}
+
+// A list stream's records of `size` bytes, after a 32-bit count.
+fn records(stream: &[u8], size: usize) -> impl Iterator<Item = &[u8]> {
+    let count = if stream.len() >= 4 {
+        u32_at(stream, 0) as usize
+    } else {
+        0
+    };
+    stream
+        .get(4..)
+        .unwrap_or_default()
+        .chunks_exact(size)
+        .take(count)
+}
+
+// The `size` bytes at `at`, unless the dump ends before them.
+fn slice(data: &[u8], at: usize, size: usize) -> Result<&[u8]> {
+    at.checked_add(size)
+        .and_then(|end| data.get(at..end))
+        .ok_or_else(|| "truncated minidump".into())
+}
+
+// What a MINIDUMP_LOCATION_DESCRIPTOR, a size and offset, points at.
+fn location<'a>(data: &'a [u8], descriptor: &[u8]) -> Result<&'a [u8]> {
+    let size = u32_at(descriptor, 0) as usize;
+    let rva = u32_at(descriptor, 4) as usize;
+    slice(data, rva, size)
+}
+
+fn context(data: &[u8], descriptor: &[u8]) -> Result<Registers> {
+    let context = location(data, descriptor)?;
+    if context.len() < CONTEXT_SIZE {
+        return Err("the minidump isn't of an x86-64 process".into());
+    }
+    let mut registers = Registers::default();
+    for (i, name) in CONTEXT_REGISTERS.iter().enumerate() {
+        registers.set(name, u64_at(context, CONTEXT_RAX + i * 8));
+    }
+    for (name, at) in CONTEXT_SEGMENTS {
+        registers.set(
+            name,
+            u16::from_le_bytes([context[*at], context[at + 1]]) as u64,
+        );
+    }
+    registers.set("eflags", u32_at(context, CONTEXT_EFLAGS) as u64);
+    Ok(registers)
+}
+
+// A MINIDUMP_STRING, its length in bytes and then UTF-16.
+fn string(data: &[u8], rva: usize) -> Result<String> {
+    let len = u32_at(slice(data, rva, 4)?, 0) as usize;
+    let bytes = slice(data, rva + 4, len)?;
+    let units: Vec<u16> = bytes
+        .chunks_exact(2)
+        .map(|unit| u16::from_le_bytes([unit[0], unit[1]]))
+        .collect();
+    Ok(String::from_utf16_lossy(&units))
+}
+
+fn segment(start: u64, contents: &[u8]) -> Segment {
+    Segment {
+        vaddr: start,
+        memsz: contents.len() as u64,
+        flags: PF_R | PF_W,
+        data: contents.to_vec(),
+    }
+}
+
+// Without a memory map, the dumped memory and the modules are all there is
+// to go on.
+fn regions(core: &Core, modules: &[(u64, u64, String)]) -> Vec<Region> {
+    let dumped = core.segments.iter().map(|segment| Region {
+        start: segment.vaddr as usize,
+        end: segment.vaddr.saturating_add(segment.memsz) as usize,
+        perms: "rw-p".to_owned(),
+        offset: 0,
+        path: None,
+        name: None,
+    });
+    let mapped = modules.iter().map(|(start, end, path)| Region {
+        start: *start as usize,
+        end: *end as usize,
+        perms: "r-xp".to_owned(),
+        offset: 0,
+        path: Some(path.into()),
+        name: None,
+    });
+    let mut regions: Vec<Region> = dumped.chain(mapped).collect();
+    regions.sort_by_key(|region| region.start);
+    regions
+}
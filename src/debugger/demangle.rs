@@ -0,0 +1,87 @@
+use std::collections::HashMap;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Turns linker names back into source ones, Rust names with
+/// `rustc-demangle` and C++ (Itanium ABI) names with `c++filt`. C++ names
+/// are demangled ahead of time, a symbol table at a time, since each run of
+/// `c++filt` is a process.
+#[derive(Debug, Clone, Default)]
+pub struct Demangler {
+    cpp: HashMap<String, String>,
+}
+
+impl Demangler {
+    /// Demangles whichever of `names` are C++ ones. Without `c++filt`
+    /// installed, they stay as they are.
+    pub fn add<'a>(&mut self, names: impl Iterator<Item = &'a str>) {
+        let mangled: Vec<&str> = names
+            .filter(|name| name.starts_with("_Z") && !self.cpp.contains_key(*name))
+            .filter(|name| rustc_demangle::try_demangle(name).is_err())
+            .collect();
+        if mangled.is_empty() {
+            return;
+        }
+        let demangled = match cxxfilt(&mangled) {
+            Some(demangled) => demangled,
+            None => return,
+        };
+        for (name, demangled) in mangled.iter().zip(demangled) {
+            if *name != demangled {
+                self.cpp.insert(name.to_string(), demangled);
+            }
+        }
+    }
+
+    /// `name` as it reads in the source, without the hash for Rust.
+    pub fn demangle(&self, name: &str) -> String {
+        match rustc_demangle::try_demangle(name) {
+            // The alternate form leaves off the hash.
+            Ok(demangled) => format!("{:#}", demangled),
+            Err(_) => self
+                .cpp
+                .get(name)
+                .cloned()
+                .unwrap_or_else(|| name.to_owned()),
+        }
+    }
+
+    /// Whether `wanted` names the symbol `name` once demangled. Rust names
+    /// match with or without their hash, C++ ones with or without their
+    /// parameters, like `demo::shout` for `demo::shout(int)`.
+    pub fn matches(&self, name: &str, wanted: &str) -> bool {
+        if let Ok(demangled) = rustc_demangle::try_demangle(name) {
+            return format!("{:#}", demangled) == wanted || demangled.to_string() == wanted;
+        }
+        match self.cpp.get(name) {
+            Some(demangled) => demangled == wanted || demangled.split('(').next() == Some(wanted),
+            None => false,
+        }
+    }
+}
+
+// Each of `names` demangled by `c++filt`, which writes a line for each one
+// it reads, as it is when it isn't a C++ name.
+fn cxxfilt(names: &[&str]) -> Option<Vec<String>> {
+    let mut child = Command::new("c++filt")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+    let mut stdin = child.stdin.take()?;
+    let input = names.join("\n") + "\n";
+    // Written from a thread, c++filt could fill the pipe back before it's
+    // read everything otherwise.
+    let writer = std::thread::spawn(move || stdin.write_all(input.as_bytes()));
+    let output = child.wait_with_output().ok()?;
+    writer.join().ok()?.ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let demangled: Vec<String> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::to_owned)
+        .collect();
+    Some(demangled).filter(|demangled| demangled.len() == names.len())
+}
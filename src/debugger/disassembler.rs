@@ -1,15 +1,197 @@
+use crate::debugger::registers::{CF, OF, PF, SF, ZF};
+use crate::debugger::{syscalls, Annotations, RefKind, Registers, Subordinate};
 use crate::result::Result;
-use iced_x86::{Decoder, DecoderOptions, Formatter, Instruction, NasmFormatter};
+use iced_x86::{
+    ConditionCode, Decoder, DecoderError, DecoderOptions, FlowControl, Formatter, Instruction,
+    InstructionInfoFactory, Mnemonic, NasmFormatter, OpAccess, OpKind, Register,
+};
 use std::io::prelude::*;
 
-pub struct Disassembler {}
+pub const MAX_INSTRUCTION_LEN: usize = 15;
+const MAX_TIGHT_LOOP_LEN: u64 = 64;
+const SYSCALL: [u8; 2] = [0x0f, 0x05];
+
+#[derive(Default)]
+pub struct Disassembler {
+    annotations: Option<Annotations>,
+}
 
 impl Disassembler {
     pub fn new() -> Self {
-        Self {}
+        Self { annotations: None }
+    }
+
+    /// A disassembler that notes what immediates may stand for, per
+    /// `annotations`.
+    pub fn annotated(annotations: &Annotations) -> Self {
+        Self {
+            annotations: Some(annotations.clone()),
+        }
+    }
+
+    /// Disassembles all of `bytes`, with `notes` adding to what each
+    /// instruction is annotated with, like the symbols it refers to.
+    pub fn disassemble_noting(
+        &self,
+        rip: u64,
+        bytes: &[u8],
+        notes: &dyn Fn(&Instruction) -> Vec<String>,
+    ) -> Result<String> {
+        Ok(self.disassemble_count(rip, bytes, usize::MAX, notes)?.0)
+    }
+
+    /// Disassembles all of `bytes`, noting the function each direct call
+    /// and jump goes to, by the subordinate's symbols.
+    pub fn disassemble_symbolized(
+        &self,
+        subordinate: &Subordinate,
+        rip: u64,
+        bytes: &[u8],
+    ) -> Result<String> {
+        self.disassemble_noting(rip, bytes, &|instruction| {
+            let target = match branch_target(instruction) {
+                Some(target) => target,
+                None => return Vec::new(),
+            };
+            match subordinate.symbol_at(target) {
+                Some((symbol, 0)) => vec![format!("<{}>", subordinate.symbol_name(symbol))],
+                Some((symbol, offset)) => vec![format!(
+                    "<{}+0x{:x}>",
+                    subordinate.symbol_name(symbol),
+                    offset
+                )],
+                None => Vec::new(),
+            }
+        })
+    }
+
+    /// Each instruction's address and text, without the encoding, for
+    /// lining code up against other code.
+    pub fn instructions(&self, rip: u64, bytes: &[u8]) -> Vec<(u64, String)> {
+        let mut decoder = Decoder::new(64, bytes, DecoderOptions::NONE);
+        decoder.set_ip(rip);
+
+        let mut formatter = NasmFormatter::new();
+        let mut instructions = Vec::new();
+        let mut instruction = Instruction::default();
+        while decoder.can_decode() {
+            decoder.decode_out(&mut instruction);
+            if decoder.last_error() == DecoderError::NoMoreBytes {
+                break;
+            }
+            let mut text = String::new();
+            formatter.format(&instruction, &mut text);
+            instructions.push((instruction.ip(), text));
+        }
+        instructions
+    }
+
+    /// Addresses of the `call`s in `bytes` that go straight to `target`.
+    /// Calls through a register or memory can't be told apart without
+    /// running them, so they're left out.
+    pub fn call_sites(&self, rip: u64, bytes: &[u8], target: u64) -> Vec<u64> {
+        let mut decoder = Decoder::new(64, bytes, DecoderOptions::NONE);
+        decoder.set_ip(rip);
+
+        let mut sites = Vec::new();
+        let mut instruction = Instruction::default();
+        while decoder.can_decode() {
+            decoder.decode_out(&mut instruction);
+            if decoder.last_error() == DecoderError::NoMoreBytes {
+                break;
+            }
+            if instruction.flow_control() == FlowControl::Call
+                && instruction.near_branch_target() == target
+            {
+                sites.push(instruction.ip());
+            }
+        }
+        sites
+    }
+
+    /// Each direct call, jump, rip-relative memory operand or immediate in
+    /// `bytes` as the address of the instruction, the address it refers to
+    /// and how. Immediates are included since that's how code that isn't
+    /// position independent takes addresses, whether or not they are ones.
+    pub fn references(&self, rip: u64, bytes: &[u8]) -> Vec<(u64, u64, RefKind)> {
+        let mut decoder = Decoder::new(64, bytes, DecoderOptions::NONE);
+        decoder.set_ip(rip);
+
+        let mut references = Vec::new();
+        let mut instruction = Instruction::default();
+        while decoder.can_decode() {
+            decoder.decode_out(&mut instruction);
+            let kind = match instruction.flow_control() {
+                FlowControl::Call => Some(RefKind::Call),
+                FlowControl::UnconditionalBranch | FlowControl::ConditionalBranch => {
+                    Some(RefKind::Jump)
+                }
+                _ => None,
+            };
+            let branches = (0..instruction.op_count()).any(|operand| {
+                matches!(
+                    instruction.op_kind(operand),
+                    OpKind::NearBranch16 | OpKind::NearBranch32 | OpKind::NearBranch64
+                )
+            });
+            if let Some(kind) = kind.filter(|_| branches) {
+                references.push((instruction.ip(), instruction.near_branch_target(), kind));
+            }
+            if instruction.is_ip_rel_memory_operand() {
+                references.push((
+                    instruction.ip(),
+                    instruction.ip_rel_memory_address(),
+                    RefKind::Data,
+                ));
+            }
+            for operand in 0..instruction.op_count() {
+                let immediate = matches!(
+                    instruction.op_kind(operand),
+                    OpKind::Immediate32 | OpKind::Immediate32to64 | OpKind::Immediate64
+                );
+                if immediate {
+                    let value = instruction.immediate(operand);
+                    references.push((instruction.ip(), value, RefKind::Data));
+                }
+            }
+        }
+        references
+    }
+
+    /// The first instruction in `bytes`, as `disassemble_noting` shows it.
+    pub fn disassemble_one(&self, rip: u64, bytes: &[u8]) -> Result<String> {
+        let (text, _) = self.disassemble_count(rip, bytes, 1, &|_| Vec::new())?;
+        Ok(text.trim_end().to_owned())
     }
 
-    pub fn disassemble(&self, rip: u64, bytes: &[u8]) -> Result<String> {
+    /// Disassembles `count` instructions from `addr` in the subordinate,
+    /// also returning the address of the instruction after the last one.
+    pub fn disassemble_at(
+        &self,
+        subordinate: &Subordinate,
+        addr: u64,
+        count: usize,
+    ) -> Result<(String, u64)> {
+        // The page after may not be mapped, so fall back to reading up to
+        // the end of this one.
+        let page_end = (addr | 0xfff) + 1;
+        let wanted = count.saturating_mul(MAX_INSTRUCTION_LEN) as u64;
+        let bytes = match subordinate.read_bytes(addr as usize, wanted as usize) {
+            Ok(bytes) => bytes,
+            Err(_) => {
+                subordinate.read_bytes(addr as usize, (page_end - addr).min(wanted) as usize)?
+            }
+        };
+        self.disassemble_count(addr, &bytes, count, &|_| Vec::new())
+    }
+
+    fn disassemble_count(
+        &self,
+        rip: u64,
+        bytes: &[u8],
+        count: usize,
+        extra_notes: &dyn Fn(&Instruction) -> Vec<String>,
+    ) -> Result<(String, u64)> {
         let mut decoder = Decoder::new(64, &bytes, DecoderOptions::NONE);
         decoder.set_ip(rip);
 
@@ -18,11 +200,15 @@ impl Disassembler {
         let mut buf = String::new();
         let mut instruction = Instruction::default();
 
-        while decoder.can_decode() {
+        let mut next = rip;
+        let mut decoded = 0;
+        while decoder.can_decode() && decoded < count {
             decoder.decode_out(&mut instruction);
-            if decoder.invalid_no_more_bytes() {
+            if decoder.last_error() == DecoderError::NoMoreBytes {
                 break;
             }
+            decoded += 1;
+            next = instruction.next_ip();
             buf.clear();
             formatter.format(&instruction, &mut buf);
 
@@ -37,9 +223,350 @@ impl Disassembler {
                     write!(ret, "  ")?;
                 }
             }
-            writeln!(ret, " {}", buf)?;
+            let mut notes = extra_notes(&instruction);
+            notes.extend(self.immediate_notes(&instruction));
+            if notes.is_empty() {
+                writeln!(ret, " {}", buf)?;
+            } else {
+                writeln!(ret, " {} ; {}", buf, notes.join(", "))?;
+            }
+        }
+
+        Ok((String::from_utf8_lossy(ret.as_slice()).to_string(), next))
+    }
+
+    /// Decodes the instruction at rip and annotates it with the current value
+    /// of each register operand and the address and contents of each memory
+    /// operand, e.g. `mov rax,[rbp-8] ; rax = 0x0, [0x7ffe3c28] = 0x2a`.
+    pub fn preview(&self, subordinate: &Subordinate) -> Result<String> {
+        let regs = subordinate.registers();
+        let instruction = self.decode_at_rip(subordinate)?;
+
+        let mut text = String::new();
+        NasmFormatter::new().format(&instruction, &mut text);
+
+        let mut notes = Vec::new();
+        for operand in 0..instruction.op_count() {
+            let kind = instruction.op_kind(operand);
+            if kind == OpKind::Register {
+                let register = instruction.op_register(operand);
+                if let Some(value) = register_value(regs, register) {
+                    notes.push(format!("{} = 0x{:x}", register_name(register), value));
+                }
+            } else if is_memory(kind) {
+                let addr = match instruction
+                    .virtual_address(operand, 0, |register, _, _| register_value(regs, register))
+                {
+                    Some(addr) => addr,
+                    None => continue,
+                };
+                let size = instruction.memory_size().size();
+                notes.push(match read_sized(subordinate, addr as usize, size) {
+                    Some(value) => format!("[0x{:x}] = 0x{:x}", addr, value),
+                    None if size == 0 => format!("0x{:x}", addr),
+                    None => format!("[0x{:x}] = ??", addr),
+                });
+            }
+        }
+
+        if instruction.mnemonic() == Mnemonic::Syscall {
+            let args = syscalls::arguments(regs);
+            notes.push(syscalls::format_call(subordinate, regs.rax, &args));
         }
 
-        Ok(String::from_utf8_lossy(ret.as_slice()).to_string())
+        if notes.is_empty() {
+            return Ok(text);
+        }
+        Ok(format!("{} ; {}", text, notes.join(", ")))
+    }
+
+    /// If rip is at a conditional branch, says whether it will be taken
+    /// given the current flags.
+    pub fn branch(&self, subordinate: &Subordinate) -> Result<Option<String>> {
+        let regs = subordinate.registers();
+        let instruction = self.decode_at_rip(subordinate)?;
+
+        let taken = if instruction.is_jcc_short_or_near() {
+            condition_holds(regs, instruction.condition_code())
+        } else {
+            match instruction.mnemonic() {
+                Mnemonic::Jrcxz => regs.rcx == 0,
+                Mnemonic::Jecxz => regs.rcx as u32 == 0,
+                Mnemonic::Loop => regs.rcx.wrapping_sub(1) != 0,
+                _ => return Ok(None),
+            }
+        };
+
+        if taken {
+            let target = instruction.near_branch_target();
+            Ok(Some(format!(
+                "will be TAKEN → {}",
+                subordinate.describe(target)
+            )))
+        } else {
+            Ok(Some("not taken".to_owned()))
+        }
+    }
+
+    /// For a rep-prefixed string instruction or the back edge of a short
+    /// loop at rip, returns the address execution falls through to once the
+    /// repetition is done.
+    pub fn fast_forward_target(&self, subordinate: &Subordinate) -> Result<Option<u64>> {
+        let instruction = self.decode_at_rip(subordinate)?;
+
+        if repeats(&instruction) {
+            return Ok(Some(instruction.next_ip()));
+        }
+
+        let is_loop = matches!(
+            instruction.mnemonic(),
+            Mnemonic::Loop | Mnemonic::Loope | Mnemonic::Loopne
+        );
+        if instruction.is_jcc_short_or_near() || is_loop {
+            let target = instruction.near_branch_target();
+            if target <= instruction.ip() && instruction.ip() - target <= MAX_TIGHT_LOOP_LEN {
+                return Ok(Some(instruction.next_ip()));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Whether the instruction at rip is a rep-prefixed string instruction,
+    /// which traps at its own address again after each repetition.
+    pub fn is_repeated_string(&self, subordinate: &Subordinate) -> Result<bool> {
+        Ok(repeats(&self.decode_at_rip(subordinate)?))
+    }
+
+    /// Every address execution can continue at after the instruction at
+    /// rip, for stepping with breakpoints instead of the trap flag. Both
+    /// sides of a conditional branch are included rather than predicting it.
+    pub fn successors(&self, subordinate: &Subordinate) -> Result<Vec<u64>> {
+        let regs = subordinate.registers();
+        let instruction = self.decode_at_rip(subordinate)?;
+        let next = instruction.next_ip();
+
+        Ok(match instruction.flow_control() {
+            FlowControl::ConditionalBranch => vec![next, instruction.near_branch_target()],
+            FlowControl::UnconditionalBranch | FlowControl::Call => {
+                vec![instruction.near_branch_target()]
+            }
+            FlowControl::IndirectBranch | FlowControl::IndirectCall => {
+                let kind = instruction.op0_kind();
+                let target = if kind == OpKind::Register {
+                    register_value(regs, instruction.op0_register())
+                } else if is_memory(kind) {
+                    instruction
+                        .virtual_address(0, 0, |register, _, _| register_value(regs, register))
+                        .and_then(|addr| {
+                            read_sized(subordinate, addr as usize, instruction.memory_size().size())
+                        })
+                } else {
+                    None
+                };
+                vec![target.ok_or("couldn't work out the target of an indirect branch")?]
+            }
+            FlowControl::Return => vec![subordinate.peek(regs.rsp as usize)? as u64],
+            _ => vec![next],
+        })
+    }
+
+    /// The address of the instruction after the one at rip, with a warning
+    /// if skipping it would leave the stack unlike what the code expects.
+    pub fn skip(&self, subordinate: &Subordinate) -> Result<(u64, Option<String>)> {
+        let instruction = self.decode_at_rip(subordinate)?;
+
+        let unbalances = instruction.stack_pointer_increment() != 0
+            || matches!(instruction.mnemonic(), Mnemonic::Enter | Mnemonic::Leave);
+        let warning = if unbalances {
+            let mut text = String::new();
+            NasmFormatter::new().format(&instruction, &mut text);
+            Some(format!(
+                "skipped `{}` which adjusts the stack, rsp is now off from what the code expects",
+                text
+            ))
+        } else {
+            None
+        };
+
+        Ok((instruction.next_ip(), warning))
+    }
+
+    pub fn is_syscall(&self, subordinate: &Subordinate) -> Result<bool> {
+        let rip = subordinate.registers().rip as usize;
+        Ok(subordinate.read_bytes(rip, SYSCALL.len())? == SYSCALL)
+    }
+
+    fn immediate_notes(&self, instruction: &Instruction) -> Vec<String> {
+        let annotations = match &self.annotations {
+            Some(annotations) => annotations,
+            None => return Vec::new(),
+        };
+        let mut notes = Vec::new();
+        for operand in 0..instruction.op_count() {
+            let width = match instruction.op_kind(operand) {
+                OpKind::Immediate8
+                | OpKind::Immediate8to16
+                | OpKind::Immediate8to32
+                | OpKind::Immediate8to64 => 1,
+                OpKind::Immediate16 => 2,
+                OpKind::Immediate32 | OpKind::Immediate32to64 => 4,
+                OpKind::Immediate64 => 8,
+                _ => continue,
+            };
+            notes.extend(annotations.describe(instruction.immediate(operand), width));
+        }
+        notes
+    }
+
+    /// Where the instruction at rip writes memory, as addresses and sizes,
+    /// including the stack writes of pushes and calls.
+    pub fn memory_writes(&self, subordinate: &Subordinate) -> Result<Vec<(u64, usize)>> {
+        let regs = subordinate.registers();
+        let instruction = self.decode_at_rip(subordinate)?;
+        let mut factory = InstructionInfoFactory::new();
+        let info = factory.info(&instruction);
+
+        let mut writes = Vec::new();
+        for used in info.used_memory() {
+            if !matches!(
+                used.access(),
+                OpAccess::Write
+                    | OpAccess::CondWrite
+                    | OpAccess::ReadWrite
+                    | OpAccess::ReadCondWrite
+            ) {
+                continue;
+            }
+            // rip-relative operands come with the address in the
+            // displacement.
+            let value = |register: Register| match register {
+                Register::None | Register::RIP => Some(0),
+                register => register_value(regs, register),
+            };
+            let addr = value(used.segment())
+                .zip(value(used.base()))
+                .zip(value(used.index()))
+                .map(|((segment, base), index)| {
+                    segment
+                        .wrapping_add(base)
+                        .wrapping_add(index.wrapping_mul(used.scale() as u64))
+                        .wrapping_add(used.displacement())
+                })
+                .ok_or("can't tell where the instruction writes")?;
+            writes.push((addr, used.memory_size().size()));
+        }
+        Ok(writes)
+    }
+
+    fn decode_at_rip(&self, subordinate: &Subordinate) -> Result<Instruction> {
+        let rip = subordinate.registers().rip;
+        let bytes = subordinate.read_bytes(rip as usize, MAX_INSTRUCTION_LEN)?;
+        let mut decoder = Decoder::new(64, &bytes, DecoderOptions::NONE);
+        decoder.set_ip(rip);
+        Ok(decoder.decode())
+    }
+}
+
+fn repeats(instruction: &Instruction) -> bool {
+    // Only string instructions repeat, `rep ret` is an ordinary return.
+    let prefixed = instruction.has_rep_prefix()
+        || instruction.has_repe_prefix()
+        || instruction.has_repne_prefix();
+    prefixed && instruction.is_string_instruction()
+}
+
+fn condition_holds(regs: &Registers, condition: ConditionCode) -> bool {
+    let (cf, pf, zf, sf, of) = (
+        regs.flag(CF),
+        regs.flag(PF),
+        regs.flag(ZF),
+        regs.flag(SF),
+        regs.flag(OF),
+    );
+    match condition {
+        ConditionCode::None => true,
+        ConditionCode::o => of,
+        ConditionCode::no => !of,
+        ConditionCode::b => cf,
+        ConditionCode::ae => !cf,
+        ConditionCode::e => zf,
+        ConditionCode::ne => !zf,
+        ConditionCode::be => cf || zf,
+        ConditionCode::a => !cf && !zf,
+        ConditionCode::s => sf,
+        ConditionCode::ns => !sf,
+        ConditionCode::p => pf,
+        ConditionCode::np => !pf,
+        ConditionCode::l => sf != of,
+        ConditionCode::ge => sf == of,
+        ConditionCode::le => zf || sf != of,
+        ConditionCode::g => !zf && sf == of,
+    }
+}
+
+/// Where a direct call or jump goes, None for any other instruction.
+pub(crate) fn branch_target(instruction: &Instruction) -> Option<u64> {
+    let direct = (0..instruction.op_count()).any(|operand| {
+        matches!(
+            instruction.op_kind(operand),
+            OpKind::NearBranch16 | OpKind::NearBranch32 | OpKind::NearBranch64
+        )
+    });
+    direct.then_some(instruction.near_branch_target())
+}
+
+fn is_memory(kind: OpKind) -> bool {
+    matches!(
+        kind,
+        OpKind::Memory
+            | OpKind::MemorySegSI
+            | OpKind::MemorySegESI
+            | OpKind::MemorySegRSI
+            | OpKind::MemorySegDI
+            | OpKind::MemorySegEDI
+            | OpKind::MemorySegRDI
+            | OpKind::MemoryESDI
+            | OpKind::MemoryESEDI
+            | OpKind::MemoryESRDI
+    )
+}
+
+fn read_sized(subordinate: &Subordinate, addr: usize, size: usize) -> Option<u64> {
+    match size {
+        1 | 2 | 4 | 8 => {
+            let bytes = subordinate.read_bytes(addr, size).ok()?;
+            let mut buf = [0u8; 8];
+            buf[..size].copy_from_slice(&bytes);
+            Some(u64::from_le_bytes(buf))
+        }
+        _ => None,
+    }
+}
+
+pub fn register_name(register: Register) -> String {
+    format!("{:?}", register).to_lowercase()
+}
+
+/// Resolves an iced register, including 8/16/32-bit views of the general
+/// purpose registers, against a register snapshot.
+pub fn register_value(regs: &Registers, register: Register) -> Option<u64> {
+    match register {
+        Register::RIP => Some(regs.rip),
+        Register::FS => Some(regs.fs_base),
+        Register::GS => Some(regs.gs_base),
+        Register::ES | Register::CS | Register::SS | Register::DS => Some(0),
+        Register::AH | Register::BH | Register::CH | Register::DH => {
+            let full = regs.get(&register_name(register.full_register()))?;
+            Some((full >> 8) & 0xff)
+        }
+        r if r.is_gpr() => {
+            let full = regs.get(&register_name(r.full_register()))?;
+            match r.size() {
+                8 => Some(full),
+                size => Some(full & ((1 << (size * 8)) - 1)),
+            }
+        }
+        _ => None,
     }
 }
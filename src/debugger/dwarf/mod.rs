@@ -1,6 +1,17 @@
+mod types;
+mod unwind;
+mod variables;
+
+pub use types::{TypeId, Types};
+pub use unwind::{CallFrames, FrameRegisters, Unwind};
+pub use variables::{FrameState, Function, Place};
+
 use crate::result::Result;
 use object::{Object, ObjectSection};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::io::{BufRead, BufReader};
+use std::ops::Bound;
+use std::path::Path;
 use std::{borrow, fs::File, path::PathBuf};
 
 #[derive(Debug, Clone)]
@@ -12,14 +23,23 @@ pub struct LineInfo {
 
 #[derive(Debug, Clone)]
 pub struct DebugInfo {
-    pc_to_line: HashMap<usize, LineInfo>,
+    pc_to_line: BTreeMap<usize, LineInfo>,
+    // Where each run of rows in the line table ends, the code after the
+    // last row before it not being on any line.
+    sequence_ends: BTreeSet<usize>,
     source_code: HashMap<PathBuf, Vec<String>>,
+    call_frames: CallFrames,
+    functions: Vec<Function>,
+    types: Types,
 }
 
 impl DebugInfo {
     pub fn new(file: File) -> Result<Self> {
-        let mut pc_to_line: HashMap<usize, LineInfo> = HashMap::new();
+        let mut pc_to_line: BTreeMap<usize, LineInfo> = BTreeMap::new();
+        let mut sequence_ends = BTreeSet::new();
         let mut source_code: HashMap<PathBuf, Vec<String>> = HashMap::new();
+        let mut functions = Vec::new();
+        let mut types = Types::default();
 
         let mmap = unsafe { memmap::Mmap::map(&file).unwrap() };
         let object = object::File::parse(&*mmap).unwrap();
@@ -29,6 +49,8 @@ impl DebugInfo {
             gimli::RunTimeEndian::Big
         };
 
+        let call_frames = CallFrames::load(&object, endian);
+
         // Load a section and return as `Cow<[u8]>`.
         let load_section = |id: gimli::SectionId| -> Result<borrow::Cow<[u8]>> {
             match object.section_by_name(id.name()) {
@@ -58,8 +80,25 @@ impl DebugInfo {
         // Iterate over the compilation units.
         let mut iter = dwarf.units();
         while let Some(header) = iter.next()? {
+            let unit_base = header.offset().0;
             let unit = dwarf.unit(header)?;
 
+            let mut file = PathBuf::new();
+            if let Some(ref dir) = unit.comp_dir {
+                file.push(dir.to_string_lossy().as_ref());
+            }
+            if let Some(ref name) = unit.name {
+                file.push(name.to_string_lossy().as_ref());
+            }
+            variables::UnitLoader {
+                dwarf: &dwarf,
+                unit: &unit,
+                unit_base,
+                endian,
+                file,
+            }
+            .load(&mut functions, &mut types)?;
+
             // Get the line program for the compilation unit.
             if let Some(program) = unit.line_program.clone() {
                 let comp_dir = if let Some(ref dir) = unit.comp_dir {
@@ -72,6 +111,7 @@ impl DebugInfo {
                 let mut rows = program.rows();
                 while let Some((header, row)) = rows.next_row()? {
                     if row.end_sequence() {
+                        sequence_ends.insert(row.address() as usize);
                         continue;
                     }
 
@@ -117,7 +157,11 @@ impl DebugInfo {
 
         Ok(DebugInfo {
             pc_to_line,
+            sequence_ends,
             source_code,
+            call_frames,
+            functions,
+            types,
         })
     }
 
@@ -125,7 +169,69 @@ impl DebugInfo {
         self.pc_to_line.get(&rip)
     }
 
-    pub fn lines(&self, path: &PathBuf) -> Option<&Vec<String>> {
+    /// The line containing `addr`, i.e. the closest row at or before it,
+    /// unless the rows it's in end before `addr` does.
+    pub fn line_for_addr(&self, addr: usize) -> Option<&LineInfo> {
+        let (start, line_info) = self.pc_to_line.range(..=addr).next_back()?;
+        let after = (Bound::Excluded(*start), Bound::Included(addr));
+        if self.sequence_ends.range(after).next().is_some() {
+            return None;
+        }
+        Some(line_info)
+    }
+
+    /// Where the code for `line` of the file `path` starts, or for the next
+    /// line after it that has code, like a blank line or a comment would
+    /// need. `path` can be the end of one, like just the file's name.
+    pub fn addr_for_line(&self, path: &Path, line: u64) -> Option<usize> {
+        self.pc_to_line
+            .iter()
+            .filter(|(_, info)| info.line >= line && info.path.ends_with(path))
+            .min_by_key(|(addr, info)| (info.line, **addr))
+            .map(|(addr, _)| *addr)
+    }
+
+    /// Every address where code for `line` of `path` starts, lowest first.
+    pub fn addresses_of(&self, path: &Path, line: u64) -> Vec<usize> {
+        self.pc_to_line
+            .iter()
+            .filter(|(_, info)| info.line == line && info.path == path)
+            .map(|(addr, _)| *addr)
+            .collect()
+    }
+
+    /// The lines of the source file at `path`, read from disk the first time
+    /// they're wanted.
+    pub fn source(&mut self, path: &Path) -> Option<&Vec<String>> {
+        if !self.source_code.contains_key(path) {
+            self.reload_source(path).ok()?;
+        }
         self.source_code.get(path)
     }
+
+    /// Re-reads a source file, e.g. after it was edited.
+    pub fn reload_source(&mut self, path: &Path) -> Result<()> {
+        let lines = BufReader::new(File::open(path)?)
+            .lines()
+            .collect::<std::io::Result<Vec<String>>>()?;
+        self.source_code.insert(path.to_path_buf(), lines);
+        Ok(())
+    }
+
+    /// The function whose code contains `pc`, a file address.
+    pub fn function_at(&self, pc: u64) -> Option<&Function> {
+        self.functions.iter().find(|function| function.contains(pc))
+    }
+
+    pub fn functions(&self) -> &[Function] {
+        &self.functions
+    }
+
+    pub fn types(&self) -> &Types {
+        &self.types
+    }
+
+    pub fn call_frames(&self) -> &CallFrames {
+        &self.call_frames
+    }
 }
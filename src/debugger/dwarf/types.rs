@@ -0,0 +1,563 @@
+use crate::result::Result;
+use gimli::{AttributeValue, DwAte, EndianSlice, EntriesTreeNode, RunTimeEndian};
+use std::collections::HashMap;
+
+type Slice<'a> = EndianSlice<'a, RunTimeEndian>;
+
+// Arrays longer than this are printed with a trailing `...`.
+const MAX_ELEMENTS: usize = 16;
+
+/// A type's offset in `.debug_info`, which is how DIEs refer to each other.
+pub type TypeId = usize;
+
+/// Formats a value its own way, or returns None to leave it to its type.
+pub type CustomFormat<'a> = dyn Fn(Option<TypeId>, &[u8]) -> Option<String> + 'a;
+
+#[derive(Debug, Clone)]
+pub enum Type {
+    Base {
+        name: String,
+        size: u64,
+        encoding: DwAte,
+    },
+    Pointer {
+        target: Option<TypeId>,
+        // References, which Rust's are, can't be null.
+        nonnull: bool,
+    },
+    Struct {
+        name: String,
+        size: u64,
+        members: Vec<Member>,
+    },
+    Array {
+        element: Option<TypeId>,
+        count: Option<u64>,
+    },
+    Enum {
+        name: String,
+        size: u64,
+        values: Vec<(String, i64)>,
+    },
+    Typedef {
+        name: String,
+        target: Option<TypeId>,
+    },
+    // const and volatile, which don't change how a value reads.
+    Qualified {
+        qualifier: &'static str,
+        target: Option<TypeId>,
+    },
+    // Unions, function types and whatever else we can only size.
+    Opaque {
+        name: String,
+        size: u64,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct Member {
+    pub name: String,
+    pub offset: u64,
+    pub ty: Option<TypeId>,
+}
+
+/// A run of bytes in a type's layout, a field or the padding between.
+#[derive(Debug, Clone)]
+pub struct Span {
+    pub offset: u64,
+    pub size: u64,
+    // The field as a path from the outer type, like `where.y`, None for
+    // padding.
+    pub field: Option<String>,
+    pub ty: Option<TypeId>,
+}
+
+/// Every type in the program's DWARF, owned so it outlives the mapping.
+#[derive(Debug, Clone, Default)]
+pub struct Types {
+    types: HashMap<TypeId, Type>,
+}
+
+impl Types {
+    pub fn get(&self, id: TypeId) -> Option<&Type> {
+        self.types.get(&id)
+    }
+
+    /// Adds the type a DIE describes, if it describes one.
+    pub(super) fn load(
+        &mut self,
+        dwarf: &gimli::Dwarf<Slice>,
+        unit: &gimli::Unit<Slice>,
+        unit_base: usize,
+        node: EntriesTreeNode<Slice>,
+    ) -> Result<()> {
+        let entry = node.entry();
+        let id = unit_base + entry.offset().0;
+        let name = name_of(dwarf, unit, entry)?;
+        let size = entry
+            .attr(gimli::DW_AT_byte_size)?
+            .and_then(|attr| attr.udata_value())
+            .unwrap_or(0);
+        let target = type_of(entry, unit_base)?;
+
+        let ty = match entry.tag() {
+            gimli::DW_TAG_base_type => match entry.attr_value(gimli::DW_AT_encoding)? {
+                Some(AttributeValue::Encoding(encoding)) => Type::Base {
+                    name: name.unwrap_or_default(),
+                    size,
+                    encoding,
+                },
+                _ => Type::Opaque {
+                    name: name.unwrap_or_default(),
+                    size,
+                },
+            },
+            gimli::DW_TAG_pointer_type | gimli::DW_TAG_reference_type => Type::Pointer {
+                target,
+                nonnull: entry.tag() == gimli::DW_TAG_reference_type
+                    || name.as_deref().is_some_and(|name| name.starts_with('&')),
+            },
+            gimli::DW_TAG_typedef => Type::Typedef {
+                name: name.unwrap_or_default(),
+                target,
+            },
+            gimli::DW_TAG_const_type => Type::Qualified {
+                qualifier: "const",
+                target,
+            },
+            gimli::DW_TAG_volatile_type => Type::Qualified {
+                qualifier: "volatile",
+                target,
+            },
+            gimli::DW_TAG_structure_type | gimli::DW_TAG_class_type => {
+                let mut members = Vec::new();
+                let mut children = node.children();
+                while let Some(child) = children.next()? {
+                    let entry = child.entry();
+                    if entry.tag() != gimli::DW_TAG_member {
+                        continue;
+                    }
+                    members.push(Member {
+                        name: name_of(dwarf, unit, entry)?.unwrap_or_default(),
+                        offset: entry
+                            .attr(gimli::DW_AT_data_member_location)?
+                            .and_then(|attr| attr.udata_value())
+                            .unwrap_or(0),
+                        ty: type_of(entry, unit_base)?,
+                    });
+                }
+                Type::Struct {
+                    name: format!("struct {}", name.unwrap_or_default()),
+                    size,
+                    members,
+                }
+            }
+            gimli::DW_TAG_array_type => {
+                let mut count = None;
+                let mut children = node.children();
+                while let Some(child) = children.next()? {
+                    let entry = child.entry();
+                    if entry.tag() != gimli::DW_TAG_subrange_type {
+                        continue;
+                    }
+                    count = match entry.attr(gimli::DW_AT_count)? {
+                        Some(attr) => attr.udata_value(),
+                        None => entry
+                            .attr(gimli::DW_AT_upper_bound)?
+                            .and_then(|attr| attr.udata_value())
+                            .map(|bound| bound + 1),
+                    };
+                    break;
+                }
+                Type::Array {
+                    element: target,
+                    count,
+                }
+            }
+            gimli::DW_TAG_enumeration_type => {
+                let mut values = Vec::new();
+                let mut children = node.children();
+                while let Some(child) = children.next()? {
+                    let entry = child.entry();
+                    if entry.tag() != gimli::DW_TAG_enumerator {
+                        continue;
+                    }
+                    let value = entry
+                        .attr(gimli::DW_AT_const_value)?
+                        .and_then(|attr| attr.sdata_value())
+                        .unwrap_or(0);
+                    values.push((name_of(dwarf, unit, entry)?.unwrap_or_default(), value));
+                }
+                Type::Enum {
+                    name: format!("enum {}", name.unwrap_or_default()),
+                    size,
+                    values,
+                }
+            }
+            gimli::DW_TAG_union_type => Type::Opaque {
+                name: format!("union {}", name.unwrap_or_default()),
+                size,
+            },
+            gimli::DW_TAG_subroutine_type => Type::Opaque {
+                name: "function".to_owned(),
+                size: 0,
+            },
+            _ => return Ok(()),
+        };
+        self.types.insert(id, ty);
+        Ok(())
+    }
+
+    /// The size of a floating point type, None for anything else.
+    pub fn float_size(&self, id: Option<TypeId>) -> Option<u64> {
+        match self.get(id?)? {
+            Type::Base { encoding, size, .. } if *encoding == gimli::DW_ATE_float => Some(*size),
+            Type::Typedef { target, .. } | Type::Qualified { target, .. } => {
+                self.float_size(*target)
+            }
+            _ => None,
+        }
+    }
+
+    /// A struct's member, looking through typedefs and qualifiers.
+    pub fn member(&self, id: Option<TypeId>, name: &str) -> Option<&Member> {
+        match self.get(id?)? {
+            Type::Struct { members, .. } => members.iter().find(|member| member.name == name),
+            Type::Typedef { target, .. } | Type::Qualified { target, .. } => {
+                self.member(*target, name)
+            }
+            _ => None,
+        }
+    }
+
+    /// The type as C would spell it, e.g. `char *` or `struct point`.
+    pub fn name(&self, id: Option<TypeId>) -> String {
+        let ty = match id.and_then(|id| self.get(id)) {
+            Some(ty) => ty,
+            None => return "void".to_owned(),
+        };
+        match ty {
+            Type::Base { name, .. }
+            | Type::Struct { name, .. }
+            | Type::Enum { name, .. }
+            | Type::Typedef { name, .. }
+            | Type::Opaque { name, .. } => name.clone(),
+            Type::Pointer { target, .. } => format!("{} *", self.name(*target)),
+            Type::Array { element, count } => match count {
+                Some(count) => format!("{} [{}]", self.name(*element), count),
+                None => format!("{} []", self.name(*element)),
+            },
+            Type::Qualified { qualifier, target } => {
+                format!("{} {}", qualifier, self.name(*target))
+            }
+        }
+    }
+
+    pub fn size(&self, id: Option<TypeId>) -> Option<u64> {
+        match self.get(id?)? {
+            Type::Base { size, .. }
+            | Type::Struct { size, .. }
+            | Type::Enum { size, .. }
+            | Type::Opaque { size, .. } => Some(*size),
+            Type::Pointer { .. } => Some(8),
+            Type::Array { element, count } => Some(self.size(*element)? * (*count)?),
+            Type::Typedef { target, .. } | Type::Qualified { target, .. } => self.size(*target),
+        }
+    }
+
+    /// Finds a type by its name as C spells it, e.g. `long int` or
+    /// `struct point`, where a struct, enum or union can go without its
+    /// keyword. Definitions win over declarations, which have no size.
+    pub fn find(&self, name: &str) -> Option<TypeId> {
+        let squashed = |name: &str| name.split_whitespace().collect::<String>();
+        let wanted = squashed(name);
+        let mut found: Vec<TypeId> = self
+            .types
+            .keys()
+            .copied()
+            .filter(|id| {
+                let name = squashed(&self.name(Some(*id)));
+                name == wanted
+                    || ["struct", "enum", "union"]
+                        .iter()
+                        .any(|keyword| name == format!("{}{}", keyword, wanted))
+            })
+            .collect();
+        found.sort_by_key(|id| (self.size(Some(*id)).unwrap_or(0) == 0, *id));
+        found.first().copied()
+    }
+
+    /// The type written out the way `ptype` shows it, with the members of
+    /// structs and the values of enums, and through typedefs.
+    pub fn definition(&self, id: TypeId) -> String {
+        match self.get(id) {
+            Some(Type::Struct { name, members, .. }) => {
+                let mut text = format!("{} {{\n", name);
+                for member in members {
+                    text += &format!("    {};\n", self.declaration(member.ty, &member.name));
+                }
+                text + "}"
+            }
+            Some(Type::Enum { name, values, .. }) => {
+                let names: Vec<&str> = values.iter().map(|(name, _)| name.as_str()).collect();
+                format!("{} {{{}}}", name, names.join(", "))
+            }
+            Some(Type::Typedef {
+                target: Some(target),
+                ..
+            }) => self.definition(*target),
+            _ => self.name(Some(id)),
+        }
+    }
+
+    /// The fields and padding of a type, in offset order. Structs inside
+    /// it are broken down too, so their padding shows where it is in the
+    /// outer one.
+    pub fn layout(&self, id: TypeId) -> Vec<Span> {
+        let mut spans = Vec::new();
+        self.add_spans(Some(id), 0, None, &mut spans);
+        spans
+    }
+
+    fn add_spans(
+        &self,
+        id: Option<TypeId>,
+        offset: u64,
+        path: Option<&str>,
+        spans: &mut Vec<Span>,
+    ) {
+        let (size, mut members) = match self.resolved(id) {
+            Some(Type::Struct { size, members, .. }) if !members.is_empty() => {
+                (*size, members.clone())
+            }
+            _ => {
+                // A type that isn't a struct is one nameless field.
+                spans.push(Span {
+                    offset,
+                    size: self.size(id).unwrap_or(0),
+                    field: Some(path.unwrap_or_default().to_owned()),
+                    ty: id,
+                });
+                return;
+            }
+        };
+        members.sort_by_key(|member| member.offset);
+        let mut end = 0;
+        for member in &members {
+            if member.offset > end {
+                spans.push(padding(offset + end, member.offset - end));
+            }
+            let path = match path {
+                Some(path) => format!("{}.{}", path, member.name),
+                None => member.name.clone(),
+            };
+            self.add_spans(member.ty, offset + member.offset, Some(&path), spans);
+            end = end.max(member.offset + self.size(member.ty).unwrap_or(0));
+        }
+        if size > end {
+            spans.push(padding(offset + end, size - end));
+        }
+    }
+
+    /// The values a type's bytes never hold, which Rust keeps enum
+    /// discriminants in when it can. None when every bit pattern is valid.
+    pub fn niche(&self, id: Option<TypeId>) -> Option<String> {
+        match self.resolved(id)? {
+            Type::Base { encoding, .. } if *encoding == gimli::DW_ATE_boolean => {
+                Some("only 0 and 1".to_owned())
+            }
+            Type::Base { encoding, .. } if *encoding == gimli::DW_ATE_UTF => {
+                Some("only up to 0x10ffff".to_owned())
+            }
+            Type::Pointer { nonnull: true, .. } => Some("never 0".to_owned()),
+            Type::Enum { values, .. } if !values.is_empty() => {
+                let mut values: Vec<i64> = values.iter().map(|(_, value)| *value).collect();
+                values.sort_unstable();
+                values.dedup();
+                let (first, last) = (values[0], values[values.len() - 1]);
+                if last.checked_sub(first) == Some(values.len() as i64 - 1) {
+                    Some(format!("only {} to {}", first, last))
+                } else {
+                    Some(format!("only its {} values", values.len()))
+                }
+            }
+            _ => None,
+        }
+    }
+
+    // The type under any typedefs and qualifiers.
+    fn resolved(&self, id: Option<TypeId>) -> Option<&Type> {
+        match self.get(id?)? {
+            Type::Typedef { target, .. } | Type::Qualified { target, .. } => self.resolved(*target),
+            ty => Some(ty),
+        }
+    }
+
+    /// A member declared as C would, e.g. `char *name` or `int primes[4]`.
+    pub fn declaration(&self, id: Option<TypeId>, name: &str) -> String {
+        if let Some(Type::Array { element, count }) = id.and_then(|id| self.get(id)) {
+            let count = count.map(|count| count.to_string()).unwrap_or_default();
+            return format!("{} {}[{}]", self.name(*element), name, count);
+        }
+        let ty = self.name(id);
+        if ty.ends_with('*') {
+            format!("{}{}", ty, name)
+        } else {
+            format!("{} {}", ty, name)
+        }
+    }
+
+    /// The value as a 64-bit word for arithmetic, sign-extending signed
+    /// types. None for floats and aggregates.
+    pub fn integer(&self, id: Option<TypeId>, bytes: &[u8]) -> Option<u64> {
+        match self.get(id?)? {
+            Type::Base { encoding, .. } => match *encoding {
+                gimli::DW_ATE_signed | gimli::DW_ATE_signed_char => Some(signed(bytes) as u64),
+                gimli::DW_ATE_unsigned | gimli::DW_ATE_unsigned_char | gimli::DW_ATE_boolean => {
+                    Some(unsigned(bytes))
+                }
+                _ => None,
+            },
+            Type::Pointer { .. } => Some(unsigned(bytes)),
+            Type::Enum { .. } => Some(signed(bytes) as u64),
+            Type::Typedef { target, .. } | Type::Qualified { target, .. } => {
+                self.integer(*target, bytes)
+            }
+            _ => None,
+        }
+    }
+
+    /// Decodes `bytes`, which should be `size(id)` long, as a value of the
+    /// type. `custom` gets first go at the value and at each member and
+    /// element in it.
+    pub fn format_with(&self, id: Option<TypeId>, bytes: &[u8], custom: &CustomFormat) -> String {
+        if let Some(text) = custom(id, bytes) {
+            return text;
+        }
+        let ty = match id.and_then(|id| self.get(id)) {
+            Some(ty) => ty,
+            None => return hex_bytes(bytes),
+        };
+        match ty {
+            Type::Base { encoding, .. } => format_base(*encoding, bytes),
+            Type::Pointer { .. } => format!("0x{:x}", unsigned(bytes)),
+            Type::Struct { members, .. } => {
+                let fields: Vec<String> = members
+                    .iter()
+                    .map(|member| {
+                        let start = member.offset as usize;
+                        let size = self.size(member.ty).unwrap_or(0) as usize;
+                        let value = match bytes.get(start..start + size) {
+                            Some(bytes) => self.format_with(member.ty, bytes, custom),
+                            None => "??".to_owned(),
+                        };
+                        format!("{} = {}", member.name, value)
+                    })
+                    .collect();
+                format!("{{{}}}", fields.join(", "))
+            }
+            Type::Array { element, .. } => {
+                let size = self.size(*element).unwrap_or(0) as usize;
+                if size == 0 {
+                    return hex_bytes(bytes);
+                }
+                let mut elements: Vec<String> = bytes
+                    .chunks(size)
+                    .take(MAX_ELEMENTS)
+                    .map(|chunk| self.format_with(*element, chunk, custom))
+                    .collect();
+                if bytes.len() / size > MAX_ELEMENTS {
+                    elements.push("...".to_owned());
+                }
+                format!("{{{}}}", elements.join(", "))
+            }
+            Type::Enum { values, .. } => {
+                let value = signed(bytes);
+                match values.iter().find(|(_, v)| *v == value) {
+                    Some((name, _)) => name.clone(),
+                    None => value.to_string(),
+                }
+            }
+            Type::Typedef { target, .. } | Type::Qualified { target, .. } => {
+                self.format_with(*target, bytes, custom)
+            }
+            Type::Opaque { .. } => hex_bytes(bytes),
+        }
+    }
+}
+
+fn format_base(encoding: DwAte, bytes: &[u8]) -> String {
+    match encoding {
+        gimli::DW_ATE_boolean => (unsigned(bytes) != 0).to_string(),
+        gimli::DW_ATE_float if bytes.len() == 4 => {
+            f32::from_bits(unsigned(bytes) as u32).to_string()
+        }
+        gimli::DW_ATE_float if bytes.len() == 8 => f64::from_bits(unsigned(bytes)).to_string(),
+        gimli::DW_ATE_signed_char | gimli::DW_ATE_unsigned_char if bytes.len() == 1 => {
+            let c = bytes[0];
+            if c.is_ascii_graphic() || c == b' ' {
+                format!("{} '{}'", c as i8, c as char)
+            } else {
+                format!("{} '\\x{:02x}'", c as i8, c)
+            }
+        }
+        gimli::DW_ATE_signed | gimli::DW_ATE_signed_char => signed(bytes).to_string(),
+        gimli::DW_ATE_unsigned | gimli::DW_ATE_unsigned_char => unsigned(bytes).to_string(),
+        _ => hex_bytes(bytes),
+    }
+}
+
+fn padding(offset: u64, size: u64) -> Span {
+    Span {
+        offset,
+        size,
+        field: None,
+        ty: None,
+    }
+}
+
+fn unsigned(bytes: &[u8]) -> u64 {
+    let mut buf = [0u8; 8];
+    let len = bytes.len().min(8);
+    buf[..len].copy_from_slice(&bytes[..len]);
+    u64::from_le_bytes(buf)
+}
+
+// Sign-extends from however many bytes there are.
+fn signed(bytes: &[u8]) -> i64 {
+    let bits = (bytes.len().clamp(1, 8) * 8) as u32;
+    let shift = 64 - bits;
+    ((unsigned(bytes) << shift) as i64) >> shift
+}
+
+fn hex_bytes(bytes: &[u8]) -> String {
+    let hex: Vec<String> = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("[{}]", hex.join(" "))
+}
+
+pub(super) fn name_of(
+    dwarf: &gimli::Dwarf<Slice>,
+    unit: &gimli::Unit<Slice>,
+    entry: &gimli::DebuggingInformationEntry<Slice>,
+) -> Result<Option<String>> {
+    match entry.attr_value(gimli::DW_AT_name)? {
+        Some(value) => Ok(Some(
+            dwarf
+                .attr_string(unit, value)?
+                .to_string_lossy()
+                .into_owned(),
+        )),
+        None => Ok(None),
+    }
+}
+
+pub(super) fn type_of(
+    entry: &gimli::DebuggingInformationEntry<Slice>,
+    unit_base: usize,
+) -> Result<Option<TypeId>> {
+    match entry.attr_value(gimli::DW_AT_type)? {
+        Some(AttributeValue::UnitRef(offset)) => Ok(Some(unit_base + offset.0)),
+        _ => Ok(None),
+    }
+}
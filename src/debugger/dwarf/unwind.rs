@@ -0,0 +1,139 @@
+use gimli::{
+    BaseAddresses, CfaRule, DebugFrame, EhFrame, EndianSlice, RegisterRule, RunTimeEndian,
+    UninitializedUnwindContext, UnwindSection, UnwindTableRow, X86_64,
+};
+use object::{Object, ObjectSection};
+
+type Slice<'a> = EndianSlice<'a, RunTimeEndian>;
+
+/// The registers needed to find a frame's caller.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrameRegisters {
+    pub pc: u64,
+    pub sp: u64,
+    pub fp: u64,
+}
+
+pub enum Unwind {
+    Caller(FrameRegisters),
+    // CFI marks the return address undefined, as it does in `_start`.
+    Outermost,
+    // No CFI covers the address, or it uses rules we don't evaluate.
+    NoInfo,
+}
+
+/// Call frame information from `.eh_frame` and `.debug_frame`, kept as
+/// owned copies since the file mapping doesn't outlive `DebugInfo::new`.
+#[derive(Debug, Clone)]
+pub struct CallFrames {
+    endian: RunTimeEndian,
+    eh_frame: Vec<u8>,
+    eh_frame_addr: u64,
+    text_addr: u64,
+    debug_frame: Vec<u8>,
+}
+
+impl CallFrames {
+    pub fn load(object: &object::File, endian: RunTimeEndian) -> Self {
+        let section = |name: &str| {
+            object
+                .section_by_name(name)
+                .map(|section| {
+                    let data = section.uncompressed_data().unwrap_or_default();
+                    (data.into_owned(), section.address())
+                })
+                .unwrap_or_default()
+        };
+        let (eh_frame, eh_frame_addr) = section(".eh_frame");
+        let (debug_frame, _) = section(".debug_frame");
+        let (_, text_addr) = section(".text");
+
+        Self {
+            endian,
+            eh_frame,
+            eh_frame_addr,
+            text_addr,
+            debug_frame,
+        }
+    }
+
+    /// Works out the caller of `frame`. `address` is the file address to
+    /// look up, which for all but the innermost frame should point into the
+    /// call instruction rather than at the return address after it.
+    pub fn unwind(
+        &self,
+        frame: &FrameRegisters,
+        address: u64,
+        read: &dyn Fn(u64) -> Option<u64>,
+    ) -> Unwind {
+        let row = match self.row(address) {
+            Some(row) => row,
+            None => return Unwind::NoInfo,
+        };
+        let cfa = match cfa_of(&row, frame) {
+            Some(cfa) => cfa,
+            None => return Unwind::NoInfo,
+        };
+
+        let pc = match row.register(X86_64::RA) {
+            RegisterRule::Offset(offset) => read(cfa.wrapping_add(offset as u64)),
+            RegisterRule::Undefined => return Unwind::Outermost,
+            _ => return Unwind::NoInfo,
+        };
+        let fp = match row.register(X86_64::RBP) {
+            RegisterRule::Offset(offset) => read(cfa.wrapping_add(offset as u64)),
+            _ => Some(frame.fp),
+        };
+
+        match (pc, fp) {
+            (Some(pc), Some(fp)) => Unwind::Caller(FrameRegisters { pc, sp: cfa, fp }),
+            _ => Unwind::Outermost,
+        }
+    }
+
+    /// The canonical frame address of `frame`, the value of rsp just before
+    /// the call into it, which `DW_OP_call_frame_cfa` refers to.
+    pub fn cfa(&self, frame: &FrameRegisters, address: u64) -> Option<u64> {
+        cfa_of(&self.row(address)?, frame)
+    }
+
+    fn row(&self, address: u64) -> Option<UnwindTableRow<Slice<'_>>> {
+        let eh_frame = EhFrame::new(&self.eh_frame, self.endian);
+        let bases = BaseAddresses::default()
+            .set_eh_frame(self.eh_frame_addr)
+            .set_text(self.text_addr);
+        if let Some(row) = row_for(&eh_frame, &bases, address) {
+            return Some(row);
+        }
+
+        let debug_frame = DebugFrame::new(&self.debug_frame, self.endian);
+        row_for(&debug_frame, &BaseAddresses::default(), address)
+    }
+}
+
+fn cfa_of(row: &UnwindTableRow<Slice<'_>>, frame: &FrameRegisters) -> Option<u64> {
+    match row.cfa() {
+        CfaRule::RegisterAndOffset { register, offset } => {
+            let base = match *register {
+                X86_64::RSP => frame.sp,
+                X86_64::RBP => frame.fp,
+                _ => return None,
+            };
+            Some(base.wrapping_add(*offset as u64))
+        }
+        CfaRule::Expression(_) => None,
+    }
+}
+
+fn row_for<'a, S: UnwindSection<Slice<'a>>>(
+    section: &S,
+    bases: &BaseAddresses,
+    address: u64,
+) -> Option<UnwindTableRow<Slice<'a>>> {
+    let mut ctx = UninitializedUnwindContext::new();
+    section
+        .unwind_info_for_address(bases, &mut ctx, address, |section, bases, offset| {
+            section.cie_from_offset(bases, offset)
+        })
+        .ok()
+}
@@ -0,0 +1,235 @@
+use super::types::{name_of, type_of, TypeId, Types};
+use crate::result::Result;
+use gimli::{
+    AttributeValue, Encoding, EndianSlice, EntriesTreeNode, EvaluationResult, Expression, Location,
+    RunTimeEndian, Value,
+};
+
+use std::path::PathBuf;
+
+type Slice<'a> = EndianSlice<'a, RunTimeEndian>;
+
+/// A function with code, and the variables declared in it.
+#[derive(Debug, Clone)]
+pub struct Function {
+    pub name: String,
+    // The compilation unit's source file.
+    pub file: PathBuf,
+    pub low_pc: u64,
+    pub high_pc: u64,
+    encoding: Encoding,
+    endian: RunTimeEndian,
+    frame_base: Option<Vec<u8>>,
+    pub variables: Vec<Variable>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Variable {
+    pub name: String,
+    pub ty: Option<TypeId>,
+    pub parameter: bool,
+    // A location expression, None when it was optimized out or is described
+    // by a location list, which we don't read.
+    location: Option<Vec<u8>>,
+    // The lexical block it's declared in, when that's narrower than the
+    // function.
+    scope: Option<(u64, u64)>,
+}
+
+/// Where a variable lives, once its location has been evaluated.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Place {
+    Address(u64),
+    Register(u16),
+    Value(u64),
+}
+
+/// What evaluating a location needs from the frame it's evaluated in.
+/// Registers are DWARF numbered.
+pub struct FrameState<'a> {
+    pub register: &'a dyn Fn(u16) -> Option<u64>,
+    pub read: &'a dyn Fn(u64) -> Option<u64>,
+    pub cfa: Option<u64>,
+    // Where a position independent binary was loaded, added to addresses
+    // from DW_OP_addr.
+    pub bias: u64,
+}
+
+impl Function {
+    pub fn contains(&self, pc: u64) -> bool {
+        self.low_pc <= pc && pc < self.high_pc
+    }
+
+    /// The variables whose scope covers `pc`, parameters first.
+    pub fn variables_at(&self, pc: u64) -> impl Iterator<Item = &Variable> {
+        self.variables
+            .iter()
+            .filter(move |variable| match variable.scope {
+                Some((low, high)) => low <= pc && pc < high,
+                None => true,
+            })
+    }
+
+    pub fn locate(&self, variable: &Variable, frame: &FrameState) -> Option<Place> {
+        let frame_base = match &self.frame_base {
+            Some(expr) => match self.evaluate(expr, frame, None)? {
+                Place::Address(addr) | Place::Value(addr) => Some(addr),
+                Place::Register(register) => (frame.register)(register),
+            },
+            None => None,
+        };
+        self.evaluate(variable.location.as_ref()?, frame, frame_base)
+    }
+
+    fn evaluate(&self, expr: &[u8], frame: &FrameState, frame_base: Option<u64>) -> Option<Place> {
+        let expr = Expression(EndianSlice::new(expr, self.endian));
+        let mut evaluation = expr.evaluation(self.encoding);
+        let mut result = evaluation.evaluate().ok()?;
+        loop {
+            result = match result {
+                EvaluationResult::Complete => break,
+                EvaluationResult::RequiresRegister { register, .. } => {
+                    let value = (frame.register)(register.0)?;
+                    evaluation
+                        .resume_with_register(Value::Generic(value))
+                        .ok()?
+                }
+                EvaluationResult::RequiresMemory { address, size, .. } => {
+                    let mut value = (frame.read)(address)?;
+                    if size < 8 {
+                        value &= (1 << (size * 8)) - 1;
+                    }
+                    evaluation.resume_with_memory(Value::Generic(value)).ok()?
+                }
+                EvaluationResult::RequiresFrameBase => {
+                    evaluation.resume_with_frame_base(frame_base?).ok()?
+                }
+                EvaluationResult::RequiresCallFrameCfa => {
+                    evaluation.resume_with_call_frame_cfa(frame.cfa?).ok()?
+                }
+                EvaluationResult::RequiresRelocatedAddress(address) => evaluation
+                    .resume_with_relocated_address(address.wrapping_add(frame.bias))
+                    .ok()?,
+                _ => return None,
+            };
+        }
+
+        // Variables split into pieces across registers aren't supported.
+        let pieces = evaluation.result();
+        match pieces.as_slice() {
+            [piece] => match piece.location {
+                Location::Address { address } => Some(Place::Address(address)),
+                Location::Register { register } => Some(Place::Register(register.0)),
+                Location::Value { value } => Some(Place::Value(value.to_u64(!0).ok()?)),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+/// Reads the functions, variables and types of one compilation unit.
+pub(super) struct UnitLoader<'a, 'b> {
+    pub dwarf: &'a gimli::Dwarf<Slice<'b>>,
+    pub unit: &'a gimli::Unit<Slice<'b>>,
+    pub unit_base: usize,
+    pub endian: RunTimeEndian,
+    pub file: PathBuf,
+}
+
+impl<'a, 'b> UnitLoader<'a, 'b> {
+    pub fn load(&self, functions: &mut Vec<Function>, types: &mut Types) -> Result<()> {
+        let mut tree = self.unit.entries_tree(None)?;
+        let root = tree.root()?;
+        self.walk(root, None, None, functions, types)
+    }
+
+    fn walk(
+        &self,
+        node: EntriesTreeNode<Slice<'b>>,
+        mut function: Option<&mut Function>,
+        scope: Option<(u64, u64)>,
+        functions: &mut Vec<Function>,
+        types: &mut Types,
+    ) -> Result<()> {
+        let entry = node.entry();
+        match entry.tag() {
+            gimli::DW_TAG_subprogram => {
+                let (low_pc, high_pc) = match self.pc_range(entry)? {
+                    Some(range) => range,
+                    // A declaration, or inlined everywhere.
+                    None => return Ok(()),
+                };
+                let mut inner = Function {
+                    name: name_of(self.dwarf, self.unit, entry)?.unwrap_or_default(),
+                    file: self.file.clone(),
+                    low_pc,
+                    high_pc,
+                    encoding: self.unit.encoding(),
+                    endian: self.endian,
+                    frame_base: expression(entry.attr_value(gimli::DW_AT_frame_base)?),
+                    variables: Vec::new(),
+                };
+                let mut children = node.children();
+                while let Some(child) = children.next()? {
+                    self.walk(child, Some(&mut inner), None, functions, types)?;
+                }
+                functions.push(inner);
+            }
+            gimli::DW_TAG_lexical_block => {
+                let scope = self.pc_range(entry)?.or(scope);
+                let mut children = node.children();
+                while let Some(child) = children.next()? {
+                    self.walk(child, function.as_deref_mut(), scope, functions, types)?;
+                }
+            }
+            gimli::DW_TAG_variable | gimli::DW_TAG_formal_parameter => {
+                // Globals are found through the symbol table instead.
+                if let Some(function) = function {
+                    function.variables.push(Variable {
+                        name: name_of(self.dwarf, self.unit, entry)?.unwrap_or_default(),
+                        ty: type_of(entry, self.unit_base)?,
+                        parameter: entry.tag() == gimli::DW_TAG_formal_parameter,
+                        location: expression(entry.attr_value(gimli::DW_AT_location)?),
+                        scope,
+                    });
+                }
+            }
+            _ if entry.has_children() && entry.tag() == gimli::DW_TAG_compile_unit => {
+                let mut children = node.children();
+                while let Some(child) = children.next()? {
+                    self.walk(child, None, None, functions, types)?;
+                }
+            }
+            _ => types.load(self.dwarf, self.unit, self.unit_base, node)?,
+        }
+        Ok(())
+    }
+
+    fn pc_range(
+        &self,
+        entry: &gimli::DebuggingInformationEntry<Slice<'b>>,
+    ) -> Result<Option<(u64, u64)>> {
+        let low_pc = match entry.attr_value(gimli::DW_AT_low_pc)? {
+            Some(AttributeValue::Addr(addr)) => addr,
+            _ => return Ok(None),
+        };
+        let high_pc = match entry.attr_value(gimli::DW_AT_high_pc)? {
+            Some(AttributeValue::Addr(addr)) => addr,
+            // Since DWARF 4 it's usually the length instead.
+            Some(value) => match value.udata_value() {
+                Some(len) => low_pc + len,
+                None => return Ok(None),
+            },
+            None => return Ok(None),
+        };
+        Ok(Some((low_pc, high_pc)))
+    }
+}
+
+fn expression(value: Option<AttributeValue<Slice>>) -> Option<Vec<u8>> {
+    match value? {
+        AttributeValue::Exprloc(Expression(slice)) => Some(slice.to_vec()),
+        _ => None,
+    }
+}
@@ -0,0 +1,56 @@
+use std::time::{Duration, Instant};
+
+/// A session-wide record of what happened to the subordinate: stops,
+/// signals, breakpoint hits and process lifetime, separate from command
+/// output.
+#[derive(Debug, Clone)]
+pub struct EventLog {
+    start: Instant,
+    entries: Vec<(Duration, String)>,
+}
+
+impl Default for EventLog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EventLog {
+    pub fn new() -> Self {
+        Self {
+            start: Instant::now(),
+            entries: Vec::new(),
+        }
+    }
+
+    pub fn record(&mut self, description: String) {
+        self.entries.push((self.start.elapsed(), description));
+    }
+
+    pub fn last(&self) -> Option<&str> {
+        self.entries
+            .last()
+            .map(|(_, description)| description.as_str())
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Entries formatted with their time since the session started, e.g.
+    /// `+1.204s breakpoint at 0x401136 <add>`.
+    pub fn lines(&self) -> impl Iterator<Item = String> + '_ {
+        self.entries.iter().map(|(time, description)| {
+            format!(
+                "+{}.{:03}s {}",
+                time.as_secs(),
+                time.subsec_millis(),
+                description
+            )
+        })
+    }
+}
@@ -0,0 +1,345 @@
+use crate::debugger::Subordinate;
+use crate::result::Result;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinaryOp {
+    Or,
+    And,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    BitOr,
+    BitXor,
+    BitAnd,
+    Shl,
+    Shr,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UnaryOp {
+    Neg,
+    Not,
+    BitNot,
+    Deref,
+}
+
+/// A parsed expression over registers (`$rax`), symbols, their addresses
+/// (`&name`) and memory (`*addr`). Every value is a 64-bit word, arithmetic
+/// wraps.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Number(u64),
+    Register(String),
+    Symbol(String),
+    AddressOf(String),
+    Unary(UnaryOp, Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    pub fn parse(input: &str) -> Result<Expr> {
+        let tokens = tokenize(input)?;
+        let mut parser = Parser {
+            tokens,
+            pos: 0,
+            depth: 0,
+        };
+        let expr = parser.expr()?;
+        if let Some(token) = parser.tokens.get(parser.pos) {
+            return Err(format!("unexpected `{}` in expression", token).into());
+        }
+        Ok(expr)
+    }
+
+    pub fn evaluate(&self, subordinate: &Subordinate) -> Result<u64> {
+        self.evaluate_with(subordinate, &|_| None)
+    }
+
+    /// Evaluates with extra names in scope, which shadow symbols.
+    pub fn evaluate_with(
+        &self,
+        subordinate: &Subordinate,
+        names: &dyn Fn(&str) -> Option<u64>,
+    ) -> Result<u64> {
+        match self {
+            Expr::Number(n) => Ok(*n),
+            Expr::Register(name) => subordinate
+                .registers()
+                .get(name)
+                .ok_or_else(|| format!("unknown register `${}`", name).into()),
+            Expr::Symbol(name) => names(name)
+                .or_else(|| subordinate.symbol(name).map(|symbol| symbol.value))
+                .ok_or_else(|| format!("unknown symbol `{}`", name).into()),
+            // Straight from the symbol table, as extra names are values.
+            Expr::AddressOf(name) => subordinate
+                .symbol(name)
+                .map(|symbol| symbol.value)
+                .ok_or_else(|| format!("unknown symbol `{}`", name).into()),
+            Expr::Unary(op, e) => {
+                let value = e.evaluate_with(subordinate, names)?;
+                Ok(match op {
+                    UnaryOp::Neg => value.wrapping_neg(),
+                    UnaryOp::Not => (value == 0) as u64,
+                    UnaryOp::BitNot => !value,
+                    UnaryOp::Deref => subordinate.peek(value as usize)? as u64,
+                })
+            }
+            Expr::Binary(op, l, r) => {
+                let l = l.evaluate_with(subordinate, names)?;
+                // Short-circuit so `$rdi && *$rdi == 1` doesn't fault on null.
+                match op {
+                    BinaryOp::And if l == 0 => return Ok(0),
+                    BinaryOp::Or if l != 0 => return Ok(1),
+                    _ => {}
+                }
+                let r = r.evaluate_with(subordinate, names)?;
+                apply(*op, l, r)
+            }
+        }
+    }
+}
+
+fn apply(op: BinaryOp, l: u64, r: u64) -> Result<u64> {
+    Ok(match op {
+        BinaryOp::Or => (l != 0 || r != 0) as u64,
+        BinaryOp::And => (l != 0 && r != 0) as u64,
+        BinaryOp::Eq => (l == r) as u64,
+        BinaryOp::Ne => (l != r) as u64,
+        BinaryOp::Lt => (l < r) as u64,
+        BinaryOp::Le => (l <= r) as u64,
+        BinaryOp::Gt => (l > r) as u64,
+        BinaryOp::Ge => (l >= r) as u64,
+        BinaryOp::BitOr => l | r,
+        BinaryOp::BitXor => l ^ r,
+        BinaryOp::BitAnd => l & r,
+        BinaryOp::Shl => l.checked_shl(r as u32).unwrap_or(0),
+        BinaryOp::Shr => l.checked_shr(r as u32).unwrap_or(0),
+        BinaryOp::Add => l.wrapping_add(r),
+        BinaryOp::Sub => l.wrapping_sub(r),
+        BinaryOp::Mul => l.wrapping_mul(r),
+        BinaryOp::Div => l.checked_div(r).ok_or("division by zero")?,
+        BinaryOp::Rem => l.checked_rem(r).ok_or("division by zero")?,
+    })
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(u64),
+    Register(String),
+    Ident(String),
+    Op(&'static str),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::Number(n) => write!(f, "{}", n),
+            Token::Register(name) => write!(f, "${}", name),
+            Token::Ident(name) => f.write_str(name),
+            Token::Op(op) => f.write_str(op),
+        }
+    }
+}
+
+// Longest operators first so `<<` isn't read as two `<`.
+const OPERATORS: &[&str] = &[
+    "||", "&&", "==", "!=", "<=", ">=", "<<", ">>", "<", ">", "|", "^", "&", "+", "-", "*", "/",
+    "%", "!", "~", "(", ")",
+];
+
+fn tokenize(input: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut rest = input.trim_start();
+
+    while !rest.is_empty() {
+        let c = rest.chars().next().unwrap();
+        if c.is_ascii_digit() {
+            let len = rest
+                .find(|c: char| !c.is_ascii_alphanumeric())
+                .unwrap_or(rest.len());
+            tokens.push(Token::Number(parse_number(&rest[..len])?));
+            rest = &rest[len..];
+        } else if c == '$' || c.is_alphabetic() || c == '_' {
+            let start = if c == '$' { 1 } else { 0 };
+            let len = rest[start..]
+                .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '.' || c == ':'))
+                .map(|len| len + start)
+                .unwrap_or(rest.len());
+            let name = rest[start..len].to_owned();
+            if name.is_empty() {
+                return Err("expected a register name after `$`".into());
+            }
+            tokens.push(if c == '$' {
+                Token::Register(name)
+            } else {
+                Token::Ident(name)
+            });
+            rest = &rest[len..];
+        } else if let Some(op) = OPERATORS.iter().find(|op| rest.starts_with(*op)) {
+            tokens.push(Token::Op(op));
+            rest = &rest[op.len()..];
+        } else {
+            return Err(format!("unexpected character `{}` in expression", c).into());
+        }
+        rest = rest.trim_start();
+    }
+
+    Ok(tokens)
+}
+
+/// Parses `0x` hex, `0b` binary and plain decimal numbers.
+pub fn parse_number(s: &str) -> Result<u64> {
+    let parsed = if let Some(hex) = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        u64::from_str_radix(hex, 16)
+    } else if let Some(bin) = s.strip_prefix("0b") {
+        u64::from_str_radix(bin, 2)
+    } else {
+        s.parse()
+    };
+    parsed.map_err(|_| {
+        // Most likely an address copied without its prefix.
+        if s.chars().all(|c| c.is_ascii_hexdigit()) {
+            format!("invalid number `{}`, hex takes a 0x prefix", s).into()
+        } else {
+            format!("invalid number `{}`", s).into()
+        }
+    })
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    // How deep the tree being built is, parsing and evaluating it both
+    // recurse that far.
+    depth: usize,
+}
+
+const MAX_DEPTH: usize = 256;
+
+// Binary operator precedence levels, loosest first.
+const LEVELS: &[&[(&str, BinaryOp)]] = &[
+    &[("||", BinaryOp::Or)],
+    &[("&&", BinaryOp::And)],
+    &[
+        ("==", BinaryOp::Eq),
+        ("!=", BinaryOp::Ne),
+        ("<", BinaryOp::Lt),
+        ("<=", BinaryOp::Le),
+        (">", BinaryOp::Gt),
+        (">=", BinaryOp::Ge),
+    ],
+    &[("|", BinaryOp::BitOr)],
+    &[("^", BinaryOp::BitXor)],
+    &[("&", BinaryOp::BitAnd)],
+    &[("<<", BinaryOp::Shl), (">>", BinaryOp::Shr)],
+    &[("+", BinaryOp::Add), ("-", BinaryOp::Sub)],
+    &[
+        ("*", BinaryOp::Mul),
+        ("/", BinaryOp::Div),
+        ("%", BinaryOp::Rem),
+    ],
+];
+
+impl Parser {
+    fn expr(&mut self) -> Result<Expr> {
+        self.binary(0)
+    }
+
+    fn binary(&mut self, level: usize) -> Result<Expr> {
+        if level == LEVELS.len() {
+            return self.unary();
+        }
+
+        let mut lhs = self.binary(level + 1)?;
+        let mut ops = 0;
+        while let Some(op) = self.peek_op().and_then(|op| {
+            LEVELS[level]
+                .iter()
+                .find(|(symbol, _)| *symbol == op)
+                .map(|(_, op)| *op)
+        }) {
+            self.pos += 1;
+            self.nest()?;
+            let rhs = self.binary(level + 1)?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+            ops += 1;
+        }
+        self.depth -= ops;
+        Ok(lhs)
+    }
+
+    fn unary(&mut self) -> Result<Expr> {
+        let op = match self.peek_op() {
+            Some("-") => UnaryOp::Neg,
+            Some("!") => UnaryOp::Not,
+            Some("~") => UnaryOp::BitNot,
+            Some("*") => UnaryOp::Deref,
+            Some("&") => {
+                self.pos += 1;
+                return match self.tokens.get(self.pos).cloned() {
+                    Some(Token::Ident(name)) => {
+                        self.pos += 1;
+                        Ok(Expr::AddressOf(name))
+                    }
+                    _ => Err("`&` takes a symbol name".into()),
+                };
+            }
+            _ => return self.primary(),
+        };
+        self.pos += 1;
+        self.nest()?;
+        let operand = self.unary()?;
+        self.depth -= 1;
+        Ok(Expr::Unary(op, Box::new(operand)))
+    }
+
+    fn primary(&mut self) -> Result<Expr> {
+        let token = self
+            .tokens
+            .get(self.pos)
+            .cloned()
+            .ok_or("unexpected end of expression")?;
+        self.pos += 1;
+
+        match token {
+            Token::Number(n) => Ok(Expr::Number(n)),
+            Token::Register(name) => Ok(Expr::Register(name)),
+            Token::Ident(name) => Ok(Expr::Symbol(name)),
+            Token::Op("(") => {
+                self.nest()?;
+                let expr = self.expr()?;
+                if self.peek_op() != Some(")") {
+                    return Err("expected `)`".into());
+                }
+                self.pos += 1;
+                self.depth -= 1;
+                Ok(expr)
+            }
+            other => Err(format!("unexpected `{}` in expression", other).into()),
+        }
+    }
+
+    fn nest(&mut self) -> Result<()> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err("expression nested too deeply".into());
+        }
+        Ok(())
+    }
+
+    fn peek_op(&self) -> Option<&'static str> {
+        match self.tokens.get(self.pos) {
+            Some(Token::Op(op)) => Some(op),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use crate::error::Error;
+use crate::result::Result;
+use crate::sys::ptrace;
+use libc::{pid_t, EINVAL, ENODEV};
+use std::convert::TryInto;
+
+// Where things live in the `fxsave` area, which is also how the `xsave`
+// area starts.
+const FCW: usize = 0;
+const FSW: usize = 2;
+const FTW: usize = 4;
+const MXCSR: usize = 24;
+const ST: usize = 32;
+const XMM: usize = 160;
+const FXSAVE_SIZE: usize = 512;
+// The `xsave` header's bitmap of which components hold live state, and
+// the fixed offset of the upper halves of the ymm registers.
+const XSTATE_BV: usize = 512;
+const YMM_HI: usize = 576;
+const AVX_STATE: u64 = 1 << 2;
+// Big enough for everything up to AVX-512.
+const XSAVE_SIZE: usize = 4096;
+
+/// The x87, SSE and AVX registers of a stopped thread.
+#[derive(Clone, Debug)]
+pub struct FloatRegisters {
+    pub fcw: u16,
+    pub fsw: u16,
+    /// The abridged tag word, a set bit meaning that st register is in use.
+    pub ftw: u8,
+    pub mxcsr: u32,
+    /// st0-st7 as 80-bit extended precision values, st0 being the top of
+    /// the stack.
+    pub st: [[u8; 10]; 8],
+    pub xmm: [[u8; 16]; 16],
+    /// The upper halves of ymm0-ymm15, when the CPU has AVX.
+    pub ymm_hi: Option<[[u8; 16]; 16]>,
+}
+
+impl FloatRegisters {
+    /// Reads the thread's state with `PTRACE_GETREGSET`, falling back to
+    /// the `fxsave` layout on kernels or CPUs without `xsave`.
+    pub fn read(pid: pid_t) -> Result<Self> {
+        let mut buf = vec![0; XSAVE_SIZE];
+        let size = match ptrace::getregset(pid, ptrace::NT_X86_XSTATE, &mut buf) {
+            Ok(size) => size,
+            Err(Error::Errno(EINVAL)) | Err(Error::Errno(ENODEV)) => {
+                ptrace::getregset(pid, ptrace::NT_PRFPREG, &mut buf)?
+            }
+            Err(e) => return Err(e),
+        };
+        if size < FXSAVE_SIZE {
+            return Err(format!("short floating point state, {} bytes", size).into());
+        }
+        Ok(Self::parse(&buf[..size]))
+    }
+
+    fn parse(area: &[u8]) -> Self {
+        let mut st = [[0; 10]; 8];
+        for (i, st) in st.iter_mut().enumerate() {
+            st.copy_from_slice(&area[ST + i * 16..][..10]);
+        }
+        let mut xmm = [[0; 16]; 16];
+        for (i, xmm) in xmm.iter_mut().enumerate() {
+            xmm.copy_from_slice(&area[XMM + i * 16..][..16]);
+        }
+        let ymm_hi = if area.len() >= YMM_HI + 256 {
+            let mut ymm_hi = [[0; 16]; 16];
+            // A component the header says is unused is in its initial,
+            // zeroed, state whatever the area holds.
+            if u64::from_le_bytes(area[XSTATE_BV..][..8].try_into().unwrap()) & AVX_STATE != 0 {
+                for (i, hi) in ymm_hi.iter_mut().enumerate() {
+                    hi.copy_from_slice(&area[YMM_HI + i * 16..][..16]);
+                }
+            }
+            Some(ymm_hi)
+        } else {
+            None
+        };
+        FloatRegisters {
+            fcw: u16::from_le_bytes([area[FCW], area[FCW + 1]]),
+            fsw: u16::from_le_bytes([area[FSW], area[FSW + 1]]),
+            ftw: area[FTW],
+            mxcsr: u32::from_le_bytes(area[MXCSR..][..4].try_into().unwrap()),
+            st,
+            xmm,
+            ymm_hi,
+        }
+    }
+
+    /// The physical register behind stN, which rotates with the stack top.
+    fn physical(&self, n: usize) -> usize {
+        (n + ((self.fsw >> 11) & 7) as usize) % 8
+    }
+
+    /// Whether stN holds a value.
+    pub fn st_in_use(&self, n: usize) -> bool {
+        self.ftw & (1 << self.physical(n)) != 0
+    }
+
+    /// stN, as near as an f64 gets to it.
+    pub fn st_value(&self, n: usize) -> f64 {
+        extended_to_f64(&self.st[n])
+    }
+}
+
+/// Converts an 80-bit extended precision value, which keeps its leading
+/// mantissa bit explicitly.
+pub fn extended_to_f64(bytes: &[u8; 10]) -> f64 {
+    let mantissa = u64::from_le_bytes(bytes[..8].try_into().unwrap());
+    let sign_exponent = u16::from_le_bytes([bytes[8], bytes[9]]);
+    let sign = if sign_exponent & 0x8000 != 0 {
+        -1.0
+    } else {
+        1.0
+    };
+    let exponent = (sign_exponent & 0x7fff) as i32;
+    let magnitude = match exponent {
+        0 if mantissa == 0 => 0.0,
+        0x7fff if mantissa << 1 == 0 => f64::INFINITY,
+        0x7fff => f64::NAN,
+        // Subnormals use the exponent of the smallest normal.
+        _ => (mantissa as f64) * 2f64.powi(exponent.max(1) - 16383 - 63),
+    };
+    sign * magnitude
+}
@@ -0,0 +1,182 @@
+use crate::debugger::dwarf::TypeId;
+use crate::debugger::{Expr, Subordinate};
+use crate::result::Result;
+use regex::Regex;
+
+// How much of a string a `{...:s}` placeholder reads.
+const MAX_STRING: usize = 200;
+
+/// Pretty-printers for types whose layout says little on its own, like
+/// interned string ids or arena indices. Each is picked by a regex over the
+/// type's name and renders a template, e.g. `{*(names + id * 8):s}`.
+#[derive(Debug, Clone, Default)]
+pub struct Formatters {
+    formatters: Vec<Formatter>,
+}
+
+#[derive(Debug, Clone)]
+struct Formatter {
+    pattern: Regex,
+    template: Vec<Piece>,
+    source: String,
+}
+
+#[derive(Debug, Clone)]
+enum Piece {
+    Text(String),
+    // A member of the value, shown as its own type would be.
+    Member(String),
+    // An expression where the value's members and `value` itself are
+    // names, shown per the format letter.
+    Expr(Expr, Option<char>),
+}
+
+impl Formatters {
+    /// Registers a formatter, which wins over earlier ones matching the
+    /// same type.
+    pub fn add(&mut self, pattern: &str, template: &str) -> Result<()> {
+        self.formatters.push(Formatter {
+            pattern: Regex::new(pattern).map_err(|e| e.to_string())?,
+            template: parse_template(template)?,
+            source: template.to_owned(),
+        });
+        Ok(())
+    }
+
+    /// Each formatter's pattern and template, oldest first.
+    pub fn list(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.formatters
+            .iter()
+            .map(|formatter| (formatter.pattern.as_str(), formatter.source.as_str()))
+    }
+
+    pub fn remove(&mut self, n: usize) -> Result<()> {
+        if n >= self.formatters.len() {
+            return Err(format!("no formatter {}", n + 1).into());
+        }
+        self.formatters.remove(n);
+        Ok(())
+    }
+
+    /// Formats a value by its type, applying formatters to it and to
+    /// anything inside it.
+    pub fn format(&self, subordinate: &Subordinate, ty: Option<TypeId>, bytes: &[u8]) -> String {
+        let types = subordinate.debug_info().types();
+        types.format_with(ty, bytes, &|ty, bytes| {
+            if self.formatters.is_empty() {
+                return None;
+            }
+            let name = types.name(ty);
+            let formatter = self
+                .formatters
+                .iter()
+                .rev()
+                .find(|formatter| formatter.pattern.is_match(&name))?;
+            Some(self.render(subordinate, formatter, ty, bytes))
+        })
+    }
+
+    fn render(
+        &self,
+        subordinate: &Subordinate,
+        formatter: &Formatter,
+        ty: Option<TypeId>,
+        bytes: &[u8],
+    ) -> String {
+        let types = subordinate.debug_info().types();
+        let member = |name: &str| {
+            let member = types.member(ty, name)?;
+            let start = member.offset as usize;
+            let size = types.size(member.ty)? as usize;
+            Some((member.ty, bytes.get(start..start + size)?))
+        };
+        let names = |name: &str| match name {
+            "value" => types.integer(ty, bytes),
+            name => member(name).and_then(|(ty, bytes)| types.integer(ty, bytes)),
+        };
+
+        let mut text = String::new();
+        for piece in &formatter.template {
+            match piece {
+                Piece::Text(literal) => text.push_str(literal),
+                Piece::Member(name) => match member(name) {
+                    // The member's own type may have a formatter too.
+                    Some((ty, bytes)) => text.push_str(&self.format(subordinate, ty, bytes)),
+                    None => text.push_str(&format!("<no member {}>", name)),
+                },
+                Piece::Expr(expr, letter) => {
+                    let shown = expr
+                        .evaluate_with(subordinate, &names)
+                        .and_then(|value| show(subordinate, value, *letter));
+                    match shown {
+                        Ok(shown) => text.push_str(&shown),
+                        Err(e) => text.push_str(&format!("<{}>", e)),
+                    }
+                }
+            }
+        }
+        text
+    }
+}
+
+fn show(subordinate: &Subordinate, value: u64, letter: Option<char>) -> Result<String> {
+    Ok(match letter {
+        Some('s') => format!("{:?}", subordinate.read_string(value as usize, MAX_STRING)?),
+        Some('x') => format!("0x{:x}", value),
+        Some('d') => format!("{}", value as i64),
+        _ => format!("{}", value),
+    })
+}
+
+/// Splits a template into text and `{...}` placeholders, `{{` and `}}`
+/// standing for literal braces. A placeholder that's just a name shows
+/// that member; anything else is an expression, optionally followed by
+/// `:s` (string at), `:x` or `:d`.
+fn parse_template(template: &str) -> Result<Vec<Piece>> {
+    let mut pieces = Vec::new();
+    let mut text = String::new();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                text.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                text.push('}');
+            }
+            '{' => {
+                let inner: String = chars.by_ref().take_while(|c| *c != '}').collect();
+                if !text.is_empty() {
+                    pieces.push(Piece::Text(std::mem::take(&mut text)));
+                }
+                pieces.push(parse_placeholder(inner.trim())?);
+            }
+            '}' => return Err("unmatched `}` in template, `}}` is a literal one".into()),
+            c => text.push(c),
+        }
+    }
+    if !text.is_empty() {
+        pieces.push(Piece::Text(text));
+    }
+    Ok(pieces)
+}
+
+fn parse_placeholder(inner: &str) -> Result<Piece> {
+    let is_name = |s: &str| {
+        !s.is_empty()
+            && s != "value"
+            && s.chars().all(|c| c.is_alphanumeric() || c == '_')
+            && !s.starts_with(|c: char| c.is_ascii_digit())
+    };
+    if is_name(inner) {
+        return Ok(Piece::Member(inner.to_owned()));
+    }
+    let (expr, letter) = match inner.rsplit_once(':') {
+        Some((expr, letter @ ("s" | "x" | "d"))) => (expr, letter.chars().next()),
+        Some((_, letter)) => return Err(format!("unknown format letter `{}`", letter).into()),
+        None => (inner, None),
+    };
+    Ok(Piece::Expr(Expr::parse(expr)?, letter))
+}
@@ -0,0 +1,64 @@
+//! x86 debug registers. DR0-DR3 hold breakpoint addresses, DR7 enables them
+//! and says what each one traps on, DR6 reports which one fired.
+
+pub const SLOTS: usize = 4;
+pub const DR6: usize = 6;
+pub const DR7: usize = 7;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Access {
+    Execute,
+    Write,
+    // x86 can't trap on reads alone.
+    ReadWrite,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HwBreakpoint {
+    pub addr: usize,
+    pub access: Access,
+    // 1, 2, 4 or 8 bytes, and `addr` must be aligned to it.
+    pub len: usize,
+    // Last value seen at a watched address, to show what a write changed.
+    pub value: u64,
+}
+
+impl HwBreakpoint {
+    fn control(&self, slot: usize) -> u64 {
+        let rw = match self.access {
+            Access::Execute => 0b00,
+            Access::Write => 0b01,
+            Access::ReadWrite => 0b11,
+        };
+        let len = match self.len {
+            2 => 0b01,
+            8 => 0b10,
+            4 => 0b11,
+            _ => 0b00,
+        };
+        1 << (slot * 2) | rw << (16 + slot * 4) | len << (18 + slot * 4)
+    }
+}
+
+/// The DR7 value enabling every occupied slot.
+pub fn control(slots: &[Option<HwBreakpoint>; SLOTS]) -> u64 {
+    slots
+        .iter()
+        .enumerate()
+        .filter_map(|(i, slot)| slot.map(|slot| slot.control(i)))
+        .fold(0, |dr7, bits| dr7 | bits)
+}
+
+/// The slot whose condition was met according to DR6.
+pub fn fired(dr6: u64) -> Option<usize> {
+    (0..SLOTS).find(|i| dr6 & (1 << i) != 0)
+}
+
+/// The widest watch length `addr` is aligned for, at most `size`.
+pub fn watch_len(addr: usize, size: usize) -> usize {
+    [8, 4, 2, 1]
+        .iter()
+        .cloned()
+        .find(|len| *len <= size && addr.is_multiple_of(*len))
+        .unwrap_or(1)
+}
@@ -0,0 +1,144 @@
+use crate::result::Result;
+use crate::sys::{disable_aslr, pin_to_one_cpu, setrlimit, unshare_namespaces};
+use libc::__rlimit_resource_t;
+
+/// Environment a deterministic launch fixes, so backtraces, formatting and
+/// local times come out the same on every machine.
+const FIXED_ENV: &[(&str, &str)] = &[
+    ("RUST_BACKTRACE", "1"),
+    ("LC_ALL", "C"),
+    ("LANG", "C"),
+    ("TZ", "UTC"),
+];
+
+/// The resources `set rlimit` takes, by the names `ulimit` and prlimit(1)
+/// use.
+const RLIMITS: &[(&str, __rlimit_resource_t)] = &[
+    ("as", libc::RLIMIT_AS),
+    ("core", libc::RLIMIT_CORE),
+    ("cpu", libc::RLIMIT_CPU),
+    ("data", libc::RLIMIT_DATA),
+    ("fsize", libc::RLIMIT_FSIZE),
+    ("memlock", libc::RLIMIT_MEMLOCK),
+    ("nofile", libc::RLIMIT_NOFILE),
+    ("nproc", libc::RLIMIT_NPROC),
+    ("stack", libc::RLIMIT_STACK),
+];
+
+/// How the subordinate is set up between fork and exec, kept so `restart`
+/// launches it the same way.
+#[derive(Debug, Clone, Default)]
+pub struct Launch {
+    pub env: Vec<(String, String)>,
+    pub pin_cpu: bool,
+    /// Whether getrandom and the clock syscalls return fixed values.
+    pub fixed_syscalls: bool,
+    /// Whether to run in new mount and network namespaces.
+    pub sandbox: bool,
+    /// A memory limit for a transient cgroup to run in.
+    pub memory_limit: Option<u64>,
+    /// Whether addresses are randomized as usual. They aren't by default, so
+    /// breakpoints and addresses stay put from one run to the next.
+    pub aslr: bool,
+    // Resource limits by name, `None` being unlimited.
+    rlimits: Vec<(&'static str, Option<u64>)>,
+}
+
+impl Launch {
+    /// Fixes the environment and pins to a single CPU, the parts of a
+    /// deterministic launch that need no help from the tracer.
+    pub fn make_deterministic(&mut self) {
+        for (name, value) in FIXED_ENV {
+            self.env.push((name.to_string(), value.to_string()));
+        }
+        self.pin_cpu = true;
+    }
+
+    pub fn set_rlimit(&mut self, resource: &str, limit: Option<u64>) -> Result<()> {
+        let name = match RLIMITS.iter().find(|(name, _)| *name == resource) {
+            Some((name, _)) => *name,
+            None => {
+                let names: Vec<&str> = RLIMITS.iter().map(|(name, _)| *name).collect();
+                return Err(format!(
+                    "unknown resource `{}`, it's one of {}",
+                    resource,
+                    names.join(", ")
+                )
+                .into());
+            }
+        };
+        self.rlimits.retain(|(set, _)| *set != name);
+        self.rlimits.push((name, limit));
+        Ok(())
+    }
+
+    /// The limits `set rlimit` asked for, in the order they were set.
+    pub fn rlimits(&self) -> &[(&'static str, Option<u64>)] {
+        &self.rlimits
+    }
+
+    /// Runs in the forked child, before exec.
+    pub fn apply(&self) -> Result<()> {
+        if !self.aslr {
+            disable_aslr()?;
+        }
+        if self.sandbox {
+            unshare_namespaces()?;
+        }
+        for (name, value) in &self.env {
+            std::env::set_var(name, value);
+        }
+        if self.pin_cpu {
+            pin_to_one_cpu()?;
+        }
+        for (name, limit) in &self.rlimits {
+            let resource = RLIMITS.iter().find(|(known, _)| known == name).unwrap().1;
+            setrlimit(resource, *limit)?;
+        }
+        Ok(())
+    }
+}
+
+/// Stands in for entropy and time: random bytes come from a fixed-seed
+/// generator and the clock starts at 2000-01-01 and ticks a millisecond
+/// per reading.
+#[derive(Debug, Clone)]
+pub struct FixedSyscalls {
+    state: u64,
+    nanos: u64,
+}
+
+const EPOCH_2000: u64 = 946_684_800;
+const TICK_NANOS: u64 = 1_000_000;
+const SEED: u64 = 0x9e37_79b9_7f4a_7c15;
+
+impl Default for FixedSyscalls {
+    fn default() -> Self {
+        FixedSyscalls {
+            state: SEED,
+            nanos: EPOCH_2000 * 1_000_000_000,
+        }
+    }
+}
+
+impl FixedSyscalls {
+    pub fn random_bytes(&mut self, len: usize) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(len + 8);
+        while bytes.len() < len {
+            // xorshift64
+            self.state ^= self.state << 13;
+            self.state ^= self.state >> 7;
+            self.state ^= self.state << 17;
+            bytes.extend_from_slice(&self.state.to_le_bytes());
+        }
+        bytes.truncate(len);
+        bytes
+    }
+
+    /// The next clock reading, in seconds and nanoseconds.
+    pub fn now(&mut self) -> (u64, u64) {
+        let now = self.nanos;
+        self.nanos += TICK_NANOS;
+        (now / 1_000_000_000, now % 1_000_000_000)
+    }
+}
@@ -0,0 +1,156 @@
+use crate::debugger::auxv::Entry::{BaseAddr, ProgramHeaderAddr, ProgramHeaderCount};
+use crate::debugger::build_id::{u32_at, u64_at, PHDR_SIZE};
+use crate::debugger::Subordinate;
+use crate::result::Result;
+use std::path::PathBuf;
+
+const PT_DYNAMIC: u32 = 2;
+const DT_NULL: u64 = 0;
+const DT_DEBUG: u64 = 21;
+const DYN_SIZE: usize = 16;
+// Where the fields are in `struct r_debug` and `struct link_map`.
+const R_MAP: usize = 8;
+const R_BRK: usize = 16;
+const R_STATE: usize = 24;
+const L_NAME: usize = 8;
+const L_NEXT: usize = 24;
+// Guards against a link map that loops, which a corrupted one could.
+const MAX_LIBRARIES: usize = 4096;
+// `r_state` when the list isn't halfway through a change.
+const RT_CONSISTENT: u32 = 0;
+// What `r_brk` points at, for finding it before the dynamic linker ran.
+const DEBUG_STATE: &str = "_dl_debug_state";
+
+/// A shared object the dynamic linker loaded, with its symbols moved to
+/// where it is in the process.
+#[derive(Clone)]
+pub struct Library {
+    pub path: PathBuf,
+    // `l_addr`, what the addresses in the file are offset by.
+    pub base: u64,
+    pub symbols: Vec<elf::types::Symbol>,
+}
+
+impl Library {
+    /// Reads the symbols of the file at `path`, the full table when it
+    /// wasn't stripped and the dynamic one when it was.
+    pub fn load(path: PathBuf, base: u64) -> Result<Self> {
+        let elf = elf::File::open_path(&path)?;
+        let section = elf
+            .get_section(".symtab")
+            .or_else(|| elf.get_section(".dynsym"));
+        let mut symbols = match section {
+            Some(section) => elf.get_symbols(section)?,
+            None => Vec::new(),
+        };
+        // Undefined symbols are the ones it imports from elsewhere.
+        symbols.retain(|symbol| symbol.shndx != 0 && symbol.value != 0);
+        for symbol in &mut symbols {
+            symbol.value += base;
+        }
+        Ok(Library {
+            path,
+            base,
+            symbols,
+        })
+    }
+}
+
+/// The shared objects in the dynamic linker's list, as their paths and
+/// base addresses, in the order they were loaded. The executable itself and
+/// the vDSO, which have no file to read, are left out. The list is empty
+/// until the dynamic linker has filled in `DT_DEBUG`, and always is for
+/// static binaries. None while the linker is in the middle of changing it.
+pub fn loaded(subordinate: &Subordinate) -> Result<Option<Vec<(PathBuf, u64)>>> {
+    let r_debug = match r_debug(subordinate)? {
+        Some(r_debug) => r_debug,
+        None => return Ok(Some(Vec::new())),
+    };
+    if subordinate.peek(r_debug + R_STATE)? as u32 != RT_CONSISTENT {
+        return Ok(None);
+    }
+    let mut libraries = Vec::new();
+    let mut entry = subordinate.peek(r_debug + R_MAP)?;
+    while entry != 0 && libraries.len() < MAX_LIBRARIES {
+        let base = subordinate.peek(entry)? as u64;
+        let name = subordinate.peek(entry + L_NAME)?;
+        if name != 0 {
+            let path = subordinate.read_string(name, libc::PATH_MAX as usize)?;
+            if path.starts_with('/') {
+                libraries.push((PathBuf::from(path), base));
+            }
+        }
+        entry = subordinate.peek(entry + L_NEXT)?;
+    }
+    Ok(Some(libraries))
+}
+
+/// Where the dynamic linker calls out before and after it changes its list
+/// of shared objects, `r_brk`, for a breakpoint to notice libraries coming
+/// and going. Until the linker has filled in `r_debug`, it's found by
+/// symbol in the linker itself, which the auxv says where it's loaded.
+/// None for static binaries.
+pub fn notification(subordinate: &Subordinate) -> Result<Option<u64>> {
+    if let Some(r_debug) = r_debug(subordinate)? {
+        return Ok(Some(subordinate.peek(r_debug + R_BRK)? as u64));
+    }
+    let base = subordinate.auxv().iter().find_map(|entry| match entry {
+        BaseAddr(addr) if *addr != 0 => Some(*addr),
+        _ => None,
+    });
+    let base = match base {
+        Some(base) => base,
+        None => return Ok(None),
+    };
+    let interpreter = subordinate
+        .maps()?
+        .into_iter()
+        .find(|region| region.start == base)
+        .and_then(|region| region.path)
+        .ok_or("couldn't find the dynamic linker's file")?;
+    let linker = Library::load(interpreter, base as u64)?;
+    Ok(linker
+        .symbols
+        .iter()
+        .find(|symbol| symbol.name == DEBUG_STATE)
+        .map(|symbol| symbol.value))
+}
+
+// Where `struct r_debug` is, from the `DT_DEBUG` entry in the executable's
+// dynamic section, which the auxv's program headers lead to.
+fn r_debug(subordinate: &Subordinate) -> Result<Option<usize>> {
+    let mut phdr = None;
+    let mut count = None;
+    for entry in subordinate.auxv() {
+        match entry {
+            ProgramHeaderAddr(addr) => phdr = Some(*addr),
+            ProgramHeaderCount(n) => count = Some(*n),
+            _ => {}
+        }
+    }
+    let (phdr, count) = match (phdr, count) {
+        (Some(phdr), Some(count)) => (phdr, count),
+        _ => return Ok(None),
+    };
+    let headers = subordinate.read_bytes(phdr, count * PHDR_SIZE)?;
+    let dynamic = match headers
+        .chunks(PHDR_SIZE)
+        .find(|header| u32_at(header, 0) == PT_DYNAMIC)
+    {
+        Some(header) => (u64_at(header, 16) + subordinate.load_bias()) as usize,
+        None => return Ok(None),
+    };
+
+    let mut at = dynamic;
+    loop {
+        let entry = subordinate.read_bytes(at, DYN_SIZE)?;
+        match u64_at(&entry, 0) {
+            DT_NULL => return Ok(None),
+            DT_DEBUG => {
+                let r_debug = u64_at(&entry, 8) as usize;
+                return Ok(Some(r_debug).filter(|addr| *addr != 0));
+            }
+            _ => at += DYN_SIZE,
+        }
+    }
+}
@@ -0,0 +1,117 @@
+use crate::debugger::dwarf::{FrameRegisters, FrameState, Place, TypeId};
+use crate::debugger::Subordinate;
+use crate::result::Result;
+
+pub struct Local {
+    pub name: String,
+    pub parameter: bool,
+    pub ty: Option<TypeId>,
+    pub type_name: String,
+    // None when the variable has no location here, e.g. optimized out.
+    pub place: Option<Place>,
+    pub bytes: Option<Vec<u8>>,
+}
+
+impl Local {
+    /// The value decoded by its type, e.g. `{x = 4, y = 5}`, or by a
+    /// formatter registered for it.
+    pub fn value(&self, subordinate: &Subordinate) -> Option<String> {
+        let bytes = self.bytes.as_ref()?;
+        Some(subordinate.formatters().format(subordinate, self.ty, bytes))
+    }
+
+    /// The value as a word for expressions, if it's a scalar.
+    pub fn integer(&self, subordinate: &Subordinate) -> Option<u64> {
+        let types = subordinate.debug_info().types();
+        types.integer(self.ty, self.bytes.as_ref()?)
+    }
+}
+
+/// The variables in scope at the selected frame's pc.
+pub fn locals(subordinate: &Subordinate) -> Result<Vec<Local>> {
+    let regs = subordinate.frame_registers();
+    let debug_info = subordinate.debug_info();
+    let types = debug_info.types();
+
+    // Callers' pcs are return addresses, just past the call.
+    let mut pc = regs.rip - subordinate.load_bias();
+    if subordinate.selected_frame() > 0 {
+        pc -= 1;
+    }
+    let function = debug_info
+        .function_at(pc)
+        .ok_or_else(|| format!("no debug info for 0x{:x}", regs.rip))?;
+
+    let frame = FrameRegisters {
+        pc: regs.rip,
+        sp: regs.rsp,
+        fp: regs.rbp,
+    };
+    let read = |addr: u64| subordinate.peek(addr as usize).ok().map(|word| word as u64);
+    let register = |number: u16| regs.dwarf(number);
+    let state = FrameState {
+        register: &register,
+        read: &read,
+        cfa: debug_info.call_frames().cfa(&frame, pc),
+        bias: subordinate.load_bias(),
+    };
+
+    let mut locals = Vec::new();
+    for variable in function.variables_at(pc) {
+        let size = types.size(variable.ty).unwrap_or(8) as usize;
+        let place = function.locate(variable, &state);
+        let bytes = match place {
+            Some(Place::Address(addr)) => subordinate.read_bytes(addr as usize, size).ok(),
+            Some(Place::Register(number)) => regs
+                .dwarf(number)
+                .map(|value| value.to_le_bytes()[..size.min(8)].to_vec()),
+            Some(Place::Value(value)) => Some(value.to_le_bytes()[..size.min(8)].to_vec()),
+            None => None,
+        };
+        locals.push(Local {
+            name: variable.name.clone(),
+            parameter: variable.parameter,
+            ty: variable.ty,
+            type_name: types.name(variable.ty),
+            place,
+            bytes,
+        });
+    }
+    Ok(locals)
+}
+
+/// The innermost variable called `name` in the selected frame, if we can
+/// tell what's in scope there. `name` can reach into structs, as in
+/// `origin.x`.
+pub fn local(subordinate: &Subordinate, name: &str) -> Option<Local> {
+    let mut path = name.split('.');
+    let first = path.next()?;
+    // Inner blocks come after the function's own variables.
+    let mut local = locals(subordinate)
+        .ok()?
+        .into_iter()
+        .rev()
+        .find(|local| local.name == first)?;
+
+    let types = subordinate.debug_info().types();
+    for field in path {
+        let member = types.member(local.ty, field)?;
+        let offset = member.offset as usize;
+        let size = types.size(member.ty).unwrap_or(0) as usize;
+        local = Local {
+            name: format!("{}.{}", local.name, field),
+            parameter: local.parameter,
+            ty: member.ty,
+            type_name: types.name(member.ty),
+            place: match local.place {
+                Some(Place::Address(addr)) => Some(Place::Address(addr + offset as u64)),
+                // A struct in a register can't be partly written back.
+                _ => None,
+            },
+            bytes: local
+                .bytes
+                .and_then(|bytes| bytes.get(offset..offset + size).map(|b| b.to_vec())),
+        };
+    }
+    Some(local)
+}
@@ -0,0 +1,53 @@
+use crate::result::Result;
+use std::fmt;
+
+/// The architecture a binary or core dump is for, from the `e_machine`
+/// field of its ELF header.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Machine {
+    X86_64,
+    I386,
+    Arm,
+    Aarch64,
+    RiscV,
+    Other(u16),
+}
+
+impl Machine {
+    pub fn from_elf(e_machine: u16) -> Self {
+        match e_machine {
+            3 => Machine::I386,
+            40 => Machine::Arm,
+            62 => Machine::X86_64,
+            183 => Machine::Aarch64,
+            243 => Machine::RiscV,
+            other => Machine::Other(other),
+        }
+    }
+
+    /// Errors for anything the registers and disassembler can't make sense
+    /// of, which is everything but x86-64, `what` being the file's kind.
+    pub fn check(self, what: &str) -> Result<()> {
+        if self == Machine::X86_64 {
+            return Ok(());
+        }
+        Err(format!(
+            "the {} is for {}, and only x86-64 can be debugged",
+            what, self
+        )
+        .into())
+    }
+}
+
+impl fmt::Display for Machine {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Machine::X86_64 => f.write_str("x86-64"),
+            Machine::I386 => f.write_str("i386"),
+            Machine::Arm => f.write_str("arm"),
+            Machine::Aarch64 => f.write_str("aarch64"),
+            Machine::RiscV => f.write_str("riscv"),
+            Machine::Other(n) => write!(f, "machine type {}", n),
+        }
+    }
+}
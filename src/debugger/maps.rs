@@ -0,0 +1,163 @@
+use crate::result::Result;
+use libc::pid_t;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RegionKind {
+    Text,
+    Heap,
+    Stack,
+    Library,
+    Other,
+}
+
+/// A single line of /proc/<pid>/maps.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Region {
+    pub start: usize,
+    pub end: usize,
+    pub perms: String,
+    pub offset: usize,
+    pub path: Option<PathBuf>,
+    pub name: Option<String>,
+}
+
+impl Region {
+    pub fn contains(&self, addr: usize) -> bool {
+        addr >= self.start && addr < self.end
+    }
+
+    pub fn kind(&self, executable: &Path) -> RegionKind {
+        match (self.name.as_deref(), &self.path) {
+            (Some("[heap]"), _) => RegionKind::Heap,
+            (Some(name), _) if name.starts_with("[stack") => RegionKind::Stack,
+            (_, Some(path)) if path == executable => RegionKind::Text,
+            (_, Some(_)) => RegionKind::Library,
+            _ => RegionKind::Other,
+        }
+    }
+}
+
+/// How the memory map differs from one stop to the next.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MapChange {
+    Mapped(Region),
+    Unmapped(Region),
+    // Permissions changed, with what they were before.
+    Protected(Region, String),
+}
+
+impl fmt::Display for Region {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "0x{:x}-0x{:x} {}", self.start, self.end, self.perms)?;
+        match (&self.path, &self.name) {
+            (Some(path), _) => write!(f, " {}", path.display()),
+            (None, Some(name)) => write!(f, " {}", name),
+            (None, None) => Ok(()),
+        }
+    }
+}
+
+impl fmt::Display for MapChange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let region = match self {
+            MapChange::Mapped(region) => {
+                write!(f, "mapped {}", region)?;
+                region
+            }
+            MapChange::Unmapped(region) => return write!(f, "unmapped {}", region),
+            MapChange::Protected(region, old) => {
+                write!(f, "protected {} (was {})", region, old)?;
+                region
+            }
+        };
+        // Writable code is what JITs and shellcode need, worth pointing out.
+        if region.perms.starts_with("rwx") {
+            write!(f, ", writable and executable")?;
+        }
+        Ok(())
+    }
+}
+
+/// Compares two readings of the map. Regions the kernel merely split or
+/// merged without changing permissions aren't reported.
+pub fn changes(old: &[Region], new: &[Region]) -> Vec<MapChange> {
+    // Whether two regions share memory of the same mapping, rather than
+    // one replacing the other.
+    let overlaps = |a: &Region, b: &Region| {
+        a.start < b.end && b.start < a.end && a.path == b.path && a.name == b.name
+    };
+    let mut changes = Vec::new();
+    for region in new.iter().filter(|region| !old.contains(region)) {
+        let before: Vec<&Region> = old.iter().filter(|o| overlaps(o, region)).collect();
+        if let Some(changed) = before.iter().find(|o| o.perms != region.perms) {
+            changes.push(MapChange::Protected(region.clone(), changed.perms.clone()));
+        } else if !covers(&before, region) {
+            changes.push(MapChange::Mapped(region.clone()));
+        }
+    }
+    for region in old.iter().filter(|region| !new.contains(region)) {
+        if !new.iter().any(|n| overlaps(n, region)) {
+            changes.push(MapChange::Unmapped(region.clone()));
+        }
+    }
+    changes
+}
+
+// Whether `regions`, sorted as the kernel lists them, span all of `region`.
+fn covers(regions: &[&Region], region: &Region) -> bool {
+    let mut reached = region.start;
+    for r in regions {
+        if r.start > reached {
+            return false;
+        }
+        reached = reached.max(r.end);
+    }
+    reached >= region.end
+}
+
+pub fn read(pid: pid_t) -> Result<Vec<Region>> {
+    parse(&fs::read_to_string(format!("/proc/{}/maps", pid))?)
+}
+
+/// Parses the memory map in the format of /proc/<pid>/maps.
+pub fn parse(maps: &str) -> Result<Vec<Region>> {
+    maps.lines().map(parse_line).collect()
+}
+
+fn parse_line(line: &str) -> Result<Region> {
+    let mut fields = line.splitn(6, ' ');
+    let mut next = || {
+        fields
+            .next()
+            .ok_or_else(|| format!("malformed maps line `{}`", line))
+    };
+
+    let range = next()?;
+    let perms = next()?.to_owned();
+    let offset = usize::from_str_radix(next()?, 16)?;
+    let _dev = next()?;
+    let _inode = next()?;
+    let pathname = next().unwrap_or("").trim();
+
+    let mut bounds = range.splitn(2, '-');
+    let start = usize::from_str_radix(bounds.next().unwrap_or(""), 16)?;
+    let end = usize::from_str_radix(bounds.next().unwrap_or(""), 16)?;
+
+    let (path, name) = match pathname {
+        "" => (None, None),
+        p if p.starts_with('/') => (Some(PathBuf::from(p)), None),
+        p => (None, Some(p.to_owned())),
+    };
+
+    Ok(Region {
+        start,
+        end,
+        perms,
+        offset,
+        path,
+        name,
+    })
+}
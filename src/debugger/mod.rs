@@ -1,10 +1,50 @@
+mod annotations;
 mod auxv;
+pub mod backtrace;
+pub mod build_id;
+mod capabilities;
+pub mod coredump;
+mod demangle;
 mod disassembler;
 mod dwarf;
-mod registers;
+mod events;
+mod expression;
+mod float;
+mod formatters;
+mod hardware;
+mod launch;
+mod link_map;
+mod locals;
+mod machine;
+mod maps;
+mod objdump;
+pub mod processes;
+pub mod registers;
+mod signals;
+mod stops;
 mod subordinate;
+pub mod syscalls;
+mod trace;
+mod xrefs;
 
-pub use disassembler::Disassembler;
-pub use dwarf::DebugInfo;
+pub use annotations::Annotations;
+pub use backtrace::backtrace;
+pub use capabilities::Capabilities;
+pub use disassembler::{Disassembler, MAX_INSTRUCTION_LEN};
+pub use dwarf::{DebugInfo, Place};
+pub use events::EventLog;
+pub use expression::Expr;
+pub use float::FloatRegisters;
+pub use hardware::Access;
+pub use launch::Launch;
+pub use locals::{local, locals};
+pub use machine::Machine;
+pub use maps::{Region, RegionKind};
+pub use objdump::objdump;
 pub use registers::Registers;
-pub use subordinate::Subordinate;
+pub use signals::Signals;
+pub use stops::{StopDiff, StopRecorder};
+pub use subordinate::{FollowFork, StepMode, Subordinate};
+use trace::PendingSyscall;
+pub use trace::{chrome_trace, TraceEvent, Tracepoint};
+pub use xrefs::{rodata_strings, RefKind, Xrefs};
@@ -0,0 +1,254 @@
+use crate::debugger::build_id::{u32_at, u64_at};
+use crate::debugger::demangle::Demangler;
+use crate::debugger::disassembler::branch_target;
+use crate::debugger::Disassembler;
+use crate::result::Result;
+use iced_x86::Instruction;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+const RELA_SIZE: usize = 24;
+const PLT_ENTRY_SIZE: usize = 16;
+// `jmp [rip+disp32]`, how PLT entries go through their GOT slot.
+const JMP_RIP: [u8; 2] = [0xff, 0x25];
+const R_X86_64_GLOB_DAT: u32 = 6;
+const R_X86_64_JUMP_SLOT: u32 = 7;
+
+/// Disassembles the function `name` straight from the binary at `path`,
+/// with no process needed. Branch targets and rip-relative operands are
+/// named by symbol, calls through the PLT and loads from the GOT by the
+/// dynamic symbol their relocation binds them to, and in object files each
+/// instruction a relocation patches shows it.
+pub fn objdump(path: &Path, name: &str, disassembler: &Disassembler) -> Result<String> {
+    let elf = elf::File::open_path(path)?;
+    let image = Image::read(&elf)?;
+    let function = image
+        .functions
+        .iter()
+        .find(|symbol| symbol.name == name || image.demangler.matches(&symbol.name, name))
+        .ok_or_else(|| format!("no function named `{}` in {}", name, path.display()))?;
+    let section = &elf.sections[function.shndx as usize];
+    let start = (function.value - section.shdr.addr) as usize;
+    let bytes = section
+        .data
+        .get(start..start + function.size as usize)
+        .ok_or("the function isn't in its section's data")?;
+
+    let relocations = image.relocations(&elf, function.shndx as usize);
+    let names = image.names(&elf, function.shndx);
+    let notes = |instruction: &Instruction| {
+        let mut notes = Vec::new();
+        if let Some(target) = branch_target(instruction) {
+            notes.extend(describe(&names, target));
+        }
+        if instruction.is_ip_rel_memory_operand() {
+            let addr = instruction.ip_rel_memory_address();
+            match image.got.get(&addr) {
+                Some(symbol) => notes.push(format!("{}@got", symbol)),
+                None => notes.extend(describe(&names, addr)),
+            }
+        }
+        for (offset, relocation) in relocations.range(instruction.ip()..instruction.next_ip()) {
+            notes.push(format!("{} at 0x{:x}", relocation, offset));
+        }
+        notes
+    };
+
+    let code = disassembler.disassemble_noting(function.value, bytes, &notes)?;
+    Ok(format!(
+        "0x{:x} <{}>:\n{}",
+        function.value,
+        image.demangler.demangle(&function.name),
+        code
+    ))
+}
+
+// The symbols and dynamic relocations of a binary, read once.
+struct Image {
+    functions: Vec<elf::types::Symbol>,
+    symbols: Vec<elf::types::Symbol>,
+    dynamic: Vec<elf::types::Symbol>,
+    // GOT slots by the dynamic symbol the linker fills them in with.
+    got: HashMap<u64, String>,
+    demangler: Demangler,
+}
+
+impl Image {
+    fn read(elf: &elf::File) -> Result<Self> {
+        let symbols = match elf.get_section(".symtab") {
+            Some(section) => elf.get_symbols(section)?,
+            None => Vec::new(),
+        };
+        let dynamic = match elf.get_section(".dynsym") {
+            Some(section) => elf.get_symbols(section)?,
+            None => Vec::new(),
+        };
+        let functions = symbols
+            .iter()
+            .chain(&dynamic)
+            .filter(|symbol| symbol.symtype == elf::types::STT_FUNC && symbol.size > 0)
+            .filter(|symbol| (symbol.shndx as usize) < elf.sections.len())
+            .cloned()
+            .collect();
+
+        let mut got = HashMap::new();
+        for section in &elf.sections {
+            let dynamic_relocations = section.shdr.shtype == elf::types::SHT_RELA
+                && elf
+                    .sections
+                    .get(section.shdr.link as usize)
+                    .is_some_and(|symbols| symbols.shdr.name == ".dynsym");
+            if !dynamic_relocations {
+                continue;
+            }
+            for (offset, kind, index, _) in rela_entries(&section.data) {
+                if kind != R_X86_64_GLOB_DAT && kind != R_X86_64_JUMP_SLOT {
+                    continue;
+                }
+                if let Some(symbol) = dynamic.get(index) {
+                    got.insert(offset, symbol.name.clone());
+                }
+            }
+        }
+        let mut demangler = Demangler::default();
+        demangler.add(
+            symbols
+                .iter()
+                .chain(&dynamic)
+                .map(|symbol| symbol.name.as_str()),
+        );
+        Ok(Image {
+            functions,
+            symbols,
+            dynamic,
+            got,
+            demangler,
+        })
+    }
+
+    /// What's at each address, the symbols in `section` for an object file,
+    /// where every section starts at 0, and all of them for anything linked,
+    /// along with the PLT entries.
+    fn names(&self, elf: &elf::File, section: u16) -> BTreeMap<u64, (u64, String)> {
+        let relocatable = elf.ehdr.elftype == elf::types::ET_REL;
+        let mut names = BTreeMap::new();
+        for symbol in self.symbols.iter().chain(&self.dynamic) {
+            let named =
+                symbol.symtype == elf::types::STT_FUNC || symbol.symtype == elf::types::STT_OBJECT;
+            let here = if relocatable {
+                symbol.shndx == section
+            } else {
+                symbol.shndx != 0
+            };
+            if named && here && !symbol.name.is_empty() {
+                names
+                    .entry(symbol.value)
+                    .or_insert_with(|| (symbol.size, self.demangler.demangle(&symbol.name)));
+            }
+        }
+        for (entry, name) in self.plt_entries(elf) {
+            names.insert(entry, (PLT_ENTRY_SIZE as u64, format!("{}@plt", name)));
+        }
+        names
+    }
+
+    // Each PLT entry that jumps through a GOT slot a dynamic relocation
+    // binds, by the symbol it binds it to. The slot is found from the
+    // entry's `jmp [rip+disp32]`, which is where the entry's code ends.
+    fn plt_entries(&self, elf: &elf::File) -> Vec<(u64, String)> {
+        let mut entries = Vec::new();
+        for section in [".plt", ".plt.sec", ".plt.got"] {
+            let section = match elf.get_section(section) {
+                Some(section) => section,
+                None => continue,
+            };
+            for (i, entry) in section.data.chunks_exact(PLT_ENTRY_SIZE).enumerate() {
+                let at = match entry.windows(2).position(|bytes| bytes == JMP_RIP) {
+                    Some(at) if at + 6 <= entry.len() => at,
+                    _ => continue,
+                };
+                let disp = u32_at(entry, at + 2) as i32 as i64;
+                let addr = section.shdr.addr + (i * PLT_ENTRY_SIZE) as u64;
+                let slot = (addr + (at + 6) as u64).wrapping_add(disp as u64);
+                if let Some(name) = self.got.get(&slot) {
+                    entries.push((addr, name.clone()));
+                }
+            }
+        }
+        entries
+    }
+
+    /// The relocations that patch the section numbered `target`, as what
+    /// they say by where they apply. Only object files have these.
+    fn relocations(&self, elf: &elf::File, target: usize) -> BTreeMap<u64, String> {
+        let mut relocations = BTreeMap::new();
+        for section in &elf.sections {
+            if section.shdr.shtype != elf::types::SHT_RELA || section.shdr.info as usize != target {
+                continue;
+            }
+            let symbols = match elf.sections.get(section.shdr.link as usize) {
+                Some(symtab) if symtab.shdr.name == ".symtab" => &self.symbols,
+                _ => &self.dynamic,
+            };
+            for (offset, kind, index, addend) in rela_entries(&section.data) {
+                let symbol = match symbols.get(index) {
+                    Some(symbol) if !symbol.name.is_empty() => {
+                        self.demangler.demangle(&symbol.name)
+                    }
+                    // Section symbols have no name, the section is what
+                    // they stand for.
+                    Some(symbol) => elf
+                        .sections
+                        .get(symbol.shndx as usize)
+                        .map_or_else(String::new, |section| section.shdr.name.clone()),
+                    None => String::new(),
+                };
+                let target = match addend {
+                    0 => symbol,
+                    addend if addend < 0 => format!("{}-0x{:x}", symbol, -addend),
+                    addend => format!("{}+0x{:x}", symbol, addend),
+                };
+                relocations.insert(offset, format!("{} {}", relocation_name(kind), target));
+            }
+        }
+        relocations
+    }
+}
+
+// Each Elf64_Rela in a relocation section as its offset, type, symbol
+// index and addend.
+fn rela_entries(data: &[u8]) -> impl Iterator<Item = (u64, u32, usize, i64)> + '_ {
+    data.chunks_exact(RELA_SIZE).map(|entry| {
+        let info = u64_at(entry, 8);
+        (
+            u64_at(entry, 0),
+            info as u32,
+            (info >> 32) as usize,
+            u64_at(entry, 16) as i64,
+        )
+    })
+}
+
+fn relocation_name(kind: u32) -> String {
+    match kind {
+        1 => "R_X86_64_64".to_owned(),
+        2 => "R_X86_64_PC32".to_owned(),
+        4 => "R_X86_64_PLT32".to_owned(),
+        9 => "R_X86_64_GOTPCREL".to_owned(),
+        10 => "R_X86_64_32".to_owned(),
+        11 => "R_X86_64_32S".to_owned(),
+        41 => "R_X86_64_GOTPCRELX".to_owned(),
+        42 => "R_X86_64_REX_GOTPCRELX".to_owned(),
+        kind => format!("relocation type {}", kind),
+    }
+}
+
+// `<name>` or `<name+0x10>` for an address inside something named.
+fn describe(names: &BTreeMap<u64, (u64, String)>, addr: u64) -> Option<String> {
+    let (start, (size, name)) = names.range(..=addr).next_back()?;
+    match addr - start {
+        0 => Some(format!("<{}>", name)),
+        offset if offset < *size => Some(format!("<{}+0x{:x}>", name, offset)),
+        _ => None,
+    }
+}
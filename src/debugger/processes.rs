@@ -0,0 +1,92 @@
+use crate::result::Result;
+use libc::pid_t;
+use std::fs;
+
+/// A process as /proc/<pid>/stat describes it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Process {
+    pub pid: pid_t,
+    pub ppid: pid_t,
+    pub name: String,
+    pub state: char,
+}
+
+impl Process {
+    pub fn state_name(&self) -> &'static str {
+        match self.state {
+            'R' => "running",
+            'S' => "sleeping",
+            'D' => "waiting on disk",
+            'Z' => "zombie",
+            'T' => "stopped",
+            't' => "tracing stop",
+            'X' => "dead",
+            'I' => "idle",
+            _ => "unknown",
+        }
+    }
+}
+
+/// `pid` and everything below it, depth first, each with its depth.
+pub fn tree(pid: pid_t) -> Result<Vec<(usize, Process)>> {
+    let processes = all()?;
+    let root = processes
+        .iter()
+        .find(|process| process.pid == pid)
+        .cloned()
+        .ok_or_else(|| format!("process {} not found in /proc", pid))?;
+
+    let mut tree = Vec::new();
+    let mut pending = vec![(0, root)];
+    while let Some((depth, process)) = pending.pop() {
+        let mut children: Vec<Process> = processes
+            .iter()
+            .filter(|child| child.ppid == process.pid)
+            .cloned()
+            .collect();
+        // Reversed so they come off the stack lowest pid first.
+        children.sort_by_key(|child| -child.pid);
+        pending.extend(children.into_iter().map(|child| (depth + 1, child)));
+        tree.push((depth, process));
+    }
+    Ok(tree)
+}
+
+fn all() -> Result<Vec<Process>> {
+    let mut processes = Vec::new();
+    for entry in fs::read_dir("/proc")? {
+        let entry = entry?;
+        let pid = match entry
+            .file_name()
+            .to_str()
+            .and_then(|name| name.parse().ok())
+        {
+            Some(pid) => pid,
+            None => continue,
+        };
+        // Processes can exit between listing and reading.
+        if let Ok(stat) = fs::read_to_string(entry.path().join("stat")) {
+            if let Some(process) = parse_stat(pid, &stat) {
+                processes.push(process);
+            }
+        }
+    }
+    Ok(processes)
+}
+
+// The name is in parentheses and can itself contain spaces and parentheses,
+// so the fields after it are found from the last `)`.
+fn parse_stat(pid: pid_t, stat: &str) -> Option<Process> {
+    let open = stat.find('(')?;
+    let close = stat.rfind(')')?;
+    let name = stat[open + 1..close].to_owned();
+    let mut fields = stat[close + 1..].split_whitespace();
+    let state = fields.next()?.chars().next()?;
+    let ppid = fields.next()?.parse().ok()?;
+    Some(Process {
+        pid,
+        ppid,
+        name,
+        state,
+    })
+}
@@ -1,5 +1,59 @@
 use libc::user_regs_struct;
 
+pub const CF: u64 = 1 << 0;
+pub const PF: u64 = 1 << 2;
+pub const AF: u64 = 1 << 4;
+pub const ZF: u64 = 1 << 6;
+pub const SF: u64 = 1 << 7;
+pub const TF: u64 = 1 << 8;
+pub const IF: u64 = 1 << 9;
+pub const DF: u64 = 1 << 10;
+pub const OF: u64 = 1 << 11;
+
+/// The rflags bits worth naming, lowest first.
+pub const FLAG_NAMES: &[(u64, &str)] = &[
+    (CF, "CF"),
+    (PF, "PF"),
+    (AF, "AF"),
+    (ZF, "ZF"),
+    (SF, "SF"),
+    (TF, "TF"),
+    (IF, "IF"),
+    (DF, "DF"),
+    (OF, "OF"),
+];
+
+/// The names of the flags set in `eflags`, e.g. `[ PF ZF IF ]`.
+pub fn decode_flags(eflags: u64) -> String {
+    let set: Vec<&str> = FLAG_NAMES
+        .iter()
+        .filter(|(bit, _)| eflags & bit != 0)
+        .map(|(_, name)| *name)
+        .collect();
+    format!("[ {} ]", set.join(" "))
+}
+
+/// The general purpose registers by their DWARF number, as the x86-64
+/// psABI orders them.
+pub const DWARF_NAMES: &[&str] = &[
+    "rax", "rdx", "rcx", "rbx", "rsi", "rdi", "rbp", "rsp", "r8", "r9", "r10", "r11", "r12", "r13",
+    "r14", "r15", "rip",
+];
+
+/// The registers a full register listing shows, in the order it shows
+/// them.
+pub const DISPLAY_NAMES: &[&str] = &[
+    "rip", "rsp", "rbp", "rax", "rbx", "rcx", "rdx", "rdi", "rsi", "r8", "r9", "r10", "r11", "r12",
+    "r13", "r14", "r15", "eflags", "cs", "ss", "ds", "es", "fs", "gs", "fs_base", "gs_base",
+];
+
+/// Register names in `user_regs_struct` order.
+pub const NAMES: &[&str] = &[
+    "r15", "r14", "r13", "r12", "rbp", "rbx", "r11", "r10", "r9", "r8", "rax", "rcx", "rdx", "rsi",
+    "rdi", "orig_rax", "rip", "cs", "eflags", "rsp", "ss", "fs_base", "gs_base", "ds", "es", "fs",
+    "gs",
+];
+
 #[derive(Clone, Default, Debug)]
 pub struct Registers {
     pub r15: u64,
@@ -100,6 +154,14 @@ impl From<Registers> for user_regs_struct {
 }
 
 impl Registers {
+    pub fn flag(&self, flag: u64) -> bool {
+        self.eflags & flag != 0
+    }
+
+    pub fn dwarf(&self, number: u16) -> Option<u64> {
+        self.get(DWARF_NAMES.get(number as usize)?)
+    }
+
     pub fn get(&self, name: &str) -> Option<u64> {
         match name {
             "r15" => Some(self.r15),
@@ -120,7 +182,7 @@ impl Registers {
             "orig_rax" => Some(self.orig_rax),
             "rip" => Some(self.rip),
             "cs" => Some(self.cs),
-            "eflags" => Some(self.eflags),
+            "eflags" | "rflags" => Some(self.eflags),
             "rsp" => Some(self.rsp),
             "ss" => Some(self.ss),
             "fs_base" => Some(self.fs_base),
@@ -132,4 +194,54 @@ impl Registers {
             _ => None,
         }
     }
+
+    /// Sets a register by name, returning false for an unknown name.
+    pub fn set(&mut self, name: &str, value: u64) -> bool {
+        match name {
+            "r15" => self.r15 = value,
+            "r14" => self.r14 = value,
+            "r13" => self.r13 = value,
+            "r12" => self.r12 = value,
+            "rbp" => self.rbp = value,
+            "rbx" => self.rbx = value,
+            "r11" => self.r11 = value,
+            "r10" => self.r10 = value,
+            "r9" => self.r9 = value,
+            "r8" => self.r8 = value,
+            "rax" => self.rax = value,
+            "rcx" => self.rcx = value,
+            "rdx" => self.rdx = value,
+            "rsi" => self.rsi = value,
+            "rdi" => self.rdi = value,
+            "orig_rax" => self.orig_rax = value,
+            "rip" => self.rip = value,
+            "cs" => self.cs = value,
+            "eflags" | "rflags" => self.eflags = value,
+            "rsp" => self.rsp = value,
+            "ss" => self.ss = value,
+            "fs_base" => self.fs_base = value,
+            "gs_base" => self.gs_base = value,
+            "ds" => self.ds = value,
+            "es" => self.es = value,
+            "fs" => self.fs = value,
+            "gs" => self.gs = value,
+            _ => return false,
+        }
+        true
+    }
+
+    /// The registers whose values differ, as (name, self, other).
+    pub fn diff(&self, other: &Registers) -> Vec<(&'static str, u64, u64)> {
+        NAMES
+            .iter()
+            .filter_map(|name| {
+                let (a, b) = (self.get(name)?, other.get(name)?);
+                if a == b {
+                    None
+                } else {
+                    Some((*name, a, b))
+                }
+            })
+            .collect()
+    }
 }
@@ -0,0 +1,55 @@
+use libc::c_int;
+use std::collections::HashMap;
+
+/// What to do when the subordinate gets a signal: whether to stop and
+/// hand control back, and whether the program gets the signal when it
+/// resumes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Policy {
+    pub stop: bool,
+    pub pass: bool,
+}
+
+/// `handle`'s table, per signal, over the defaults.
+#[derive(Debug, Clone, Default)]
+pub struct Signals {
+    policies: HashMap<c_int, Policy>,
+}
+
+impl Signals {
+    pub fn policy(&self, signal: c_int) -> Policy {
+        if let Some(policy) = self.policies.get(&signal) {
+            return *policy;
+        }
+        match signal {
+            // Routine signals programs rely on, which would make stopping
+            // at every one unbearable.
+            libc::SIGALRM
+            | libc::SIGCHLD
+            | libc::SIGURG
+            | libc::SIGWINCH
+            | libc::SIGPROF
+            | libc::SIGVTALRM
+            | libc::SIGIO => Policy {
+                stop: false,
+                pass: true,
+            },
+            // Ctrl-C is for the debugger, and a stop signal would only stop
+            // the process again.
+            libc::SIGINT | libc::SIGSTOP | libc::SIGTSTP | libc::SIGTTIN | libc::SIGTTOU => {
+                Policy {
+                    stop: true,
+                    pass: false,
+                }
+            }
+            _ => Policy {
+                stop: true,
+                pass: true,
+            },
+        }
+    }
+
+    pub fn set(&mut self, signal: c_int, policy: Policy) {
+        self.policies.insert(signal, policy);
+    }
+}
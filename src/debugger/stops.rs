@@ -0,0 +1,155 @@
+use crate::debugger::registers::NAMES;
+use crate::debugger::{Registers, Subordinate};
+use crate::result::Result;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Where a run stopped and its registers there, one line per stop in a
+/// recording, e.g. `r15=0x0 r14=0x0 ... gs=0x0 # 0x401126 <add>`.
+#[derive(Debug, Clone)]
+struct Stop {
+    place: String,
+    registers: Registers,
+}
+
+impl Stop {
+    fn take(subordinate: &Subordinate) -> Self {
+        let registers = subordinate.registers().clone();
+        Stop {
+            place: subordinate.describe(registers.rip),
+            registers,
+        }
+    }
+
+    fn line(&self) -> String {
+        let registers: Vec<String> = NAMES
+            .iter()
+            .map(|name| format!("{}=0x{:x}", name, self.registers.get(name).unwrap_or(0)))
+            .collect();
+        format!("{} # {}", registers.join(" "), self.place)
+    }
+
+    fn parse(line: &str) -> Result<Self> {
+        let (words, place) = line.split_once(" # ").unwrap_or((line, ""));
+        let mut registers = Registers::default();
+        for word in words.split_whitespace() {
+            let parsed = word.split_once('=').and_then(|(name, value)| {
+                let value = u64::from_str_radix(value.strip_prefix("0x")?, 16).ok()?;
+                Some(registers.set(name, value))
+            });
+            if parsed != Some(true) {
+                return Err(format!("bad register `{}`", word).into());
+            }
+        }
+        Ok(Stop {
+            place: place.to_owned(),
+            registers,
+        })
+    }
+}
+
+/// Writes each stop of a session to a file, for `StopDiff` to compare a
+/// later run against.
+pub struct StopRecorder {
+    file: BufWriter<File>,
+}
+
+impl StopRecorder {
+    pub fn create(path: &Path) -> Result<Self> {
+        Ok(StopRecorder {
+            file: BufWriter::new(File::create(path)?),
+        })
+    }
+
+    pub fn record(&mut self, subordinate: &Subordinate) -> Result<()> {
+        writeln!(self.file, "{}", Stop::take(subordinate).line())?;
+        self.file.flush()?;
+        Ok(())
+    }
+}
+
+/// Compares each stop of a session with the same stop of a recorded one,
+/// to find where two runs first part ways.
+pub struct StopDiff {
+    recorded: Vec<Stop>,
+    seen: usize,
+    diverged: bool,
+}
+
+impl StopDiff {
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+        let recorded = text
+            .lines()
+            .enumerate()
+            .map(|(n, line)| {
+                Stop::parse(line)
+                    .map_err(|e| format!("{} line {}: {}", path.display(), n + 1, e).into())
+            })
+            .collect::<Result<_>>()?;
+        Ok(StopDiff {
+            recorded,
+            seen: 0,
+            diverged: false,
+        })
+    }
+
+    /// Checks the stop the subordinate is at, describing how it differs
+    /// from the recording if this is the first one that does.
+    pub fn check(&mut self, subordinate: &Subordinate) -> Option<String> {
+        let now = Stop::take(subordinate);
+        self.seen += 1;
+        if self.diverged {
+            return None;
+        }
+        let n = self.seen;
+        let recorded = match self.recorded.get(n - 1) {
+            Some(recorded) => recorded,
+            None => {
+                self.diverged = true;
+                return Some(format!(
+                    "runs diverge at stop {}: the recorded run had stopped only {} times",
+                    n,
+                    self.recorded.len()
+                ));
+            }
+        };
+        if recorded.registers.rip != now.registers.rip {
+            self.diverged = true;
+            return Some(format!(
+                "runs diverge at stop {}: recorded at {}, now at {}",
+                n, recorded.place, now.place
+            ));
+        }
+        let changes: Vec<String> = recorded
+            .registers
+            .diff(&now.registers)
+            .into_iter()
+            .map(|(name, old, new)| format!("{} 0x{:x} recorded, 0x{:x} now", name, old, new))
+            .collect();
+        if changes.is_empty() {
+            return None;
+        }
+        self.diverged = true;
+        Some(format!(
+            "runs diverge at stop {} at {}: {}",
+            n,
+            now.place,
+            changes.join(", ")
+        ))
+    }
+
+    /// Once the session is over, says if the recorded run went on to stop
+    /// where this one didn't.
+    pub fn finish(&self) -> Option<String> {
+        if self.diverged || self.seen >= self.recorded.len() {
+            return None;
+        }
+        Some(format!(
+            "runs diverge after stop {}: the recorded run stopped {} times",
+            self.seen,
+            self.recorded.len()
+        ))
+    }
+}
@@ -1,44 +1,446 @@
 use crate::debugger::{
     auxv::{self, Entry::*},
-    DebugInfo, Registers,
+    backtrace, build_id,
+    coredump::Core,
+    demangle::Demangler,
+    formatters::Formatters,
+    hardware::{self, Access, HwBreakpoint},
+    launch::{FixedSyscalls, Launch},
+    link_map::{self, Library},
+    maps::{self, MapChange},
+    syscalls, Capabilities, DebugInfo, Disassembler, EventLog, Expr, FloatRegisters, Machine,
+    PendingSyscall, Region, Registers, Signals, TraceEvent, Tracepoint,
 };
 
+use crate::error::Error;
 use crate::result::Result;
-use crate::sys::{Fork::*, WaitStatus::*, *};
-use std::collections::HashMap;
-use std::fs::File;
+use crate::sys::{cgroup::Cgroup, Fork::*, WaitStatus::*, *};
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
 use elf;
 
+#[derive(Debug, Clone)]
+pub struct Breakpoint {
+    // The word that was at the address before the int3 went in, only its low
+    // byte is ever written back.
+    original: usize,
+    pub condition: Option<Expr>,
+    pub tracepoint: Option<Tracepoint>,
+    // Deleted the first time it stops the subordinate.
+    pub temporary: bool,
+    // Only there to notice a scoped watchpoint's frame returning, it never
+    // stops the subordinate itself.
+    pub watch_scope: bool,
+    // On the panic machinery for `catch panic`.
+    pub panic: bool,
+    // Where the dynamic linker reports loading and unloading libraries, it
+    // never stops the subordinate itself.
+    pub loader: bool,
+    // The number of the only thread it stops, the others run through it.
+    pub thread: Option<usize>,
+}
+
+/// How a single instruction is executed. Hardware uses the trap flag via
+/// PTRACE_SINGLESTEP, software plants int3 at every address the instruction
+/// can continue at, for kernels and emulators where single-stepping is flaky.
+/// Software stepping runs a rep-prefixed instruction to completion.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StepMode {
+    Hardware,
+    Software,
+}
+
+/// Which process debugging carries on in when the subordinate forks, the
+/// other being detached. Threads are never followed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FollowFork {
+    Parent,
+    Child,
+}
+
+/// A user-initiated change to the subordinate's state, holding what it
+/// overwrote.
+#[derive(Debug, Clone)]
+enum Change {
+    Registers(Vec<(&'static str, u64)>),
+    // Bytes written at an address, with what was there before.
+    Memory(usize, Vec<u8>),
+}
+
+/// What one single step overwrote, for `reverse_step` to put back.
+#[derive(Debug, Clone)]
+struct Recorded {
+    // The registers it changed, with their values before.
+    registers: Vec<(&'static str, u64)>,
+    // Memory it wrote, with what was there before.
+    memory: Vec<(usize, Vec<u8>)>,
+}
+
+/// The instructions stepped since recording started, oldest first.
+#[derive(Debug, Clone, Default)]
+struct Recording {
+    steps: VecDeque<Recorded>,
+    // The registers the last step left behind. Running any other way moves
+    // on from them, and what was recorded can't be stepped back over.
+    after: Option<Registers>,
+}
+
+/// A watchpoint on a stack local, deleted when the frame it lives in returns
+/// to `return_addr` with rsp back at `cfa`. Comparing rsp tells the frame
+/// apart from recursive calls that return to the same place.
+#[derive(Debug, Clone, Copy)]
+struct WatchScope {
+    addr: usize,
+    access: Access,
+    return_addr: usize,
+    cfa: u64,
+}
+
+/// A watchpoint that stopped the subordinate, with the watched value from
+/// before and after the access.
+#[derive(Debug, Clone, Copy)]
+pub struct WatchHit {
+    pub watchpoint: HwBreakpoint,
+    pub old: u64,
+    pub new: u64,
+}
+
+/// A traced thread of the subordinate, numbered from 1 in the order they
+/// started.
+#[derive(Debug, Clone)]
+pub struct Thread {
+    pub number: usize,
+    pub tid: i32,
+    // A signal it stopped for while it was being stopped for another
+    // thread's sake, delivered when it resumes.
+    pending_signal: i32,
+}
+
+/// A copy of the process forked off by `checkpoint`, kept stopped to go
+/// back to. Only the thread that was current is in it.
+pub struct Checkpoint {
+    pub number: usize,
+    pub pid: i32,
+    pub registers: Registers,
+    // The breakpoints as they were, as they're in its memory.
+    breakpoints: HashMap<usize, Breakpoint>,
+    lifted: Option<usize>,
+}
+
 pub struct Subordinate {
     pid: i32,
+    // The thread commands act on, `pid` being the process's first one.
+    tid: i32,
+    threads: Vec<Thread>,
+    // Whether the last resume let threads other than `tid` run, which have
+    // to be stopped again once it stops.
+    others_running: bool,
+    cmd: Vec<String>,
+    // How far a PIE was moved from its link-time addresses.
+    load_bias: u64,
+    // Which frame of the backtrace `frame`/`up`/`down` picked, 0 being
+    // where the process stopped.
+    selected_frame: usize,
+    path: PathBuf,
     registers: Registers,
     stack: Vec<usize>,
     wait_status: WaitStatus,
-    breakpoints: HashMap<usize, usize>,
+    breakpoints: HashMap<usize, Breakpoint>,
     debug_info: DebugInfo,
     auxv: Vec<auxv::Entry>,
     symbols: Vec<elf::types::Symbol>,
+    // The shared objects from the dynamic linker's list, as of the last
+    // `update_libraries`.
+    libraries: Vec<Library>,
+    // Symbols to break on once a library defining them loads.
+    pending: Vec<String>,
+    demangler: Demangler,
+    capabilities: Capabilities,
+    fast_reads: Cell<bool>,
+    detached: bool,
+    // Breakpoint at rip whose original byte is back in place so the
+    // instruction can run, it gets re-armed on the next step or cont.
+    lifted: Option<usize>,
+    trace_events: Vec<TraceEvent>,
+    // What's in DR0-DR3, and the slot that stopped us on its instruction
+    // and must be disabled for a step to get past it.
+    hw_breakpoints: [Option<HwBreakpoint>; hardware::SLOTS],
+    hw_lifted: Option<usize>,
+    watch_hit: Option<WatchHit>,
+    watch_scopes: Vec<WatchScope>,
+    // Watched addresses whose scope ended at the last stop.
+    left_scope: Vec<usize>,
+    step_mode: StepMode,
+    journal: Vec<Change>,
+    events: EventLog,
+    // Traced syscall numbers, each with the condition a call must meet to
+    // be recorded.
+    traced_syscalls: HashMap<u64, Option<Expr>>,
+    // Set between the entry and exit stops of any syscall while syscall
+    // tracing is on, holding the call when it's one being traced.
+    in_syscall: Option<Option<PendingSyscall>>,
+    // Syscalls `cont` stops after, those it stops at the entry of, and the
+    // last one it stopped for.
+    caught_syscalls: HashSet<u64>,
+    caught_entries: HashSet<u64>,
+    caught: Option<String>,
+    // Parts of library file names `catch load` stops for.
+    caught_loads: Vec<String>,
+    // The memory map as of the last `map_changes`.
+    known_maps: Option<Vec<Region>>,
+    // How deep the stack may get before `cont` stops, checked by stopping
+    // the process every so often to look at rsp.
+    stack_limit: Option<u64>,
+    // A SIGSTOP sent to sample rsp that hasn't been seen yet.
+    sample_pending: bool,
+    // How deep the stack was when it passed the limit, if that's why `cont`
+    // stopped.
+    stack_exceeded: Option<u64>,
+    // Show symbols as the linker sees them rather than demangled.
+    print_mangled: bool,
+    formatters: Formatters,
+    launch: Launch,
+    // Where getrandom and clock results come from when the launch fixes
+    // them.
+    fixed_syscalls: Option<FixedSyscalls>,
+    signals: Signals,
+    // The signal the process gets when it next resumes, 0 for none.
+    pending_signal: i32,
+    follow_fork: FollowFork,
+    // How the process was last resumed, to carry on the same way past a
+    // fork.
+    resumed_with: fn(i32, i32) -> Result<()>,
+    // New processes whose first stop was reported before the fork that made
+    // them.
+    early_stops: Vec<i32>,
+    // Where a memory-limited launch runs, only held so dropping it removes
+    // the cgroup.
+    _cgroup: Option<Cgroup>,
+    // Where the launch couldn't be set up quite as asked.
+    launch_warnings: Vec<String>,
+    // What memory and registers come from instead of a live process when
+    // looking at a core dump.
+    core: Option<Core>,
+    checkpoints: Vec<Checkpoint>,
+    recording: Option<Recording>,
+}
+
+impl Drop for Subordinate {
+    // Left behind, checkpoints would carry on running once the debugger
+    // exits.
+    fn drop(&mut self) {
+        for checkpoint in &self.checkpoints {
+            let _ = kill(checkpoint.pid, libc::SIGKILL);
+            let _ = wait_for(checkpoint.pid);
+        }
+    }
+}
+
+/// A `timespec` or `timeval`, both being two 64-bit fields.
+fn timespec(seconds: u64, fraction: u64) -> Vec<u8> {
+    let mut bytes = seconds.to_le_bytes().to_vec();
+    bytes.extend_from_slice(&fraction.to_le_bytes());
+    bytes
+}
+
+/// Makes the stopped thread `tid` fork by running a fork syscall in place
+/// of the instruction at rip, then puts both it and the child back as they
+/// were. The child is traced and stays stopped.
+fn fork_stopped(tid: i32, registers: &Registers) -> Result<i32> {
+    let rip = registers.rip as usize;
+    let word = ptrace::peek(tid, rip)?;
+    ptrace::poke(tid, rip, (word & !0xffff) | SYSCALL_INSN)?;
+    let mut call = registers.clone();
+    call.rax = libc::SYS_fork as u64;
+    ptrace::setregs(tid, &call.into())?;
+    ptrace::singlestep(tid, 0)?;
+
+    // The fork event comes before the syscall returns. A checkpoint gets
+    // SIGCHLD when a process restored from it exits, which is let go of.
+    let child = loop {
+        match wait_for(tid)? {
+            Event(_, libc::PTRACE_EVENT_FORK) => {
+                let child = ptrace::geteventmsg(tid)? as i32;
+                wait_for(child)?;
+                ptrace::singlestep(tid, 0)?;
+                wait_for(tid)?;
+                break Some(child);
+            }
+            Stopped(_, libc::SIGCHLD) => ptrace::singlestep(tid, 0)?,
+            _ => break None,
+        }
+    };
+    let failed = ptrace::getregs(tid)?.rax;
+    ptrace::poke(tid, rip, word)?;
+    ptrace::setregs(tid, &registers.clone().into())?;
+
+    let child = child.ok_or_else(|| match syscalls::errno(failed) {
+        Some(errno) => format!(
+            "couldn't fork: {}",
+            std::io::Error::from_raw_os_error(errno)
+        ),
+        None => "couldn't fork".to_owned(),
+    })?;
+    ptrace::poke(child, rip, word)?;
+    ptrace::setregs(child, &registers.clone().into())?;
+    Ok(child)
 }
 
+// With PTRACE_O_TRACESYSGOOD syscall stops report SIGTRAP with bit 7 set.
+const SYSCALL_STOP: i32 = libc::SIGTRAP | 0x80;
+const INT3: u8 = 0xcc;
+const SYSCALL_INSN: usize = 0x050f;
+const SHN_UNDEF: u16 = 0;
+const SHN_ABS: u16 = 0xfff1;
+const PAGE_SIZE: u64 = 4096;
+// How many steps a recording keeps, dropping the oldest past that.
+const RECORD_LIMIT: usize = 100_000;
+const STACK_SAMPLE_INTERVAL: Duration = Duration::from_millis(10);
+const WAIT_POLL_INTERVAL: Duration = Duration::from_millis(1);
+
 impl Subordinate {
-    pub fn spawn(cmd: Vec<String>) -> Result<Self> {
+    /// Starts `cmd` under ptrace, set up per `launch` before exec.
+    pub fn spawn(cmd: Vec<String>, launch: Launch) -> Result<Self> {
         if cmd.len() == 0 {
             return Err("empty command given".into());
         }
 
+        let capabilities = Capabilities::detect();
+        if capabilities.ptrace_disabled() {
+            return Err("ptrace is disabled on this system (kernel.yama.ptrace_scope = 3)".into());
+        }
+
         info!("spawning with cmd: {:?}", cmd);
+        let elf = elf::File::open_path(&cmd[0])?;
+        Machine::from_elf(elf.ehdr.machine.0).check("program")?;
+
+        // Without a cgroup to use, the address space limit is the nearest
+        // thing.
+        let mut notes = Vec::new();
+        let (cgroup, applied) = match launch.memory_limit.map(Cgroup::create) {
+            Some(Ok(cgroup)) => (Some(cgroup), launch.clone()),
+            Some(Err(e)) => {
+                notes.push(format!(
+                    "no memory cgroup ({}), limiting address space instead",
+                    e
+                ));
+                let mut applied = launch.clone();
+                applied.set_rlimit("as", launch.memory_limit)?;
+                (None, applied)
+            }
+            None => (None, launch.clone()),
+        };
 
         let pid = match fork()? {
             Parent(child_pid) => child_pid,
             Child => {
+                if let Some(cgroup) = &cgroup {
+                    cgroup.join()?;
+                }
+                applied.apply()?;
                 ptrace::traceme()?;
                 execvp(&cmd)?;
                 0
             }
         };
 
+        let mut subordinate = Subordinate::load(pid, cmd.clone(), launch, capabilities, &elf)?;
+        subordinate._cgroup = cgroup;
+
+        subordinate
+            .events
+            .record(format!("started process {}: {}", pid, cmd.join(" ")));
+        for note in &notes {
+            subordinate.events.record(note.clone());
+        }
+        subordinate.launch_warnings = notes;
+        subordinate.fetch_state()?;
+        ptrace::setoptions(
+            pid,
+            libc::PTRACE_O_TRACESYSGOOD
+                | libc::PTRACE_O_TRACEFORK
+                | libc::PTRACE_O_TRACEVFORK
+                | libc::PTRACE_O_TRACECLONE,
+        )?;
+
+        let auxv = auxv::read(&subordinate)?;
+        subordinate.set_auxv(auxv, elf.ehdr.entry);
+        if let Some(fixed) = &mut subordinate.fixed_syscalls {
+            // Without the vDSO the clock is read through real syscalls, and
+            // AT_RANDOM seeds the stack protector and pointer guard.
+            let random = fixed.random_bytes(auxv::RANDOM_LEN);
+            auxv::fix(&subordinate, &random)?;
+        }
+
+        Ok(subordinate)
+    }
+
+    /// Opens a core dump of `cmd`, for looking around in without being able
+    /// to run anything.
+    pub fn open_core(cmd: Vec<String>, path: &Path) -> Result<Self> {
+        if cmd.is_empty() {
+            return Err("no program given for the core file".into());
+        }
+        let elf = elf::File::open_path(&cmd[0])?;
+        Machine::from_elf(elf.ehdr.machine.0).check("program")?;
+        let mut core = Core::open(path)?;
+        let pid = core.pid();
+        let auxv = core.take_auxv();
+
+        let mut subordinate =
+            Subordinate::load(pid, cmd, Launch::default(), Capabilities::detect(), &elf)?;
+        subordinate.wait_status = Stopped(pid, core.signal());
+        subordinate.threads = core
+            .threads()
+            .enumerate()
+            .map(|(i, tid)| Thread {
+                number: i + 1,
+                tid,
+                pending_signal: 0,
+            })
+            .collect();
+        subordinate.core = Some(core);
+        subordinate.refresh()?;
+        subordinate.set_auxv(auxv, elf.ehdr.entry);
+        subordinate.bias_from_maps(&elf)?;
+        subordinate.events.record(format!(
+            "opened core file {} of process {}",
+            path.display(),
+            pid
+        ));
+        Ok(subordinate)
+    }
+
+    /// Reads `cmd`'s binary for looking at without running it, until `run`
+    /// starts it. Addresses are the ones in the file, which for a PIE are
+    /// before it's moved to wherever it's loaded. `launch` is for when it
+    /// runs.
+    pub fn inspect(cmd: Vec<String>, launch: Launch) -> Result<Self> {
+        if cmd.is_empty() {
+            return Err("no program given to inspect".into());
+        }
         let elf = elf::File::open_path(&cmd[0])?;
+        Machine::from_elf(elf.ehdr.machine.0).check("program")?;
+        let mut subordinate = Subordinate::load(0, cmd, launch, Capabilities::detect(), &elf)?;
+        subordinate.events.record(format!(
+            "inspecting {} without running it",
+            subordinate.path.display()
+        ));
+        Ok(subordinate)
+    }
+
+    /// Reads the symbols and debug info of `cmd`, whose process is `pid`.
+    fn load(
+        pid: i32,
+        cmd: Vec<String>,
+        launch: Launch,
+        capabilities: Capabilities,
+        elf: &elf::File,
+    ) -> Result<Self> {
         let debug_info = DebugInfo::new(File::open(&cmd[0])?)?;
 
         let mut symbols: Vec<elf::types::Symbol> = Vec::new();
@@ -46,8 +448,16 @@ impl Subordinate {
             symbols = elf.get_symbols(section)?;
         }
 
-        let mut subordinate = Subordinate {
+        let path = fs::canonicalize(&cmd[0]).unwrap_or_else(|_| PathBuf::from(&cmd[0]));
+        let mut demangler = Demangler::default();
+        demangler.add(symbols.iter().map(|symbol| symbol.name.as_str()));
+
+        Ok(Subordinate {
             pid,
+            cmd: cmd.clone(),
+            load_bias: 0,
+            selected_frame: 0,
+            path,
             wait_status: WaitStatus::Unknwon(0, 0),
             registers: Registers::default(),
             stack: Vec::new(),
@@ -55,61 +465,385 @@ impl Subordinate {
             debug_info,
             auxv: Vec::new(),
             symbols,
-        };
-
-        subordinate.fetch_state()?;
+            libraries: Vec::new(),
+            pending: Vec::new(),
+            demangler,
+            fast_reads: Cell::new(capabilities.process_vm_readv),
+            detached: false,
+            lifted: None,
+            trace_events: Vec::new(),
+            hw_breakpoints: [None; hardware::SLOTS],
+            hw_lifted: None,
+            watch_hit: None,
+            watch_scopes: Vec::new(),
+            left_scope: Vec::new(),
+            step_mode: StepMode::Hardware,
+            journal: Vec::new(),
+            events: EventLog::new(),
+            traced_syscalls: HashMap::new(),
+            in_syscall: None,
+            caught_syscalls: HashSet::new(),
+            caught_entries: HashSet::new(),
+            caught: None,
+            caught_loads: Vec::new(),
+            known_maps: None,
+            stack_limit: None,
+            sample_pending: false,
+            stack_exceeded: None,
+            print_mangled: false,
+            formatters: Formatters::default(),
+            fixed_syscalls: launch.fixed_syscalls.then(FixedSyscalls::default),
+            launch,
+            signals: Signals::default(),
+            pending_signal: 0,
+            tid: pid,
+            threads: vec![Thread {
+                number: 1,
+                tid: pid,
+                pending_signal: 0,
+            }],
+            others_running: false,
+            follow_fork: FollowFork::Parent,
+            resumed_with: ptrace::cont,
+            early_stops: Vec::new(),
+            _cgroup: None,
+            launch_warnings: Vec::new(),
+            core: None,
+            checkpoints: Vec::new(),
+            recording: None,
+            capabilities,
+        })
+    }
 
-        let auxv = auxv::read(&subordinate)?;
+    /// Takes in the auxv, which says where a PIE was loaded.
+    fn set_auxv(&mut self, auxv: Vec<auxv::Entry>, link_entry: u64) {
         for entry in &auxv {
             match entry {
                 EntryAddr(addr) => {
-                    let amount = *addr as u64 - elf.ehdr.entry;
-                    subordinate.shift_symbols(amount);
+                    let amount = *addr as u64 - link_entry;
+                    self.shift_symbols(amount);
+                    self.load_bias = amount;
                     break;
                 }
                 _ => {}
             }
         }
-
-        subordinate.auxv = auxv;
-
-        Ok(subordinate)
+        self.auxv = auxv;
     }
 
     pub fn step(&mut self) -> Result<()> {
-        ptrace::singlestep(self.pid)?;
-        self.fetch_state()?;
+        if self.recording.is_none() {
+            return self.step_unrecorded();
+        }
+        let before = self.registers.clone();
+        let mut memory = Vec::new();
+        for (addr, len) in Disassembler::new().memory_writes(self)? {
+            let addr = addr as usize;
+            memory.push((addr, self.original_bytes(addr, len)?));
+        }
+        self.step_unrecorded()?;
+        if !self.is_running() {
+            return Ok(());
+        }
+
+        let recording = self.recording.as_mut().unwrap();
+        if recording
+            .after
+            .as_ref()
+            .map(|after| after.diff(&before).is_empty())
+            == Some(false)
+        {
+            recording.steps.clear();
+        }
+        if recording.steps.len() == RECORD_LIMIT {
+            recording.steps.pop_front();
+        }
+        recording.steps.push_back(Recorded {
+            registers: before
+                .diff(&self.registers)
+                .into_iter()
+                .map(|(name, old, _)| (name, old))
+                .collect(),
+            memory,
+        });
+        recording.after = Some(self.registers.clone());
         Ok(())
     }
 
+    fn step_unrecorded(&mut self) -> Result<()> {
+        if self.hw_lifted.is_some() {
+            return self.step_past_hw_breakpoint();
+        }
+        if self.lifted.is_some() {
+            return self.step_past_breakpoint();
+        }
+        self.single_step()?;
+        self.handle_stop()?;
+        self.remove_if_temporary()
+    }
+
+    pub fn set_step_mode(&mut self, mode: StepMode) {
+        self.step_mode = mode;
+    }
+
+    pub fn set_follow_fork(&mut self, mode: FollowFork) {
+        self.follow_fork = mode;
+    }
+
     pub fn cont(&mut self) -> Result<()> {
-        ptrace::cont(self.pid)?;
+        loop {
+            if self.hw_lifted.is_some() {
+                self.step_past_hw_breakpoint()?;
+                if self.stop_signal() != Some(libc::SIGTRAP) {
+                    return Ok(());
+                }
+            }
+            if let Some(addr) = self.lifted {
+                // A rep string instruction starts over at its own address
+                // after each repetition, it's only past once rip moves on.
+                let repeated = Disassembler::new().is_repeated_string(self)?;
+                loop {
+                    self.step_past_breakpoint()?;
+                    if self.stop_signal() != Some(libc::SIGTRAP) {
+                        return Ok(());
+                    }
+                    if !repeated || self.registers.rip as usize != addr {
+                        break;
+                    }
+                    if let Some(original) = self.breakpoints.get(&addr).map(|b| b.original) {
+                        self.restore_byte(addr, original)?;
+                    }
+                    self.lifted = Some(addr);
+                }
+            }
+            self.resume()?;
+            self.wait_sampling_stack()?;
+            if self.stack_exceeded.is_some() {
+                return Ok(());
+            }
+            if self.stop_signal() == Some(SYSCALL_STOP) {
+                self.record_syscall();
+                if self.caught.is_some() {
+                    return Ok(());
+                }
+                continue;
+            }
+            match self.stop_signal() {
+                Some(libc::SIGTRAP) | None => {}
+                Some(signal) if !self.signals.policy(signal).stop => continue,
+                Some(_) => return Ok(()),
+            }
+
+            let lifted = self.lifted;
+            if let Some(addr) = lifted {
+                if self.end_watch_scopes(addr)? {
+                    return Ok(());
+                }
+                if self.breakpoints.get(&addr).is_some_and(|b| b.watch_scope) {
+                    continue;
+                }
+                if self.breakpoints.get(&addr).is_some_and(|b| b.loader) {
+                    let loaded = self.update_libraries()?;
+                    if let Some(path) = loaded.iter().find(|path| self.catches_load(path)) {
+                        let description = format!("load of {}", path.display());
+                        self.events.record(format!("caught {}", description));
+                        self.caught = Some(description);
+                        return Ok(());
+                    }
+                    continue;
+                }
+            }
+            match lifted {
+                Some(addr) if !self.condition_holds(addr) => continue,
+                Some(addr) if self.record_trace(addr) => continue,
+                _ => return self.remove_if_temporary(),
+            }
+        }
+    }
+
+    /// Runs to the next syscall entry or exit and describes the call
+    /// strace-style, ending in `...` at the entry and with its result at the
+    /// exit. Returns None if the process stopped for anything else first.
+    pub fn syscall_step(&mut self) -> Result<Option<String>> {
+        if self.hw_lifted.is_some() {
+            self.step_past_hw_breakpoint()?;
+            if self.stop_signal() != Some(libc::SIGTRAP) {
+                return Ok(None);
+            }
+        }
+        if self.lifted.is_some() {
+            self.step_past_breakpoint()?;
+            if self.stop_signal() != Some(libc::SIGTRAP) {
+                return Ok(None);
+            }
+        }
+        self.resume_with(ptrace::syscall)?;
         self.fetch_state()?;
+        if self.stop_signal() != Some(SYSCALL_STOP) {
+            self.remove_if_temporary()?;
+            return Ok(None);
+        }
+
+        let entering = self.in_syscall.is_none();
+        self.record_syscall();
+        // The arguments are still in their registers at the exit, and
+        // buffers the call filled in are worth seeing there.
+        let regs = &self.registers;
+        let call = syscalls::format_call(self, regs.orig_rax, &syscalls::arguments(regs));
+        if entering {
+            Ok(Some(format!("{} ...", call)))
+        } else {
+            let result = syscalls::format_return(regs.orig_rax, regs.rax);
+            Ok(Some(format!("{} = {}", call, result)))
+        }
+    }
+
+    /// Takes out every breakpoint and lets the subordinate run on untraced.
+    pub fn detach(&mut self) -> Result<()> {
+        self.ensure_live()?;
+        let breakpoints: Vec<(usize, Breakpoint)> = self.breakpoints.drain().collect();
+        for (addr, breakpoint) in breakpoints {
+            self.restore_byte(addr, breakpoint.original)?;
+        }
+        if self.hw_breakpoints.iter().any(Option::is_some) {
+            self.set_debug_register(hardware::DR7, 0)?;
+        }
+        for thread in &self.threads {
+            ptrace::detach(thread.tid)?;
+        }
+        self.detached = true;
+        self.events
+            .record(format!("detached from process {}", self.pid));
         Ok(())
     }
 
+    pub fn is_detached(&self) -> bool {
+        self.detached
+    }
+
+    pub fn pid(&self) -> i32 {
+        self.pid
+    }
+
     pub fn peek(&self, addr: usize) -> Result<usize> {
-        ptrace::peek(self.pid, addr)
+        if let Some(core) = &self.core {
+            let mut word = [0u8; 8];
+            word.copy_from_slice(&core.read(addr, 8)?);
+            return Ok(usize::from_le_bytes(word));
+        }
+        ptrace::peek(self.tid, addr)
     }
 
     pub fn poke(&self, addr: usize, data: usize) -> Result<()> {
-        ptrace::poke(self.pid, addr, data)
+        self.ensure_live()?;
+        ptrace::poke(self.tid, addr, data)
+    }
+
+    /// Writes `bytes` so `undo` can put back what was there. Breakpoints in
+    /// the range stay armed, taking the new bytes as their originals.
+    pub fn write_bytes(&mut self, addr: usize, bytes: &[u8]) -> Result<()> {
+        let old = self.patch(addr, bytes)?;
+        self.journal.push(Change::Memory(addr, old));
+        Ok(())
+    }
+
+    /// Writes `bytes` around any breakpoints, returning the bytes the
+    /// program saw there before.
+    fn patch(&mut self, addr: usize, bytes: &[u8]) -> Result<Vec<u8>> {
+        let mut old = self.read_bytes(addr, bytes.len())?;
+        let mut memory = bytes.to_vec();
+        let lifted = self.lifted;
+        for (i, byte) in bytes.iter().enumerate() {
+            if let Some(breakpoint) = self.breakpoints.get_mut(&(addr + i)) {
+                old[i] = breakpoint.original as u8;
+                breakpoint.original = breakpoint.original & !0xff | *byte as usize;
+                if lifted != Some(addr + i) {
+                    memory[i] = INT3;
+                }
+            }
+        }
+        self.store(addr, &memory)?;
+        Ok(old)
+    }
+
+    /// Writes `bytes` a word at a time, keeping whatever else shares the
+    /// first and last words.
+    fn store(&self, addr: usize, bytes: &[u8]) -> Result<()> {
+        let word = std::mem::size_of::<usize>();
+        let start = addr - addr % word;
+        let mut at = start;
+        while at < addr + bytes.len() {
+            let mut data = self.peek(at)?.to_le_bytes();
+            for (i, byte) in data.iter_mut().enumerate() {
+                let target = at + i;
+                if target >= addr && target < addr + bytes.len() {
+                    *byte = bytes[target - addr];
+                }
+            }
+            self.poke(at, usize::from_le_bytes(data))?;
+            at += word;
+        }
+        Ok(())
+    }
+
+    /// Whether memory is read with process_vm_readv rather than a word at a
+    /// time with ptrace peeks, which is all WSL1 can do.
+    pub fn set_fast_reads(&self, on: bool) {
+        self.fast_reads.set(on);
     }
 
     pub fn read_bytes(&self, from: usize, size: usize) -> Result<Vec<u8>> {
-        let mut bytes = Vec::with_capacity(size);
-        let wordlen = std::mem::size_of::<usize>();
-        for i in 0..(size / wordlen) + 1 {
-            for byte in self.peek(from + wordlen * i)?.to_ne_bytes().iter() {
-                bytes.push(*byte);
-                if bytes.len() == size {
-                    break;
+        if let Some(core) = &self.core {
+            return core.read(from, size);
+        }
+        if self.fast_reads.get() {
+            match process_vm_readv(self.tid, from, size) {
+                Ok(bytes) if bytes.len() == size => return Ok(bytes),
+                Ok(_) => {}
+                Err(Error::Errno(errno)) if errno == libc::ENOSYS || errno == libc::EPERM => {
+                    warn!("process_vm_readv unavailable, falling back to ptrace peeks");
+                    self.fast_reads.set(false);
                 }
+                Err(_) => {}
             }
         }
+
+        let end = from
+            .checked_add(size)
+            .ok_or("read past the end of the address space")?;
+        // Aligned words, so that none reaches past the last byte wanted into
+        // memory that may not be mapped.
+        let wordlen = std::mem::size_of::<usize>();
+        let start = from - from % wordlen;
+        let mut bytes = Vec::with_capacity(size + 2 * wordlen);
+        let mut addr = start;
+        while addr < end {
+            bytes.extend_from_slice(&self.peek(addr)?.to_ne_bytes());
+            addr = addr.saturating_add(wordlen);
+        }
+        bytes.drain(..from - start);
+        bytes.truncate(size);
         Ok(bytes)
     }
 
+    /// Reads a NUL-terminated string of at most `max` bytes.
+    pub fn read_string(&self, from: usize, max: usize) -> Result<String> {
+        let wordlen = std::mem::size_of::<usize>();
+        let mut bytes = Vec::new();
+        let mut addr = from;
+        while bytes.len() < max {
+            let word = self.peek(addr)?.to_ne_bytes();
+            for byte in word.iter() {
+                if *byte == 0 || bytes.len() == max {
+                    return Ok(String::from_utf8_lossy(&bytes).to_string());
+                }
+                bytes.push(*byte);
+            }
+            addr += wordlen;
+        }
+        Ok(String::from_utf8_lossy(&bytes).to_string())
+    }
+
     pub fn read_words(&self, from: usize, size: usize) -> Result<Vec<usize>> {
         let mut words = Vec::with_capacity(size);
         let wordlen = std::mem::size_of::<usize>();
@@ -126,74 +860,1919 @@ impl Subordinate {
         None
     }
 
-    pub fn breakpoint(&mut self, addr: usize) -> Result<()> {
-        if let Some(_) = self.breakpoints.get(&addr) {
-            return Ok(());
+    /// The signal that killed the subordinate, if one did.
+    pub fn term_signal(&self) -> Option<i32> {
+        if let Signaled(_, signal) = self.wait_status {
+            return Some(signal);
         }
+        None
+    }
 
-        let data = self.peek(addr)?;
-        self.poke(addr, data & (usize::max_value() - 255) | 0xcc)?;
-        self.breakpoints.insert(addr, data);
+    /// Whether there's a live, attached process to debug.
+    pub fn is_running(&self) -> bool {
+        !self.detached && matches!(self.wait_status, Stopped(_, _))
+    }
+
+    /// Kills the process if it's still around and starts the same command
+    /// again. Breakpoints, watchpoints and syscall tracing carry over, and
+    /// ones set inside a symbol follow it in case the binary was rebuilt.
+    pub fn restart(&mut self) -> Result<()> {
+        self.kill()?;
+        let mut next = Subordinate::spawn(self.cmd.clone(), self.launch.clone())?;
+
+        let mut breakpoints: Vec<(&usize, &Breakpoint)> = self.breakpoints.iter().collect();
+        breakpoints.sort_by_key(|(addr, _)| **addr);
+        // Scoped watchpoints are on stack slots of frames that are gone.
+        let breakpoints = breakpoints
+            .into_iter()
+            .filter(|(_, b)| !b.watch_scope && !b.loader);
+        for (addr, breakpoint) in breakpoints {
+            // Libraries aren't loaded yet in the new process, so breakpoints
+            // on their functions wait for them again.
+            if let Some(name) = self.library_function(*addr) {
+                next.pending.push(name);
+                continue;
+            }
+            let addr = self.relocate(*addr, &next);
+            next.insert_breakpoint(addr, breakpoint.condition.clone())?;
+            if let Some(inserted) = next.breakpoints.get_mut(&addr) {
+                inserted.tracepoint = breakpoint.tracepoint.clone();
+                inserted.temporary = breakpoint.temporary;
+                inserted.panic = breakpoint.panic;
+                inserted.thread = breakpoint.thread;
+            }
+        }
+        let scoped = |hw: &&HwBreakpoint| self.watch_scopes.iter().any(|s| s.addr == hw.addr);
+        for hw in self
+            .hw_breakpoints
+            .iter()
+            .flatten()
+            .filter(|hw| !scoped(hw))
+        {
+            let addr = self.relocate(hw.addr, &next);
+            match hw.access {
+                Access::Execute => next.hardware_breakpoint(addr)?,
+                access => next.watchpoint(addr, access)?,
+            }
+        }
+        next.pending.extend(self.pending.iter().cloned());
+        next.caught_loads = self.caught_loads.clone();
+        if !next.pending.is_empty() || !next.caught_loads.is_empty() {
+            next.watch_loader()?;
+        }
+        next.traced_syscalls = self.traced_syscalls.clone();
+        next.caught_syscalls = self.caught_syscalls.clone();
+        next.caught_entries = self.caught_entries.clone();
+        next.stack_limit = self.stack_limit;
+        next.print_mangled = self.print_mangled;
+        next.signals = std::mem::take(&mut self.signals);
+        next.formatters = std::mem::take(&mut self.formatters);
+        next.step_mode = self.step_mode;
+        next.follow_fork = self.follow_fork;
+        // One log across runs.
+        next.events = std::mem::take(&mut self.events);
+        next.events
+            .record(format!("restarted as process {}", next.pid));
+
+        *self = next;
         Ok(())
     }
 
-    pub fn registers(&self) -> &Registers {
-        &self.registers
+    /// Forks the stopped process to make a checkpoint it can go back to
+    /// with `restore_checkpoint`, returning the checkpoint's number.
+    pub fn checkpoint(&mut self) -> Result<usize> {
+        self.ensure_live()?;
+        if self.in_syscall.is_some() || self.stop_signal() == Some(SYSCALL_STOP) {
+            return Err("can't checkpoint in the middle of a syscall".into());
+        }
+        let pid = fork_stopped(self.tid, &self.registers)?;
+        let number = self.checkpoints.last().map_or(1, |c| c.number + 1);
+        self.checkpoints.push(Checkpoint {
+            number,
+            pid,
+            registers: self.registers.clone(),
+            breakpoints: self.breakpoints.clone(),
+            lifted: self.lifted,
+        });
+        self.events.record(format!(
+            "checkpoint {} at {} as process {}",
+            number,
+            self.describe(self.registers.rip),
+            pid
+        ));
+        Ok(number)
     }
 
-    pub fn instructions(&self, symbol: &elf::types::Symbol) -> Result<Vec<u8>> {
-        Ok(self.read_bytes(symbol.value as usize, symbol.size as usize)?)
+    pub fn checkpoints(&self) -> &[Checkpoint] {
+        &self.checkpoints
     }
 
-    pub fn stack(&self) -> &[usize] {
-        &self.stack
+    /// Goes back to checkpoint `number`, replacing the process with a fresh
+    /// fork of it so it can be gone back to again. Breakpoints set or
+    /// deleted since are brought over.
+    pub fn restore_checkpoint(&mut self, number: usize) -> Result<()> {
+        let checkpoint = self
+            .checkpoints
+            .iter()
+            .find(|checkpoint| checkpoint.number == number)
+            .ok_or_else(|| format!("no checkpoint {}", number))?;
+        let pid = fork_stopped(checkpoint.pid, &checkpoint.registers)?;
+        let registers = checkpoint.registers.clone();
+        let lifted = checkpoint.lifted;
+        let then = checkpoint.breakpoints.clone();
+        self.kill()?;
+
+        self.pid = pid;
+        self.tid = pid;
+        self.threads = vec![Thread {
+            number: 1,
+            tid: pid,
+            pending_signal: 0,
+        }];
+        self.wait_status = Stopped(pid, libc::SIGTRAP);
+        self.others_running = false;
+        self.in_syscall = None;
+        self.pending_signal = 0;
+        self.hw_lifted = None;
+        self.selected_frame = 0;
+        self.journal.clear();
+
+        for (addr, breakpoint) in &then {
+            if !self.breakpoints.contains_key(addr) && lifted != Some(*addr) {
+                self.restore_byte(*addr, breakpoint.original)?;
+            }
+        }
+        let rip = registers.rip as usize;
+        self.lifted = None;
+        let added: Vec<usize> = self
+            .breakpoints
+            .keys()
+            .filter(|addr| !then.contains_key(addr) || lifted == Some(**addr))
+            .copied()
+            .collect();
+        for addr in added {
+            if addr == rip {
+                self.lifted = Some(addr);
+            } else {
+                self.store(addr, &[INT3])?;
+            }
+        }
+        self.apply_debug_registers()?;
+        self.refresh()?;
+        self.events
+            .record(format!("restored checkpoint {} as process {}", number, pid));
+        Ok(())
     }
 
-    pub fn debug_info(&self) -> &DebugInfo {
-        &self.debug_info
+    /// Starts or stops recording single steps for `reverse_step`. Memory
+    /// the kernel writes during a syscall isn't recorded.
+    pub fn set_recording(&mut self, on: bool) {
+        if !on {
+            self.recording = None;
+        } else if self.recording.is_none() {
+            self.recording = Some(Recording::default());
+        }
     }
 
-    pub fn symbols(&self) -> &Vec<elf::types::Symbol> {
-        &self.symbols
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
     }
 
-    pub fn symbol(&self, name: &str) -> Option<&elf::types::Symbol> {
-        for symbol in &self.symbols {
-            if symbol.name == name {
-                return Some(symbol);
+    /// How many steps there are to go back over, none when the process ran
+    /// on without recording since the last one.
+    pub fn recorded_steps(&self) -> usize {
+        match &self.recording {
+            Some(recording) if self.recording_current() => recording.steps.len(),
+            _ => 0,
+        }
+    }
+
+    fn recording_current(&self) -> bool {
+        self.recording
+            .as_ref()
+            .and_then(|recording| recording.after.as_ref())
+            .is_some_and(|after| after.diff(&self.registers).is_empty())
+    }
+
+    /// Undoes the last recorded step, putting back the memory it wrote and
+    /// the registers it changed.
+    pub fn reverse_step(&mut self) -> Result<()> {
+        self.ensure_live()?;
+        if self.recording.is_none() {
+            return Err("not recording, `record` starts".into());
+        }
+        if self.recorded_steps() == 0 {
+            return Err("no recorded steps to go back over".into());
+        }
+        let recording = self.recording.as_mut().unwrap();
+        let step = recording.steps.pop_back().unwrap();
+
+        for (addr, old) in step.memory.iter().rev() {
+            self.patch(*addr, old)?;
+        }
+        if let Some(addr) = self.lifted.take() {
+            self.store(addr, &[INT3])?;
+        }
+        self.hw_lifted = None;
+        let mut registers = self.registers.clone();
+        for (name, value) in step.registers {
+            registers.set(name, value);
+        }
+        ptrace::setregs(self.tid, &registers.clone().into())?;
+        self.registers = registers;
+        self.selected_frame = 0;
+        self.refresh()?;
+
+        let rip = self.registers.rip as usize;
+        if let Some(original) = self.breakpoints.get(&rip).map(|b| b.original) {
+            self.restore_byte(rip, original)?;
+            self.lifted = Some(rip);
+        }
+        if let Some(recording) = &mut self.recording {
+            recording.after = Some(self.registers.clone());
+        }
+        Ok(())
+    }
+
+    /// Steps back until a breakpoint or the start of the recording,
+    /// returning how many steps were undone and whether a breakpoint
+    /// stopped it.
+    pub fn reverse_cont(&mut self) -> Result<(usize, bool)> {
+        let mut undone = 0;
+        loop {
+            self.reverse_step()?;
+            undone += 1;
+            let rip = self.registers.rip as usize;
+            let at_breakpoint = self
+                .breakpoints
+                .get(&rip)
+                .is_some_and(|b| b.tracepoint.is_none() && !b.watch_scope)
+                && self.condition_holds(rip);
+            if at_breakpoint || self.recorded_steps() == 0 {
+                return Ok((undone, at_breakpoint));
             }
         }
-        None
     }
 
-    fn shift_symbols(&mut self, amount: u64) {
-        for symbol in &mut self.symbols {
-            if symbol.bind == elf::types::STB_WEAK {
-                continue;
+    /// SIGKILLs the process and reaps it so it doesn't linger as a zombie.
+    pub fn kill(&mut self) -> Result<()> {
+        // The process a core came from is long gone, whatever has its pid
+        // now isn't ours.
+        if !self.is_running() || self.core.is_some() {
+            return Ok(());
+        }
+        kill(self.pid, libc::SIGKILL)?;
+        self.events.record(format!("killed process {}", self.pid));
+        loop {
+            self.wait_status = self.next_status()?;
+            if let Exited(_, _) | Signaled(_, _) = self.wait_status {
+                return Ok(());
             }
-            symbol.value += amount;
         }
     }
 
-    fn fetch_state(&mut self) -> Result<()> {
-        self.wait_status = wait()?;
-        if let Stopped(_, _) = self.wait_status {
-            self.registers = ptrace::getregs(self.pid)?.into();
-            self.stack = self.read_words(self.registers.rsp as usize, 16)?;
-            self.handle_breakpoint()?;
+    /// Waits for a process that vanished from under a command, e.g. one
+    /// killed from outside, to finish dying, so how it ended is known.
+    pub fn reap(&mut self) -> Result<()> {
+        self.events.record(format!("process {} is gone", self.pid));
+        loop {
+            self.wait_status = self.next_status()?;
+            if let Exited(_, _) | Signaled(_, _) = self.wait_status {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Maps an address in this process to the same place in `other`, by
+    /// symbol and line or offset when it's inside one.
+    fn relocate(&self, addr: usize, other: &Subordinate) -> usize {
+        self.moved(addr, other).unwrap_or(addr)
+    }
+
+    // Where `addr` is in `other`: on the same line of the same function,
+    // counted from its first line, so that it survives the function
+    // changing size, or at the same offset into it without line info.
+    // Addresses outside any function stay put, and None means the function
+    // isn't in `other` at all.
+    fn moved(&self, addr: usize, other: &Subordinate) -> Option<usize> {
+        let addr = addr as u64;
+        let symbol = match self
+            .symbols
+            .iter()
+            .filter(|s| !s.name.is_empty() && s.size > 0)
+            .find(|s| addr >= s.value && addr < s.value + s.size)
+        {
+            Some(symbol) => symbol,
+            None => return Some(addr as usize),
         };
-        Ok(())
+        let moved = other.symbol(&symbol.name)?;
+        let same_offset = moved.value + (addr - symbol.value);
+        let lines = match self.line_in_function(symbol, addr) {
+            Some(line) => other.addresses_in_function(moved, line),
+            None => Vec::new(),
+        };
+        // A line with code in several places keeps the one it was at.
+        if lines.is_empty() || lines.contains(&same_offset) {
+            return Some(same_offset as usize);
+        }
+        Some(lines[0] as usize)
     }
 
-    fn handle_breakpoint(&mut self) -> Result<()> {
-        let addr = (self.registers.rip - 1) as usize;
-        if let Some(data) = self.breakpoints.remove(&addr) {
-            info!("hit breakpoint: {:x}", addr);
-            self.registers.rip = addr as u64;
-            self.poke(self.registers.rip as usize, data)?;
-            ptrace::setregs(self.pid, &self.registers.clone().into())?;
+    // How many lines into `function` the line starting at `addr` is, when
+    // both are in the same file.
+    fn line_in_function(&self, function: &elf::types::Symbol, addr: u64) -> Option<u64> {
+        let first = self
+            .debug_info
+            .line_info((function.value - self.load_bias) as usize)?;
+        let line = self
+            .debug_info
+            .line_info((addr - self.load_bias) as usize)?;
+        (line.path == first.path && line.line >= first.line).then(|| line.line - first.line)
+    }
+
+    // Where code for the line `line` lines into `function` starts.
+    fn addresses_in_function(&self, function: &elf::types::Symbol, line: u64) -> Vec<u64> {
+        let first = match self
+            .debug_info
+            .line_info((function.value - self.load_bias) as usize)
+        {
+            Some(first) => first,
+            None => return Vec::new(),
+        };
+        self.debug_info
+            .addresses_of(&first.path, first.line + line)
+            .into_iter()
+            .map(|addr| addr as u64 + self.load_bias)
+            .filter(|addr| *addr >= function.value && *addr < function.value + function.size)
+            .collect()
+    }
+
+    /// Reads the binary's symbols and debug info again after it was rebuilt,
+    /// moving breakpoints to the same lines of the same functions in it.
+    /// Only between runs, since a process would still be running the old
+    /// code. Says what happened to each breakpoint that had to move.
+    pub fn reload_symbols(&mut self) -> Result<Vec<String>> {
+        if self.is_running() {
+            return Err(
+                "the process is running the old binary, `run` restarts it with the new one".into(),
+            );
+        }
+        let elf = elf::File::open_path(&self.cmd[0])?;
+        Machine::from_elf(elf.ehdr.machine.0).check("program")?;
+        let mut fresh = Subordinate::load(
+            self.pid,
+            self.cmd.clone(),
+            self.launch.clone(),
+            self.capabilities.clone(),
+            &elf,
+        )?;
+        fresh.shift_symbols(self.load_bias);
+        fresh.load_bias = self.load_bias;
+
+        let mut notes = Vec::new();
+        let mut breakpoints = HashMap::new();
+        for (addr, breakpoint) in std::mem::take(&mut self.breakpoints) {
+            let moved = match self.moved(addr, &fresh) {
+                Some(moved) => moved,
+                None => {
+                    notes.push(format!(
+                        "breakpoint at {} removed, the function is gone",
+                        self.describe(addr as u64)
+                    ));
+                    continue;
+                }
+            };
+            if moved != addr {
+                notes.push(format!(
+                    "breakpoint at {} moved to {}",
+                    self.describe(addr as u64),
+                    fresh.describe(moved as u64)
+                ));
+            }
+            breakpoints.insert(moved, breakpoint);
+        }
+        let mut hw_breakpoints = self.hw_breakpoints;
+        for hw in hw_breakpoints.iter_mut().flatten() {
+            hw.addr = self.relocate(hw.addr, &fresh);
+        }
+        self.hw_breakpoints = hw_breakpoints;
+        self.breakpoints = breakpoints;
+        self.symbols = std::mem::take(&mut fresh.symbols);
+        std::mem::swap(&mut self.debug_info, &mut fresh.debug_info);
+        self.demangler = std::mem::take(&mut fresh.demangler);
+        self.path = std::mem::take(&mut fresh.path);
+        self.events
+            .record(format!("reloaded symbols from {}", self.path.display()));
+        Ok(notes)
+    }
+
+    pub fn load_bias(&self) -> u64 {
+        self.load_bias
+    }
+
+    pub fn events(&self) -> &EventLog {
+        &self.events
+    }
+
+    pub fn stop_signal(&self) -> Option<i32> {
+        if let Stopped(_, signal) = self.wait_status {
+            return Some(signal);
         }
+        None
+    }
+
+    pub fn breakpoint(&mut self, addr: usize) -> Result<()> {
+        self.insert_breakpoint(addr, None)
+    }
+
+    /// Sets a breakpoint that only stops when `condition` evaluates non-zero.
+    pub fn conditional_breakpoint(&mut self, addr: usize, condition: Expr) -> Result<()> {
+        self.insert_breakpoint(addr, Some(condition))
+    }
+
+    /// Sets a breakpoint in a debug register, which leaves the code alone.
+    /// There are only four of them.
+    pub fn hardware_breakpoint(&mut self, addr: usize) -> Result<()> {
+        self.insert_hw_breakpoint(HwBreakpoint {
+            addr,
+            access: Access::Execute,
+            len: 1,
+            value: 0,
+        })
+    }
+
+    /// Stops after `addr` is written, or read or written with
+    /// `Access::ReadWrite`. A data symbol at `addr` is watched whole if it
+    /// fits in a debug register, otherwise as much as the alignment allows.
+    pub fn watchpoint(&mut self, addr: usize, access: Access) -> Result<()> {
+        let size = self
+            .symbols
+            .iter()
+            .find(|s| s.symtype == elf::types::STT_OBJECT && s.value == addr as u64)
+            .map_or(8, |s| s.size as usize);
+        self.sized_watchpoint(addr, size, access)
+    }
+
+    /// Watches `size` bytes at `addr` in the selected frame, deleting the
+    /// watchpoint once that frame returns so a later frame reusing the
+    /// stack slot doesn't trigger it.
+    pub fn scoped_watchpoint(&mut self, addr: usize, size: usize, access: Access) -> Result<()> {
+        let frames = backtrace(self);
+        let caller = frames
+            .get(self.selected_frame + 1)
+            .ok_or("the selected frame has no caller to return to")?;
+        let return_addr = caller.pc as usize;
 
+        self.sized_watchpoint(addr, size, access)?;
+        if !self.breakpoints.contains_key(&return_addr) {
+            self.insert_breakpoint(return_addr, None)?;
+            if let Some(breakpoint) = self.breakpoints.get_mut(&return_addr) {
+                breakpoint.watch_scope = true;
+            }
+        }
+        self.watch_scopes.push(WatchScope {
+            addr,
+            access,
+            return_addr,
+            cfa: caller.sp,
+        });
+        Ok(())
+    }
+
+    /// Watched addresses deleted at the last stop because their frame
+    /// returned.
+    pub fn left_scope(&self) -> &[usize] {
+        &self.left_scope
+    }
+
+    fn sized_watchpoint(&mut self, addr: usize, size: usize, access: Access) -> Result<()> {
+        let len = hardware::watch_len(addr, size);
+        let value = self.read_value(addr, len)?;
+        self.insert_hw_breakpoint(HwBreakpoint {
+            addr,
+            access,
+            len,
+            value,
+        })
+    }
+
+    /// The watchpoint behind the last stop, if it was one.
+    pub fn watch_hit(&self) -> Option<&WatchHit> {
+        self.watch_hit.as_ref()
+    }
+
+    fn insert_hw_breakpoint(&mut self, breakpoint: HwBreakpoint) -> Result<()> {
+        let existing = |slot: &Option<HwBreakpoint>| {
+            slot.is_some_and(|s| s.addr == breakpoint.addr && s.access == breakpoint.access)
+        };
+        if self.hw_breakpoints.iter().any(existing) {
+            return Ok(());
+        }
+        let slot = self
+            .hw_breakpoints
+            .iter()
+            .position(Option::is_none)
+            .ok_or("all 4 hardware breakpoint slots are in use")?;
+        self.set_debug_register(slot, breakpoint.addr)?;
+        self.hw_breakpoints[slot] = Some(breakpoint);
+        let dr7 = hardware::control(&self.hw_breakpoints);
+        self.set_debug_register(hardware::DR7, dr7 as usize)
+    }
+
+    fn remove_hw_breakpoint(&mut self, addr: usize, access: Access) -> Result<()> {
+        let slot = self
+            .hw_breakpoints
+            .iter()
+            .position(|slot| slot.is_some_and(|s| s.addr == addr && s.access == access));
+        if let Some(slot) = slot {
+            self.hw_breakpoints[slot] = None;
+            if self.hw_lifted == Some(slot) {
+                self.hw_lifted = None;
+            }
+            let dr7 = hardware::control(&self.hw_breakpoints);
+            self.set_debug_register(hardware::DR7, dr7 as usize)?;
+        }
+        Ok(())
+    }
+
+    /// Deletes the scoped watchpoints whose frame just returned to `addr`,
+    /// returning whether there were any.
+    fn end_watch_scopes(&mut self, addr: usize) -> Result<bool> {
+        let rsp = self.registers.rsp;
+        let (ended, kept): (Vec<WatchScope>, Vec<WatchScope>) = self
+            .watch_scopes
+            .iter()
+            .partition(|scope| scope.return_addr == addr && rsp >= scope.cfa);
+        if ended.is_empty() {
+            return Ok(false);
+        }
+
+        self.watch_scopes = kept;
+        for scope in ended {
+            self.remove_hw_breakpoint(scope.addr, scope.access)?;
+            self.events.record(format!(
+                "watchpoint 0x{:x} deleted, its frame returned",
+                scope.addr
+            ));
+            self.left_scope.push(scope.addr);
+        }
+        let still_needed = self.watch_scopes.iter().any(|s| s.return_addr == addr);
+        if !still_needed && self.breakpoints.get(&addr).is_some_and(|b| b.watch_scope) {
+            self.remove_breakpoint(addr)?;
+        }
+        Ok(true)
+    }
+
+    fn read_value(&self, addr: usize, len: usize) -> Result<u64> {
+        let mut bytes = [0; 8];
+        bytes[..len].copy_from_slice(&self.read_bytes(addr, len)?);
+        Ok(u64::from_le_bytes(bytes))
+    }
+
+    /// Sets a breakpoint that is deleted after it first stops the
+    /// subordinate. An existing breakpoint at `addr` is left as it is.
+    pub fn temporary_breakpoint(&mut self, addr: usize) -> Result<()> {
+        if self.breakpoints.contains_key(&addr) {
+            return Ok(());
+        }
+        self.insert_breakpoint(addr, None)?;
+        if let Some(breakpoint) = self.breakpoints.get_mut(&addr) {
+            breakpoint.temporary = true;
+        }
+        Ok(())
+    }
+
+    /// Sets a breakpoint that only stops thread `number`, any other thread
+    /// passes it as if it weren't there.
+    pub fn thread_breakpoint(&mut self, addr: usize, number: usize) -> Result<()> {
+        if !self.threads.iter().any(|thread| thread.number == number) {
+            return Err(format!("no thread {}", number).into());
+        }
+        self.insert_breakpoint(addr, None)?;
+        if let Some(breakpoint) = self.breakpoints.get_mut(&addr) {
+            breakpoint.thread = Some(number);
+        }
+        Ok(())
+    }
+
+    /// Sets a breakpoint on the function a Rust program calls when it
+    /// panics, see `at_panic`.
+    pub fn panic_breakpoint(&mut self, addr: usize) -> Result<()> {
+        self.insert_breakpoint(addr, None)?;
+        if let Some(breakpoint) = self.breakpoints.get_mut(&addr) {
+            breakpoint.panic = true;
+        }
+        Ok(())
+    }
+
+    /// Whether the subordinate stopped at a `panic_breakpoint`.
+    pub fn at_panic(&self) -> bool {
+        self.lifted
+            .and_then(|addr| self.breakpoints.get(&addr))
+            .is_some_and(|breakpoint| breakpoint.panic)
+    }
+
+    /// Sets a tracepoint, which logs the call and carries on running.
+    pub fn tracepoint(&mut self, addr: usize, tracepoint: Tracepoint) -> Result<()> {
+        self.insert_breakpoint(addr, None)?;
+        if let Some(breakpoint) = self.breakpoints.get_mut(&addr) {
+            breakpoint.tracepoint = Some(tracepoint);
+        }
+        Ok(())
+    }
+
+    /// Records calls to these syscalls, with their results, while continuing.
+    /// With a condition only calls it holds for are recorded, the
+    /// condition can name the call's arguments, see `argument_named`.
+    pub fn trace_syscalls(&mut self, numbers: &[u64], condition: Option<Expr>) {
+        for number in numbers {
+            self.traced_syscalls.insert(*number, condition.clone());
+        }
+    }
+
+    /// Makes `cont` stop once the stack is more than `limit` bytes deep,
+    /// or never with None.
+    pub fn set_stack_limit(&mut self, limit: Option<u64>) {
+        self.stack_limit = limit;
+    }
+
+    pub fn stack_limit(&self) -> Option<u64> {
+        self.stack_limit
+    }
+
+    /// How deep the stack was if passing the limit is what `cont` stopped
+    /// for.
+    pub fn stack_exceeded(&self) -> Option<u64> {
+        self.stack_exceeded
+    }
+
+    /// Makes `cont` stop when this syscall returns.
+    pub fn catch_syscall(&mut self, number: u64) {
+        self.caught_syscalls.insert(number);
+    }
+
+    /// Makes `cont` stop when this syscall is entered, before it runs.
+    pub fn catch_syscall_entry(&mut self, number: u64) {
+        self.caught_entries.insert(number);
+    }
+
+    /// Makes `cont` stop when a library whose file name has `name` in it
+    /// is loaded, before any of its code runs. False if the program has no
+    /// dynamic linker to load one.
+    pub fn catch_load(&mut self, name: &str) -> Result<bool> {
+        if !self.watch_loader()? {
+            return Ok(false);
+        }
+        self.caught_loads.push(name.to_owned());
+        Ok(true)
+    }
+
+    fn catches_load(&self, path: &Path) -> bool {
+        let file = path.file_name().unwrap_or_default().to_string_lossy();
+        self.caught_loads
+            .iter()
+            .any(|name| file.contains(name.as_str()))
+    }
+
+    /// The caught syscall or library load, with the syscall's result when
+    /// it was caught returning, if that's what `cont` stopped for.
+    pub fn caught(&self) -> Option<&str> {
+        self.caught.as_deref()
+    }
+
+    /// How the memory map changed since the last call. The first call only
+    /// takes a reading.
+    pub fn map_changes(&mut self) -> Result<Vec<MapChange>> {
+        let maps = self.maps()?;
+        let changes = match &self.known_maps {
+            Some(known) => maps::changes(known, &maps),
+            None => Vec::new(),
+        };
+        self.known_maps = Some(maps);
+        Ok(changes)
+    }
+
+    /// Every tracepoint hit and traced syscall so far, oldest first.
+    pub fn trace_events(&self) -> &[TraceEvent] {
+        &self.trace_events
+    }
+
+    fn insert_breakpoint(&mut self, addr: usize, condition: Option<Expr>) -> Result<()> {
+        if let Some(breakpoint) = self.breakpoints.get_mut(&addr) {
+            breakpoint.condition = condition;
+            return Ok(());
+        }
+
+        let data = self.peek(addr)?;
+        self.store(addr, &[INT3])?;
+        self.breakpoints.insert(
+            addr,
+            Breakpoint {
+                original: data,
+                condition,
+                tracepoint: None,
+                temporary: false,
+                watch_scope: false,
+                panic: false,
+                loader: false,
+                thread: None,
+            },
+        );
+        Ok(())
+    }
+
+    pub fn remove_breakpoint(&mut self, addr: usize) -> Result<()> {
+        if let Some(breakpoint) = self.breakpoints.remove(&addr) {
+            self.restore_byte(addr, breakpoint.original)?;
+        }
+        Ok(())
+    }
+
+    /// Continues until `addr` is reached, using a temporary breakpoint that
+    /// is taken out again if something else stops the subordinate first.
+    pub fn run_to(&mut self, addr: usize) -> Result<()> {
+        let temporary = !self.breakpoints.contains_key(&addr);
+        if temporary {
+            self.breakpoint(addr)?;
+        }
+        self.cont()?;
+        if temporary && self.stop_signal().is_some() {
+            self.remove_breakpoint(addr)?;
+        }
+        Ok(())
+    }
+
+    pub fn registers(&self) -> &Registers {
+        &self.registers
+    }
+
+    /// The x87, SSE and AVX registers, which are only read when asked for.
+    pub fn float_registers(&self) -> Result<FloatRegisters> {
+        if self.core.is_some() {
+            return Err("floating point registers aren't read from core files".into());
+        }
+        FloatRegisters::read(self.tid)
+    }
+
+    /// The registers as seen from the selected frame. Only rip, rsp and rbp
+    /// are unwound, the rest are the innermost frame's.
+    pub fn frame_registers(&self) -> Registers {
+        let mut regs = self.registers.clone();
+        if self.selected_frame > 0 {
+            if let Some(frame) = backtrace(self).get(self.selected_frame) {
+                regs.rip = frame.pc;
+                regs.rsp = frame.sp;
+                regs.rbp = frame.fp;
+            }
+        }
+        regs
+    }
+
+    pub fn selected_frame(&self) -> usize {
+        self.selected_frame
+    }
+
+    /// Every traced thread, in the order they started.
+    pub fn threads(&self) -> &[Thread] {
+        &self.threads
+    }
+
+    /// The thread registers, stepping and the backtrace are for.
+    pub fn current_thread(&self) -> i32 {
+        self.tid
+    }
+
+    /// Where a thread is stopped.
+    pub fn thread_rip(&self, tid: i32) -> Result<u64> {
+        if tid == self.tid {
+            return Ok(self.registers.rip);
+        }
+        Ok(self.thread_registers(tid)?.rip)
+    }
+
+    /// A thread's registers as they are now.
+    pub fn thread_registers(&self, tid: i32) -> Result<Registers> {
+        match &self.core {
+            Some(core) => core.registers(tid),
+            None => Ok(ptrace::getregs(tid)?.into()),
+        }
+    }
+
+    /// The name a thread goes by, e.g. as set with pthread_setname_np.
+    pub fn thread_name(&self, tid: i32) -> Option<String> {
+        if self.core.is_some() {
+            return None;
+        }
+        let comm = fs::read_to_string(format!("/proc/{}/task/{}/comm", self.pid, tid)).ok()?;
+        Some(comm.trim_end().to_owned())
+    }
+
+    /// Makes thread `number` the current one. If the one it replaces is
+    /// stopped at a breakpoint, that breakpoint is hit again when it
+    /// resumes, while the new one steps past the breakpoint it's at.
+    pub fn select_thread(&mut self, number: usize) -> Result<()> {
+        let tid = self
+            .threads
+            .iter()
+            .find(|thread| thread.number == number)
+            .map(|thread| thread.tid)
+            .ok_or_else(|| format!("no thread {}", number))?;
+        if tid == self.tid {
+            return Ok(());
+        }
+        if let Some(addr) = self.lifted.take() {
+            self.store(addr, &[INT3])?;
+        }
+        self.hw_lifted = None;
+        self.make_current(tid);
+        self.selected_frame = 0;
+        self.refresh()?;
+        let rip = self.registers.rip as usize;
+        if let Some(original) = self.breakpoints.get(&rip).map(|b| b.original) {
+            self.restore_byte(rip, original)?;
+            self.lifted = Some(rip);
+        }
+        Ok(())
+    }
+
+    /// Selects frame `n` of the backtrace, returning its pc.
+    pub fn select_frame(&mut self, n: usize) -> Result<u64> {
+        let frames = backtrace(self);
+        match frames.get(n) {
+            Some(frame) => {
+                self.selected_frame = n;
+                Ok(frame.pc)
+            }
+            None => Err(format!("no frame {}, the stack is {} deep", n, frames.len()).into()),
+        }
+    }
+
+    /// Writes the registers on the user's behalf, journaling the old values
+    /// of the ones that change so `undo` can put them back.
+    pub fn set_registers(&mut self, registers: Registers) -> Result<()> {
+        let changed = self.registers.diff(&registers);
+        if changed.is_empty() {
+            return Ok(());
+        }
+        self.ensure_live()?;
+        ptrace::setregs(self.tid, &registers.clone().into())?;
+        self.registers = registers;
+        self.journal.push(Change::Registers(
+            changed
+                .into_iter()
+                .map(|(name, old, _)| (name, old))
+                .collect(),
+        ));
+        Ok(())
+    }
+
+    /// Reverts the most recent change made through `set_registers` or
+    /// `write_bytes`, describing what was put back, or returns None with
+    /// nothing to undo.
+    pub fn undo(&mut self) -> Result<Option<String>> {
+        let change = match self.journal.pop() {
+            Some(change) => change,
+            None => return Ok(None),
+        };
+
+        match change {
+            Change::Registers(old) => {
+                let mut registers = self.registers.clone();
+                let mut restored = Vec::new();
+                for (name, value) in old {
+                    registers.set(name, value);
+                    restored.push(format!("{} = 0x{:x}", name, value));
+                }
+                ptrace::setregs(self.tid, &registers.clone().into())?;
+                self.registers = registers;
+                Ok(Some(restored.join(", ")))
+            }
+            Change::Memory(addr, old) => {
+                self.patch(addr, &old)?;
+                let mut word = [0u8; 8];
+                let shown = old.len().min(word.len());
+                word[..shown].copy_from_slice(&old[..shown]);
+                let more = if old.len() > shown { ", ..." } else { "" };
+                Ok(Some(format!(
+                    "[0x{:x}] = 0x{:x}{}",
+                    addr,
+                    u64::from_le_bytes(word),
+                    more
+                )))
+            }
+        }
+    }
+
+    /// The code of a function as it was compiled, with the original bytes
+    /// back in place of any breakpoints set in it.
+    pub fn instructions(&self, symbol: &elf::types::Symbol) -> Result<Vec<u8>> {
+        self.original_bytes(symbol.value as usize, symbol.size as usize)
+    }
+
+    /// Memory with the original bytes in place of any breakpoints.
+    fn original_bytes(&self, start: usize, len: usize) -> Result<Vec<u8>> {
+        let mut bytes = self.read_bytes(start, len)?;
+        for (addr, breakpoint) in &self.breakpoints {
+            if (start..start + bytes.len()).contains(addr) {
+                bytes[addr - start] = breakpoint.original as u8;
+            }
+        }
+        Ok(bytes)
+    }
+
+    pub fn stack(&self) -> &[usize] {
+        &self.stack
+    }
+
+    pub fn debug_info(&self) -> &DebugInfo {
+        &self.debug_info
+    }
+
+    pub fn debug_info_mut(&mut self) -> &mut DebugInfo {
+        &mut self.debug_info
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn maps(&self) -> Result<Vec<Region>> {
+        match &self.core {
+            Some(core) => Ok(core.regions()),
+            None => maps::read(self.pid),
+        }
+    }
+
+    /// Whether this is a core dump rather than a live process.
+    pub fn is_core(&self) -> bool {
+        self.core.is_some()
+    }
+
+    /// Fails for a core dump, which can only be looked at.
+    pub fn ensure_live(&self) -> Result<()> {
+        match self.core {
+            Some(_) => Err("a core dump can't be run or changed, only looked at".into()),
+            None => Ok(()),
+        }
+    }
+
+    pub(crate) fn auxv(&self) -> &[auxv::Entry] {
+        &self.auxv
+    }
+
+    /// The build-ids of the binary on disk and of the one actually running,
+    /// if they differ. Symbols and line numbers come from the file, so a
+    /// mismatch means they may be silently wrong.
+    pub fn build_id_mismatch(&self) -> Option<(Vec<u8>, Vec<u8>)> {
+        let on_disk = build_id::of_file(&fs::read(&self.path).ok()?)?;
+        let running = build_id::of_process(self)?;
+        if on_disk == running {
+            return None;
+        }
+        Some((on_disk, running))
+    }
+
+    pub fn capabilities(&self) -> &Capabilities {
+        &self.capabilities
+    }
+
+    pub fn symbols(&self) -> &Vec<elf::types::Symbol> {
+        &self.symbols
+    }
+
+    /// Looks `name` up in the executable, then in the shared libraries in
+    /// load order. Symbols can also go by their demangled names, like
+    /// `demo::greet`, see `Demangler::matches`.
+    pub fn symbol(&self, name: &str) -> Option<&elf::types::Symbol> {
+        self.all_symbols()
+            .find(|symbol| symbol.name == name)
+            .or_else(|| {
+                self.all_symbols()
+                    .find(|symbol| self.demangler.matches(&symbol.name, name))
+            })
+    }
+
+    /// Finds the function containing `addr` and the offset into it.
+    pub fn symbol_at(&self, addr: u64) -> Option<(&elf::types::Symbol, u64)> {
+        self.all_symbols()
+            .filter(|s| s.symtype == elf::types::STT_FUNC)
+            .find(|s| addr >= s.value && (addr < s.value + s.size || addr == s.value))
+            .map(|s| (s, addr - s.value))
+    }
+
+    fn all_symbols(&self) -> impl Iterator<Item = &elf::types::Symbol> {
+        self.symbols.iter().chain(
+            self.libraries
+                .iter()
+                .flat_map(|library| library.symbols.iter()),
+        )
+    }
+
+    /// The shared libraries found the last time `update_libraries` looked.
+    pub fn libraries(&self) -> &[Library] {
+        &self.libraries
+    }
+
+    /// Catches up with the shared libraries the dynamic linker loaded and
+    /// unloaded since the last call, by walking its link map, and reads the
+    /// symbols of new ones. Returns the paths of the new ones.
+    pub fn update_libraries(&mut self) -> Result<Vec<PathBuf>> {
+        let loaded = match link_map::loaded(self)? {
+            Some(loaded) => loaded,
+            None => return Ok(Vec::new()),
+        };
+        let mut libraries = Vec::new();
+        let mut new = Vec::new();
+        for (path, base) in loaded {
+            let known = self
+                .libraries
+                .iter()
+                .position(|library| library.path == path && library.base == base);
+            if let Some(i) = known {
+                libraries.push(self.libraries.swap_remove(i));
+                continue;
+            }
+            // A library whose file can't be read is still listed, it just
+            // has no symbols.
+            new.push(path.clone());
+            let library = Library::load(path.clone(), base).unwrap_or(Library {
+                path,
+                base,
+                symbols: Vec::new(),
+            });
+            self.demangler
+                .add(library.symbols.iter().map(|symbol| symbol.name.as_str()));
+            libraries.push(library);
+        }
+        self.libraries = libraries;
+
+        for name in std::mem::take(&mut self.pending) {
+            let addr = match self.symbol(&name) {
+                Some(symbol) => symbol.value as usize,
+                None => {
+                    self.pending.push(name);
+                    continue;
+                }
+            };
+            self.insert_breakpoint(addr, None)?;
+            let at = self.describe(addr as u64);
+            self.events
+                .record(format!("pending breakpoint on {} set at {}", name, at));
+        }
+        Ok(new)
+    }
+
+    /// Breaks on `name` once a library that defines it is loaded. False if
+    /// the program has no dynamic linker to load one.
+    pub fn break_pending(&mut self, name: &str) -> Result<bool> {
+        if !self.watch_loader()? {
+            return Ok(false);
+        }
+        if !self.pending.iter().any(|pending| pending == name) {
+            self.pending.push(name.to_owned());
+        }
+        Ok(true)
+    }
+
+    pub fn pending_breakpoints(&self) -> &[String] {
+        &self.pending
+    }
+
+    // Puts a breakpoint where the dynamic linker reports changes to its
+    // list, unless there's one there already. False for static binaries.
+    fn watch_loader(&mut self) -> Result<bool> {
+        if self.breakpoints.values().any(|b| b.loader) {
+            return Ok(true);
+        }
+        let addr = match link_map::notification(self)? {
+            Some(addr) => addr as usize,
+            None => return Ok(false),
+        };
+        self.insert_breakpoint(addr, None)?;
+        if let Some(breakpoint) = self.breakpoints.get_mut(&addr) {
+            breakpoint.loader = true;
+        }
+        Ok(true)
+    }
+
+    // The name of the library function starting at `addr`, if one does.
+    fn library_function(&self, addr: usize) -> Option<String> {
+        self.libraries
+            .iter()
+            .flat_map(|library| library.symbols.iter())
+            .find(|s| s.value == addr as u64 && s.symtype == elf::types::STT_FUNC)
+            .map(|s| s.name.clone())
+    }
+
+    /// Software breakpoints and tracepoints, by address.
+    pub fn breakpoints(&self) -> Vec<(usize, &Breakpoint)> {
+        let mut breakpoints: Vec<(usize, &Breakpoint)> = self
+            .breakpoints
+            .iter()
+            .filter(|(_, breakpoint)| !breakpoint.loader)
+            .map(|(addr, breakpoint)| (*addr, breakpoint))
+            .collect();
+        breakpoints.sort_by_key(|(addr, _)| *addr);
+        breakpoints
+    }
+
+    pub fn hardware_breakpoints(&self) -> impl Iterator<Item = &HwBreakpoint> {
+        self.hw_breakpoints.iter().flatten()
+    }
+
+    /// Formats `addr` gdb-style, e.g. `0x401234 <foo+0x20>`.
+    pub fn describe(&self, addr: u64) -> String {
+        match self.symbol_at(addr) {
+            Some((symbol, 0)) => format!("0x{:x} <{}>", addr, self.symbol_name(symbol)),
+            Some((symbol, offset)) => {
+                format!("0x{:x} <{}+0x{:x}>", addr, self.symbol_name(symbol), offset)
+            }
+            None => format!("0x{:x}", addr),
+        }
+    }
+
+    /// A symbol's name as it should be shown, demangled unless
+    /// `set_print_mangled` asked for raw linker names.
+    pub fn symbol_name(&self, symbol: &elf::types::Symbol) -> String {
+        if self.print_mangled {
+            return symbol.name.clone();
+        }
+        self.demangler.demangle(&symbol.name)
+    }
+
+    pub fn demangler(&self) -> &Demangler {
+        &self.demangler
+    }
+
+    /// How signals the process gets are handled, per `handle`.
+    pub fn signals(&self) -> &Signals {
+        &self.signals
+    }
+
+    pub fn signals_mut(&mut self) -> &mut Signals {
+        &mut self.signals
+    }
+
+    /// How the next `restart` launches the process.
+    pub fn launch(&self) -> &Launch {
+        &self.launch
+    }
+
+    pub fn launch_mut(&mut self) -> &mut Launch {
+        &mut self.launch
+    }
+
+    pub fn launch_warnings(&self) -> &[String] {
+        &self.launch_warnings
+    }
+
+    pub fn set_print_mangled(&mut self, mangled: bool) {
+        self.print_mangled = mangled;
+    }
+
+    /// The pretty-printers values of matching types are shown with.
+    pub fn formatters(&self) -> &Formatters {
+        &self.formatters
+    }
+
+    pub fn formatters_mut(&mut self) -> &mut Formatters {
+        &mut self.formatters
+    }
+
+    fn shift_symbols(&mut self, amount: u64) {
+        for symbol in &mut self.symbols {
+            // Undefined and absolute symbols aren't anywhere in the binary.
+            if symbol.shndx == SHN_UNDEF || symbol.shndx == SHN_ABS {
+                continue;
+            }
+            symbol.value += amount;
+        }
+    }
+
+    /// Works out the load bias of a position independent binary from where
+    /// the start of its file is mapped, for dumps that have no auxv to get
+    /// it from.
+    fn bias_from_maps(&mut self, elf: &elf::File) -> Result<()> {
+        if self.load_bias != 0 || elf.ehdr.elftype != elf::types::ET_DYN {
+            return Ok(());
+        }
+        let first = elf
+            .phdrs
+            .iter()
+            .filter(|phdr| phdr.progtype == elf::types::PT_LOAD)
+            .map(|phdr| phdr.vaddr & !(PAGE_SIZE - 1))
+            .min();
+        let mapped = self
+            .maps()?
+            .into_iter()
+            .find(|region| region.offset == 0 && region.path.as_deref() == Some(&self.path));
+        if let (Some(first), Some(mapped)) = (first, mapped) {
+            let amount = mapped.start as u64 - first;
+            self.shift_symbols(amount);
+            self.load_bias = amount;
+        }
+        Ok(())
+    }
+
+    /// Puts back the low byte of `original` at `addr` without disturbing the
+    /// rest of the word, which may hold another breakpoint.
+    fn restore_byte(&self, addr: usize, original: usize) -> Result<()> {
+        self.store(addr, &[original as u8])
+    }
+
+    fn fetch_state(&mut self) -> Result<()> {
+        self.wait_status = self.next_status()?;
+        self.handle_stop()
+    }
+
+    /// Resumes the process with a ptrace request, passing it any pending
+    /// signal.
+    fn resume_with(&mut self, request: fn(i32, i32) -> Result<()>) -> Result<()> {
+        self.ensure_live()?;
+        self.resumed_with = request;
+        request(self.tid, std::mem::take(&mut self.pending_signal))
+    }
+
+    /// Waits for the process to change state, see `filter_status`.
+    fn next_status(&mut self) -> Result<WaitStatus> {
+        loop {
+            if let Some(status) = self.filter_status(wait()?)? {
+                return Ok(status);
+            }
+        }
+    }
+
+    /// Deals with forks and new threads, skips statuses of processes that
+    /// were let go of and notes threads exiting, returning None for all
+    /// those as the caller carries on waiting. When a thread stops, it
+    /// becomes the current one and the others are stopped too.
+    fn filter_status(&mut self, status: WaitStatus) -> Result<Option<WaitStatus>> {
+        match status {
+            Event(tid, event) if self.is_thread(tid) => {
+                let current = tid == self.tid;
+                self.fork(tid, event)?;
+                // Following a fork makes the child current.
+                if current {
+                    (self.resumed_with)(self.tid, 0)?;
+                } else {
+                    ptrace::cont(tid, 0)?;
+                }
+                Ok(None)
+            }
+            Stopped(pid, libc::SIGSTOP) if !self.is_thread(pid) => {
+                self.early_stops.push(pid);
+                Ok(None)
+            }
+            Exited(tid, _) | Signaled(tid, _) if tid != self.pid && self.is_thread(tid) => {
+                Ok(self.thread_exited(tid))
+            }
+            // Processes that were let go of, once they're done.
+            Stopped(pid, _) | Exited(pid, _) | Signaled(pid, _) if !self.is_thread(pid) => Ok(None),
+            Stopped(tid, _) => {
+                if tid != self.tid {
+                    self.make_current(tid);
+                }
+                self.stop_others()?;
+                Ok(Some(status))
+            }
+            status => Ok(Some(status)),
+        }
+    }
+
+    fn is_thread(&self, tid: i32) -> bool {
+        self.threads.iter().any(|thread| thread.tid == tid)
+    }
+
+    /// Makes `tid` the thread commands act on, it having its own pending
+    /// signal and syscall state.
+    fn make_current(&mut self, tid: i32) {
+        let pending = std::mem::take(&mut self.pending_signal);
+        for thread in &mut self.threads {
+            if thread.tid == self.tid {
+                thread.pending_signal = pending;
+            } else if thread.tid == tid {
+                self.pending_signal = std::mem::take(&mut thread.pending_signal);
+            }
+        }
+        self.tid = tid;
+        self.in_syscall = None;
+        self.sample_pending = false;
+    }
+
+    /// Forgets a thread that exited. When it was the current one the first
+    /// thread takes over, reported as stopped if it's holding still.
+    fn thread_exited(&mut self, tid: i32) -> Option<WaitStatus> {
+        let number = self.threads.iter().find(|t| t.tid == tid)?.number;
+        self.threads.retain(|thread| thread.tid != tid);
+        self.events.record(format!("thread {} exited", number));
+        if tid != self.tid {
+            return None;
+        }
+        self.tid = self.pid;
+        self.pending_signal = 0;
+        self.in_syscall = None;
+        if self.others_running {
+            None
+        } else {
+            Some(Stopped(self.pid, libc::SIGTRAP))
+        }
+    }
+
+    /// Stops every thread but the current one, after it stopped with the
+    /// others running. A thread that stopped for a signal of its own first
+    /// gets it when it resumes, one that hit a breakpoint will hit it again.
+    fn stop_others(&mut self) -> Result<()> {
+        if !self.others_running {
+            return Ok(());
+        }
+        self.others_running = false;
+        let others: Vec<i32> = self
+            .threads
+            .iter()
+            .map(|thread| thread.tid)
+            .filter(|tid| *tid != self.tid)
+            .collect();
+        for tid in others {
+            if tgkill(self.pid, tid, libc::SIGSTOP).is_err() {
+                self.thread_exited(tid);
+                continue;
+            }
+            loop {
+                match wait_for(tid)? {
+                    Stopped(_, libc::SIGSTOP) => break,
+                    Stopped(_, libc::SIGTRAP) => {
+                        let mut regs = ptrace::getregs(tid)?;
+                        if self.breakpoints.contains_key(&(regs.rip as usize - 1)) {
+                            regs.rip -= 1;
+                            ptrace::setregs(tid, &regs)?;
+                        }
+                        // The SIGSTOP is delivered before anything runs.
+                        ptrace::cont(tid, 0)?;
+                    }
+                    Stopped(_, signal) => {
+                        if let Some(thread) = self.threads.iter_mut().find(|t| t.tid == tid) {
+                            thread.pending_signal = signal;
+                        }
+                        ptrace::cont(tid, 0)?;
+                    }
+                    Event(_, event) => {
+                        self.fork(tid, event)?;
+                        ptrace::cont(tid, 0)?;
+                    }
+                    _ => {
+                        self.thread_exited(tid);
+                        break;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// At a fork, vfork or clone event stop of thread `tid`, keeps debugging
+    /// one of the two processes and detaches the other, or starts tracing a
+    /// new thread. The detached process gets its own copy of memory back
+    /// without breakpoints in it, except after vfork where it's shared.
+    /// Only forks of the current thread are followed into the child.
+    fn fork(&mut self, tid: i32, event: i32) -> Result<()> {
+        let child = ptrace::geteventmsg(tid)? as i32;
+        match self.early_stops.iter().position(|pid| *pid == child) {
+            Some(i) => {
+                self.early_stops.remove(i);
+            }
+            None => {
+                wait_for(child)?;
+            }
+        }
+
+        if event == libc::PTRACE_EVENT_CLONE {
+            let number = self.threads.last().map_or(1, |thread| thread.number + 1);
+            self.threads.push(Thread {
+                number,
+                tid: child,
+                pending_signal: 0,
+            });
+            // Debug registers aren't inherited.
+            self.apply_debug_registers()?;
+            if self.others_running {
+                ptrace::cont(child, 0)?;
+            }
+            self.events
+                .record(format!("thread {} started as {}", number, child));
+            return Ok(());
+        }
+
+        let kind = if event == libc::PTRACE_EVENT_FORK {
+            "fork"
+        } else {
+            "vfork"
+        };
+        let follow = tid == self.tid && self.follow_fork == FollowFork::Child;
+        let left = if follow { tid } else { child };
+        if event == libc::PTRACE_EVENT_FORK {
+            for (addr, breakpoint) in &self.breakpoints {
+                let word = ptrace::peek(left, *addr)?;
+                ptrace::poke(left, *addr, (word & !0xff) | (breakpoint.original & 0xff))?;
+            }
+        }
+        if !follow {
+            ptrace::detach(child)?;
+            self.events
+                .record(format!("detached {} child process {}", kind, child));
+            return Ok(());
+        }
+
+        self.stop_others()?;
+        for thread in &self.threads {
+            ptrace::detach(thread.tid)?;
+        }
+        self.pid = child;
+        self.tid = child;
+        self.threads = vec![Thread {
+            number: 1,
+            tid: child,
+            pending_signal: 0,
+        }];
+        self.in_syscall = None;
+        self.apply_debug_registers()?;
+        self.events
+            .record(format!("followed {} into process {}", kind, child));
+        Ok(())
+    }
+
+    fn apply_debug_registers(&self) -> Result<()> {
+        for (slot, hw) in self.hw_breakpoints.iter().enumerate() {
+            if let Some(hw) = hw {
+                self.set_debug_register(slot, hw.addr)?;
+            }
+        }
+        let dr7 = hardware::control(&self.hw_breakpoints);
+        self.set_debug_register(hardware::DR7, dr7 as usize)
+    }
+
+    /// Lets the process run on as `cont` does, stopping at syscalls when
+    /// any are traced or caught.
+    fn resume(&mut self) -> Result<()> {
+        let stops_at_syscalls = !self.traced_syscalls.is_empty()
+            || !self.caught_syscalls.is_empty()
+            || !self.caught_entries.is_empty()
+            || self.fixed_syscalls.is_some();
+        if !stops_at_syscalls {
+            // A syscall `syscall_step` stopped at the entry of finishes
+            // without an exit stop.
+            self.in_syscall = None;
+            self.resume_with(ptrace::cont)?;
+        } else {
+            self.resume_with(ptrace::syscall)?;
+        }
+        // The other threads run freely, syscall stops are only for this one.
+        for thread in &mut self.threads {
+            if thread.tid != self.tid {
+                ptrace::cont(thread.tid, std::mem::take(&mut thread.pending_signal))?;
+            }
+        }
+        self.others_running = true;
+        Ok(())
+    }
+
+    /// `fetch_state` for `cont`, which with a stack limit set stops the
+    /// process with SIGSTOP every `STACK_SAMPLE_INTERVAL` to check how deep
+    /// its stack is, hiding those stops unless it's too deep.
+    fn wait_sampling_stack(&mut self) -> Result<()> {
+        let limit = match self.stack_limit {
+            Some(limit) => limit,
+            None => return self.fetch_state(),
+        };
+
+        let mut sampled = Instant::now();
+        let status = loop {
+            let status = match try_wait()? {
+                Some(status) => match self.filter_status(status)? {
+                    Some(status) => status,
+                    None => continue,
+                },
+                None => {
+                    if !self.sample_pending && sampled.elapsed() >= STACK_SAMPLE_INTERVAL {
+                        tgkill(self.pid, self.tid, libc::SIGSTOP)?;
+                        self.sample_pending = true;
+                    }
+                    std::thread::sleep(WAIT_POLL_INTERVAL);
+                    continue;
+                }
+            };
+            if !matches!(status, Stopped(_, libc::SIGSTOP)) || !self.sample_pending {
+                break status;
+            }
+
+            self.sample_pending = false;
+            sampled = Instant::now();
+            let depth = self.stack_depth(ptrace::getregs(self.tid)?.rsp)?;
+            if depth > limit {
+                self.wait_status = status;
+                self.handle_stop()?;
+                self.stack_exceeded = Some(depth);
+                self.events.record(format!(
+                    "stack {} bytes deep, past the limit of {}",
+                    depth, limit
+                ));
+                return Ok(());
+            }
+            self.resume()?;
+        };
+
+        self.wait_status = status;
+        // The SIGSTOP is still queued behind whatever stopped the process
+        // first. Resuming has it delivered before another instruction runs,
+        // which takes it out of the way of the next step or cont. From a
+        // syscall stop the call would run first, so those are left to the
+        // loop in `cont`, which takes the SIGSTOP at its next wait.
+        let stopped = matches!(self.wait_status, Stopped(_, signal) if signal != SYSCALL_STOP);
+        if !matches!(self.wait_status, Stopped(_, _)) {
+            self.sample_pending = false;
+        } else if self.sample_pending && stopped {
+            self.resumed_with = ptrace::cont;
+            ptrace::cont(self.tid, 0)?;
+            let drained = self.next_status()?;
+            self.sample_pending = false;
+            if !matches!(drained, Stopped(_, libc::SIGSTOP)) {
+                self.wait_status = drained;
+            }
+        }
+        self.handle_stop()
+    }
+
+    /// Bytes between `rsp` and the top of the stack mapping it's in.
+    fn stack_depth(&self, rsp: u64) -> Result<u64> {
+        let region = self
+            .maps()?
+            .into_iter()
+            .find(|region| region.contains(rsp as usize));
+        Ok(region.map_or(0, |region| region.end as u64 - rsp))
+    }
+
+    fn handle_stop(&mut self) -> Result<()> {
+        self.watch_hit = None;
+        self.caught = None;
+        self.stack_exceeded = None;
+        self.left_scope.clear();
+        self.selected_frame = 0;
+        match self.wait_status {
+            Stopped(_, libc::SIGTRAP) | Stopped(_, SYSCALL_STOP) => {}
+            Stopped(_, signal) => {
+                let name = signal_name(signal).unwrap_or("unknown signal");
+                let rip = ptrace::getregs(self.tid)?.rip;
+                let at = self.describe(rip);
+                let policy = self.signals.policy(signal);
+                if policy.pass {
+                    self.pending_signal = signal;
+                }
+                if policy.stop {
+                    self.events.record(format!("stopped by {} at {}", name, at));
+                } else {
+                    self.events.record(format!("passed {} at {}", name, at));
+                }
+            }
+            Exited(_, status) => {
+                self.events.record(format!("exited with status {}", status));
+            }
+            Signaled(_, signal) => {
+                let name = signal_name(signal).unwrap_or("unknown signal");
+                self.events.record(format!("killed by {}", name));
+            }
+            _ => {}
+        }
+        if let Stopped(_, _) = self.wait_status {
+            self.refresh()?;
+            self.handle_breakpoint()?;
+        };
+        Ok(())
+    }
+
+    fn refresh(&mut self) -> Result<()> {
+        self.registers = self.thread_registers(self.tid)?;
+        // The stack may not have made it into a core file.
+        self.stack = match self.read_words(self.registers.rsp as usize, 16) {
+            Err(_) if self.core.is_some() => Vec::new(),
+            stack => stack?,
+        };
+        Ok(())
+    }
+
+    /// Sets a debug register in every thread, as breakpoints and
+    /// watchpoints are for the whole process.
+    fn set_debug_register(&self, n: usize, value: usize) -> Result<()> {
+        self.ensure_live()?;
+        let offset = std::mem::offset_of!(libc::user, u_debugreg) + n * 8;
+        for thread in &self.threads {
+            ptrace::pokeuser(thread.tid, offset, value)?;
+        }
+        Ok(())
+    }
+
+    fn debug_register(&self, n: usize) -> Result<usize> {
+        let offset = std::mem::offset_of!(libc::user, u_debugreg) + n * 8;
+        ptrace::peekuser(self.tid, offset)
+    }
+
+    fn handle_breakpoint(&mut self) -> Result<()> {
+        if self.stop_signal() != Some(libc::SIGTRAP) {
+            return Ok(());
+        }
+
+        if self.hw_breakpoints.iter().any(Option::is_some) {
+            let dr6 = self.debug_register(hardware::DR6)?;
+            let fired = hardware::fired(dr6 as u64)
+                .and_then(|slot| self.hw_breakpoints[slot].map(|hw| (slot, hw)));
+            if let Some((slot, hw)) = fired {
+                // DR6 is sticky, clear it so the next trap isn't misread.
+                self.set_debug_register(hardware::DR6, 0)?;
+                info!("hit hardware breakpoint {}", slot);
+                let at = self.describe(self.registers.rip);
+                if hw.access == Access::Execute {
+                    self.events.record(format!("hardware breakpoint at {}", at));
+                    // Instruction breakpoints trap before the instruction
+                    // runs, data ones after the access is done.
+                    self.hw_lifted = Some(slot);
+                } else {
+                    let new = self.read_value(hw.addr, hw.len)?;
+                    self.events.record(format!(
+                        "watchpoint 0x{:x} hit at {}, 0x{:x} -> 0x{:x}",
+                        hw.addr, at, hw.value, new
+                    ));
+                    self.watch_hit = Some(WatchHit {
+                        watchpoint: hw,
+                        old: hw.value,
+                        new,
+                    });
+                    if let Some(slot) = &mut self.hw_breakpoints[slot] {
+                        slot.value = new;
+                    }
+                }
+                return Ok(());
+            }
+        }
+
+        let addr = (self.registers.rip - 1) as usize;
+        if let Some(original) = self.breakpoints.get(&addr).map(|b| b.original) {
+            info!("hit breakpoint: {:x}", addr);
+            self.registers.rip = addr as u64;
+            self.restore_byte(addr, original)?;
+            ptrace::setregs(self.tid, &self.registers.clone().into())?;
+            self.lifted = Some(addr);
+            let breakpoint = &self.breakpoints[&addr];
+            let quiet =
+                breakpoint.tracepoint.is_some() || breakpoint.watch_scope || breakpoint.loader;
+            if !quiet && self.condition_holds(addr) {
+                let at = self.describe(addr as u64);
+                self.events.record(format!("breakpoint at {}", at));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// A condition that fails to evaluate (say, a bad pointer deref) counts
+    /// as true, stopping is the safer mistake. A breakpoint for another
+    /// thread never holds.
+    fn condition_holds(&self, addr: usize) -> bool {
+        let breakpoint = match self.breakpoints.get(&addr) {
+            Some(breakpoint) => breakpoint,
+            None => return true,
+        };
+        if let Some(number) = breakpoint.thread {
+            let current = self.threads.iter().find(|thread| thread.tid == self.tid);
+            if current.is_some_and(|thread| thread.number != number) {
+                return false;
+            }
+        }
+        let condition = match &breakpoint.condition {
+            Some(condition) => condition,
+            None => return true,
+        };
+        match condition.evaluate(self) {
+            Ok(value) => value != 0,
+            Err(e) => {
+                warn!(
+                    "couldn't evaluate breakpoint condition at 0x{:x}: {}",
+                    addr, e
+                );
+                true
+            }
+        }
+    }
+
+    fn remove_if_temporary(&mut self) -> Result<()> {
+        match self.lifted {
+            Some(addr) if self.breakpoints.get(&addr).is_some_and(|b| b.temporary) => {
+                self.remove_breakpoint(addr)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn record_trace(&mut self, addr: usize) -> bool {
+        let event = match self
+            .breakpoints
+            .get(&addr)
+            .and_then(|b| b.tracepoint.as_ref())
+        {
+            Some(tracepoint) => tracepoint.record(self),
+            None => return false,
+        };
+        self.trace_events.push(event);
+        true
+    }
+
+    /// Like breakpoint conditions, one that fails to evaluate counts as true.
+    fn syscall_condition_holds(&self, number: u64, args: &[u64; 6]) -> bool {
+        let condition = match self.traced_syscalls.get(&number) {
+            Some(Some(condition)) => condition,
+            Some(None) => return true,
+            None => return false,
+        };
+        let names = |name: &str| syscalls::argument_named(number, args, name);
+        match condition.evaluate_with(self, &names) {
+            Ok(value) => value != 0,
+            Err(e) => {
+                warn!("couldn't evaluate syscall trace condition: {}", e);
+                true
+            }
+        }
+    }
+
+    fn record_syscall(&mut self) {
+        let regs = &self.registers;
+        match self.in_syscall.take() {
+            None => {
+                let number = regs.orig_rax;
+                let args = syscalls::arguments(regs);
+                let traced = self.syscall_condition_holds(number, &args);
+                let entry_caught = self.caught_entries.contains(&number);
+                let pending = if traced || entry_caught || self.caught_syscalls.contains(&number) {
+                    Some(PendingSyscall {
+                        number,
+                        call: syscalls::format_call(self, number, &args),
+                        path: syscalls::path_argument(self, number, &args),
+                        entered: Instant::now(),
+                        traced,
+                    })
+                } else {
+                    None
+                };
+                if let Some(pending) = pending.as_ref().filter(|_| entry_caught) {
+                    self.events.record(format!("caught {}", pending.call));
+                    self.caught = Some(pending.call.clone());
+                }
+                self.in_syscall = Some(pending);
+            }
+            Some(None) => self.fix_syscall_result(),
+            Some(Some(pending)) => {
+                self.fix_syscall_result();
+                let regs = &self.registers;
+                let result = syscalls::format_return(regs.orig_rax, regs.rax);
+                let description = format!("{} = {}", pending.call, result);
+                if self.caught_syscalls.contains(&pending.number) {
+                    self.events.record(format!("caught {}", description));
+                    self.caught = Some(description.clone());
+                }
+                if !pending.traced {
+                    return;
+                }
+                let name = match syscalls::by_number(pending.number) {
+                    Some(syscall) => syscall.name.to_owned(),
+                    None => format!("syscall_{}", pending.number),
+                };
+                self.trace_events.push(TraceEvent {
+                    name,
+                    description,
+                    time: pending.entered,
+                    duration: Some(pending.entered.elapsed()),
+                    path: pending.path,
+                    errno: syscalls::errno(regs.rax),
+                });
+            }
+        }
+    }
+
+    /// At a syscall's exit stop, replaces what getrandom and the clock
+    /// syscalls returned with fixed values if the launch asked for them.
+    fn fix_syscall_result(&mut self) {
+        let mut fixed = match self.fixed_syscalls.take() {
+            Some(fixed) => fixed,
+            None => return,
+        };
+        let regs = self.registers.clone();
+        let (seconds, nanos) = match regs.orig_rax as i64 {
+            libc::SYS_time | libc::SYS_gettimeofday | libc::SYS_clock_gettime => fixed.now(),
+            _ => (0, 0),
+        };
+        let fixed_up = match regs.orig_rax as i64 {
+            libc::SYS_getrandom if (regs.rax as i64) > 0 => {
+                let bytes = fixed.random_bytes(regs.rax as usize);
+                self.patch(regs.rdi as usize, &bytes).map(|_| ())
+            }
+            libc::SYS_time => {
+                let mut regs = regs.clone();
+                regs.rax = seconds;
+                let stored = match regs.rdi {
+                    0 => Ok(()),
+                    at => self.patch(at as usize, &seconds.to_le_bytes()).map(|_| ()),
+                };
+                stored.and_then(|_| {
+                    ptrace::setregs(self.tid, &regs.clone().into())?;
+                    self.registers = regs;
+                    Ok(())
+                })
+            }
+            libc::SYS_gettimeofday if regs.rax == 0 && regs.rdi != 0 => self
+                .patch(regs.rdi as usize, &timespec(seconds, nanos / 1000))
+                .map(|_| ()),
+            libc::SYS_clock_gettime if regs.rax == 0 && regs.rsi != 0 => self
+                .patch(regs.rsi as usize, &timespec(seconds, nanos))
+                .map(|_| ()),
+            _ => Ok(()),
+        };
+        if let Err(e) = fixed_up {
+            warn!(
+                "couldn't fix the result of syscall {}: {}",
+                regs.orig_rax, e
+            );
+        }
+        self.fixed_syscalls = Some(fixed);
+    }
+
+    /// Runs one instruction per the step mode and waits for it, leaving the
+    /// subordinate as PTRACE_SINGLESTEP would.
+    fn single_step(&mut self) -> Result<()> {
+        let rip = self.registers.rip;
+        let successors = match self.step_mode {
+            StepMode::Hardware => Vec::new(),
+            StepMode::Software => Disassembler::new().successors(self)?,
+        };
+        // An instruction that jumps to itself can't be trapped with int3.
+        if successors.is_empty() || successors.contains(&rip) {
+            self.resume_with(ptrace::singlestep)?;
+            self.wait_status = self.next_status()?;
+            return Ok(());
+        }
+
+        let mut planted = Vec::new();
+        for addr in successors.iter().map(|addr| *addr as usize) {
+            if self.breakpoints.contains_key(&addr) || planted.iter().any(|(a, _)| *a == addr) {
+                continue;
+            }
+            let data = self.peek(addr)?;
+            self.store(addr, &[INT3])?;
+            planted.push((addr, data));
+        }
+
+        self.resume_with(ptrace::cont)?;
+        self.wait_status = self.next_status()?;
+        if let Stopped(_, signal) = self.wait_status {
+            for (addr, data) in planted {
+                self.restore_byte(addr, data)?;
+            }
+            let mut regs = ptrace::getregs(self.tid)?;
+            if signal == libc::SIGTRAP && successors.contains(&(regs.rip - 1)) {
+                regs.rip -= 1;
+                ptrace::setregs(self.tid, &regs)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Steps over the instruction a hardware breakpoint stopped on, with the
+    /// breakpoint disabled for that one step so it doesn't fire again.
+    fn step_past_hw_breakpoint(&mut self) -> Result<()> {
+        if self.hw_lifted.take().is_none() {
+            return Ok(());
+        }
+
+        self.set_debug_register(hardware::DR7, 0)?;
+        self.single_step()?;
+        if let Stopped(_, _) = self.wait_status {
+            let dr7 = hardware::control(&self.hw_breakpoints);
+            self.set_debug_register(hardware::DR7, dr7 as usize)?;
+            self.refresh()?;
+        }
+        Ok(())
+    }
+
+    /// Single-steps the original instruction under a lifted breakpoint and
+    /// puts the int3 back behind it.
+    fn step_past_breakpoint(&mut self) -> Result<()> {
+        let addr = match self.lifted.take() {
+            Some(addr) => addr,
+            None => return Ok(()),
+        };
+
+        self.single_step()?;
+        if let Stopped(_, _) = self.wait_status {
+            if self.breakpoints.contains_key(&addr) {
+                self.store(addr, &[INT3])?;
+            }
+            self.refresh()?;
+        }
         Ok(())
     }
 }
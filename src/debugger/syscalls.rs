@@ -0,0 +1,538 @@
+use crate::debugger::{Registers, Subordinate};
+use crate::sys::signal_name;
+
+const AT_FDCWD: i64 = -100;
+const MAX_STRING_LEN: usize = 256;
+const MAX_BUFFER_PREVIEW: usize = 32;
+
+/// How a syscall argument (or return value) should be rendered.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Arg {
+    Int,
+    UInt,
+    Hex,
+    Ptr,
+    Size,
+    Fd,
+    DirFd,
+    Pid,
+    Path,
+    Str,
+    Buf,
+    OpenFlags,
+    Mode,
+    Prot,
+    MapFlags,
+    AtFlags,
+    AccessMode,
+    Signal,
+}
+
+#[derive(Debug)]
+pub struct Syscall {
+    pub number: u64,
+    pub name: &'static str,
+    pub args: &'static [Arg],
+    pub ret: Arg,
+}
+
+const fn sys(number: u64, name: &'static str, args: &'static [Arg]) -> Syscall {
+    Syscall {
+        number,
+        name,
+        args,
+        ret: Arg::Int,
+    }
+}
+
+const fn sys_ptr(number: u64, name: &'static str, args: &'static [Arg]) -> Syscall {
+    Syscall {
+        number,
+        name,
+        args,
+        ret: Arg::Ptr,
+    }
+}
+
+use Arg::*;
+
+/// x86_64 syscalls, in syscall number order.
+pub static SYSCALLS: &[Syscall] = &[
+    sys(0, "read", &[Fd, Ptr, Size]),
+    sys(1, "write", &[Fd, Buf, Size]),
+    sys(2, "open", &[Path, OpenFlags, Mode]),
+    sys(3, "close", &[Fd]),
+    sys(4, "stat", &[Path, Ptr]),
+    sys(5, "fstat", &[Fd, Ptr]),
+    sys(6, "lstat", &[Path, Ptr]),
+    sys(7, "poll", &[Ptr, UInt, Int]),
+    sys(8, "lseek", &[Fd, Int, Int]),
+    sys_ptr(9, "mmap", &[Ptr, Size, Prot, MapFlags, Fd, Hex]),
+    sys(10, "mprotect", &[Ptr, Size, Prot]),
+    sys(11, "munmap", &[Ptr, Size]),
+    sys_ptr(12, "brk", &[Ptr]),
+    sys(13, "rt_sigaction", &[Signal, Ptr, Ptr, Size]),
+    sys(14, "rt_sigprocmask", &[Int, Ptr, Ptr, Size]),
+    sys(15, "rt_sigreturn", &[]),
+    sys(16, "ioctl", &[Fd, Hex, Ptr]),
+    sys(17, "pread64", &[Fd, Ptr, Size, Int]),
+    sys(18, "pwrite64", &[Fd, Buf, Size, Int]),
+    sys(19, "readv", &[Fd, Ptr, Int]),
+    sys(20, "writev", &[Fd, Ptr, Int]),
+    sys(21, "access", &[Path, AccessMode]),
+    sys(22, "pipe", &[Ptr]),
+    sys(23, "select", &[Int, Ptr, Ptr, Ptr, Ptr]),
+    sys(24, "sched_yield", &[]),
+    sys_ptr(25, "mremap", &[Ptr, Size, Size, Hex, Ptr]),
+    sys(26, "msync", &[Ptr, Size, Hex]),
+    sys(27, "mincore", &[Ptr, Size, Ptr]),
+    sys(28, "madvise", &[Ptr, Size, Int]),
+    sys(32, "dup", &[Fd]),
+    sys(33, "dup2", &[Fd, Fd]),
+    sys(34, "pause", &[]),
+    sys(35, "nanosleep", &[Ptr, Ptr]),
+    sys(37, "alarm", &[UInt]),
+    sys(39, "getpid", &[]),
+    sys(40, "sendfile", &[Fd, Fd, Ptr, Size]),
+    sys(41, "socket", &[Int, Int, Int]),
+    sys(42, "connect", &[Fd, Ptr, Int]),
+    sys(43, "accept", &[Fd, Ptr, Ptr]),
+    sys(44, "sendto", &[Fd, Buf, Size, Hex, Ptr, Int]),
+    sys(45, "recvfrom", &[Fd, Ptr, Size, Hex, Ptr, Ptr]),
+    sys(46, "sendmsg", &[Fd, Ptr, Hex]),
+    sys(47, "recvmsg", &[Fd, Ptr, Hex]),
+    sys(48, "shutdown", &[Fd, Int]),
+    sys(49, "bind", &[Fd, Ptr, Int]),
+    sys(50, "listen", &[Fd, Int]),
+    sys(51, "getsockname", &[Fd, Ptr, Ptr]),
+    sys(52, "getpeername", &[Fd, Ptr, Ptr]),
+    sys(53, "socketpair", &[Int, Int, Int, Ptr]),
+    sys(54, "setsockopt", &[Fd, Int, Int, Ptr, Int]),
+    sys(55, "getsockopt", &[Fd, Int, Int, Ptr, Ptr]),
+    sys(56, "clone", &[Hex, Ptr, Ptr, Ptr, Hex]),
+    sys(57, "fork", &[]),
+    sys(58, "vfork", &[]),
+    sys(59, "execve", &[Path, Ptr, Ptr]),
+    sys(60, "exit", &[Int]),
+    sys(61, "wait4", &[Pid, Ptr, Hex, Ptr]),
+    sys(62, "kill", &[Pid, Signal]),
+    sys(63, "uname", &[Ptr]),
+    sys(72, "fcntl", &[Fd, Int, Hex]),
+    sys(73, "flock", &[Fd, Int]),
+    sys(74, "fsync", &[Fd]),
+    sys(75, "fdatasync", &[Fd]),
+    sys(76, "truncate", &[Path, Int]),
+    sys(77, "ftruncate", &[Fd, Int]),
+    sys(78, "getdents", &[Fd, Ptr, Size]),
+    sys(79, "getcwd", &[Ptr, Size]),
+    sys(80, "chdir", &[Path]),
+    sys(81, "fchdir", &[Fd]),
+    sys(82, "rename", &[Path, Path]),
+    sys(83, "mkdir", &[Path, Mode]),
+    sys(84, "rmdir", &[Path]),
+    sys(85, "creat", &[Path, Mode]),
+    sys(86, "link", &[Path, Path]),
+    sys(87, "unlink", &[Path]),
+    sys(88, "symlink", &[Path, Path]),
+    sys(89, "readlink", &[Path, Ptr, Size]),
+    sys(90, "chmod", &[Path, Mode]),
+    sys(91, "fchmod", &[Fd, Mode]),
+    sys(92, "chown", &[Path, Int, Int]),
+    sys(93, "fchown", &[Fd, Int, Int]),
+    sys(95, "umask", &[Mode]),
+    sys(96, "gettimeofday", &[Ptr, Ptr]),
+    sys(97, "getrlimit", &[Int, Ptr]),
+    sys(98, "getrusage", &[Int, Ptr]),
+    sys(99, "sysinfo", &[Ptr]),
+    sys(102, "getuid", &[]),
+    sys(104, "getgid", &[]),
+    sys(105, "setuid", &[Int]),
+    sys(106, "setgid", &[Int]),
+    sys(107, "geteuid", &[]),
+    sys(108, "getegid", &[]),
+    sys(109, "setpgid", &[Pid, Pid]),
+    sys(110, "getppid", &[]),
+    sys(111, "getpgrp", &[]),
+    sys(112, "setsid", &[]),
+    sys(131, "sigaltstack", &[Ptr, Ptr]),
+    sys(137, "statfs", &[Path, Ptr]),
+    sys(138, "fstatfs", &[Fd, Ptr]),
+    sys(157, "prctl", &[Int, Hex, Hex, Hex, Hex]),
+    sys(158, "arch_prctl", &[Hex, Ptr]),
+    sys(186, "gettid", &[]),
+    sys(200, "tkill", &[Pid, Signal]),
+    sys(201, "time", &[Ptr]),
+    sys(202, "futex", &[Ptr, Int, Int, Ptr, Ptr, Int]),
+    sys(204, "sched_getaffinity", &[Pid, Size, Ptr]),
+    sys(217, "getdents64", &[Fd, Ptr, Size]),
+    sys(218, "set_tid_address", &[Ptr]),
+    sys(228, "clock_gettime", &[Int, Ptr]),
+    sys(230, "clock_nanosleep", &[Int, Hex, Ptr, Ptr]),
+    sys(231, "exit_group", &[Int]),
+    sys(232, "epoll_wait", &[Fd, Ptr, Int, Int]),
+    sys(233, "epoll_ctl", &[Fd, Int, Fd, Ptr]),
+    sys(234, "tgkill", &[Pid, Pid, Signal]),
+    sys(257, "openat", &[DirFd, Path, OpenFlags, Mode]),
+    sys(258, "mkdirat", &[DirFd, Path, Mode]),
+    sys(262, "newfstatat", &[DirFd, Path, Ptr, AtFlags]),
+    sys(263, "unlinkat", &[DirFd, Path, AtFlags]),
+    sys(264, "renameat", &[DirFd, Path, DirFd, Path]),
+    sys(267, "readlinkat", &[DirFd, Path, Ptr, Size]),
+    sys(268, "fchmodat", &[DirFd, Path, Mode]),
+    sys(269, "faccessat", &[DirFd, Path, AccessMode]),
+    sys(270, "pselect6", &[Int, Ptr, Ptr, Ptr, Ptr, Ptr]),
+    sys(271, "ppoll", &[Ptr, UInt, Ptr, Ptr, Size]),
+    sys(273, "set_robust_list", &[Ptr, Size]),
+    sys(281, "epoll_pwait", &[Fd, Ptr, Int, Int, Ptr, Size]),
+    sys(284, "eventfd", &[UInt]),
+    sys(288, "accept4", &[Fd, Ptr, Ptr, Hex]),
+    sys(290, "eventfd2", &[UInt, Hex]),
+    sys(291, "epoll_create1", &[Hex]),
+    sys(292, "dup3", &[Fd, Fd, OpenFlags]),
+    sys(293, "pipe2", &[Ptr, OpenFlags]),
+    sys(302, "prlimit64", &[Pid, Int, Ptr, Ptr]),
+    sys(318, "getrandom", &[Ptr, Size, Hex]),
+    sys(319, "memfd_create", &[Path, Hex]),
+    sys(322, "execveat", &[DirFd, Path, Ptr, Ptr, AtFlags]),
+    sys(332, "statx", &[DirFd, Path, AtFlags, Hex, Ptr]),
+    sys(334, "rseq", &[Ptr, UInt, Hex, Hex]),
+    sys(435, "clone3", &[Ptr, Size]),
+    sys(439, "faccessat2", &[DirFd, Path, AccessMode, AtFlags]),
+];
+
+static ERRNO_NAMES: &[(i32, &str)] = &[
+    (1, "EPERM"),
+    (2, "ENOENT"),
+    (3, "ESRCH"),
+    (4, "EINTR"),
+    (5, "EIO"),
+    (6, "ENXIO"),
+    (7, "E2BIG"),
+    (8, "ENOEXEC"),
+    (9, "EBADF"),
+    (10, "ECHILD"),
+    (11, "EAGAIN"),
+    (12, "ENOMEM"),
+    (13, "EACCES"),
+    (14, "EFAULT"),
+    (16, "EBUSY"),
+    (17, "EEXIST"),
+    (18, "EXDEV"),
+    (19, "ENODEV"),
+    (20, "ENOTDIR"),
+    (21, "EISDIR"),
+    (22, "EINVAL"),
+    (23, "ENFILE"),
+    (24, "EMFILE"),
+    (25, "ENOTTY"),
+    (26, "ETXTBSY"),
+    (27, "EFBIG"),
+    (28, "ENOSPC"),
+    (29, "ESPIPE"),
+    (30, "EROFS"),
+    (31, "EMLINK"),
+    (32, "EPIPE"),
+    (33, "EDOM"),
+    (34, "ERANGE"),
+    (35, "EDEADLK"),
+    (36, "ENAMETOOLONG"),
+    (37, "ENOLCK"),
+    (38, "ENOSYS"),
+    (39, "ENOTEMPTY"),
+    (40, "ELOOP"),
+    (61, "ENODATA"),
+    (62, "ETIME"),
+    (75, "EOVERFLOW"),
+    (88, "ENOTSOCK"),
+    (95, "EOPNOTSUPP"),
+    (97, "EAFNOSUPPORT"),
+    (98, "EADDRINUSE"),
+    (99, "EADDRNOTAVAIL"),
+    (101, "ENETUNREACH"),
+    (104, "ECONNRESET"),
+    (105, "ENOBUFS"),
+    (106, "EISCONN"),
+    (107, "ENOTCONN"),
+    (110, "ETIMEDOUT"),
+    (111, "ECONNREFUSED"),
+    (113, "EHOSTUNREACH"),
+    (114, "EALREADY"),
+    (115, "EINPROGRESS"),
+];
+
+static OPEN_FLAGS: &[(u64, &str)] = &[
+    (libc::O_CREAT as u64, "O_CREAT"),
+    (libc::O_EXCL as u64, "O_EXCL"),
+    (libc::O_NOCTTY as u64, "O_NOCTTY"),
+    (libc::O_TRUNC as u64, "O_TRUNC"),
+    (libc::O_APPEND as u64, "O_APPEND"),
+    (libc::O_NONBLOCK as u64, "O_NONBLOCK"),
+    (libc::O_DSYNC as u64, "O_DSYNC"),
+    (libc::O_DIRECT as u64, "O_DIRECT"),
+    (libc::O_LARGEFILE as u64, "O_LARGEFILE"),
+    (libc::O_DIRECTORY as u64, "O_DIRECTORY"),
+    (libc::O_NOFOLLOW as u64, "O_NOFOLLOW"),
+    (libc::O_NOATIME as u64, "O_NOATIME"),
+    (libc::O_CLOEXEC as u64, "O_CLOEXEC"),
+    (libc::O_PATH as u64, "O_PATH"),
+];
+
+static PROT_FLAGS: &[(u64, &str)] = &[
+    (libc::PROT_READ as u64, "PROT_READ"),
+    (libc::PROT_WRITE as u64, "PROT_WRITE"),
+    (libc::PROT_EXEC as u64, "PROT_EXEC"),
+];
+
+static MAP_FLAGS: &[(u64, &str)] = &[
+    (libc::MAP_SHARED as u64, "MAP_SHARED"),
+    (libc::MAP_PRIVATE as u64, "MAP_PRIVATE"),
+    (libc::MAP_FIXED as u64, "MAP_FIXED"),
+    (libc::MAP_ANONYMOUS as u64, "MAP_ANONYMOUS"),
+    (libc::MAP_GROWSDOWN as u64, "MAP_GROWSDOWN"),
+    (libc::MAP_DENYWRITE as u64, "MAP_DENYWRITE"),
+    (libc::MAP_NORESERVE as u64, "MAP_NORESERVE"),
+    (libc::MAP_POPULATE as u64, "MAP_POPULATE"),
+    (libc::MAP_STACK as u64, "MAP_STACK"),
+];
+
+static AT_FLAGS: &[(u64, &str)] = &[
+    (libc::AT_SYMLINK_NOFOLLOW as u64, "AT_SYMLINK_NOFOLLOW"),
+    (libc::AT_REMOVEDIR as u64, "AT_REMOVEDIR"),
+    (libc::AT_SYMLINK_FOLLOW as u64, "AT_SYMLINK_FOLLOW"),
+    (libc::AT_EMPTY_PATH as u64, "AT_EMPTY_PATH"),
+];
+
+static ACCESS_MODES: &[(u64, &str)] = &[
+    (libc::R_OK as u64, "R_OK"),
+    (libc::W_OK as u64, "W_OK"),
+    (libc::X_OK as u64, "X_OK"),
+];
+
+/// Syscalls `trace files` watches, everything that looks a path up.
+pub const FILE_SYSCALLS: &[&str] = &[
+    "open",
+    "openat",
+    "stat",
+    "lstat",
+    "newfstatat",
+    "statx",
+    "access",
+    "faccessat",
+    "faccessat2",
+    "readlink",
+    "readlinkat",
+    "execve",
+];
+
+pub fn by_number(number: u64) -> Option<&'static Syscall> {
+    SYSCALLS.iter().find(|syscall| syscall.number == number)
+}
+
+pub fn by_name(name: &str) -> Option<&'static Syscall> {
+    SYSCALLS.iter().find(|syscall| syscall.name == name)
+}
+
+impl Arg {
+    /// The name a tracer condition can use for the first argument of this kind.
+    pub fn name(self) -> &'static str {
+        match self {
+            Int => "int",
+            UInt => "uint",
+            Hex => "hex",
+            Ptr => "ptr",
+            Size => "size",
+            Fd => "fd",
+            DirFd => "dirfd",
+            Pid => "pid",
+            Path => "path",
+            Str => "str",
+            Buf => "buf",
+            OpenFlags | MapFlags | AtFlags => "flags",
+            Mode | AccessMode => "mode",
+            Prot => "prot",
+            Signal => "sig",
+        }
+    }
+}
+
+/// Looks up an argument of a call by position (`arg0`..`arg5`) or by the
+/// name of its kind, e.g. `fd` for the first argument of `write`.
+pub fn argument_named(number: u64, args: &[u64; 6], name: &str) -> Option<u64> {
+    if let Some(index) = name.strip_prefix("arg") {
+        return index
+            .parse::<usize>()
+            .ok()
+            .and_then(|i| args.get(i).cloned());
+    }
+    let syscall = by_number(number)?;
+    let index = syscall.args.iter().position(|kind| kind.name() == name)?;
+    Some(args[index])
+}
+
+/// The first path argument of a call, for syscalls that take one.
+pub fn path_argument(subordinate: &Subordinate, number: u64, args: &[u64; 6]) -> Option<String> {
+    let syscall = by_number(number)?;
+    let index = syscall.args.iter().position(|kind| *kind == Path)?;
+    subordinate
+        .read_string(args[index] as usize, MAX_STRING_LEN)
+        .ok()
+}
+
+/// The errno of a failed syscall's return value.
+pub fn errno(value: u64) -> Option<i32> {
+    let signed = value as i64;
+    if (-4095..0).contains(&signed) {
+        Some(-signed as i32)
+    } else {
+        None
+    }
+}
+
+pub fn errno_name(errno: i32) -> Option<&'static str> {
+    ERRNO_NAMES
+        .iter()
+        .find(|(number, _)| *number == errno)
+        .map(|(_, name)| *name)
+}
+
+/// The syscall arguments in the order the kernel takes them.
+pub fn arguments(regs: &Registers) -> [u64; 6] {
+    [regs.rdi, regs.rsi, regs.rdx, regs.r10, regs.r8, regs.r9]
+}
+
+/// The integer arguments of a function call per the System V ABI, which
+/// differs from the syscall convention only in using rcx instead of r10.
+pub fn function_arguments(regs: &Registers) -> [u64; 6] {
+    [regs.rdi, regs.rsi, regs.rdx, regs.rcx, regs.r8, regs.r9]
+}
+
+/// Formats a library call like `getenv("HOME")` using the same argument
+/// kinds as syscalls.
+pub fn format_function(
+    subordinate: &Subordinate,
+    name: &str,
+    kinds: &[Arg],
+    args: &[u64; 6],
+) -> String {
+    let formatted: Vec<String> = kinds
+        .iter()
+        .enumerate()
+        .map(|(i, kind)| format_arg(subordinate, *kind, args[i], args.get(i + 1).cloned()))
+        .collect();
+    format!("{}({})", name, formatted.join(", "))
+}
+
+/// Formats a call strace-style, e.g. `openat(AT_FDCWD, "foo.txt", O_RDONLY)`.
+pub fn format_call(subordinate: &Subordinate, number: u64, args: &[u64; 6]) -> String {
+    let syscall = match by_number(number) {
+        Some(syscall) => syscall,
+        None => {
+            let raw: Vec<String> = args.iter().map(|arg| format!("0x{:x}", arg)).collect();
+            return format!("syscall_{}({})", number, raw.join(", "));
+        }
+    };
+
+    format_function(subordinate, syscall.name, syscall.args, args)
+}
+
+/// Formats a return value, decoding errors, e.g. `-1 ENOENT (No such file or directory)`.
+pub fn format_return(number: u64, value: u64) -> String {
+    if let Some(errno) = errno(value) {
+        let description = std::io::Error::from_raw_os_error(errno).to_string();
+        let description = description.split(" (os error").next().unwrap_or("");
+        return match errno_name(errno) {
+            Some(name) => format!("-1 {} ({})", name, description),
+            None => format!("-1 errno {} ({})", errno, description),
+        };
+    }
+
+    match by_number(number).map(|syscall| syscall.ret) {
+        Some(Arg::Ptr) => format!("0x{:x}", value),
+        _ => format!("{}", value as i64),
+    }
+}
+
+fn format_arg(subordinate: &Subordinate, kind: Arg, value: u64, next: Option<u64>) -> String {
+    match kind {
+        Int | Fd | Pid => format!("{}", value as i64 as i32),
+        UInt | Size => format!("{}", value),
+        Hex => format!("0x{:x}", value),
+        Ptr if value == 0 => "NULL".to_owned(),
+        Ptr => format!("0x{:x}", value),
+        DirFd if value as i64 as i32 as i64 == AT_FDCWD => "AT_FDCWD".to_owned(),
+        DirFd => format!("{}", value as i64 as i32),
+        Path | Str => match subordinate.read_string(value as usize, MAX_STRING_LEN) {
+            Ok(s) => format!("{:?}", s),
+            Err(_) => format!("0x{:x}", value),
+        },
+        Buf => {
+            let len = next.unwrap_or(0) as usize;
+            let shown = len.min(MAX_BUFFER_PREVIEW);
+            match subordinate.read_bytes(value as usize, shown) {
+                Ok(bytes) => {
+                    let text = format!("{:?}", String::from_utf8_lossy(&bytes));
+                    if shown < len {
+                        format!("{}...", text)
+                    } else {
+                        text
+                    }
+                }
+                Err(_) => format!("0x{:x}", value),
+            }
+        }
+        OpenFlags => format_open_flags(value),
+        Mode if value == 0 => "0".to_owned(),
+        Mode => format!("0{:o}", value),
+        Prot if value == 0 => "PROT_NONE".to_owned(),
+        Prot => format_flags(value, PROT_FLAGS),
+        MapFlags => format_flags(value, MAP_FLAGS),
+        AtFlags => format_flags(value, AT_FLAGS),
+        AccessMode if value == 0 => "F_OK".to_owned(),
+        AccessMode => format_flags(value, ACCESS_MODES),
+        Signal => match signal_name(value as i32) {
+            Some(name) => name.to_owned(),
+            None => format!("{}", value),
+        },
+    }
+}
+
+/// Formats `open` flags, e.g. `O_WRONLY|O_CREAT|O_TRUNC`.
+pub fn format_open_flags(value: u64) -> String {
+    let mode = match value & libc::O_ACCMODE as u64 {
+        0 => "O_RDONLY",
+        1 => "O_WRONLY",
+        _ => "O_RDWR",
+    };
+    let rest = format_flags(value & !(libc::O_ACCMODE as u64), OPEN_FLAGS);
+    if rest == "0" {
+        mode.to_owned()
+    } else {
+        format!("{}|{}", mode, rest)
+    }
+}
+
+/// Whether every bit of `value` is an `open` flag, with at least one
+/// beyond the access mode.
+pub fn is_open_flags(value: u64) -> bool {
+    let known = OPEN_FLAGS.iter().fold(0, |all, (bit, _)| all | bit);
+    let flags = value & !(libc::O_ACCMODE as u64);
+    flags != 0 && flags & !known == 0 && value & libc::O_ACCMODE as u64 != 3
+}
+
+fn format_flags(value: u64, table: &[(u64, &str)]) -> String {
+    let mut names = Vec::new();
+    let mut rest = value;
+    for (bit, name) in table {
+        if value & bit != 0 {
+            names.push((*name).to_owned());
+            rest &= !bit;
+        }
+    }
+    if rest != 0 {
+        names.push(format!("0x{:x}", rest));
+    }
+    if names.is_empty() {
+        return "0".to_owned();
+    }
+    names.join("|")
+}
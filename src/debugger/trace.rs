@@ -0,0 +1,104 @@
+use crate::debugger::syscalls::{self, Arg};
+use crate::debugger::Subordinate;
+use std::time::{Duration, Instant};
+
+/// A breakpoint that records the call and its arguments instead of stopping.
+#[derive(Debug, Clone)]
+pub struct Tracepoint {
+    pub name: String,
+    pub args: &'static [Arg],
+}
+
+#[derive(Debug, Clone)]
+pub struct TraceEvent {
+    pub name: String,
+    pub description: String,
+    pub time: Instant,
+    // How long a syscall took from entry to exit, tracepoints are instants.
+    pub duration: Option<Duration>,
+    // The path argument of a traced syscall, and its errno if it failed.
+    pub path: Option<String>,
+    pub errno: Option<i32>,
+}
+
+impl Tracepoint {
+    pub fn new(name: &str, args: &'static [Arg]) -> Self {
+        Self {
+            name: name.to_owned(),
+            args,
+        }
+    }
+
+    pub fn record(&self, subordinate: &Subordinate) -> TraceEvent {
+        let args = syscalls::function_arguments(subordinate.registers());
+        TraceEvent {
+            name: self.name.clone(),
+            description: syscalls::format_function(subordinate, &self.name, self.args, &args),
+            time: Instant::now(),
+            duration: None,
+            path: None,
+            errno: None,
+        }
+    }
+}
+
+/// A traced or caught syscall between its entry and exit stops.
+#[derive(Debug, Clone)]
+pub(crate) struct PendingSyscall {
+    pub number: u64,
+    pub call: String,
+    pub path: Option<String>,
+    pub entered: Instant,
+    // Whether it goes in the trace, rather than only being caught.
+    pub traced: bool,
+}
+
+/// Renders events in the Chrome trace event format, which chrome://tracing
+/// and Perfetto load. Syscalls become complete events spanning entry to
+/// exit, tracepoints become instant events.
+pub fn chrome_trace(events: &[TraceEvent], pid: i32) -> String {
+    let start = match events.first() {
+        Some(event) => event.time,
+        None => return "{\"traceEvents\":[]}\n".to_owned(),
+    };
+
+    let entries: Vec<String> = events
+        .iter()
+        .map(|event| {
+            let ts = event.time.duration_since(start).as_micros();
+            let phase = match event.duration {
+                Some(duration) => format!("\"ph\":\"X\",\"dur\":{}", duration.as_micros()),
+                None => "\"ph\":\"i\",\"s\":\"t\"".to_owned(),
+            };
+            format!(
+                "{{\"name\":{},{},\"ts\":{},\"pid\":{},\"tid\":{},\"args\":{{\"call\":{}}}}}",
+                json_string(&event.name),
+                phase,
+                ts,
+                pid,
+                pid,
+                json_string(&event.description)
+            )
+        })
+        .collect();
+
+    format!("{{\"traceEvents\":[\n{}\n]}}\n", entries.join(",\n"))
+}
+
+pub fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
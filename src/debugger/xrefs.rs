@@ -0,0 +1,89 @@
+use crate::debugger::{Disassembler, Subordinate};
+use crate::result::Result;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::Range;
+
+/// How an instruction refers to an address.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RefKind {
+    Call,
+    Jump,
+    // A rip-relative memory operand or an immediate, like loading a
+    // global or the address of a string.
+    Data,
+}
+
+impl fmt::Display for RefKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            RefKind::Call => "call",
+            RefKind::Jump => "jump",
+            RefKind::Data => "data",
+        })
+    }
+}
+
+/// Every address the code in `.text` refers to directly, with the
+/// instructions that refer to it. Targets only known at runtime, like calls
+/// through a register, aren't in it.
+#[derive(Debug, Clone, Default)]
+pub struct Xrefs {
+    by_target: BTreeMap<u64, Vec<(u64, RefKind)>>,
+}
+
+impl Xrefs {
+    /// Disassembles the binary's `.text` section as it is on disk, so
+    /// breakpoints in the process don't get in the way.
+    pub fn build(subordinate: &Subordinate) -> Result<Self> {
+        let elf = elf::File::open_path(subordinate.path())?;
+        let text = elf
+            .get_section(".text")
+            .ok_or("the binary has no .text section")?;
+        let start = text.shdr.addr + subordinate.load_bias();
+
+        let mut by_target: BTreeMap<u64, Vec<(u64, RefKind)>> = BTreeMap::new();
+        for (from, target, kind) in Disassembler::new().references(start, &text.data) {
+            by_target.entry(target).or_default().push((from, kind));
+        }
+        Ok(Xrefs { by_target })
+    }
+
+    /// The instructions referring to `addr`, in address order.
+    pub fn to(&self, addr: u64) -> &[(u64, RefKind)] {
+        self.by_target.get(&addr).map_or(&[], Vec::as_slice)
+    }
+
+    /// The instructions referring to anywhere in `range`, like into the
+    /// middle of a string that shares its bytes with others.
+    pub fn within(&self, range: Range<u64>) -> impl Iterator<Item = &(u64, RefKind)> {
+        self.by_target.range(range).flat_map(|(_, from)| from)
+    }
+}
+
+/// Runs of at least `min_len` printable ASCII characters in the binary's
+/// `.rodata`, with their addresses. Strings in Rust aren't NUL-terminated,
+/// so neighbours can run together.
+pub fn rodata_strings(subordinate: &Subordinate, min_len: usize) -> Result<Vec<(u64, String)>> {
+    let elf = elf::File::open_path(subordinate.path())?;
+    let rodata = elf
+        .get_section(".rodata")
+        .ok_or("the binary has no .rodata section")?;
+    let start = rodata.shdr.addr + subordinate.load_bias();
+
+    let printable = |b: &u8| (0x20..0x7f).contains(b) || *b == b'\t' || *b == b'\n';
+    let mut strings = Vec::new();
+    let mut at = 0;
+    while at < rodata.data.len() {
+        let len = rodata.data[at..]
+            .iter()
+            .take_while(|b| printable(b))
+            .count();
+        if len >= min_len {
+            let text = String::from_utf8_lossy(&rodata.data[at..at + len]).into_owned();
+            strings.push((start + at as u64, text));
+        }
+        at += len.max(1);
+    }
+    Ok(strings)
+}
@@ -1,4 +1,4 @@
-use libc::c_int;
+use libc::{c_int, pid_t};
 use std::{error, fmt};
 
 #[derive(Debug)]
@@ -6,6 +6,7 @@ pub enum Error {
     String(String),
     NulError(std::ffi::NulError),
     Errno(c_int),
+    TargetGone(pid_t),
     IntoStringError(std::ffi::IntoStringError),
     ParseIntError(std::num::ParseIntError),
     GimliError(gimli::Error),
@@ -21,6 +22,7 @@ impl error::Error for Error {
             Error::String(_) => None,
             Error::NulError(ref e) => Some(e),
             Error::Errno(_) => None,
+            Error::TargetGone(_) => None,
             Error::IntoStringError(ref e) => Some(e),
             Error::ParseIntError(ref e) => Some(e),
             Error::GimliError(ref e) => Some(e),
@@ -38,6 +40,7 @@ impl fmt::Display for Error {
             Error::String(ref s) => f.write_str(s),
             Error::NulError(ref e) => e.fmt(f),
             Error::Errno(errno) => write!(f, "errno {}", errno),
+            Error::TargetGone(pid) => write!(f, "process {} is gone", pid),
             Error::IntoStringError(ref e) => e.fmt(f),
             Error::ParseIntError(ref e) => e.fmt(f),
             Error::GimliError(ref e) => e.fmt(f),
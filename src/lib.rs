@@ -0,0 +1,12 @@
+#![allow(non_upper_case_globals)]
+
+//! The debugger itself, for the command line front end in `main.rs` and
+//! for tests to drive a `Subordinate` directly.
+
+pub mod debugger;
+pub mod error;
+pub mod result;
+pub mod sys;
+
+#[macro_use]
+extern crate log;
@@ -1,23 +1,23 @@
 #![allow(non_upper_case_globals)]
 #![feature(str_strip)]
 
+mod cargo;
 mod cli;
-mod debugger;
-mod error;
-mod result;
-mod sys;
 // mod tui;
 
 #[macro_use]
 extern crate log;
 
-use crate::cli::Cli;
-use crate::debugger::Subordinate;
+use rust_debugger::{debugger, error, result, sys};
+
+use crate::cli::{parse_size, Cli};
+use crate::debugger::{Launch, Subordinate};
 use crate::error::Error;
 use crate::result::Result;
-use crate::sys::{disable_aslr, strerror};
+use crate::sys::{catch_sigint, strerror};
 use human_panic::setup_panic;
 use std::env::args;
+use std::path::PathBuf;
 use std::process::exit;
 
 fn main() {
@@ -38,10 +38,97 @@ fn main() {
     }
 }
 
+/// Debugger flags, which come before the command to debug.
+#[derive(Debug, Default)]
+struct Options {
+    control: Option<PathBuf>,
+    record_stops: Option<PathBuf>,
+    diff_stops: Option<PathBuf>,
+    core: Option<PathBuf>,
+    inspect: bool,
+    watch_binary: bool,
+    launch: Launch,
+    cmd: Vec<String>,
+}
+
+impl Options {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self> {
+        let mut options = Options::default();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--control" => {
+                    let path = args.next().ok_or("--control needs a socket path")?;
+                    options.control = Some(PathBuf::from(path));
+                }
+                "--record-stops" => {
+                    let path = args.next().ok_or("--record-stops needs a file")?;
+                    options.record_stops = Some(PathBuf::from(path));
+                }
+                "--diff-stops" => {
+                    let path = args.next().ok_or("--diff-stops needs a file")?;
+                    options.diff_stops = Some(PathBuf::from(path));
+                }
+                "--core" => {
+                    let path = args.next().ok_or("--core needs a core file")?;
+                    options.core = Some(PathBuf::from(path));
+                }
+                "--inspect" => options.inspect = true,
+                "--watch-binary" => options.watch_binary = true,
+                "--deterministic" => options.launch.make_deterministic(),
+                "--fixed-syscalls" => options.launch.fixed_syscalls = true,
+                "--sandbox" => options.launch.sandbox = true,
+                "--aslr" => options.launch.aslr = true,
+                "--no-aslr" => options.launch.aslr = false,
+                "--sandbox-memory" => {
+                    let size = args.next().ok_or("--sandbox-memory needs a size")?;
+                    options.launch.memory_limit = Some(parse_size(&size)?);
+                }
+                "--" => break,
+                flag if flag.starts_with("--") => {
+                    return Err(format!("unknown option `{}`", flag).into())
+                }
+                _ => {
+                    options.cmd.push(arg);
+                    break;
+                }
+            }
+        }
+        options.cmd.extend(args);
+        Ok(options)
+    }
+}
+
 fn app() -> Result<()> {
-    disable_aslr()?;
-    let subordinate = Subordinate::spawn(args().skip(1).collect())?;
+    let mut options = Options::parse(args().skip(1))?;
+    let mut test = None;
+    if cargo::is_cargo(&options.cmd) {
+        let target = cargo::build(&options.cmd)?;
+        options.cmd = target.cmd;
+        test = target.test;
+    }
+    catch_sigint()?;
+    let subordinate = match &options.core {
+        Some(_) if options.inspect => return Err("--inspect doesn't go with --core".into()),
+        Some(core) => Subordinate::open_core(options.cmd, core)?,
+        None if options.inspect => Subordinate::inspect(options.cmd, options.launch)?,
+        None => Subordinate::spawn(options.cmd, options.launch)?,
+    };
     let mut cli = Cli::new(subordinate);
+    if let Some(test) = test {
+        cli.break_on(&test)?;
+    }
+    if let Some(path) = options.control {
+        cli.control_socket(path);
+    }
+    if options.watch_binary {
+        cli.watch_binary();
+    }
+    if let Some(path) = options.record_stops {
+        cli.record_stops(&path)?;
+    }
+    if let Some(path) = options.diff_stops {
+        cli.diff_stops(&path)?;
+    }
     cli.start()?;
     Ok(())
 }
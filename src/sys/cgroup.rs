@@ -0,0 +1,74 @@
+use crate::result::Result;
+use std::fs;
+use std::path::PathBuf;
+
+const CGROUP_ROOT: &str = "/sys/fs/cgroup";
+
+/// A transient memory cgroup for one run of the subordinate, removed once
+/// the run is over.
+#[derive(Debug)]
+pub struct Cgroup {
+    path: PathBuf,
+}
+
+impl Cgroup {
+    /// Creates a cgroup under the debugger's own, limited to `limit` bytes
+    /// of memory, with either cgroup v2 or the v1 memory controller.
+    pub fn create(limit: u64) -> Result<Self> {
+        let own = fs::read_to_string("/proc/self/cgroup")?;
+        let unified = PathBuf::from(CGROUP_ROOT)
+            .join("cgroup.controllers")
+            .exists();
+        let (parent, limit_file) = if unified {
+            let path = own
+                .lines()
+                .find_map(|line| line.strip_prefix("0::"))
+                .ok_or("not in a cgroup v2 hierarchy")?;
+            (
+                PathBuf::from(CGROUP_ROOT).join(path.trim_start_matches('/')),
+                "memory.max",
+            )
+        } else {
+            let path = own
+                .lines()
+                .filter_map(|line| {
+                    // Each line is `id:controllers:path`.
+                    let (_, rest) = line.split_once(':')?;
+                    rest.split_once(':')
+                })
+                .find(|(controllers, _)| controllers.split(',').any(|c| c == "memory"))
+                .map(|(_, path)| path)
+                .ok_or("no memory cgroup controller")?;
+            let root = PathBuf::from(CGROUP_ROOT).join("memory");
+            (
+                root.join(path.trim_start_matches('/')),
+                "memory.limit_in_bytes",
+            )
+        };
+
+        let path = parent.join(format!("rust-debugger-{}", std::process::id()));
+        // A previous run's cgroup is empty by now and can be reused.
+        if !path.exists() {
+            fs::create_dir(&path)
+                .map_err(|e| format!("couldn't create {}: {}", path.display(), e))?;
+        }
+        let cgroup = Cgroup { path };
+        fs::write(cgroup.path.join(limit_file), limit.to_string())
+            .map_err(|e| format!("couldn't set the memory limit: {}", e))?;
+        Ok(cgroup)
+    }
+
+    /// Moves the calling process into the cgroup.
+    pub fn join(&self) -> Result<()> {
+        fs::write(self.path.join("cgroup.procs"), "0")?;
+        Ok(())
+    }
+}
+
+impl Drop for Cgroup {
+    fn drop(&mut self) {
+        // Fails while a process is still inside, which only happens if the
+        // subordinate outlived the debugger's hold on it.
+        let _ = fs::remove_dir(&self.path);
+    }
+}
@@ -1,18 +1,59 @@
+pub mod cgroup;
 pub mod ptrace;
 
 use crate::error::Error;
 use crate::result::Result;
 use libc::{
-    __errno_location, c_int, dup2 as libcdup2, execvp as libcexecvp, fork as libcfork,
-    personality as libcpersonality, pid_t, pipe as libcpipe, strerror as libcstrerror,
-    wait as libcwait, WEXITSTATUS, WIFCONTINUED, WIFEXITED, WIFSIGNALED, WIFSTOPPED, WSTOPSIG,
-    WTERMSIG,
+    __errno_location, c_int, dup2 as libcdup2, execvp as libcexecvp, fork as libcfork, iovec,
+    personality as libcpersonality, pid_t, pipe as libcpipe,
+    process_vm_readv as libcprocess_vm_readv, strerror as libcstrerror, waitpid as libcwaitpid,
+    WEXITSTATUS, WIFCONTINUED, WIFEXITED, WIFSIGNALED, WIFSTOPPED, WNOHANG, WSTOPSIG, WTERMSIG,
 };
-use std::ffi::CString;
+use std::convert::TryInto;
+use std::ffi::{CString, OsString};
 use std::fs::File;
-use std::os::unix::io::{FromRawFd, RawFd};
+use std::io::{Read, Write};
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+use std::os::unix::io::{AsRawFd, FromRawFd, RawFd};
+use std::path::Path;
 
 const ADDR_NO_RANDOMIZE: u64 = 0x40000;
+// The fixed part of a `struct inotify_event`, before the name.
+const INOTIFY_EVENT_SIZE: usize = 16;
+
+static SIGNAL_NAMES: &[&str] = &[
+    "SIGHUP",
+    "SIGINT",
+    "SIGQUIT",
+    "SIGILL",
+    "SIGTRAP",
+    "SIGABRT",
+    "SIGBUS",
+    "SIGFPE",
+    "SIGKILL",
+    "SIGUSR1",
+    "SIGSEGV",
+    "SIGUSR2",
+    "SIGPIPE",
+    "SIGALRM",
+    "SIGTERM",
+    "SIGSTKFLT",
+    "SIGCHLD",
+    "SIGCONT",
+    "SIGSTOP",
+    "SIGTSTP",
+    "SIGTTIN",
+    "SIGTTOU",
+    "SIGURG",
+    "SIGXCPU",
+    "SIGXFSZ",
+    "SIGVTALRM",
+    "SIGPROF",
+    "SIGWINCH",
+    "SIGIO",
+    "SIGPWR",
+    "SIGSYS",
+];
 
 pub enum Fork {
     Parent(pid_t),
@@ -39,6 +80,38 @@ where
     }
 }
 
+pub fn signal_name(signal: c_int) -> Option<&'static str> {
+    if signal < 1 {
+        return None;
+    }
+    SIGNAL_NAMES.get(signal as usize - 1).cloned()
+}
+
+/// The number of a signal named like `SIGSEGV`, `segv` or `11`.
+pub fn signal_number(name: &str) -> Option<c_int> {
+    if let Ok(number) = name.parse::<c_int>() {
+        return signal_name(number).map(|_| number);
+    }
+    let name = name.to_uppercase();
+    let name = if name.starts_with("SIG") {
+        name
+    } else {
+        format!("SIG{}", name)
+    };
+    SIGNAL_NAMES
+        .iter()
+        .position(|known| *known == name)
+        .map(|i| i as c_int + 1)
+}
+
+/// Every signal with a name, by number.
+pub fn signals() -> impl Iterator<Item = (c_int, &'static str)> {
+    SIGNAL_NAMES
+        .iter()
+        .enumerate()
+        .map(|(i, name)| (i as c_int + 1, *name))
+}
+
 pub fn strerror(errno: c_int) -> Result<String> {
     let str_ptr = errwrap(|| unsafe { libcstrerror(errno) })?;
     let cs = unsafe { CString::from_raw(str_ptr) };
@@ -54,10 +127,11 @@ pub fn execvp(cmd: &Vec<String>) -> Result<()> {
     for arg in cmd {
         cstr_array.push(CString::new(arg.clone())?);
     }
-    let mut ptr_array = Vec::with_capacity(cmd.len());
+    let mut ptr_array = Vec::with_capacity(cmd.len() + 1);
     for arg in &cstr_array {
         ptr_array.push(arg.as_ptr());
     }
+    ptr_array.push(std::ptr::null());
 
     errwrap(|| unsafe {
         libcexecvp(*ptr_array.first().unwrap(), ptr_array.as_ptr());
@@ -67,17 +141,60 @@ pub fn execvp(cmd: &Vec<String>) -> Result<()> {
 #[derive(Debug)]
 pub enum WaitStatus {
     Stopped(pid_t, i32),
+    /// A ptrace event stop, with the `PTRACE_EVENT_*` it's for.
+    Event(pid_t, i32),
     Continued(pid_t),
     Exited(pid_t, i32),
     Signaled(pid_t, i32),
     Unknwon(pid_t, i32),
 }
 
+/// Waits for any child, or traced thread, to change state.
 pub fn wait() -> Result<WaitStatus> {
     let mut status = 0;
-    let pid = errwrap(|| unsafe { libcwait(&mut status) })?;
+    let pid = loop {
+        match errwrap(|| unsafe { libcwaitpid(-1, &mut status, libc::__WALL) }) {
+            Err(Error::Errno(libc::EINTR)) => continue,
+            result => break result?,
+        }
+    };
+
+    Ok(decode_status(pid, status))
+}
+
+/// Waits for one process, or thread, in particular.
+pub fn wait_for(pid: pid_t) -> Result<WaitStatus> {
+    let mut status = 0;
+    let pid = loop {
+        match errwrap(|| unsafe { libcwaitpid(pid, &mut status, libc::__WALL) }) {
+            Err(Error::Errno(libc::EINTR)) => continue,
+            result => break result?,
+        }
+    };
+
+    Ok(decode_status(pid, status))
+}
+
+/// Like `wait` but returns None straight away when no child has changed
+/// state.
+pub fn try_wait() -> Result<Option<WaitStatus>> {
+    let mut status = 0;
+    let pid = loop {
+        match errwrap(|| unsafe { libcwaitpid(-1, &mut status, WNOHANG | libc::__WALL) }) {
+            Err(Error::Errno(libc::EINTR)) => continue,
+            result => break result?,
+        }
+    };
+    if pid == 0 {
+        return Ok(None);
+    }
+    Ok(Some(decode_status(pid, status)))
+}
 
-    let ws = if unsafe { WIFSTOPPED(status) } {
+fn decode_status(pid: pid_t, status: c_int) -> WaitStatus {
+    if unsafe { WIFSTOPPED(status) } && status >> 16 != 0 {
+        WaitStatus::Event(pid, status >> 16)
+    } else if unsafe { WIFSTOPPED(status) } {
         let stopsig = unsafe { WSTOPSIG(status) };
         WaitStatus::Stopped(pid, stopsig)
     } else if unsafe { WIFEXITED(status) } {
@@ -90,9 +207,26 @@ pub fn wait() -> Result<WaitStatus> {
         WaitStatus::Signaled(pid, termsig)
     } else {
         WaitStatus::Unknwon(pid, status)
+    }
+}
+
+pub fn process_vm_readv(pid: pid_t, addr: usize, size: usize) -> Result<Vec<u8>> {
+    let mut bytes = vec![0u8; size];
+    let local = iovec {
+        iov_base: bytes.as_mut_ptr() as *mut libc::c_void,
+        iov_len: size,
+    };
+    let remote = iovec {
+        iov_base: addr as *mut libc::c_void,
+        iov_len: size,
     };
 
-    Ok(ws)
+    let read = errwrap(|| unsafe { libcprocess_vm_readv(pid, &local, 1, &remote, 1, 0) })?;
+    if read < 0 {
+        return Err("process_vm_readv failed".into());
+    }
+    bytes.truncate(read as usize);
+    Ok(bytes)
 }
 
 pub fn pipe() -> Result<(File, File)> {
@@ -108,11 +242,147 @@ pub fn dup2(from: RawFd, to: RawFd) -> Result<()> {
     Ok(())
 }
 
+/// Runs `f` with our stdout going into a pipe, returning what it printed.
+pub fn capture_stdout<F, T>(f: F) -> Result<(T, String)>
+where
+    F: FnOnce() -> T,
+{
+    let (mut read, write) = pipe()?;
+    // Drained as it's written so a large output can't fill the pipe.
+    let reader = std::thread::spawn(move || {
+        let mut output = String::new();
+        let _ = read.read_to_string(&mut output);
+        output
+    });
+
+    std::io::stdout().flush()?;
+    let saved = errwrap(|| unsafe { libc::dup(1) })?;
+    dup2(write.as_raw_fd(), 1)?;
+    drop(write);
+
+    let result = f();
+
+    std::io::stdout().flush()?;
+    dup2(saved, 1)?;
+    unsafe { libc::close(saved) };
+    let output = reader
+        .join()
+        .map_err(|_| Error::from("stdout capture thread panicked"))?;
+    Ok((result, output))
+}
+
+/// Watches `dir` for files in it being written and closed or moved in,
+/// returning the inotify file to read what happened from.
+pub fn inotify_watch(dir: &Path) -> Result<File> {
+    let fd = errwrap(|| unsafe { libc::inotify_init1(libc::IN_CLOEXEC) })?;
+    let file = unsafe { File::from_raw_fd(fd) };
+    let dir = CString::new(dir.as_os_str().as_bytes())?;
+    errwrap(|| unsafe {
+        libc::inotify_add_watch(fd, dir.as_ptr(), libc::IN_CLOSE_WRITE | libc::IN_MOVED_TO)
+    })?;
+    Ok(file)
+}
+
+/// Waits for events on an inotify file from `inotify_watch`, returning the
+/// names of the files they were for.
+pub fn inotify_names(file: &mut File) -> Result<Vec<OsString>> {
+    let mut buf = [0u8; 4096];
+    let read = file.read(&mut buf)?;
+    let mut names = Vec::new();
+    let mut at = 0;
+    while at + INOTIFY_EVENT_SIZE <= read {
+        let len = u32::from_ne_bytes(buf[at + 12..at + 16].try_into().unwrap()) as usize;
+        let name = &buf[at + INOTIFY_EVENT_SIZE..(at + INOTIFY_EVENT_SIZE + len).min(read)];
+        // The name is padded out with NULs.
+        let end = name.iter().position(|b| *b == 0).unwrap_or(name.len());
+        names.push(OsString::from_vec(name[..end].to_vec()));
+        at += INOTIFY_EVENT_SIZE + len;
+    }
+    Ok(names)
+}
+
+pub fn kill(pid: pid_t, signal: c_int) -> Result<()> {
+    errwrap(|| unsafe { libc::kill(pid, signal) })?;
+    Ok(())
+}
+
+/// Sends a signal to one thread of a process.
+pub fn tgkill(pid: pid_t, tid: pid_t, signal: c_int) -> Result<()> {
+    errwrap(|| unsafe { libc::syscall(libc::SYS_tgkill, pid, tid, signal) })?;
+    Ok(())
+}
+
 pub fn personality(persona: u64) -> Result<()> {
     errwrap(|| unsafe { libcpersonality(persona) })?;
     Ok(())
 }
 
+extern "C" fn ignore_signal(_: c_int) {}
+
+/// Keeps Ctrl-C from killing the debugger. The subordinate shares our process
+/// group so it still receives the SIGINT, which stops it and hands control
+/// back to the prompt. A handler is used rather than SIG_IGN because ignored
+/// dispositions survive exec and would leak into the subordinate.
+pub fn catch_sigint() -> Result<()> {
+    errwrap(|| unsafe {
+        libc::signal(
+            libc::SIGINT,
+            ignore_signal as extern "C" fn(c_int) as libc::sighandler_t,
+        )
+    })?;
+    Ok(())
+}
+
 pub fn disable_aslr() -> Result<()> {
     personality(ADDR_NO_RANDOMIZE)
 }
+
+/// Restricts this process to the first CPU it's allowed to run on.
+pub fn pin_to_one_cpu() -> Result<()> {
+    let mut set: libc::cpu_set_t = unsafe { std::mem::zeroed() };
+    let size = std::mem::size_of::<libc::cpu_set_t>();
+    errwrap(|| unsafe { libc::sched_getaffinity(0, size, &mut set) })?;
+    let cpu = (0..libc::CPU_SETSIZE as usize)
+        .find(|cpu| unsafe { libc::CPU_ISSET(*cpu, &set) })
+        .ok_or("no CPU to run on")?;
+    let mut pinned: libc::cpu_set_t = unsafe { std::mem::zeroed() };
+    unsafe { libc::CPU_SET(cpu, &mut pinned) };
+    errwrap(|| unsafe { libc::sched_setaffinity(0, size, &pinned) })?;
+    Ok(())
+}
+
+/// Sets both the soft and hard limit of a resource, `None` lifting it.
+pub fn setrlimit(resource: libc::__rlimit_resource_t, limit: Option<u64>) -> Result<()> {
+    let value = limit.unwrap_or(libc::RLIM_INFINITY);
+    let rlimit = libc::rlimit {
+        rlim_cur: value,
+        rlim_max: value,
+    };
+    errwrap(|| unsafe { libc::setrlimit(resource, &rlimit) })?;
+    Ok(())
+}
+
+/// Moves this process into new mount and network namespaces, inside a new
+/// user namespace when it isn't root, so it can't reach the network and
+/// its mounts don't leak out.
+pub fn unshare_namespaces() -> Result<()> {
+    let (uid, gid) = unsafe { (libc::geteuid(), libc::getegid()) };
+    let user = if uid == 0 { 0 } else { libc::CLONE_NEWUSER };
+    errwrap(|| unsafe { libc::unshare(user | libc::CLONE_NEWNS | libc::CLONE_NEWNET) })?;
+    if user != 0 {
+        std::fs::write("/proc/self/setgroups", "deny")?;
+        std::fs::write("/proc/self/uid_map", format!("{} {} 1", uid, uid))?;
+        std::fs::write("/proc/self/gid_map", format!("{} {} 1", gid, gid))?;
+    }
+    let root = CString::new("/")?;
+    errwrap(|| unsafe {
+        libc::mount(
+            std::ptr::null(),
+            root.as_ptr(),
+            std::ptr::null(),
+            libc::MS_REC | libc::MS_PRIVATE,
+            std::ptr::null(),
+        )
+    })?;
+    Ok(())
+}
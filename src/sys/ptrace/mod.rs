@@ -1,25 +1,79 @@
+use crate::error::Error;
 use crate::result::Result;
 use crate::sys::errwrap;
 use libc::{
-    pid_t, ptrace, user_regs_struct, PTRACE_CONT, PTRACE_GETREGS, PTRACE_PEEKTEXT, PTRACE_POKETEXT,
-    PTRACE_SETREGS, PTRACE_SINGLESTEP, PTRACE_TRACEME,
+    c_int, iovec, pid_t, ptrace, user_regs_struct, EINTR, ESRCH, PTRACE_CONT, PTRACE_DETACH,
+    PTRACE_GETEVENTMSG, PTRACE_GETREGS, PTRACE_GETREGSET, PTRACE_PEEKTEXT, PTRACE_PEEKUSER,
+    PTRACE_POKETEXT, PTRACE_POKEUSER, PTRACE_SETOPTIONS, PTRACE_SETREGS, PTRACE_SINGLESTEP,
+    PTRACE_SYSCALL, PTRACE_TRACEME,
 };
 
+/// Runs a ptrace request against `pid`, retrying if a signal interrupted it
+/// and turning ESRCH into `Error::TargetGone` so callers can tell "the
+/// process died under us" apart from a request that was actually wrong.
+fn retry<F, T>(pid: pid_t, mut f: F) -> Result<T>
+where
+    F: FnMut() -> T,
+{
+    loop {
+        match errwrap(&mut f) {
+            Err(Error::Errno(EINTR)) => continue,
+            Err(Error::Errno(ESRCH)) => return Err(Error::TargetGone(pid)),
+            result => return result,
+        }
+    }
+}
+
 pub fn traceme() -> Result<()> {
     errwrap(|| unsafe { ptrace(PTRACE_TRACEME, 0, &mut 0, 0) })?;
     Ok(())
 }
 
-pub fn singlestep(pid: pid_t) -> Result<()> {
-    errwrap(|| unsafe { ptrace(PTRACE_SINGLESTEP, pid, &mut 0, 0) })?;
+// The ptrace requests that resume the tracee deliver the signal passed as
+// their data, 0 being none.
+
+pub fn singlestep(pid: pid_t, signal: c_int) -> Result<()> {
+    retry(pid, || unsafe {
+        ptrace(PTRACE_SINGLESTEP, pid, &mut 0, signal)
+    })?;
+    Ok(())
+}
+
+pub fn cont(pid: pid_t, signal: c_int) -> Result<()> {
+    retry(pid, || unsafe { ptrace(PTRACE_CONT, pid, &mut 0, signal) })?;
+    Ok(())
+}
+
+/// Like `cont` but also stops at every syscall entry and exit.
+pub fn syscall(pid: pid_t, signal: c_int) -> Result<()> {
+    retry(pid, || unsafe {
+        ptrace(PTRACE_SYSCALL, pid, &mut 0, signal)
+    })?;
+    Ok(())
+}
+
+pub fn setoptions(pid: pid_t, options: c_int) -> Result<()> {
+    retry(pid, || unsafe {
+        ptrace(PTRACE_SETOPTIONS, pid, 0, options)
+    })?;
     Ok(())
 }
 
-pub fn cont(pid: pid_t) -> Result<()> {
-    errwrap(|| unsafe { ptrace(PTRACE_CONT, pid, &mut 0, 0) })?;
+pub fn detach(pid: pid_t) -> Result<()> {
+    retry(pid, || unsafe { ptrace(PTRACE_DETACH, pid, 0, 0) })?;
     Ok(())
 }
 
+/// The extra detail of the event the tracee is stopped at, e.g. the new
+/// process's pid at a fork.
+pub fn geteventmsg(pid: pid_t) -> Result<u64> {
+    let mut message: libc::c_ulong = 0;
+    retry(pid, || unsafe {
+        ptrace(PTRACE_GETEVENTMSG, pid, 0, &mut message)
+    })?;
+    Ok(message as u64)
+}
+
 pub fn getregs(pid: pid_t) -> Result<user_regs_struct> {
     let mut regs: user_regs_struct = user_regs_struct {
         r15: 0,
@@ -51,21 +105,51 @@ pub fn getregs(pid: pid_t) -> Result<user_regs_struct> {
         gs: 0,
     };
 
-    errwrap(|| unsafe { ptrace(PTRACE_GETREGS, pid, 0, &mut regs) })?;
+    retry(pid, || unsafe { ptrace(PTRACE_GETREGS, pid, 0, &mut regs) })?;
 
     Ok(regs)
 }
 
 pub fn setregs(pid: pid_t, regs: &user_regs_struct) -> Result<()> {
-    errwrap(|| unsafe { ptrace(PTRACE_SETREGS, pid, 0, regs) })?;
+    retry(pid, || unsafe { ptrace(PTRACE_SETREGS, pid, 0, regs) })?;
     Ok(())
 }
 
+/// The x87 and SSE state, in `fxsave` layout.
+pub const NT_PRFPREG: usize = 2;
+/// Everything `xsave` saves, which includes AVX state where there is some.
+pub const NT_X86_XSTATE: usize = 0x202;
+
+/// Reads a register set into `buf`, returning how much of it the kernel
+/// filled in.
+pub fn getregset(pid: pid_t, note: usize, buf: &mut [u8]) -> Result<usize> {
+    let mut iov = iovec {
+        iov_base: buf.as_mut_ptr() as *mut libc::c_void,
+        iov_len: buf.len(),
+    };
+    retry(pid, || unsafe {
+        ptrace(PTRACE_GETREGSET, pid, note, &mut iov)
+    })?;
+    Ok(iov.iov_len)
+}
+
 pub fn peek(pid: pid_t, addr: usize) -> Result<usize> {
-    errwrap(|| unsafe { ptrace(PTRACE_PEEKTEXT, pid, addr, 0) }).map(|d| d as usize)
+    retry(pid, || unsafe { ptrace(PTRACE_PEEKTEXT, pid, addr, 0) }).map(|d| d as usize)
 }
 
 pub fn poke(pid: pid_t, addr: usize, data: usize) -> Result<()> {
-    errwrap(|| unsafe { ptrace(PTRACE_POKETEXT, pid, addr, data) })?;
+    retry(pid, || unsafe { ptrace(PTRACE_POKETEXT, pid, addr, data) })?;
+    Ok(())
+}
+
+/// Reads a word of the tracee's `struct user`, at a byte offset.
+pub fn peekuser(pid: pid_t, offset: usize) -> Result<usize> {
+    retry(pid, || unsafe { ptrace(PTRACE_PEEKUSER, pid, offset, 0) }).map(|d| d as usize)
+}
+
+pub fn pokeuser(pid: pid_t, offset: usize, data: usize) -> Result<()> {
+    retry(pid, || unsafe {
+        ptrace(PTRACE_POKEUSER, pid, offset, data)
+    })?;
     Ok(())
 }
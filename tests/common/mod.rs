@@ -0,0 +1,192 @@
+#![allow(dead_code)]
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+
+static FIXTURE_LOCK: Mutex<()> = Mutex::new(());
+
+/// Compiles `tests/fixtures/<name>.c` into the test tmpdir and returns the
+/// path of the binary. Fixtures are built non-PIE so symbol addresses match
+/// what the debugger sees at runtime.
+pub fn fixture(name: &str) -> PathBuf {
+    build(name, name, &["-no-pie"])
+}
+
+/// Like `fixture` but linked statically, for tests that need libc's own
+/// symbols.
+pub fn static_fixture(name: &str) -> PathBuf {
+    build(name, name, &["-static"])
+}
+
+/// Like `fixture` but position independent, loaded wherever the kernel
+/// puts it.
+pub fn pie_fixture(name: &str) -> PathBuf {
+    build(name, &format!("{}-pie", name), &["-pie", "-fPIE"])
+}
+
+/// Builds `tests/fixtures/<name>.c` as a shared library, `lib<name>.so`,
+/// for fixtures to load.
+pub fn library_fixture(name: &str) -> PathBuf {
+    build(name, &format!("lib{}.so", name), &["-shared", "-fPIC"])
+}
+
+/// Where the source of the fixture `name` is, for tests that build their
+/// own variations of it.
+pub fn fixture_source(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join(format!("{}.c", name))
+}
+
+fn build(name: &str, output: &str, flags: &[&str]) -> PathBuf {
+    let source = fixture_source(name);
+    let binary = Path::new(env!("CARGO_TARGET_TMPDIR")).join(output);
+
+    let _guard = FIXTURE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    if is_fresh(&binary, &source) {
+        return binary;
+    }
+
+    let status = Command::new("cc")
+        .args(["-g", "-O0"])
+        .args(flags)
+        .arg("-o")
+        .arg(&binary)
+        .arg(&source)
+        .status()
+        .expect("couldn't run cc");
+    assert!(status.success(), "failed to compile fixture {}", name);
+
+    binary
+}
+
+fn is_fresh(binary: &Path, source: &Path) -> bool {
+    let modified = |path: &Path| path.metadata().and_then(|m| m.modified()).ok();
+    match (modified(binary), modified(source)) {
+        (Some(binary), Some(source)) => binary >= source,
+        _ => false,
+    }
+}
+
+/// Runs `program` until it crashes and returns the core file it dumps,
+/// which the kernel writes as `core` in its working directory.
+pub fn core_dump(program: &Path) -> PathBuf {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join(format!(
+        "{}-core",
+        program.file_name().unwrap().to_string_lossy()
+    ));
+    std::fs::create_dir_all(&dir).unwrap();
+    let core = dir.join("core");
+    let _ = std::fs::remove_file(&core);
+
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg("ulimit -c unlimited; exec \"$0\"")
+        .arg(program)
+        .current_dir(&dir)
+        .status()
+        .expect("couldn't run sh");
+    assert!(!status.success(), "{} didn't crash", program.display());
+    assert!(core.exists(), "no core file, check kernel.core_pattern");
+    core
+}
+
+/// Writes `tmpdir/<name>` as an x86-64 ELF core file claiming `phnum`
+/// program headers at `phoff`, with `body` after its 64-byte header, for
+/// tests that feed the debugger broken ones.
+pub fn crafted_core(name: &str, phoff: u64, phnum: u16, body: &[u8]) -> PathBuf {
+    let mut core = vec![0u8; 64];
+    core[..7].copy_from_slice(b"\x7fELF\x02\x01\x01");
+    // ET_CORE for EM_X86_64.
+    core[16..18].copy_from_slice(&4u16.to_le_bytes());
+    core[18..20].copy_from_slice(&62u16.to_le_bytes());
+    core[32..40].copy_from_slice(&phoff.to_le_bytes());
+    core[56..58].copy_from_slice(&phnum.to_le_bytes());
+    core.extend_from_slice(body);
+
+    let path = Path::new(env!("CARGO_TARGET_TMPDIR")).join(name);
+    std::fs::write(&path, core).unwrap();
+    path
+}
+
+/// A 56-byte ELF program header of type `kind` for `size` bytes at
+/// `offset` in the file.
+pub fn program_header(kind: u32, offset: u64, size: u64) -> Vec<u8> {
+    let mut header = vec![0u8; 56];
+    header[..4].copy_from_slice(&kind.to_le_bytes());
+    header[8..16].copy_from_slice(&offset.to_le_bytes());
+    header[32..40].copy_from_slice(&size.to_le_bytes());
+    header
+}
+
+/// Runs the debugger against `program`, feeding it `commands` one per line,
+/// and returns everything it wrote to stdout.
+pub fn debug(program: &Path, commands: &[&str]) -> String {
+    debug_with_env(program, &[], commands)
+}
+
+/// Like `debug` with extra environment variables set for the debugger.
+pub fn debug_with_env(program: &Path, env: &[(&str, &str)], commands: &[&str]) -> String {
+    debug_with_options(&[], program, env, commands)
+}
+
+/// Like `debug_with_env` with debugger flags before the program.
+pub fn debug_with_options(
+    options: &[&str],
+    program: &Path,
+    env: &[(&str, &str)],
+    commands: &[&str],
+) -> String {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rust-debugger"))
+        .args(options)
+        .arg(program)
+        .envs(env.iter().copied())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("couldn't spawn debugger");
+
+    {
+        let stdin = child.stdin.as_mut().unwrap();
+        for command in commands {
+            writeln!(stdin, "{}", command).unwrap();
+        }
+    }
+
+    let output = child.wait_with_output().unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    assert!(
+        output.status.success(),
+        "debugger failed\nstdout:\n{}\nstderr:\n{}",
+        stdout,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    stdout
+}
+
+/// Finds the value printed by `r <name>`.
+pub fn register(output: &str, name: &str) -> Vec<u64> {
+    let prefix = format!("{} 0x", name);
+    output
+        .lines()
+        .filter_map(|line| line.strip_prefix(&prefix))
+        .filter_map(|rest| rest.split_whitespace().next())
+        .map(|hex| u64::from_str_radix(hex, 16).unwrap())
+        .collect()
+}
+
+/// Finds the address printed by `sym <name>`.
+pub fn symbol(output: &str, name: &str) -> u64 {
+    let suffix = format!(" {}", name);
+    output
+        .lines()
+        .filter_map(|line| line.strip_suffix(&suffix))
+        .filter_map(|addr| addr.strip_prefix("0x"))
+        .map(|hex| u64::from_str_radix(hex, 16).unwrap())
+        .next()
+        .unwrap_or_else(|| panic!("symbol {} not found in:\n{}", name, output))
+}
@@ -0,0 +1,18 @@
+#include <signal.h>
+#include <stdio.h>
+
+static volatile sig_atomic_t alarms = 0;
+
+static void on_alarm(int signal) {
+	(void)signal;
+	alarms++;
+}
+
+int main(void) {
+	signal(SIGALRM, on_alarm);
+	for (int i = 0; i < 3; i++) {
+		raise(SIGALRM);
+	}
+	printf("alarms %d\n", alarms);
+	return 0;
+}
@@ -0,0 +1,13 @@
+#include <stdio.h>
+
+long counter = 42;
+
+long add(long a, long b) {
+	return a + b;
+}
+
+int main(void) {
+	counter = add(counter, 7);
+	printf("%ld\n", counter);
+	return (int)(counter - 49);
+}
@@ -0,0 +1,24 @@
+#include <stdio.h>
+
+/* Whether n is at least 3, with its branch at the global label `decide`
+ * and the code it jumps to at `small`. */
+__asm__(
+	".globl check\n"
+	"check:\n\t"
+	"xor %eax, %eax\n\t"
+	"cmp $3, %rdi\n"
+	".globl decide\n"
+	"decide:\n\t"
+	"jb small\n\t"
+	"mov $1, %eax\n"
+	".globl small\n"
+	"small:\n\t"
+	"ret\n");
+long check(long n);
+
+int main(void) {
+	long low = check(1);
+	long high = check(5);
+	printf("%ld %ld\n", low, high);
+	return 0;
+}
@@ -0,0 +1,9 @@
+#include <fcntl.h>
+#include <stdio.h>
+
+int main(void) {
+	int flags = O_WRONLY | O_CREAT | O_TRUNC;
+	unsigned method = 0x20544547;
+	printf("%x %x\n", flags, method);
+	return 0;
+}
@@ -0,0 +1,14 @@
+#include <stdio.h>
+
+long bump(void) {
+	static long calls = 0;
+	calls++;
+	return calls;
+}
+
+int main(void) {
+	bump();
+	bump();
+	printf("%ld\n", bump());
+	return 0;
+}
@@ -0,0 +1,11 @@
+int *nowhere = 0;
+long answer = 42;
+
+void crash(int value) {
+	*nowhere = value;
+}
+
+int main(void) {
+	crash(7);
+	return 0;
+}
@@ -0,0 +1,17 @@
+#include <stdio.h>
+#include <stdlib.h>
+
+__attribute__((noinline)) long step(long n) {
+	return n * 2;
+}
+
+int main(void) {
+	/* DIVERGE=0 and DIVERGE=1 leave the stack in the same place. */
+	int bump = getenv("DIVERGE")[0] == '1';
+	long total = 0;
+	for (long i = 0; i < 4; i++) {
+		total += step(i + (i == 2 ? bump : 0));
+	}
+	printf("%ld\n", total);
+	return 0;
+}
@@ -0,0 +1,14 @@
+#include <dlfcn.h>
+#include <stdio.h>
+#include <stdlib.h>
+
+int main(void) {
+	void *plugin = dlopen(getenv("PLUGIN"), RTLD_NOW);
+	if (!plugin) {
+		fprintf(stderr, "%s\n", dlerror());
+		return 1;
+	}
+	int (*hook)(int) = (int (*)(int))dlsym(plugin, "plugin_hook");
+	printf("%d\n", hook(20));
+	return 0;
+}
@@ -0,0 +1,16 @@
+#include <locale.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <sys/random.h>
+#include <sys/time.h>
+#include <time.h>
+
+int main(void) {
+	unsigned long random = 0;
+	getrandom(&random, sizeof(random), 0);
+	struct timeval tv;
+	gettimeofday(&tv, NULL);
+	printf("random %lx time %ld tv %ld TZ %s\n", random, (long)time(NULL),
+	       (long)tv.tv_sec, getenv("TZ"));
+	return 0;
+}
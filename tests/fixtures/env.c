@@ -0,0 +1,9 @@
+#include <stdio.h>
+#include <stdlib.h>
+
+int main() {
+    const char *home = getenv("HOME");
+    const char *missing = getenv("DEBUGGER_TEST_UNSET");
+    printf("%d %d\n", home != NULL, missing != NULL);
+    return 0;
+}
@@ -0,0 +1,11 @@
+#include <fcntl.h>
+#include <stdio.h>
+
+int main(void) {
+	int opened = 0;
+	while (opened < 1000 && open("/dev/null", O_RDONLY) >= 0) {
+		opened++;
+	}
+	printf("opened %d\n", opened);
+	return 0;
+}
@@ -0,0 +1,15 @@
+#include <fcntl.h>
+#include <stdio.h>
+#include <unistd.h>
+
+int main() {
+    for (int i = 0; i < 2; i++) {
+        int fd = open("/nonexistent/config", O_RDONLY);
+        if (fd >= 0) {
+            close(fd);
+        }
+    }
+    access("/", F_OK);
+    printf("done\n");
+    return 0;
+}
@@ -0,0 +1,18 @@
+#include <stdio.h>
+
+__attribute__((noinline)) double scale(double x, double k) {
+	return x * k;
+}
+
+__attribute__((noinline)) void marker(void) {
+	__asm__ volatile("");
+}
+
+int main(void) {
+	printf("%g\n", scale(2.5, 4.0));
+	/* Leave 1 and pi on the x87 stack across the call to marker. */
+	__asm__ volatile("fld1\n\tfldpi");
+	marker();
+	__asm__ volatile("fstp %st(0)\n\tfstp %st(0)");
+	return 0;
+}
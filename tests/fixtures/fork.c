@@ -0,0 +1,20 @@
+#include <stdio.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+void in_child(void) {}
+
+void in_parent(void) {}
+
+int main(void) {
+	pid_t child = fork();
+	if (child == 0) {
+		in_child();
+		printf("child done\n");
+		return 3;
+	}
+	waitpid(child, NULL, 0);
+	in_parent();
+	printf("parent done\n");
+	return 0;
+}
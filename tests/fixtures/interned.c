@@ -0,0 +1,24 @@
+#include <stdio.h>
+
+/* Strings interned in a table, and referred to by index. */
+const char *names[] = { "alpha", "beta", "gamma" };
+
+typedef struct {
+	unsigned id;
+} symbol_t;
+
+struct entry {
+	symbol_t key;
+	int count;
+};
+
+void report(symbol_t symbol, struct entry entry) {
+	printf("%s %s %d\n", names[symbol.id], names[entry.key.id], entry.count);
+}
+
+int main(void) {
+	symbol_t symbol = { 1 };
+	struct entry entry = { { 2 }, 7 };
+	report(symbol, entry);
+	return 0;
+}
@@ -0,0 +1,12 @@
+#include <string.h>
+#include <sys/mman.h>
+
+int main(void) {
+	unsigned char *page = mmap(NULL, 4096, PROT_READ | PROT_WRITE,
+				   MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+	page[0] = 0xc3; /* ret */
+	mprotect(page, 4096, PROT_READ | PROT_WRITE | PROT_EXEC);
+	((void (*)(void))page)();
+	munmap(page, 4096);
+	return 0;
+}
@@ -0,0 +1,35 @@
+#include <stdio.h>
+
+struct point {
+	int x;
+	int y;
+};
+
+struct record {
+	char tag;
+	long id;
+	short flags;
+	struct point where;
+	const char *name;
+	unsigned char digest[5];
+};
+
+typedef struct record record_t;
+
+enum shape { CIRCLE, SQUARE, TRIANGLE };
+
+struct tagged {
+	_Bool visible;
+	enum shape shape;
+	struct point *origin;
+	struct point corner;
+	char initial;
+};
+
+int main(void) {
+	record_t record = { 'r', 42, 3, { 1, 2 }, "first", { 0 } };
+	struct tagged tagged = { 1, SQUARE, &record.where, { 3, 4 }, 't' };
+	printf("%c %ld %s\n", record.tag, record.id, record.name);
+	printf("%d %d %d\n", tagged.visible, tagged.shape, tagged.origin->x);
+	return 0;
+}
@@ -0,0 +1,26 @@
+#include <stdio.h>
+
+struct point {
+	int x;
+	int y;
+};
+
+enum color { RED, GREEN, BLUE };
+
+void report(long n) {
+	printf("%ld\n", n);
+}
+
+int main(void) {
+	int count = -3;
+	char letter = 'A';
+	double ratio = 0.5;
+	struct point origin = { 4, 5 };
+	int primes[4] = { 2, 3, 5, 7 };
+	enum color color = GREEN;
+	const char *name = "locals";
+	__int128 wide = 1;
+	report(count + origin.x);
+	printf("%c %f %d %d %s %d\n", letter, ratio, primes[0], color, name, (int)wide);
+	return 0;
+}
@@ -0,0 +1,15 @@
+#include <stdio.h>
+
+long total = 0;
+
+void tick(long i) {
+	total += i;
+}
+
+int main(void) {
+	for (long i = 0; i < 3; i++) {
+		tick(i);
+	}
+	printf("%ld\n", total);
+	return 0;
+}
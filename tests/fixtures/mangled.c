@@ -0,0 +1,23 @@
+#include <stdio.h>
+
+/* Named as rustc would name demo::greet, to stand in for a Rust binary. */
+void greet(void) __asm__("_ZN4demo5greet17h0123456789abcdefE");
+
+/* And as g++ would name demo::shout(int), for a C++ one. */
+void shout(int times) __asm__("_ZN4demo5shoutEi");
+
+void greet(void) {
+	puts("hello");
+}
+
+void shout(int times) {
+	for (int i = 0; i < times; i++) {
+		puts("HELLO");
+	}
+}
+
+int main(void) {
+	greet();
+	shout(2);
+	return 0;
+}
@@ -0,0 +1,28 @@
+#include <stdio.h>
+#include <stdlib.h>
+
+/* Named as std names its panic functions, to stand in for a Rust binary. */
+void rust_panic(void) __asm__("_ZN3std9panicking10rust_panic17h0123456789abcdefE");
+void start_panic(void) __asm__("__rust_start_panic");
+
+void start_panic(void) {
+	exit(101);
+}
+
+void rust_panic(void) {
+	start_panic();
+}
+
+void parse(int n) {
+	if (n > 2) {
+		rust_panic();
+	}
+	printf("parsed %d\n", n);
+}
+
+int main(void) {
+	for (int n = 0; n < 5; n++) {
+		parse(n);
+	}
+	return 0;
+}
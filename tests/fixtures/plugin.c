@@ -0,0 +1,3 @@
+int plugin_hook(int n) {
+	return n + 22;
+}
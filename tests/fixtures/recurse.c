@@ -0,0 +1,16 @@
+#include <stdio.h>
+#include <unistd.h>
+
+/* Recurses without end, slowly enough to be caught on the way down. The
+   volatile buffer keeps frames big and the recursion from becoming a loop. */
+long dive(long depth) {
+	volatile char frame[256];
+	frame[0] = (char)depth;
+	usleep(100);
+	return dive(depth + 1) + frame[0];
+}
+
+int main(void) {
+	printf("%ld\n", dive(0));
+	return 0;
+}
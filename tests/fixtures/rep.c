@@ -0,0 +1,25 @@
+#include <stdio.h>
+
+char buffer[64] = "not cleared";
+
+void clear(void) {
+	__asm__ volatile(
+		"lea buffer(%%rip), %%rdi\n\t"
+		"mov $64, %%ecx\n\t"
+		"xor %%eax, %%eax\n"
+		".globl fill\n"
+		"fill:\n\t"
+		"rep stosb"
+		::: "rdi", "rcx", "rax", "memory");
+}
+
+/* A return with a rep prefix, as compilers once emitted for AMD. */
+__asm__(".globl bounce\nbounce:\n\trep ret\n");
+void bounce(void);
+
+int main(void) {
+	clear();
+	bounce();
+	printf("%d\n", buffer[0]);
+	return 0;
+}
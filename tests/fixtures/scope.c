@@ -0,0 +1,22 @@
+#include <stdio.h>
+
+long fill(long n) {
+	long sum = 0;
+	for (long i = 1; i <= n; i++) {
+		sum += i;
+	}
+	return sum;
+}
+
+// Reuses the stack slot fill's sum lived in.
+long clobber(void) {
+	long junk = 99;
+	return junk;
+}
+
+int main(void) {
+	long a = fill(2);
+	long b = clobber();
+	printf("%ld %ld\n", a, b);
+	return 0;
+}
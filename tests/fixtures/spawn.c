@@ -0,0 +1,17 @@
+#include <stdio.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+void ready() {}
+
+int main() {
+    pid_t child = fork();
+    if (child == 0) {
+        sleep(1);
+        return 0;
+    }
+    ready();
+    waitpid(child, NULL, 0);
+    printf("reaped\n");
+    return 0;
+}
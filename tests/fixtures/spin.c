@@ -0,0 +1,12 @@
+#include <stdio.h>
+
+volatile int spinning = 1;
+
+int main(void) {
+	puts("spinning");
+	fflush(stdout);
+	while (spinning) {
+	}
+	puts("stopped spinning");
+	return 0;
+}
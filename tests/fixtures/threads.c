@@ -0,0 +1,23 @@
+#define _GNU_SOURCE
+#include <pthread.h>
+#include <stdio.h>
+
+int done = 0;
+
+void in_worker(void) {}
+
+void *work(void *arg) {
+	(void)arg;
+	pthread_setname_np(pthread_self(), "worker");
+	in_worker();
+	done = 1;
+	return NULL;
+}
+
+int main(void) {
+	pthread_t worker;
+	pthread_create(&worker, NULL, work, NULL);
+	pthread_join(worker, NULL);
+	printf("worker done %d\n", done);
+	return 0;
+}
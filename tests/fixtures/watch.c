@@ -0,0 +1,11 @@
+#include <stdio.h>
+
+int counter = 0;
+
+int main() {
+    for (int i = 0; i < 3; i++) {
+        counter += 2;
+    }
+    printf("%d\n", counter);
+    return 0;
+}
@@ -0,0 +1,8 @@
+#include <unistd.h>
+
+int main() {
+    write(1, "out\n", 4);
+    write(2, "err\n", 4);
+    write(1, "out\n", 4);
+    return 0;
+}
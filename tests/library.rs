@@ -0,0 +1,123 @@
+mod common;
+
+use common::fixture;
+use rust_debugger::debugger::{Capabilities, Launch, Subordinate};
+use std::sync::{Mutex, MutexGuard};
+
+// Any thread can wait for any tracee of the process, so tests that trace
+// take turns rather than reaping each other's.
+static TRACING: Mutex<()> = Mutex::new(());
+
+fn spawn(name: &str) -> (MutexGuard<'static, ()>, Subordinate) {
+    let guard = TRACING.lock().unwrap_or_else(|e| e.into_inner());
+    let cmd = vec![fixture(name).to_string_lossy().into_owned()];
+    (guard, Subordinate::spawn(cmd, Launch::default()).unwrap())
+}
+
+#[test]
+fn breakpoints_stop_with_the_arguments_in_registers() {
+    let (_guard, mut subordinate) = spawn("arith");
+    let add = subordinate.symbol("add").unwrap().value;
+    subordinate.breakpoint(add as usize).unwrap();
+    subordinate.cont().unwrap();
+    let regs = subordinate.registers();
+    assert_eq!(regs.rip, add);
+    // add(counter, 7)
+    assert_eq!((regs.rdi, regs.rsi), (42, 7));
+
+    subordinate.step().unwrap();
+    assert_eq!(subordinate.registers().rip, add + 1);
+}
+
+#[test]
+fn memory_written_is_what_the_process_sees() {
+    let (_guard, mut subordinate) = spawn("arith");
+    let main = subordinate.symbol("main").unwrap().value;
+    let counter = subordinate.symbol("counter").unwrap().value as usize;
+    subordinate.breakpoint(main as usize).unwrap();
+    subordinate.cont().unwrap();
+    assert_eq!(
+        subordinate.read_bytes(counter, 8).unwrap(),
+        42u64.to_le_bytes()
+    );
+
+    subordinate
+        .write_bytes(counter, &100u64.to_le_bytes())
+        .unwrap();
+    subordinate.cont().unwrap();
+    // main returns counter - 49 with counter now 107.
+    assert_eq!(subordinate.exit_status(), Some(58));
+}
+
+#[test]
+fn capabilities_warn_about_what_wont_work() {
+    let capable = Capabilities {
+        sys_ptrace: true,
+        ptrace_scope: Some(1),
+        perf_event_paranoid: Some(2),
+        wsl1: false,
+        process_vm_readv: true,
+    };
+    assert!(capable.warnings().is_empty());
+    assert!(!capable.ptrace_disabled());
+
+    let restricted = Capabilities {
+        sys_ptrace: false,
+        ptrace_scope: Some(2),
+        perf_event_paranoid: Some(3),
+        ..capable.clone()
+    };
+    assert_eq!(
+        restricted.warnings(),
+        vec![
+            "CAP_SYS_PTRACE is not available and kernel.yama.ptrace_scope = 2, only spawned processes can be debugged",
+            "perf events are restricted (kernel.perf_event_paranoid = 3)",
+        ]
+    );
+
+    let wsl1 = Capabilities {
+        ptrace_scope: Some(3),
+        perf_event_paranoid: None,
+        wsl1: true,
+        process_vm_readv: false,
+        ..capable
+    };
+    assert!(wsl1.ptrace_disabled());
+    assert_eq!(
+        wsl1.warnings(),
+        vec![
+            "ptrace is disabled system-wide (kernel.yama.ptrace_scope = 3)",
+            "running under WSL1, ptrace support is partial and memory reads will be slower",
+            "kernel has no perf event support, hardware breakpoints and watchpoints will not work",
+        ]
+    );
+}
+
+#[test]
+fn peeks_read_the_same_memory_as_process_vm_readv() {
+    let (_guard, mut subordinate) = spawn("arith");
+    let main = subordinate.symbol("main").unwrap().value as usize;
+    subordinate.breakpoint(main).unwrap();
+    subordinate.cont().unwrap();
+
+    // The end of the stack, where a word too many can't be read.
+    let maps = std::fs::read_to_string(format!("/proc/{}/maps", subordinate.pid())).unwrap();
+    let stack = maps.lines().find(|line| line.ends_with("[stack]")).unwrap();
+    let end = stack.split(['-', ' ']).nth(1).unwrap();
+    let end = usize::from_str_radix(end, 16).unwrap();
+
+    let reads = [(main + 1, 13), (end - 16, 16), (end - 5, 5), (main, 0)];
+    let fast: Vec<Vec<u8>> = reads
+        .iter()
+        .map(|&(addr, size)| subordinate.read_bytes(addr, size).unwrap())
+        .collect();
+    subordinate.set_fast_reads(false);
+    for (&(addr, size), fast) in reads.iter().zip(fast) {
+        assert_eq!(fast.len(), size);
+        assert_eq!(subordinate.read_bytes(addr, size).unwrap(), fast);
+    }
+
+    // Wanting more than is left before the top of the address space.
+    let error = subordinate.read_bytes(usize::MAX - 3, 8).unwrap_err();
+    assert_eq!(error.to_string(), "read past the end of the address space");
+}
@@ -0,0 +1,2394 @@
+mod common;
+
+use common::{
+    core_dump, crafted_core, debug, debug_with_env, debug_with_options, fixture, fixture_source,
+    library_fixture, pie_fixture, program_header, register, static_fixture, symbol,
+};
+
+#[test]
+fn runs_to_completion() {
+    let program = fixture("arith");
+    let output = debug(&program, &["c"]);
+    assert!(output.contains("49\n"));
+    assert!(output.contains("debugged process exited with status: 0"));
+}
+
+#[test]
+fn stops_at_breakpoint() {
+    let program = fixture("arith");
+    let output = debug(&program, &["sym add", "b add", "c", "r rip", "c"]);
+    assert_eq!(register(&output, "rip"), vec![symbol(&output, "add")]);
+}
+
+#[test]
+fn passes_arguments_in_registers() {
+    let program = fixture("arith");
+    let output = debug(&program, &["b add", "c", "r rdi", "r rsi", "c"]);
+    assert_eq!(register(&output, "rdi"), vec![42]);
+    assert_eq!(register(&output, "rsi"), vec![7]);
+}
+
+#[test]
+fn single_step_advances_rip() {
+    let program = fixture("arith");
+    let output = debug(&program, &["b main", "c", "r rip", "si", "r rip", "c"]);
+    let rips = register(&output, "rip");
+    assert_eq!(rips.len(), 2);
+    assert!(rips[1] > rips[0]);
+}
+
+#[test]
+fn errors_do_not_end_the_session() {
+    let program = fixture("arith");
+    let output = debug(&program, &["b 0x10", "b nowhere", "c"]);
+    assert!(output.contains("error: "));
+    assert!(output.contains("debugged process exited with status: 0"));
+}
+
+#[test]
+fn detach_removes_breakpoints_and_lets_the_program_finish() {
+    let program = fixture("arith");
+    let output = debug(&program, &["b main", "b add", "c", "detach"]);
+    assert!(output.contains("detached from process"));
+    assert!(output.contains("49\n"));
+}
+
+#[test]
+fn breakpoints_fire_on_every_pass() {
+    let program = fixture("loop");
+    let output = debug(
+        &program,
+        &["b tick", "c", "r rdi", "c", "r rdi", "c", "r rdi", "c"],
+    );
+    assert_eq!(register(&output, "rdi"), vec![0, 1, 2]);
+    assert!(output.contains("3\n"));
+}
+
+#[test]
+fn conditional_breakpoints_only_stop_when_true() {
+    let program = fixture("loop");
+    let output = debug(&program, &["b tick if $rdi == 2", "c", "r rdi", "c"]);
+    assert_eq!(register(&output, "rdi"), vec![2]);
+    assert!(output.contains("3\n"));
+}
+
+#[test]
+fn getenv_trace_logs_queried_variables() {
+    let program = static_fixture("env");
+    let output = debug(&program, &["trace getenv", "c"]);
+    assert!(output.contains("[trace] getenv(\"HOME\")"));
+    assert!(output.contains("[trace] getenv(\"DEBUGGER_TEST_UNSET\")"));
+    assert!(output.contains("debugged process exited with status: 0"));
+}
+
+#[test]
+fn file_trace_summarizes_paths_at_exit() {
+    let program = fixture("files");
+    let output = debug(&program, &["trace files", "c"]);
+    assert!(output.contains("= -1 ENOENT (No such file or directory)"));
+    let summary = output.split("files touched:\n").nth(1).unwrap();
+    assert_eq!(
+        summary.matches("  /nonexistent/config (ENOENT)\n").count(),
+        1
+    );
+    assert!(summary.contains("  /\n"));
+}
+
+#[test]
+fn temporary_breakpoints_only_stop_once() {
+    let program = fixture("loop");
+    let output = debug(&program, &["tbreak tick", "c", "r rdi", "c", "r rdi"]);
+    assert_eq!(register(&output, "rdi").len(), 1);
+    assert!(output.contains("debugged process exited with status: 0"));
+}
+
+#[test]
+fn syscall_trace_conditions_filter_calls() {
+    let program = fixture("writes");
+    let output = debug(&program, &["trace syscalls write if fd == 2", "c"]);
+    let traced: Vec<&str> = output
+        .lines()
+        .filter(|line| line.starts_with("[trace]"))
+        .collect();
+    assert_eq!(traced, vec!["[trace] write(2, \"err\\n\", 4) = 4"]);
+}
+
+#[test]
+fn hardware_breakpoints_fire_on_every_pass() {
+    let program = fixture("loop");
+    let output = debug(
+        &program,
+        &["hbreak tick", "c", "r rdi", "c", "r rdi", "c", "r rdi", "c"],
+    );
+    assert_eq!(register(&output, "rdi"), vec![0, 1, 2]);
+    assert!(output.contains("3\n"));
+}
+
+#[test]
+fn watchpoints_show_old_and_new_values() {
+    let program = fixture("watch");
+    let output = debug(&program, &["watch counter", "c", "c", "c", "c"]);
+    assert_eq!(output.matches("watchpoint 0x").count(), 3);
+    assert!(output.contains("old: 0x2\nnew: 0x4\n"));
+    assert!(output.contains("6\n"));
+}
+
+#[test]
+fn read_watchpoints_stop_on_reads_too() {
+    let program = fixture("watch");
+    let output = debug(&program, &["rwatch counter", "c", "c"]);
+    assert!(output.contains("value: 0x0\n"));
+    assert!(output.contains("old: 0x0\nnew: 0x2\n"));
+}
+
+#[test]
+fn trace_export_writes_chrome_trace_json_at_exit() {
+    let program = fixture("writes");
+    let file = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("writes.json");
+    let export = format!("trace export {}", file.display());
+    debug(&program, &["trace syscalls write", &export, "c"]);
+    let json = std::fs::read_to_string(&file).unwrap();
+    assert!(json.starts_with("{\"traceEvents\":["));
+    assert_eq!(json.matches("\"name\":\"write\",\"ph\":\"X\"").count(), 3);
+    assert!(json.contains("\"call\":\"write(2, \\\"err\\\\n\\\", 4) = 4\""));
+}
+
+#[test]
+fn proctree_shows_untraced_children() {
+    let program = fixture("spawn");
+    // The second cont gets past the SIGCHLD stop.
+    let output = debug(&program, &["b ready", "c", "info proctree", "c", "c"]);
+    let tree: Vec<&str> = output
+        .lines()
+        .filter(|line| line.contains(" spawn ("))
+        .collect();
+    assert_eq!(tree.len(), 2);
+    assert!(tree[0].ends_with("(tracing stop)"));
+    assert!(tree[1].starts_with("  "));
+    assert!(output.contains("reaped\n"));
+}
+
+#[test]
+fn until_runs_to_an_address_without_leaving_a_breakpoint() {
+    let program = fixture("loop");
+    let output = debug(&program, &["until tick", "r rdi", "c"]);
+    assert_eq!(register(&output, "rdi"), vec![0]);
+    assert!(output.contains("3\n"));
+    assert!(output.contains("debugged process exited with status: 0"));
+}
+
+#[test]
+fn run_restarts_with_the_same_breakpoints() {
+    let program = fixture("loop");
+    let output = debug(
+        &program,
+        &["b tick", "c", "r rdi", "c", "run", "c", "r rdi"],
+    );
+    assert_eq!(register(&output, "rdi"), vec![0, 0]);
+}
+
+#[test]
+fn run_starts_again_after_exit() {
+    let program = fixture("arith");
+    let output = debug(&program, &["c", "r rip", "run", "c"]);
+    assert_eq!(
+        output
+            .matches("debugged process exited with status: 0")
+            .count(),
+        2
+    );
+    assert!(output.contains("error: the program is not running"));
+    assert_eq!(output.matches("49\n").count(), 2);
+}
+
+#[test]
+fn event_log_records_stops_and_exit() {
+    let program = fixture("arith");
+    let output = debug(&program, &["b add", "c", "c", "events"]);
+    let events: Vec<&str> = output.lines().filter(|l| l.starts_with('+')).collect();
+    assert_eq!(events.len(), 3);
+    assert!(events[0].contains("s started process "));
+    assert!(events[1].contains("s breakpoint at 0x") && events[1].ends_with("<add>"));
+    assert!(events[2].ends_with("exited with status 0"));
+}
+
+#[test]
+fn kill_ends_the_process_but_not_the_session() {
+    let program = fixture("arith");
+    let output = debug(
+        &program,
+        &["b add", "c", "kill", "r rip", "run", "c", "r rdi"],
+    );
+    assert!(output.contains("debugged process was killed by SIGKILL"));
+    assert!(output.contains("error: the program is not running"));
+    assert_eq!(register(&output, "rdi").len(), 1);
+}
+
+#[test]
+fn control_socket_runs_commands_and_returns_output() {
+    use std::io::{Read, Write};
+    use std::os::unix::net::UnixStream;
+    use std::process::{Command, Stdio};
+
+    let program = fixture("loop");
+    let socket = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("control.sock");
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rust-debugger"))
+        .arg("--control")
+        .arg(&socket)
+        .arg(&program)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    let send = |command: &str| {
+        for _ in 0..100 {
+            if let Ok(mut stream) = UnixStream::connect(&socket) {
+                writeln!(stream, "{}", command).unwrap();
+                let mut reply = String::new();
+                stream.read_to_string(&mut reply).unwrap();
+                return reply;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(50));
+        }
+        panic!("control socket never came up");
+    };
+    let tick = symbol(&send("sym tick"), "tick");
+    send("b tick");
+    send("c");
+    let rip = register(&send("r rip"), "rip");
+
+    writeln!(child.stdin.as_mut().unwrap(), "c").unwrap();
+    drop(child.stdin.take());
+    let output = child.wait_with_output().unwrap();
+    assert_eq!(rip, vec![tick]);
+    assert!(!socket.exists());
+    // Socket commands answer over the socket, not on the terminal.
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("rip 0x"));
+}
+
+#[test]
+fn backtrace_walks_out_to_the_caller() {
+    let program = fixture("loop");
+    let output = debug(&program, &["b tick", "c", "bt"]);
+    // The selected frame is marked with a `*`.
+    let frames: Vec<&str> = output.lines().filter(|l| l[1..].starts_with('#')).collect();
+    assert!(frames[0].contains("<tick>"));
+    assert!(frames[1].contains("<main+"));
+}
+
+#[test]
+fn edit_opens_the_current_line() {
+    let program = fixture("loop");
+    let env = [("VISUAL", "echo opened")];
+    let output = debug_with_env(&program, &env, &["edit", "b tick", "c", "edit"]);
+    // At the start rip is in the dynamic loader, which has no line table.
+    assert!(output.contains("error: no source for this address\n"));
+    assert!(output.contains("opened +5 "));
+    assert!(output.contains("loop.c"));
+}
+
+#[test]
+fn up_selects_the_callers_registers() {
+    let program = fixture("loop");
+    let output = debug(
+        &program,
+        &[
+            "sym main", "sym tick", "b tick", "c", "up", "r rip", "down", "r rip",
+        ],
+    );
+    let rips = register(&output, "rip");
+    assert!(rips[0] > symbol(&output, "main"));
+    assert_eq!(rips[1], symbol(&output, "tick"));
+}
+
+#[test]
+fn info_locals_decodes_by_type() {
+    let program = fixture("locals");
+    let output = debug(&program, &["b report", "c", "up", "info locals"]);
+    assert!(output.contains("int count = -3\n"));
+    assert!(output.contains("char letter = 65 'A'\n"));
+    assert!(output.contains("double ratio = 0.5\n"));
+    assert!(output.contains("struct point origin = {x = 4, y = 5}\n"));
+    assert!(output.contains("int [4] primes = {2, 3, 5, 7}\n"));
+    assert!(output.contains("enum color color = GREEN\n"));
+}
+
+#[test]
+fn whereami_summarizes_the_stop() {
+    let program = fixture("loop");
+    let output = debug(&program, &["b tick", "c", "whereami"]);
+    assert!(output.contains("<tick> (loop.c:5)\n"));
+    assert!(output.contains("last event: breakpoint at "));
+    assert!(output.contains("  #1  "));
+    assert!(output.contains("breakpoints:\n  0x"));
+}
+
+#[test]
+fn rbreak_and_break_module_set_breakpoints_on_each_function() {
+    let program = fixture("loop");
+    let output = debug(
+        &program,
+        &[
+            "rbreak ^ti",
+            "break-module loop.c",
+            "c",
+            "r rip",
+            "c",
+            "r rip",
+        ],
+    );
+    assert!(output.contains("<tick>\nset 1 breakpoints\n"));
+    // tick already has one, so only main is new.
+    assert!(output.contains("<main>\nset 1 breakpoints\n"));
+    let rips = register(&output, "rip");
+    assert_eq!(rips.len(), 2);
+}
+
+#[test]
+fn print_evaluates_variables_and_registers() {
+    let program = fixture("locals");
+    let output = debug(
+        &program,
+        &[
+            "b report",
+            "c",
+            "up",
+            "p origin",
+            "p count + origin.x",
+            "p count * 2",
+        ],
+    );
+    assert!(output.contains("origin = {x = 4, y = 5}\n"));
+    assert!(output.contains("\n-6 (0x"));
+
+    let program = fixture("loop");
+    let output = debug(&program, &["b tick", "c", "c", "c", "p total"]);
+    assert!(output.lines().any(|line| line == "0x1"));
+}
+
+#[test]
+fn set_var_writes_through_dwarf_locations() {
+    let program = fixture("locals");
+    let output = debug(
+        &program,
+        &[
+            "b report",
+            "c",
+            "up",
+            "set var letter = 66",
+            "set var ratio = 2.25",
+            "set var origin.y = origin.x + 10",
+            "set var wide = 2",
+            "p origin",
+            "c",
+        ],
+    );
+    assert!(output.contains("origin = {x = 4, y = 14}\n"));
+    assert!(output.contains("error: can only set values up to 8 bytes\n"));
+    assert!(output.contains("B 2.250000 2 1 locals 1\n"));
+}
+
+#[test]
+fn scoped_watchpoint_is_deleted_when_its_frame_returns() {
+    let program = fixture("scope");
+    let output = debug(
+        &program,
+        &["b fill", "c", "watch -l sum", "c", "c", "c", "c", "c", "c"],
+    );
+    assert!(output.contains("hit at 0x"));
+    assert!(output.contains("deleted, its frame returned\n"));
+    // clobber reuses the slot but mustn't trigger it.
+    assert!(!output.contains("<clobber"));
+    assert!(output.contains("3 99\n"));
+}
+
+#[test]
+fn show_changes_diffs_registers_and_watched_memory() {
+    let program = fixture("loop");
+    let output = debug(
+        &program,
+        &[
+            "b tick",
+            "c",
+            "watch total",
+            "set show-changes on",
+            "c",
+            "c",
+            "c",
+        ],
+    );
+    let changes: Vec<&str> = output
+        .lines()
+        .filter(|line| line.starts_with("changed: "))
+        .collect();
+    assert_eq!(changes.len(), 3);
+    assert!(changes[1].contains("rdi 0x0→0x1"));
+    assert!(changes[2].ends_with("] 0x0→0x1"));
+    assert!(!changes[2].contains("rip"));
+}
+
+#[test]
+fn display_reevaluates_at_every_stop() {
+    let program = fixture("loop");
+    let output = debug(
+        &program,
+        &[
+            "b tick",
+            "c",
+            "display total",
+            "display $rdi * 2",
+            "c",
+            "c",
+            "undisplay 1",
+            "display",
+        ],
+    );
+    assert!(output.contains("2: $rdi * 2 = 0x2 (was 0x0)\n"));
+    assert!(output.contains("1: total = 0x1 (was 0x0)\n2: $rdi * 2 = 0x4 (was 0x2)\n"));
+    assert!(output.contains("\n1: $rdi * 2 = 0x4\ndebugged process was killed"));
+}
+
+#[test]
+fn examine_shows_memory_in_each_format() {
+    let program = fixture("locals");
+    let output = debug(
+        &program,
+        &[
+            "b report",
+            "c",
+            "up",
+            "x/s name",
+            "x/4dw primes",
+            "x/9xb primes",
+            "x",
+            "x/2ug primes",
+        ],
+    );
+    assert!(output.contains(":  \"locals\"\n"));
+    assert!(output.contains(":  2  3  5  7\n"));
+    assert!(output.contains(":  0x02  0x00  0x00  0x00  0x03  0x00  0x00  0x00\n"));
+    // A bare `x` carries on after the ninth byte, one byte at a time.
+    let lines: Vec<&str> = output.lines().collect();
+    let ninth = lines
+        .iter()
+        .position(|line| line.ends_with(":  0x05"))
+        .unwrap();
+    assert!(lines[ninth + 1].ends_with(":  0x00"));
+    assert!(output.contains(":  12884901890  30064771077\n"));
+}
+
+#[test]
+fn catch_mprotect_reports_pages_made_executable() {
+    let program = fixture("jit");
+    let output = debug(
+        &program,
+        &[
+            "b main",
+            "c",
+            "catch mmap",
+            "catch mprotect",
+            "catch munmap",
+            "c",
+            "c",
+            "c",
+        ],
+    );
+    assert!(output.contains("caught mmap(NULL, 4096, PROT_READ|PROT_WRITE, "));
+    assert!(output.contains("caught mprotect("));
+    assert!(output.contains(" rwxp (was rw-p), writable and executable\n"));
+    assert!(output.contains("caught munmap("));
+    assert!(output.contains("memory unmapped "));
+}
+
+#[test]
+fn catch_syscall_stops_at_entry() {
+    let program = fixture("files");
+    let output = debug(
+        &program,
+        &[
+            "b main",
+            "c",
+            "catch syscall openat",
+            "c",
+            "r orig_rax",
+            "c",
+            "c",
+        ],
+    );
+    let caught: Vec<&str> = output
+        .lines()
+        .filter(|line| line.starts_with("caught "))
+        .collect();
+    assert_eq!(
+        caught,
+        ["caught openat(AT_FDCWD, \"/nonexistent/config\", O_RDONLY, 0)"; 2]
+    );
+    // Stopped in the call, before it ran.
+    assert_eq!(register(&output, "orig_rax"), [257]);
+    assert!(output.contains("debugged process exited with status: 0"));
+}
+
+#[test]
+fn addresses_take_symbol_arithmetic() {
+    let program = fixture("loop");
+    let output = debug(
+        &program,
+        &[
+            "b tick+4",
+            "c",
+            "p $rip - &tick",
+            "sym total",
+            "p &total + 8",
+        ],
+    );
+    let lines: Vec<&str> = output.lines().collect();
+    assert!(lines.contains(&"0x4"));
+    let total = symbol(&output, "total");
+    assert!(lines.contains(&format!("0x{:x}", total + 8).as_str()));
+}
+
+#[test]
+fn addresses_read_the_same_everywhere() {
+    let program = fixture("loop");
+    let tick = symbol(&debug(&program, &["sym tick"]), "tick");
+    let decimal = format!("b {}", tick);
+    let output = debug(
+        &program,
+        &[&decimal, "c", "r rip", "watch 4011af", "until tick+zz"],
+    );
+    assert_eq!(register(&output, "rip"), [tick]);
+    assert!(output.contains(
+        "error: `4011af` isn't an address, invalid number `4011af`, hex takes a 0x prefix\n"
+    ));
+    assert!(output.contains("error: unknown symbol `zz` in `tick+zz`\n"));
+}
+
+#[test]
+fn forks_are_followed_per_follow_fork_mode() {
+    let program = fixture("fork");
+    let breakpoints = ["b in_child", "b in_parent", "c", "bt", "c"];
+    // The child is let go of without the breakpoints, so it runs to the end.
+    let output = debug(&program, &breakpoints);
+    assert!(output.contains("*#0  0x"));
+    assert!(output.contains(" <in_parent>\n"));
+    assert!(output.contains("child done\n"));
+    assert!(output.contains("debugged process exited with status: 0"));
+
+    let mut commands = vec!["set follow-fork-mode child"];
+    commands.extend(&breakpoints);
+    let output = debug(&program, &commands);
+    assert!(output.contains(" <in_child>\n"));
+    assert!(!output.contains(" <in_parent>\n"));
+    assert!(output.contains("parent done\n"));
+    assert!(output.contains("debugged process exited with status: 3"));
+}
+
+#[test]
+fn counted_steps_stop_at_breakpoints() {
+    let program = fixture("loop");
+    let output = debug(
+        &program,
+        &[
+            "b tick",
+            "c",
+            "si",
+            "si",
+            "si",
+            "r rip",
+            "si 3",
+            "r rip",
+            "si 100000",
+            "r rip",
+            "sym tick",
+        ],
+    );
+    let rips = register(&output, "rip");
+    assert_ne!(rips[1], rips[0]);
+    assert_eq!(rips[2], symbol(&output, "tick"));
+    assert!(output.contains(" of 100000 steps\n"));
+
+    let output = debug(&program, &["b tick", "c", "si 3", "r rip"]);
+    assert_eq!(register(&output, "rip"), [rips[0]]);
+}
+
+#[test]
+fn threads_can_be_listed_and_switched() {
+    let program = fixture("threads");
+    let output = debug(
+        &program,
+        &[
+            "b in_worker",
+            "c",
+            "info threads",
+            "thread 1",
+            "thread 2",
+            "si",
+            "c",
+        ],
+    );
+    let threads: Vec<&str> = output
+        .lines()
+        .filter(|line| line.contains(" thread ") && line.contains('"'))
+        .collect();
+    assert_eq!(threads.len(), 2);
+    assert!(threads[0].starts_with("  1 thread "));
+    assert!(threads[1].starts_with("* 2 thread "));
+    assert!(threads[1].contains(" \"worker\" 0x"));
+    assert!(threads[1].ends_with(" <in_worker>"));
+    assert!(output.contains("thread 2 ("));
+    assert!(output.contains("worker done 1\n"));
+    assert!(output.contains("debugged process exited with status: 0"));
+}
+
+#[test]
+fn thread_breakpoints_only_stop_their_thread() {
+    let program = fixture("threads");
+    let output = debug(
+        &program,
+        &[
+            "b in_worker thread 2",
+            "b in_worker thread 1",
+            "c",
+            "thread",
+        ],
+    );
+    assert!(output.contains("error: no thread 2\n"));
+    assert!(!output.contains("<in_worker>"));
+    assert!(output.contains("worker done 1\n"));
+    assert!(output.contains("debugged process exited with status: 0"));
+}
+
+#[test]
+fn catch_panic_shows_where_the_panic_came_from() {
+    let program = fixture("panic");
+    let output = debug(&program, &["catch panic", "c", "c"]);
+    assert!(output.contains("catching panics at std::panicking::rust_panic\n"));
+    let backtrace = output.split("caught panic\n").nth(1).unwrap();
+    let frames: Vec<&str> = backtrace.lines().take(3).collect();
+    assert!(frames[0].ends_with(" <std::panicking::rust_panic>"));
+    assert!(frames[1].contains(" <parse+"));
+    assert!(frames[2].contains(" <main+"));
+    assert!(output.contains("debugged process exited with status: 101"));
+}
+
+#[test]
+fn set_memory_stores_sized_values_and_undoes() {
+    let program = fixture("locals");
+    let output = debug(
+        &program,
+        &[
+            "b report",
+            "c",
+            "up",
+            "set byte primes + 4 = 0x2a",
+            "p primes",
+            "set dword primes = -1",
+            "p primes",
+            "set byte primes = 256",
+            "undo",
+            "undo",
+            "p primes",
+        ],
+    );
+    assert!(output.contains("primes = {2, 42, 5, 7}\n"));
+    assert!(output.contains("primes = {-1, 42, 5, 7}\n"));
+    assert!(output.contains("error: 0x100 doesn't fit in a byte\n"));
+    assert!(output.contains("] = 0x2\n"));
+    assert!(output.contains("primes = {2, 3, 5, 7}\ndebugged process was killed"));
+}
+
+#[test]
+fn set_memory_keeps_breakpoints_armed() {
+    let program = fixture("arith");
+    // 0x55 is add's own first byte, `push rbp`.
+    let output = debug(
+        &program,
+        &["b add", "b main", "c", "set byte add = 0x55", "c", "events"],
+    );
+    assert!(output.contains("s breakpoint at 0x") && output.contains(" <add>\n"));
+}
+
+#[test]
+fn stack_limit_stops_runaway_recursion() {
+    let program = fixture("recurse");
+    let output = debug(&program, &["set stack-limit 256K", "c", "bt"]);
+    assert!(output.contains(" bytes deep, past the limit of 262144\n"));
+    assert!(output.contains("<dive+0x"));
+
+    // Sampling stops stay out of the way of breakpoints.
+    let program = fixture("loop");
+    let output = debug(
+        &program,
+        &["set stack-limit 1M", "b tick", "c", "c", "c", "c"],
+    );
+    assert!(output.contains("\n3\ndebugged process exited with status: 0\n"));
+}
+
+#[test]
+fn backtrace_limits_and_folds_recursion() {
+    let program = fixture("recurse");
+    let output = debug(
+        &program,
+        &["set stack-limit 256K", "c", "bt 3", "bt -fold-recursion"],
+    );
+    let frames = output
+        .lines()
+        .filter(|l| l.get(1..).is_some_and(|l| l.starts_with('#')));
+    assert!(frames.clone().count() > 5);
+    assert!(output.contains(" more frames, `bt` without a count shows them)\n"));
+    let folded = output
+        .lines()
+        .find(|line| line.starts_with(" [frames "))
+        .unwrap();
+    assert!(folded.contains(": recursion of dive ×"));
+    assert!(output.contains("<main+"));
+}
+
+#[test]
+fn symbols_are_demangled_unless_asked_not_to() {
+    let program = fixture("mangled");
+    let mangled = "_ZN4demo5greet17h0123456789abcdefE";
+    let output = debug(
+        &program,
+        &[
+            &format!("b {}", mangled),
+            "c",
+            "bt 1",
+            "set print mangled on",
+            "bt 1",
+        ],
+    );
+    assert!(output.contains(" <demo::greet>\n"));
+    assert!(output.contains(&format!(" <{}>\n", mangled)));
+}
+
+#[test]
+fn breakpoints_take_demangled_names() {
+    let program = fixture("mangled");
+    let output = debug(&program, &["sym demo::greet", "b demo::greet", "c", "bt 1"]);
+    let greet = symbol(&output, "demo::greet");
+    assert!(output.contains(&format!("#0  0x{:x} <demo::greet>\n", greet)));
+}
+
+#[test]
+fn cpp_symbols_are_demangled_too() {
+    let program = fixture("mangled");
+    let output = debug(&program, &["sym demo::shout", "b demo::shout", "c", "bt 1"]);
+    let shout = symbol(&output, "demo::shout(int)");
+    assert!(output.contains(&format!("#0  0x{:x} <demo::shout(int)>\n", shout)));
+}
+
+#[test]
+fn info_maps_attributes_regions() {
+    let program = fixture("locals");
+    let output = debug(
+        &program,
+        &[
+            "b report",
+            "c",
+            "info maps",
+            "info maps $rsp",
+            "info maps 0",
+        ],
+    );
+    assert!(output.contains(" r-xp     4K text  "));
+    assert!(output.contains("locals <- rip\n"));
+    let stack: Vec<&str> = output
+        .lines()
+        .filter(|line| line.ends_with(" stack [stack] <- rsp"))
+        .collect();
+    assert_eq!(stack.len(), 2);
+    assert!(output.contains("0x0 isn't mapped\n"));
+}
+
+#[test]
+fn formatters_from_a_script_render_matching_types() {
+    let program = fixture("interned");
+    let script = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("formatters.rdb");
+    std::fs::write(
+        &script,
+        "# interned strings\n\
+         formatter ^symbol_t$ #{id} {*(names + id * 8):s}\n\
+         formatter \"^struct entry$\" {key} x{count}\n",
+    )
+    .unwrap();
+    let output = debug(
+        &program,
+        &[
+            &format!("source {}", script.display()),
+            "b report",
+            "c",
+            "up",
+            "info locals",
+            "formatter delete 2",
+            "p entry",
+        ],
+    );
+    assert!(output.contains("symbol_t symbol = #1 \"beta\"\n"));
+    assert!(output.contains("struct entry entry = #2 \"gamma\" x7\n"));
+    assert!(output.contains("entry = {key = #2 \"gamma\", count = 7}\n"));
+}
+
+#[test]
+fn full_register_view_toggles() {
+    let program = fixture("recurse");
+    let output = debug(&program, &["b main", "c", "regs full", "regs toggle"]);
+    let (full, compact) = output.split_once("gs_base:").unwrap();
+    for name in ["r8:", "r15:", "eflags:", "cs:", "fs_base:"] {
+        assert!(full.contains(&format!("\n{}", name)), "{} missing", name);
+    }
+    assert!(compact.contains("\nrsi: "));
+    assert!(!compact.contains("r15"));
+}
+
+#[test]
+fn float_registers_show_sse_and_x87_state() {
+    let program = fixture("float");
+    let output = debug(
+        &program,
+        &["b scale", "b marker", "c", "info float", "c", "info float"],
+    );
+    let (sse, x87) = output.rsplit_once("\nfcw: ").unwrap();
+    assert!(sse.contains("\nxmm0:  0x00000000000000004004000000000000 "));
+    assert!(sse.contains("d = [2.5, 0.0]"));
+    assert!(sse.contains("d = [4.0, 0.0]"));
+    assert!(x87.contains("st0: 3.141592653589793\n"));
+    assert!(x87.contains("st1: 1.0\n"));
+    assert!(x87.contains("st2: empty\n"));
+}
+
+#[test]
+fn diffing_stops_finds_where_runs_diverge() {
+    let program = fixture("diverge");
+    let stops = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("stops.txt");
+    let stops = stops.to_str().unwrap();
+    let commands = ["b step", "c", "c", "c", "c"];
+    debug_with_options(
+        &["--record-stops", stops],
+        &program,
+        &[("DIVERGE", "0")],
+        &commands,
+    );
+    let same = debug_with_options(
+        &["--diff-stops", stops],
+        &program,
+        &[("DIVERGE", "0")],
+        &commands,
+    );
+    assert!(!same.contains("runs diverge"), "{}", same);
+    let output = debug_with_options(
+        &["--diff-stops", stops],
+        &program,
+        &[("DIVERGE", "1")],
+        &commands,
+    );
+    let divergence = output
+        .lines()
+        .find(|line| line.starts_with("runs diverge"))
+        .unwrap();
+    assert!(divergence.starts_with("runs diverge at stop 3 at 0x"));
+    assert!(divergence.contains(" <step>: "));
+    assert!(divergence.contains("rdi 0x2 recorded, 0x3 now"));
+    assert_eq!(output.matches("runs diverge").count(), 1);
+}
+
+#[test]
+fn set_register_changes_what_the_program_computes() {
+    let program = fixture("arith");
+    let output = debug(
+        &program,
+        &["b add", "c", "set $rdi = 100", "set $xyz = 1", "c"],
+    );
+    assert!(output.contains("rdi = 0x64 (100)\n"));
+    assert!(output.contains("error: no register called `xyz`\n"));
+    assert!(output.contains("107\n"));
+    assert!(output.contains("exited with status: 58\n"));
+}
+
+#[test]
+fn rflags_are_decoded() {
+    let program = fixture("arith");
+    let output = debug(
+        &program,
+        &["b add", "c", "set $rflags = 0x8c5", "r eflags", "regs"],
+    );
+    assert!(output.contains("eflags 0x8c5 [ CF PF ZF SF OF ]\n"));
+    assert!(output.contains("eflags: 0x8c5 [ CF PF ZF SF OF ]\n"));
+}
+
+#[test]
+fn deterministic_launch_fixes_entropy_time_and_env() {
+    let program = fixture("entropy");
+    let run = || {
+        let output = debug_with_options(
+            &["--deterministic", "--fixed-syscalls"],
+            &program,
+            &[("TZ", "Europe/Paris")],
+            &["c"],
+        );
+        output
+            .lines()
+            .find(|line| line.starts_with("random "))
+            .unwrap()
+            .to_owned()
+    };
+    let first = run();
+    assert_eq!(first, run());
+    assert!(
+        first.contains(" time 946684800 tv 946684800 TZ UTC"),
+        "{}",
+        first
+    );
+    assert!(!first.starts_with("random 0 "));
+}
+
+#[test]
+fn rlimits_apply_from_the_next_run() {
+    let program = fixture("fds");
+    let output = debug(
+        &program,
+        &[
+            "set rlimit nofile 10",
+            "set rlimit files 1",
+            "set rlimit stack 99999999999999G",
+            "info rlimits",
+            "restart",
+            "c",
+        ],
+    );
+    assert!(output.contains("error: unknown resource `files`"));
+    assert!(output.contains("error: size too large"));
+    assert!(output.contains("nofile   10\n"));
+    let opened: u32 = output
+        .split("opened ")
+        .nth(1)
+        .and_then(|rest| rest.lines().next())
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!(opened > 0 && opened < 10, "{}", output);
+}
+
+#[test]
+fn syscall_steps_between_entries_and_exits() {
+    let program = fixture("arith");
+    let output = debug(&program, &["syscall", "syscall", "syscall", "c"]);
+    assert!(
+        output.contains("brk(NULL) ...\nbrk(NULL) = 0x"),
+        "{}",
+        output
+    );
+    assert!(output.contains("\nmmap(NULL, 8192, "));
+    assert!(output.contains("49\n"));
+    assert!(output.contains("exited with status: 0\n"));
+}
+
+#[test]
+fn signals_follow_the_handle_table() {
+    let program = fixture("alarm");
+    let output = debug(&program, &["c"]);
+    assert!(output.contains("alarms 3\n"), "{}", output);
+    assert!(!output.contains("stopped by"));
+
+    let output = debug(
+        &program,
+        &[
+            "handle SIGALRM stop",
+            "handle bogus stop",
+            "c",
+            "c",
+            "c",
+            "c",
+        ],
+    );
+    assert!(output.contains("SIGALRM    yes   yes\n"));
+    assert!(output.contains("error: unknown signal `bogus`\n"));
+    assert_eq!(output.matches("stopped by SIGALRM at ").count(), 3);
+    assert!(output.contains("alarms 3\n"));
+}
+
+#[test]
+fn sandbox_isolates_network_and_limits_memory() {
+    let program = fixture("hog");
+    let own = std::fs::read_link("/proc/self/ns/net").unwrap();
+    let output = debug_with_options(
+        &["--sandbox", "--sandbox-memory", "64M"],
+        &program,
+        &[],
+        &["c"],
+    );
+    let net = output
+        .lines()
+        .find_map(|line| line.strip_prefix("net "))
+        .unwrap();
+    assert_ne!(net, own.to_str().unwrap());
+    assert!(!output.contains("allocated 512M"), "{}", output);
+}
+
+#[test]
+fn core_files_show_where_the_process_crashed() {
+    let program = fixture("crash");
+    let core = core_dump(&program);
+    let output = debug_with_options(
+        &["--core", core.to_str().unwrap()],
+        &program,
+        &[],
+        &["bt", "p answer", "r rdi", "si"],
+    );
+    assert!(output.contains(" dumped core on SIGSEGV at 0x"));
+    let frames: Vec<&str> = output.lines().filter(|l| l.contains("#")).collect();
+    assert!(frames[0].contains(" <crash+"));
+    assert!(frames[1].contains(" <main+"));
+    assert!(output.contains("0x2a (42)"));
+    assert_eq!(register(&output, "rdi"), vec![7]);
+    assert!(output.contains("error: a core dump can't be run or changed, only looked at\n"));
+}
+
+#[test]
+fn gcore_saves_a_core_file_that_can_be_opened() {
+    let program = fixture("crash");
+    let core = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("crash.gcore");
+    let save = format!("gcore {}", core.display());
+    let output = debug(&program, &["b crash", "c", "set var answer = 43", &save]);
+    assert!(output.contains(&format!("saved core file {} with ", core.display())));
+
+    let output = debug_with_options(
+        &["--core", core.to_str().unwrap()],
+        &program,
+        &[],
+        &["bt", "p answer", "r rdi"],
+    );
+    assert!(output.contains(" dumped core on SIGTRAP at 0x"));
+    assert!(output.contains("#0  0x"));
+    assert!(output.lines().any(|line| line.ends_with(" <crash>")));
+    assert!(output.contains("0x2b (43)"));
+    assert_eq!(register(&output, "rdi"), vec![7]);
+}
+
+#[test]
+fn printed_values_note_known_constants() {
+    let program = fixture("loop");
+    let output = debug(
+        &program,
+        &[
+            "p -2",
+            "p 0x80000",
+            "p 0x20544547",
+            "set annotate ascii off",
+            "p 0x20544547",
+            "info annotations",
+        ],
+    );
+    let lines: Vec<&str> = output.lines().collect();
+    assert!(lines.contains(&"-2 (0xfffffffffffffffe) ; -ENOENT"));
+    assert!(lines.contains(&"0x80000 ; O_RDONLY|O_CLOEXEC"));
+    assert!(lines.contains(&"0x20544547 ; \"GET \""));
+    assert!(lines.contains(&"0x20544547"));
+    assert!(lines.contains(&"ascii off"));
+    assert!(lines.contains(&"errno on"));
+}
+
+#[test]
+fn checkpoints_go_back_to_where_they_were_made() {
+    let program = fixture("loop");
+    let output = debug(
+        &program,
+        &[
+            "b tick",
+            "c",
+            "c",
+            "checkpoint",
+            "c",
+            "r rdi",
+            "restart 1",
+            "r rdi",
+            "info checkpoints",
+            "c",
+            "c",
+            "restart 1",
+            "r rdi",
+        ],
+    );
+    assert!(output.contains("checkpoint 1 at 0x"));
+    assert!(output.contains("restored checkpoint 1 at 0x"));
+    assert!(output.lines().any(|line| line.starts_with("1 process ")));
+    assert_eq!(register(&output, "rdi"), vec![2, 1, 1]);
+}
+
+#[test]
+fn addresses_stay_put_across_runs_unless_aslr_is_on() {
+    let program = fixture("loop");
+    let rsp = |options: &[&str]| {
+        let output = debug_with_options(options, &program, &[], &["b tick", "c", "r rsp"]);
+        register(&output, "rsp")
+    };
+    assert_eq!(rsp(&[]), rsp(&["--no-aslr"]));
+    assert_ne!(rsp(&["--aslr"]), rsp(&["--aslr"]));
+}
+
+#[test]
+fn compressed_core_files_open_too() {
+    let program = fixture("crash");
+    let core = core_dump(&program);
+    let compressed = core.with_extension("zst");
+    let status = std::process::Command::new("zstd")
+        .args(["-qf", "-o"])
+        .arg(&compressed)
+        .arg(&core)
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let output = debug_with_options(
+        &["--core", compressed.to_str().unwrap()],
+        &program,
+        &[],
+        &["p answer"],
+    );
+    assert!(output.contains(" dumped core on SIGSEGV at 0x"));
+    assert!(output.contains("0x2a (42)"));
+}
+
+#[test]
+fn minidumps_open_like_core_files() {
+    let program = fixture("crash");
+    let crash = symbol(&debug(&program, &["sym crash"]), "crash");
+    let stack = 0x7fff_0000_0000u64;
+
+    // A header and directory of three streams: a thread and the exception,
+    // both pointing at one CONTEXT, and the stack.
+    let mut dump = vec![0u8; 32 + 3 * 12];
+    dump[..4].copy_from_slice(b"MDMP");
+    dump[8..12].copy_from_slice(&3u32.to_le_bytes());
+    dump[12..16].copy_from_slice(&32u32.to_le_bytes());
+    let stream = |dump: &mut Vec<u8>, n: usize, kind: u32, data: &[u8]| {
+        let entry = 32 + n * 12;
+        let at = dump.len() as u32;
+        dump[entry..entry + 4].copy_from_slice(&kind.to_le_bytes());
+        dump[entry + 4..entry + 8].copy_from_slice(&(data.len() as u32).to_le_bytes());
+        dump[entry + 8..entry + 12].copy_from_slice(&at.to_le_bytes());
+        dump.extend_from_slice(data);
+    };
+    let le32 = |n: usize| (n as u32).to_le_bytes();
+
+    let context_at = 1024;
+    let stack_at = context_at + 0x4d0;
+    let mut context_location = le32(0x4d0).to_vec();
+    context_location.extend_from_slice(&le32(context_at));
+
+    let mut threads = le32(1).to_vec();
+    threads.extend_from_slice(&[0u8; 48]);
+    threads[4..8].copy_from_slice(&le32(4242));
+    threads[44..52].copy_from_slice(&context_location);
+    stream(&mut dump, 0, 3, &threads);
+
+    let mut exception = vec![0u8; 168];
+    exception[..4].copy_from_slice(&le32(4242));
+    exception[8..12].copy_from_slice(&le32(11));
+    exception[160..].copy_from_slice(&context_location);
+    stream(&mut dump, 1, 6, &exception);
+
+    let mut memory = le32(1).to_vec();
+    memory.extend_from_slice(&stack.to_le_bytes());
+    memory.extend_from_slice(&le32(256));
+    memory.extend_from_slice(&le32(stack_at));
+    stream(&mut dump, 2, 5, &memory);
+
+    dump.resize(context_at, 0);
+    let mut context = vec![0u8; 0x4d0];
+    context[0x98..0xa0].copy_from_slice(&(stack + 128).to_le_bytes());
+    context[0xb0..0xb8].copy_from_slice(&7u64.to_le_bytes());
+    context[0xf8..0x100].copy_from_slice(&(crash + 4).to_le_bytes());
+    dump.extend_from_slice(&context);
+    assert_eq!(dump.len(), stack_at);
+    dump.extend_from_slice(&[0u8; 256]);
+
+    let path = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("crash.dmp");
+    std::fs::write(&path, &dump).unwrap();
+    let output = debug_with_options(
+        &["--core", path.to_str().unwrap()],
+        &program,
+        &[],
+        &["r rdi", "r rsp"],
+    );
+    assert!(output.contains("process 4242 dumped core on SIGSEGV at 0x"));
+    assert!(output.contains(" <crash+0x4>"));
+    assert_eq!(register(&output, "rdi"), vec![7]);
+    assert_eq!(register(&output, "rsp"), vec![stack + 128]);
+}
+
+#[test]
+fn malformed_minidumps_are_errors() {
+    use std::process::{Command, Stdio};
+
+    // A header and one stream, of `kind` and holding `data`.
+    let dump = |name: &str, kind: u32, data: &[u8]| {
+        let mut dump = vec![0u8; 44];
+        dump[..4].copy_from_slice(b"MDMP");
+        dump[8..12].copy_from_slice(&1u32.to_le_bytes());
+        dump[12..16].copy_from_slice(&32u32.to_le_bytes());
+        dump[32..36].copy_from_slice(&kind.to_le_bytes());
+        dump[36..40].copy_from_slice(&(data.len() as u32).to_le_bytes());
+        dump[40..44].copy_from_slice(&44u32.to_le_bytes());
+        dump.extend_from_slice(data);
+        let path = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join(name);
+        std::fs::write(&path, dump).unwrap();
+        path
+    };
+
+    // MEMORY64_LIST with one range as long as the address space.
+    let mut memory = 1u64.to_le_bytes().to_vec();
+    memory.extend_from_slice(&0u64.to_le_bytes());
+    memory.extend_from_slice(&0u64.to_le_bytes());
+    memory.extend_from_slice(&u64::MAX.to_le_bytes());
+    // MODULE_LIST with a module at the very top whose name is past the end.
+    let mut modules = 1u32.to_le_bytes().to_vec();
+    modules.extend_from_slice(&[0u8; 108]);
+    modules[4..12].copy_from_slice(&u64::MAX.to_le_bytes());
+    modules[12..16].copy_from_slice(&16u32.to_le_bytes());
+    modules[24..28].copy_from_slice(&u32::MAX.to_le_bytes());
+
+    let program = fixture("crash");
+    for path in [
+        dump("memory64.dmp", 9, &memory),
+        dump("modules.dmp", 4, &modules),
+    ] {
+        let output = Command::new(env!("CARGO_BIN_EXE_rust-debugger"))
+            .arg("--core")
+            .arg(&path)
+            .arg(&program)
+            .stdin(Stdio::null())
+            .output()
+            .unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(&output.stderr),
+            "err: truncated minidump\n"
+        );
+        assert_eq!(output.status.code(), Some(1));
+    }
+}
+
+#[test]
+fn backtraces_export_with_module_offsets() {
+    let program = fixture("loop");
+    let output = debug(
+        &program,
+        &["sym tick", "b tick", "c", "bt --symbolic", "bt 1 --json"],
+    );
+    let tick = symbol(&output, "tick");
+    // The fixtures aren't position independent, so load at 0x400000.
+    let offset = tick - 0x400000;
+    let path = std::fs::canonicalize(&program).unwrap();
+    let symbolic = format!("#0  0x{:x} {}+0x{:x} ", tick, path.display(), offset);
+    assert!(output.contains(&symbolic));
+    assert!(output.contains(&format!(
+        "{{\"instruction_addr\":\"0x{:x}\",\"function\":\"tick\",\"symbol_addr\":\"0x{:x}\"",
+        tick, tick
+    )));
+    assert!(output.contains(&format!("\"module_offset\":\"0x{:x}\"", offset)));
+    assert!(output.contains("\"lineno\":5}"));
+    assert!(output.contains("\"type\":\"elf\""));
+}
+
+#[test]
+fn position_independent_binaries_are_found_where_they_loaded() {
+    let program = pie_fixture("counter");
+    let output = debug(
+        &program,
+        &[
+            "sym bump",
+            "b bump",
+            "c",
+            "c",
+            "c",
+            "r rip",
+            "info locals",
+            "bt --json",
+        ],
+    );
+    let bump = symbol(&output, "bump");
+    assert!(bump > 0x500000, "bump at 0x{:x} isn't relocated", bump);
+    assert_eq!(register(&output, "rip"), vec![bump]);
+    // A static local is at a DW_OP_addr address, which moves too.
+    assert!(output.contains("long int calls = 2"));
+    assert!(output.contains("\"function\":\"bump\""));
+    assert!(output.contains("\"lineno\":3}"));
+}
+
+#[test]
+fn the_prompt_works_out_arithmetic() {
+    let program = fixture("loop");
+    let output = debug(
+        &program,
+        &[
+            "0x7ffe1234 - 0x7ffe1200",
+            "1 << 12",
+            "nonsense",
+            "c",
+            "2 - 4",
+        ],
+    );
+    assert!(output.contains("0x34 = 52 = 0b110100\n"));
+    assert!(output.contains("0x1000 = 4096 = 0b1000000000000\n"));
+    // Still there once the process is gone.
+    assert!(output.contains("0xfffffffffffffffe = -2 = 0b1111"));
+    assert!(output.contains("unknown command `[\"nonsense\"]`"));
+}
+
+#[test]
+fn shared_library_symbols_are_loaded_after_startup() {
+    let program = fixture("loop");
+    let output = debug(
+        &program,
+        &[
+            "sym printf",
+            "tbreak main",
+            "c",
+            "info sharedlibrary",
+            "sym printf",
+            "b printf",
+            "c",
+            "r rip",
+            "bt",
+        ],
+    );
+    // Nothing is loaded before the dynamic linker runs.
+    assert!(output.contains("couldn't find symbol with name \"printf\""));
+    assert!(output.contains("libc.so.6 ("));
+    let printf = symbol(&output, "printf");
+    assert!(
+        printf > 0x7f0000000000,
+        "printf at 0x{:x} isn't in libc",
+        printf
+    );
+    assert_eq!(register(&output, "rip"), vec![printf]);
+    assert!(output.contains(&format!("#0  0x{:x} <", printf)));
+}
+
+#[test]
+fn types_answer_layout_questions() {
+    let program = fixture("layout");
+    let output = debug(
+        &program,
+        &[
+            "ptype record_t",
+            "sizeof struct record",
+            "offsetof record.where.y",
+            "offsetof record.nope",
+            "c",
+            "sizeof int",
+        ],
+    );
+    assert!(output.contains(
+        "type = struct record {\n    char tag;\n    long int id;\n    short int flags;\n    \
+         struct point where;\n    const char *name;\n    unsigned char digest[5];\n}\n"
+    ));
+    assert!(output.contains("\n48\n24\n"));
+    assert!(output.contains("error: `struct record` has no field `nope`"));
+    // The debug info outlives the process.
+    assert!(output.ends_with("\n4\n"));
+}
+
+#[test]
+fn breakpoints_wait_for_libraries_that_define_them() {
+    let program = fixture("dlopen");
+    let plugin = library_fixture("plugin");
+    let output = debug_with_env(
+        &program,
+        &[("PLUGIN", plugin.to_str().unwrap())],
+        &["b plugin_hook", "whereami", "c", "bt", "run", "c", "bt"],
+    );
+    assert!(output.contains("breakpoint on plugin_hook pending until a library defines it"));
+    assert!(output.contains("  plugin_hook (pending)\n"));
+    // It's set again for the library loading in the new process.
+    assert_eq!(output.matches(" <plugin_hook>\n").count(), 2);
+}
+
+#[test]
+fn catch_load_stops_before_the_library_runs() {
+    let program = fixture("dlopen");
+    let plugin = library_fixture("plugin");
+    let output = debug_with_env(
+        &program,
+        &[("PLUGIN", plugin.to_str().unwrap())],
+        &["catch load plugin", "c", "b plugin_hook", "c", "bt", "c"],
+    );
+    let caught = format!("caught load of {}\n", plugin.display());
+    let (before, after) = output.split_once(&caught).expect("load wasn't caught");
+    assert!(!before.contains("42"));
+    assert!(after.contains(" <plugin_hook>\n"));
+    assert!(after.contains("42\n"));
+}
+
+#[test]
+fn layout_type_shows_padding_and_niches() {
+    let program = fixture("layout");
+    let output = debug(&program, &["layout type struct tagged"]);
+    assert!(output.contains(
+        "struct tagged, 32 bytes\noffset  size\n     0     1  _Bool visible  (niche: only 0 and 1)\n     \
+         1     3  padding\n     4     4  enum shape shape  (niche: only 0 to 2)\n     \
+         8     8  struct point *origin\n    16     4  int corner.x\n    20     4  int corner.y\n    \
+         24     1  char initial\n    25     7  padding\n10 of 32 bytes are padding\n"
+    ));
+}
+
+#[test]
+fn inspect_reads_the_binary_without_running_it() {
+    let program = fixture("layout");
+    let output = debug_with_options(
+        &["--inspect"],
+        &program,
+        &[],
+        &[
+            "sym main",
+            "sizeof record_t",
+            "info elf",
+            "r rip",
+            "run",
+            "c",
+        ],
+    );
+    assert!(output.contains(", `run` starts it\n"));
+    symbol(&output, "main");
+    assert!(output.contains("\n48\n"));
+    assert!(output.contains(": executable for x86-64, entry 0x"));
+    assert!(output.contains(" .text\n"));
+    assert!(output.contains("error: the program is not running"));
+    assert!(output.contains("r 42 first\n"));
+}
+
+#[test]
+fn rebuilt_binaries_have_their_symbols_reloaded() {
+    use std::io::{Read, Write};
+    use std::os::unix::net::UnixStream;
+    use std::process::{Command, Stdio};
+
+    let dir = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("rebuilt");
+    std::fs::create_dir_all(&dir).unwrap();
+    let source = dir.join("rebuilt.c");
+    let program = dir.join("rebuilt");
+    let socket = dir.join("control.sock");
+    let original = std::fs::read_to_string(fixture_source("loop")).unwrap();
+    let build = |source_code: &str| {
+        std::fs::write(&source, source_code).unwrap();
+        let status = Command::new("cc")
+            .args(["-g", "-O0", "-no-pie", "-o"])
+            .arg(&program)
+            .arg(&source)
+            .status()
+            .unwrap();
+        assert!(status.success());
+    };
+    build(&original);
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rust-debugger"))
+        .arg("--watch-binary")
+        .arg("--control")
+        .arg(&socket)
+        .arg(&program)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let send = |command: &str| {
+        for _ in 0..100 {
+            if let Ok(mut stream) = UnixStream::connect(&socket) {
+                writeln!(stream, "{}", command).unwrap();
+                let mut reply = String::new();
+                stream.read_to_string(&mut reply).unwrap();
+                return reply;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(50));
+        }
+        panic!("control socket never came up");
+    };
+    let before = symbol(&send("sym tick"), "tick");
+    send("b tick");
+    send("kill");
+
+    // A function ahead of `tick` moves it, and `tick` itself grows.
+    build(
+        &original
+            .replace(
+                "void tick",
+                "void unused(void) {\n\ttotal *= 2;\n}\n\nvoid tick",
+            )
+            .replace(
+                "\ttotal += i;",
+                "\tlong twice = i * 2;\n\ttotal += twice / 2;",
+            ),
+    );
+    let mut reloaded = false;
+    for _ in 0..100 {
+        if send("events").contains("reloaded symbols") {
+            reloaded = true;
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(50));
+    }
+    assert!(reloaded, "the rebuild went unnoticed");
+    let after = symbol(&send("sym tick"), "tick");
+    assert_ne!(before, after);
+
+    let stdin = child.stdin.as_mut().unwrap();
+    writeln!(stdin, "run\nc\nr rip").unwrap();
+    drop(child.stdin.take());
+    let output = String::from_utf8_lossy(&child.wait_with_output().unwrap().stdout).to_string();
+    assert!(output.contains(&format!(
+        "breakpoint at 0x{:x} <tick> moved to 0x{:x} <tick>",
+        before, after
+    )));
+    assert_eq!(register(&output, "rip"), vec![after]);
+}
+
+#[test]
+fn source_is_listed_around_the_current_line() {
+    let program = fixture("loop");
+    let output = debug(
+        &program,
+        &[
+            "b tick",
+            "set show-source on",
+            "c",
+            "list main",
+            "list printf",
+        ],
+    );
+    assert!(output.contains("=>    5  void tick(long i) {\n      6  \ttotal += i;\n"));
+    assert!(output.contains("      8  \n=>    9  int main(void) {\n"));
+    assert!(output.contains("error: no line info for "));
+}
+
+#[test]
+fn cargo_test_builds_and_breaks_on_the_test() {
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+
+    let project = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("cargo-demo");
+    std::fs::create_dir_all(project.join("src")).unwrap();
+    std::fs::write(
+        project.join("Cargo.toml"),
+        "[package]\nname = \"demo\"\nversion = \"0.1.0\"\nedition = \"2018\"\n",
+    )
+    .unwrap();
+    std::fs::write(
+        project.join("src").join("lib.rs"),
+        "pub fn add(a: u64, b: u64) -> u64 {\n    a + b\n}\n\n\
+         #[cfg(test)]\nmod tests {\n    #[test]\n    fn adds() {\n        \
+         assert_eq!(super::add(2, 3), 5);\n    }\n}\n",
+    )
+    .unwrap();
+    std::fs::write(
+        project.join("src").join("main.rs"),
+        "fn main() {\n    let args: Vec<String> = std::env::args().skip(1).collect();\n    \
+         println!(\"sum {} for {}\", demo::add(2, 3), args.join(\" \"));\n}\n",
+    )
+    .unwrap();
+
+    let debug_cargo = |args: &[&str], commands: &str| {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_rust-debugger"))
+            .args(args)
+            .current_dir(&project)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .unwrap();
+        write!(child.stdin.as_mut().unwrap(), "{}", commands).unwrap();
+        let output = child.wait_with_output().unwrap();
+        assert!(output.status.success());
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+
+    let output = debug_cargo(&["cargo", "test", "adds"], "c\nbt 1\nc\n");
+    assert!(output.contains(" <demo::tests::adds>\n"));
+    assert!(output.contains("*#0  0x"));
+    assert!(output.contains("test result: ok. 1 passed"));
+
+    let output = debug_cargo(&["cargo", "run", "--", "hello", "there"], "c\n");
+    assert!(output.contains("sum 5 for hello there\n"));
+}
+
+#[test]
+fn breakpoints_go_on_source_lines() {
+    let program = fixture("loop");
+    let output = debug(
+        &program,
+        &[
+            "b loop.c:6",
+            // A blank line breaks on the next one with code, `main`'s.
+            "b loop.c:8",
+            "sym main",
+            "c",
+            "r rip",
+            "c",
+            "whereami",
+            "b nowhere.c:3",
+        ],
+    );
+    assert_eq!(register(&output, "rip"), vec![symbol(&output, "main")]);
+    assert!(output.contains("at 0x") && output.contains(" (loop.c:6)\n"));
+    assert!(output.contains("error: no code at nowhere.c:3\n"));
+}
+
+#[test]
+fn disas_at_a_breakpoint_shows_the_code() {
+    let program = fixture("arith");
+    let output = debug(&program, &["sym main", "b main", "c", "d"]);
+    let main = symbol(&output, "main");
+    assert!(output.contains(&format!("0x{:x} 55             push rbp\n", main)));
+    assert!(output.contains(" ; <add>\n"));
+}
+
+#[test]
+fn stepo_runs_string_instructions_but_steps_rep_ret() {
+    let program = fixture("rep");
+    let output = debug(
+        &program,
+        &[
+            "sym fill", "sym main", "b fill", "b bounce", "c", "stepo", "r rip", "r rcx", "c",
+            "stepo", "r rip",
+        ],
+    );
+    let fill = symbol(&output, "fill");
+    let main = symbol(&output, "main");
+    let rips = register(&output, "rip");
+    // `rep stosb` is two bytes, and all 64 of them were cleared.
+    assert_eq!(rips[0], fill + 2);
+    assert_eq!(register(&output, "rcx"), vec![0]);
+    // `rep ret` went back into main rather than running to the end.
+    assert!(rips[1] > main && rips[1] < main + 0x40, "{:x?}", rips);
+}
+
+#[test]
+fn disas_predicts_conditional_branches() {
+    let program = fixture("branch");
+    let output = debug(
+        &program,
+        &["sym small", "b decide", "c", "d", "c", "d", "c"],
+    );
+    let small = symbol(&output, "small");
+    let taken = output
+        .find(&format!("will be TAKEN → 0x{:x}", small))
+        .unwrap();
+    let not_taken = output.find("\nnot taken\n").unwrap();
+    // check(1) jumps over setting its result, check(5) doesn't.
+    assert!(taken < not_taken);
+    assert!(output.contains("0 1\n"));
+}
+
+#[test]
+fn disas_previews_the_operands_at_rip() {
+    let program = fixture("arith");
+    // Past add's prologue and the two stores of its arguments, to where it
+    // loads `a` back, which is `counter`'s 42.
+    let output = debug(&program, &["b add", "c", "si 4", "d"]);
+    let preview = output
+        .lines()
+        .find(|line| line.starts_with("mov rdx,[rbp-8] ; "))
+        .unwrap();
+    assert!(preview.contains("rdx = 0x") && preview.ends_with("] = 0x2a"));
+}
+
+#[test]
+fn software_stepping_follows_branches_and_returns() {
+    let program = fixture("branch");
+    let output = debug(
+        &program,
+        &[
+            "sym decide",
+            "sym small",
+            "sym main",
+            "set step-mode software",
+            "b decide",
+            "c",
+            "si",
+            "r rip",
+            "si",
+            "r rip",
+            "c",
+            "si",
+            "r rip",
+            "c",
+        ],
+    );
+    let decide = symbol(&output, "decide");
+    let small = symbol(&output, "small");
+    let main = symbol(&output, "main");
+    let rips = register(&output, "rip");
+    assert_eq!(rips[0], small);
+    // `ret` goes back into main.
+    assert!(rips[1] > main && rips[1] < main + 0x40, "{:x?}", rips);
+    // `jb` is two bytes.
+    assert_eq!(rips[2], decide + 2);
+    assert!(output.contains("0 1\n"));
+}
+
+#[test]
+fn skip_insn_moves_past_the_instruction_and_warns_about_the_stack() {
+    let program = fixture("arith");
+    let output = debug(
+        &program,
+        &["sym add", "b add", "c", "skip-insn", "skip-insn", "r rip"],
+    );
+    let add = symbol(&output, "add");
+    assert!(output.contains(
+        "warning: skipped `push rbp` which adjusts the stack, rsp is now off from what the code expects\n"
+    ));
+    assert!(output.contains(&format!("rip is now 0x{:x} <add+0x1>\n", add + 1)));
+    assert_eq!(output.matches("warning: skipped").count(), 1);
+    // `mov rbp,rsp` is three bytes.
+    assert_eq!(register(&output, "rip"), vec![add + 4]);
+}
+
+#[test]
+fn compare_disas_lines_up_two_functions() {
+    let program = fixture("arith");
+    let output = debug(
+        &program,
+        &[
+            "sym add",
+            "sym main",
+            "b main",
+            "c",
+            "compare disas add main",
+            "compare disas add nowhere",
+        ],
+    );
+    let add = symbol(&output, "add");
+    let main = symbol(&output, "main");
+    let rows: Vec<&str> = output
+        .lines()
+        .skip_while(|line| !line.starts_with("add "))
+        .take_while(|line| !line.starts_with("error: "))
+        .collect();
+    assert!(rows[0].ends_with("   main"));
+    // Both start with the same prologue and end with the same `ret`.
+    assert!(rows[1].starts_with(&format!("0x{:x} push rbp ", add)));
+    assert!(rows[1].ends_with(&format!("   0x{:x} push rbp", main)));
+    assert!(rows.last().unwrap().ends_with(" ret") && !rows.last().unwrap().contains(" | "));
+    // add's arithmetic is set against main's, and the rest of main has
+    // nothing across from it.
+    assert!(rows.iter().any(|row| row.contains(" | ")));
+    assert!(rows.iter().any(|row| row.trim_start().starts_with("> ")));
+    assert!(output.contains("error: unknown symbol `nowhere`\n"));
+}
+
+#[test]
+fn history_lists_stepped_instructions_and_what_they_changed() {
+    let program = fixture("arith");
+    let output = debug(
+        &program,
+        &[
+            "sym add",
+            "b add",
+            "c",
+            "history",
+            "si 2",
+            "history",
+            "history 1",
+        ],
+    );
+    let add = symbol(&output, "add");
+    assert!(output.contains("no instructions stepped yet\n"));
+    let push = format!("0x{:x} 55             push rbp  ; rsp 0x", add);
+    let mov = format!("0x{:x} 4889e5         mov rbp,rsp  ; rbp 0x", add + 1);
+    assert_eq!(
+        output
+            .lines()
+            .filter(|line| line.starts_with(&push))
+            .count(),
+        1
+    );
+    assert_eq!(
+        output.lines().filter(|line| line.starts_with(&mov)).count(),
+        2
+    );
+}
+
+#[test]
+fn break_callers_stops_at_every_call() {
+    let program = fixture("branch");
+    let output = debug(
+        &program,
+        &[
+            "sym main",
+            "break-callers check",
+            "c",
+            "r rip",
+            "c",
+            "r rip",
+            "c",
+        ],
+    );
+    let main = symbol(&output, "main");
+    assert!(output.contains("set 2 breakpoints\n"));
+    let rips = register(&output, "rip");
+    assert_eq!(rips.len(), 2);
+    assert!(rips[0] > main && rips[0] < rips[1] && rips[1] < main + 0x40);
+    for rip in rips {
+        assert!(output.contains(&format!(
+            "breakpoint at 0x{:x} <main+0x{:x}>\n",
+            rip,
+            rip - main
+        )));
+    }
+    assert!(output.contains("0 1\n"));
+}
+
+#[test]
+fn xref_lists_calls_and_data_references() {
+    let program = fixture("arith");
+    let output = debug(
+        &program,
+        &[
+            "sym main",
+            "b main",
+            "c",
+            "xref add",
+            "xref counter",
+            "xref 0x1",
+        ],
+    );
+    let main = symbol(&output, "main");
+    let call = output
+        .lines()
+        .find(|line| line.ends_with("> call"))
+        .unwrap();
+    let offset = call
+        .split(" <main+0x")
+        .nth(1)
+        .unwrap()
+        .trim_end_matches("> call");
+    let offset = u64::from_str_radix(offset, 16).unwrap();
+    assert!(call.starts_with(&format!("0x{:x} ", main + offset)));
+    // Read for the call, written with its result, and read for printf and
+    // the exit status.
+    let data: Vec<&str> = output
+        .lines()
+        .filter(|line| line.ends_with("> data"))
+        .collect();
+    assert_eq!(data.len(), 4);
+    assert!(data.iter().all(|line| line.contains(" <main+0x")));
+    assert!(output.contains("nothing refers to 0x1\n"));
+}
+
+#[test]
+fn info_strings_names_the_functions_using_each_string() {
+    let program = fixture("writes");
+    let output = debug(
+        &program,
+        &["b main", "c", "info strings", "info strings ^err"],
+    );
+    let out = output
+        .lines()
+        .filter(|line| line.ends_with(r#" "out\n" used by main"#))
+        .count();
+    let err = output
+        .lines()
+        .filter(|line| line.ends_with(r#" "err\n" used by main"#))
+        .count();
+    // The pattern leaves out "out\n" the second time.
+    assert_eq!((out, err), (1, 2));
+}
+
+#[test]
+fn disas_annotates_known_constants() {
+    let program = fixture("constants");
+    let output = debug(
+        &program,
+        &[
+            "disas main",
+            "set annotate ascii off",
+            "disas main",
+            "p -2",
+            "set annotate wat on",
+        ],
+    );
+    assert_eq!(
+        output.matches(",241h ; O_WRONLY|O_CREAT|O_TRUNC\n").count(),
+        2
+    );
+    assert_eq!(output.matches(",20544547h ; \"GET \"\n").count(), 1);
+    assert!(output.contains(",20544547h\n"));
+    assert!(output.contains("-2 (0xfffffffffffffffe) ; -ENOENT\n"));
+    assert!(output.contains("error: no annotation table `wat`\n"));
+}
+
+#[test]
+fn reverse_step_undoes_registers_and_memory() {
+    let program = fixture("arith");
+    let output = debug(
+        &program,
+        &[
+            "sym add",
+            "b add",
+            "c",
+            "r rbp",
+            "r rsp",
+            "x/gx $rsp-8",
+            "record",
+            "si 2",
+            "info record",
+            "x/gx $rsp",
+            "rsi 2",
+            "r rbp",
+            "r rsp",
+            "x/gx $rsp-8",
+            "rsi",
+            "si 3",
+            "rc",
+        ],
+    );
+    let add = symbol(&output, "add");
+    let (rbp, rsp) = (register(&output, "rbp"), register(&output, "rsp"));
+    assert_eq!(rbp[0], rbp[1]);
+    assert_eq!(rsp[0], rsp[1]);
+    // `push rbp` wrote the caller's rbp below the stack and going back
+    // over it put back what was there.
+    let slot = format!("0x{:x}:  ", rsp[0] - 8);
+    let values: Vec<&str> = output
+        .lines()
+        .filter_map(|line| line.strip_prefix(&slot))
+        .collect();
+    assert_eq!(values.len(), 3, "{}", output);
+    assert_eq!(values[1], format!("0x{:016x}", rbp[0]));
+    assert_eq!(values[0], values[2]);
+    assert!(output.contains("recording, 2 steps to go back over\n"));
+    assert!(output.contains("error: no recorded steps to go back over\n"));
+    assert!(output.contains(&format!(
+        "back at breakpoint 0x{:x} <add> after 3 steps\n",
+        add
+    )));
+}
+
+#[test]
+fn objdump_names_symbols_plt_entries_and_globals() {
+    let program = fixture("arith");
+    let output = debug(&program, &["sym main", "objdump main", "objdump nowhere"]);
+    let main = symbol(&output, "main");
+    assert!(output.contains(&format!("0x{:x} <main>:\n0x{:x} 55 ", main, main)));
+    assert!(output.contains(" ; <add>\n"));
+    assert!(output.contains(" ; <printf@plt>\n"));
+    assert_eq!(output.matches(" ; <counter>\n").count(), 4);
+    assert!(output.contains(&format!(
+        "error: no function named `nowhere` in {}\n",
+        program.display()
+    )));
+}
+
+#[test]
+fn disas_names_call_targets_demangled() {
+    let program = fixture("mangled");
+    let output = debug(
+        &program,
+        &["disas main", "set print mangled on", "disas main"],
+    );
+    assert!(output.contains(" ; <demo::greet>\n"));
+    assert!(output.contains(" ; <demo::shout(int)>\n"));
+    assert!(output.contains(" ; <_ZN4demo5greet17h0123456789abcdefE>\n"));
+    assert!(output.contains(" ; <_ZN4demo5shoutEi>\n"));
+}
+
+#[test]
+fn examine_disassembles_with_the_i_format() {
+    let program = fixture("arith");
+    let output = debug(&program, &["sym main", "b main", "c", "x/3i $rip"]);
+    let main = symbol(&output, "main");
+    assert!(output.contains(&format!(
+        "0x{:x} 55             push rbp\n0x{:x} 4889e5         mov rbp,rsp\n0x{:x} ",
+        main,
+        main + 1,
+        main + 4
+    )));
+}
+
+#[test]
+fn a_process_killed_from_outside_leaves_the_session_running() {
+    use std::io::{Read, Write};
+    use std::os::unix::net::UnixStream;
+    use std::process::{Command, Stdio};
+
+    let program = fixture("loop");
+    let socket = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("gone.sock");
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rust-debugger"))
+        .arg("--control")
+        .arg(&socket)
+        .arg(&program)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let send = |command: &str| {
+        for _ in 0..100 {
+            if let Ok(mut stream) = UnixStream::connect(&socket) {
+                writeln!(stream, "{}", command).unwrap();
+                let mut reply = String::new();
+                stream.read_to_string(&mut reply).unwrap();
+                return reply;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(50));
+        }
+        panic!("control socket never came up");
+    };
+    send("b tick");
+    send("c");
+
+    let children = format!("/proc/{0}/task/{0}/children", child.id());
+    let pid = std::fs::read_to_string(children).unwrap();
+    let status = Command::new("kill")
+        .args(["-KILL", pid.trim()])
+        .status()
+        .unwrap();
+    assert!(status.success());
+    let gone = send("si");
+    let after = send("si");
+
+    let stdin = child.stdin.as_mut().unwrap();
+    writeln!(stdin, "run\nc\nc\nc\nc").unwrap();
+    drop(child.stdin.take());
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    assert!(gone.contains(&format!("debugged process {} is gone\n", pid.trim())));
+    assert!(gone.contains("debugged process was killed by SIGKILL\n"));
+    assert!(after.contains("error: the program is not running, `run` starts it again\n"));
+    // `run` starts it again and it gets to the end.
+    assert!(String::from_utf8_lossy(&output.stdout).contains("3\n"));
+}
+
+#[test]
+fn malformed_commands_and_expressions_are_errors_not_panics() {
+    let program = fixture("arith");
+    let nested = format!("p {}1{}", "(-".repeat(100_000), ")".repeat(100_000));
+    let output = debug(
+        &program,
+        &[
+            "b",
+            "b )",
+            "b main",
+            "c",
+            "p (1+2",
+            "p )",
+            "p 1+",
+            "p 1/0",
+            "p 0x",
+            "x/0x",
+            "x/zz 0",
+            "x/99999999999999999999x $rsp",
+            "x/1000000000000b $rsp",
+            "x/4000000000000000000g $rsp",
+            "x/8xb 0xfffffffffffffffc",
+            "formatter delete 0",
+            &nested,
+            "set var",
+            "si -1",
+            "compare disas",
+            "c",
+        ],
+    );
+    for error in [
+        "unknown command `[\"b\"]`",
+        "error: `)` isn't an address, unexpected `)` in expression",
+        "error: expected `)`",
+        "error: unexpected `)` in expression",
+        "error: unexpected end of expression",
+        "error: division by zero",
+        "error: invalid number `0x`",
+        "error: no address to examine, `x <addr>` takes one",
+        "error: unknown format letter `z`",
+        "error: count too large",
+        "error: cannot access memory at 0xfffffffffffffffc",
+        "error: no formatter 0",
+        "error: expression nested too deeply",
+        "error: expected `set var <name> = <value>`",
+        "error: not a number of steps: -1",
+    ] {
+        assert!(output.contains(&format!("{}\n", error)), "{}", error);
+    }
+    // Past the end of the stack, not a terabyte allocated to find out.
+    assert!(output.contains("error: cannot access memory at 0x7ff"));
+    // The session outlived all of them.
+    assert!(output.contains("49\n"));
+}
+
+#[test]
+fn set_radix_shows_small_values_in_both_bases() {
+    let program = fixture("arith");
+    let output = debug(
+        &program,
+        &[
+            "sym add",
+            "b add",
+            "c",
+            "regs",
+            "set radix 10",
+            "regs",
+            "p 255",
+            "set radix 8",
+        ],
+    );
+    let add = symbol(&output, "add");
+    // add(42, 7): 7 is the same in both, rip is too big to be a count.
+    assert!(output.contains(&format!("rip: 0x{:x}\n", add)));
+    assert!(output.contains("rdi: 0x2a (42)\n"));
+    assert!(output.contains("rsi: 0x7\n"));
+    assert!(output.contains(&format!("rip: {}\n", add)));
+    assert!(output.contains("rdi: 42 (0x2a)\n"));
+    assert!(output.contains("rsi: 7\n"));
+    assert!(output.contains("255 (0xff)\n"));
+    assert!(output.contains("error: unsupported radix `8`, expected 10 or 16\n"));
+}
+
+#[test]
+fn stack_colors_values_by_region_on_a_terminal() {
+    use std::process::{Command, Stdio};
+
+    let program = fixture("arith");
+    let commands = ["sym main", "b add", "c", "stack"];
+    let plain = debug(&program, &commands);
+    assert!(plain.contains("stack [text heap stack libs]\n"));
+    assert!(!plain.contains('\x1b'));
+
+    // script(1) gives the debugger a terminal to write to, while its
+    // commands still come from a file.
+    let file = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("stack-commands");
+    std::fs::write(&file, commands.join("\n") + "\n").unwrap();
+    let shell = format!(
+        "'{}' '{}' < '{}'",
+        env!("CARGO_BIN_EXE_rust-debugger"),
+        program.display(),
+        file.display()
+    );
+    let output = Command::new("script")
+        .args(["-qec", &shell, "/dev/null"])
+        .stdin(Stdio::null())
+        .output()
+        .expect("couldn't run script");
+    let colored = String::from_utf8_lossy(&output.stdout).replace("\r\n", "\n");
+    let main = symbol(&colored, "main");
+
+    let (text, stack, libs) = ("\x1b[38;5;4m", "\x1b[38;5;3m", "\x1b[38;5;5m");
+    let words: Vec<&str> = colored
+        .lines()
+        .filter(|line| line.starts_with("0x7f") && line.contains(": "))
+        .collect();
+    // add returns into main, main into libc, and argv points up the stack.
+    let ret = words[0]
+        .split(text)
+        .nth(1)
+        .unwrap()
+        .trim_end_matches("\x1b[39m");
+    let ret = u64::from_str_radix(ret.trim_start_matches("0x"), 16).unwrap();
+    assert!(ret > main && ret < main + 0x40, "{}", colored);
+    assert!(words.iter().any(|word| word.contains(libs)));
+    assert!(words.iter().any(|word| word.contains(stack)));
+}
+
+#[test]
+fn a_core_from_another_build_is_warned_about() {
+    use std::process::Command;
+
+    let program = fixture("crash");
+    let tmp = std::path::Path::new(env!("CARGO_TARGET_TMPDIR"));
+    let core = tmp.join("build-id.gcore");
+    debug(
+        &program,
+        &["b crash", "c", &format!("gcore {}", core.display())],
+    );
+
+    // The same program with a function added has another build-id.
+    let source = tmp.join("crash-rebuilt.c");
+    let original = std::fs::read_to_string(fixture_source("crash")).unwrap();
+    std::fs::write(&source, original + "\nint unused(void) {\n\treturn 1;\n}\n").unwrap();
+    let rebuilt = tmp.join("crash-rebuilt");
+    let status = Command::new("cc")
+        .args(["-g", "-O0", "-no-pie", "-o"])
+        .arg(&rebuilt)
+        .arg(&source)
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let options = ["--core", core.to_str().unwrap()];
+    let output = debug_with_options(&options, &program, &[], &[]);
+    assert!(!output.contains("warning:"));
+
+    let env = [("DEBUGINFOD_URLS", "https://debuginfod.example.org/")];
+    let output = debug_with_options(&options, &rebuilt, &env, &[]);
+    // Plain, with no colour codes, when stdout isn't a terminal.
+    let warning = format!(
+        "warning: {} doesn't match the running program, symbols and backtraces may be wrong",
+        rebuilt.display()
+    );
+    assert!(output.lines().any(|line| line == warning), "{}", output);
+    let id = |prefix: &str| {
+        output
+            .lines()
+            .find_map(|line| line.strip_prefix(prefix))
+            .unwrap()
+            .to_owned()
+    };
+    let (on_disk, running) = (id("  build-id on disk: "), id("  build-id running: "));
+    assert_ne!(on_disk, running);
+    assert!(output.contains(&format!(
+        "  the right binary may be at https://debuginfod.example.org/buildid/{}/executable\n",
+        running
+    )));
+}
+
+#[test]
+fn programs_and_cores_for_other_machines_are_refused() {
+    use std::process::{Command, Stdio};
+
+    let tmp = std::path::Path::new(env!("CARGO_TARGET_TMPDIR"));
+    let program = fixture("crash");
+    let core = tmp.join("machine.gcore");
+    debug(
+        &program,
+        &["b crash", "c", &format!("gcore {}", core.display())],
+    );
+
+    // `e_machine` is the two bytes at 18 in the ELF header.
+    let patch = |from: &std::path::Path, to: std::path::PathBuf, machine: u16| {
+        let mut bytes = std::fs::read(from).unwrap();
+        bytes[18..20].copy_from_slice(&machine.to_le_bytes());
+        std::fs::write(&to, bytes).unwrap();
+        std::fs::set_permissions(&to, std::fs::metadata(from).unwrap().permissions()).unwrap();
+        to
+    };
+    let arm_program = patch(&program, tmp.join("crash-aarch64"), 183);
+    let arm_core = patch(&core, tmp.join("machine-arm.gcore"), 40);
+
+    let refuse = |args: &[&std::ffi::OsStr]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_rust-debugger"))
+            .args(args)
+            .stdin(Stdio::null())
+            .output()
+            .unwrap();
+        assert!(!output.status.success());
+        String::from_utf8_lossy(&output.stderr).to_string()
+    };
+    assert_eq!(
+        refuse(&[arm_program.as_os_str()]),
+        "err: the program is for aarch64, and only x86-64 can be debugged\n"
+    );
+    assert_eq!(
+        refuse(&["--core".as_ref(), arm_core.as_os_str(), program.as_os_str()]),
+        "err: the core file is for arm, and only x86-64 can be debugged\n"
+    );
+}
+
+#[test]
+fn undo_restores_registers_and_memory_newest_first() {
+    let program = fixture("arith");
+    let output = debug(
+        &program,
+        &[
+            "sym counter",
+            "b add",
+            "c",
+            "set $rdi = 5",
+            "set qword &counter = 1",
+            "p counter",
+            "undo",
+            "p counter",
+            "undo",
+            "r rdi",
+            "undo",
+            "c",
+        ],
+    );
+    let counter = symbol(&output, "counter");
+    assert!(output.contains("rdi = 0x5\n0x1\n"));
+    assert!(output.contains(&format!("restored [0x{:x}] = 0x2a\n0x2a (42)\n", counter)));
+    assert!(output.contains("restored rdi = 0x2a\n"));
+    assert_eq!(register(&output, "rdi"), vec![42]);
+    assert!(output.contains("nothing to undo\n"));
+    // add(42, 7) ran as if nothing had been changed.
+    assert!(output.contains("49\n"));
+}
+
+#[test]
+fn ctrl_c_interrupts_the_program_rather_than_the_debugger() {
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::os::unix::process::CommandExt;
+    use std::process::{Command, Stdio};
+
+    let program = fixture("spin");
+    // Its own process group, like a job in a shell, for Ctrl-C to reach
+    // both the debugger and the program the way a terminal's does.
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rust-debugger"))
+        .arg(&program)
+        .process_group(0)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    let mut stdout = BufReader::new(child.stdout.take().unwrap());
+
+    writeln!(stdin, "c").unwrap();
+    let mut line = String::new();
+    while line != "spinning\n" {
+        line.clear();
+        assert_ne!(stdout.read_line(&mut line).unwrap(), 0, "never started");
+    }
+    unsafe { libc::kill(-(child.id() as i32), libc::SIGINT) };
+
+    writeln!(stdin, "set dword &spinning = 0\nc").unwrap();
+    drop(stdin);
+    let mut output = String::new();
+    stdout.read_to_string(&mut output).unwrap();
+    assert!(child.wait().unwrap().success());
+    assert!(output.contains("interrupted at 0x"), "{}", output);
+    assert!(output.contains("stopped spinning\n"));
+    assert!(output.contains("debugged process exited with status: 0\n"));
+}
+
+#[test]
+fn malformed_core_files_are_errors() {
+    use std::process::{Command, Stdio};
+
+    // An NT_FILE note claiming more mappings than memory could hold.
+    let mut note = Vec::new();
+    note.extend_from_slice(&5u32.to_le_bytes());
+    note.extend_from_slice(&16u32.to_le_bytes());
+    note.extend_from_slice(&0x4649_4c45u32.to_le_bytes());
+    note.extend_from_slice(b"CORE\0\0\0\0");
+    note.extend_from_slice(&(u64::MAX / 8).to_le_bytes());
+    note.extend_from_slice(&4096u64.to_le_bytes());
+    let mut notes = program_header(4, 64 + 56, note.len() as u64);
+    notes.extend_from_slice(&note);
+
+    let cores = [
+        (
+            crafted_core("short.core", 64, 2, &program_header(1, 0, 0)),
+            "err: truncated core file\n",
+        ),
+        (
+            crafted_core("phoff.core", u64::MAX - 8, 1, &[]),
+            "err: truncated core file\n",
+        ),
+        (
+            crafted_core("p-offset.core", 64, 1, &program_header(1, u64::MAX - 8, 16)),
+            "err: truncated core file\n",
+        ),
+        (
+            crafted_core("nt-file.core", 64, 1, &notes),
+            "err: the core file has no threads in it\n",
+        ),
+    ];
+    let program = fixture("crash");
+    for (core, error) in &cores {
+        let output = Command::new(env!("CARGO_BIN_EXE_rust-debugger"))
+            .arg("--core")
+            .arg(core)
+            .arg(&program)
+            .stdin(Stdio::null())
+            .output()
+            .unwrap();
+        assert_eq!(String::from_utf8_lossy(&output.stderr), *error);
+        assert_eq!(output.status.code(), Some(1));
+    }
+}